@@ -0,0 +1,98 @@
+//! Per-call latency of the Android backend against a desktop JVM
+//!
+//! Every operation is measured on a single thread and on multiple threads that share the
+//! `JavaVM`, so contention on the JNI layer shows up as a higher latency per call.
+//!
+//! ```sh
+//! cargo bench --features android_host_testing --bench android_host
+//! ```
+//!
+//! Per-call latency in µs before and after the JNI lookups were cached and the global mutex around
+//! the `JavaVM` was removed, as the median of three runs on a host with a single core:
+//!
+//! | Operation        | Threads | Before | After |
+//! |------------------|---------|--------|-------|
+//! | `get_public_key` | 1       |    6.9 |   3.5 |
+//! | `get_public_key` | 8       |   42.8 |  25.4 |
+//! | `sign`           | 1       |   1976 |  1873 |
+//! | `sign`           | 8       |   8612 |  7780 |
+//! | `create_keypair` | 1       |   1281 |  1363 |
+//! | `create_keypair` | 8       |   7636 |  6806 |
+//!
+//! With a single core, 8 threads can not run in parallel, so the rows with 8 threads show the
+//! overhead of contention rather than scaling. Signing and key generation are dominated by the EC
+//! implementation of the JDK, so the lookups and the mutex only make a clear difference for
+//! `get_public_key`.
+
+#[allow(dead_code)]
+#[path = "../tests/android_host/jvm.rs"]
+mod jvm;
+
+use secure_env::{KeyOps, PlatformHandles, SecureEnvironment, SecureEnvironmentConfig};
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+const THREADS: [usize; 2] = [1, 8];
+const WARMUP_ITERATIONS: usize = 100;
+
+fn measure(name: &str, iterations: usize, f: impl Fn() + Sync) {
+    for _ in 0..WARMUP_ITERATIONS {
+        f();
+    }
+
+    for threads in THREADS {
+        let start = Instant::now();
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| {
+                    for _ in 0..iterations {
+                        f();
+                    }
+                });
+            }
+        });
+        let elapsed = start.elapsed();
+
+        // Time that a single thread waits for a call to return
+        let per_call = elapsed / iterations as u32;
+
+        println!(
+            "{name:<16} threads: {threads:<2} per call: {:>8.1} µs",
+            as_micros(per_call)
+        );
+    }
+}
+
+fn as_micros(duration: Duration) -> f64 {
+    duration.as_nanos() as f64 / 1000.0
+}
+
+fn main() {
+    let environment = SecureEnvironment::new(SecureEnvironmentConfig {
+        namespace: Some("bench".to_owned()),
+        platform: PlatformHandles::with_java_vm(jvm::java_vm()).unwrap(),
+        ..Default::default()
+    });
+
+    let key = environment.create_keypair("bench-key").unwrap();
+    let msg = [0u8; 256];
+
+    measure("sign", 2000, || {
+        key.sign(&msg).unwrap();
+    });
+
+    measure("get_public_key", 2000, || {
+        key.get_public_key().unwrap();
+    });
+
+    let created = AtomicUsize::new(0);
+    measure("create_keypair", 200, || {
+        let i = created.fetch_add(1, Ordering::Relaxed);
+        environment
+            .create_keypair(format!("bench-key-{i}"))
+            .unwrap();
+    });
+}
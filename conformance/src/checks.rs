@@ -0,0 +1,418 @@
+//! Individual checks of the suite
+//!
+//! Every check takes the backend under test and returns a [`Failure`] with the reason when the
+//! backend does not behave as expected.
+
+use crate::{CheckResult, Failure};
+use p256::{
+    ecdsa::{signature::Verifier, Signature, VerifyingKey},
+    elliptic_curve::sec1::ToEncodedPoint,
+};
+use rand_core::{OsRng, RngCore};
+use secure_env::{error::SecureEnvError, BoxedKey, KeyBackend, KeyGenerationOptions, KeyId};
+use std::{collections::HashSet, thread};
+
+const THREADS: usize = 8;
+
+macro_rules! ensure {
+    ($condition:expr, $($message:tt)+) => {
+        if !$condition {
+            return Err(Failure(format!($($message)+)));
+        }
+    };
+}
+
+/// Ids of the keypairs that are created by a check. They are deleted when the check finishes.
+struct Ids<'a> {
+    backend: &'a dyn KeyBackend,
+    ids: Vec<KeyId>,
+}
+
+impl<'a> Ids<'a> {
+    fn new(backend: &'a dyn KeyBackend) -> Self {
+        Self {
+            backend,
+            ids: vec![],
+        }
+    }
+
+    /// New random id, so the checks do not collide with each other or with earlier runs
+    fn next(&mut self, name: &str) -> KeyId {
+        let mut random = [0u8; 8];
+        OsRng.fill_bytes(&mut random);
+
+        let random: String = random.iter().map(|b| format!("{b:02x}")).collect();
+        let id = KeyId::new(format!("conformance-{name}-{random}"))
+            .expect("Generated id to be a valid key id");
+
+        self.ids.push(id.clone());
+        id
+    }
+}
+
+impl Drop for Ids<'_> {
+    fn drop(&mut self) {
+        for id in &self.ids {
+            // Keypairs that have already been deleted by the check fail here
+            let _ = self.backend.delete_keypair(id);
+        }
+    }
+}
+
+fn verify(public_key: &[u8], msg: &[u8], signature: &[u8]) -> Result<bool, Failure> {
+    let verifying_key = VerifyingKey::from_sec1_bytes(public_key)
+        .map_err(|e| Failure(format!("Public key is not a valid SEC1 P-256 key: {e}")))?;
+
+    let signature = Signature::from_slice(signature)
+        .map_err(|e| Failure(format!("Signature is not a valid r || s signature: {e}")))?;
+
+    Ok(verifying_key.verify(msg, &signature).is_ok())
+}
+
+fn sign_and_verify(key: &BoxedKey, public_key: &[u8], msg: &[u8]) -> CheckResult {
+    let signature = key.sign(msg)?;
+
+    ensure!(
+        signature.len() == 64,
+        "Signature must be 64 bytes, received {} bytes",
+        signature.len()
+    );
+    ensure!(
+        verify(public_key, msg, &signature)?,
+        "Signature over a message of {} bytes does not verify",
+        msg.len()
+    );
+
+    Ok(())
+}
+
+/// A generated keypair can be used right away, and every keypair is unique
+pub fn generate_keypair(backend: &dyn KeyBackend) -> CheckResult {
+    let mut ids = Ids::new(backend);
+
+    let key = backend.create_keypair(&ids.next("generate"))?;
+    let other_key = backend.create_keypair(&ids.next("generate"))?;
+
+    let public_key = key.get_public_key()?;
+    sign_and_verify(&key, &public_key, b"Hello World!")?;
+
+    ensure!(
+        public_key != other_key.get_public_key()?,
+        "Two generated keypairs have the same public key"
+    );
+
+    Ok(())
+}
+
+/// A loaded keypair is the keypair that has been created with the same id
+pub fn load_keypair(backend: &dyn KeyBackend) -> CheckResult {
+    let mut ids = Ids::new(backend);
+    let id = ids.next("load");
+
+    let key = backend.create_keypair(&id)?;
+    let loaded = backend.load_keypair(&id)?;
+
+    let public_key = key.get_public_key()?;
+    ensure!(
+        public_key == loaded.get_public_key()?,
+        "Loaded keypair has a different public key"
+    );
+
+    sign_and_verify(&loaded, &public_key, b"Hello World!")
+}
+
+/// Loading an unknown id fails with `UnableToGetKeyPairById`
+pub fn load_keypair_that_does_not_exist(backend: &dyn KeyBackend) -> CheckResult {
+    let mut ids = Ids::new(backend);
+
+    let result = backend.load_keypair(&ids.next("missing"));
+
+    ensure!(
+        matches!(result, Err(SecureEnvError::UnableToGetKeyPairById(_))),
+        "Expected `UnableToGetKeyPairById`, received: {result:?}"
+    );
+
+    Ok(())
+}
+
+/// Creating a keypair with an id that is in use fails and leaves the existing keypair intact
+pub fn duplicate_id(backend: &dyn KeyBackend) -> CheckResult {
+    let mut ids = Ids::new(backend);
+    let id = ids.next("duplicate");
+
+    let key = backend.create_keypair(&id)?;
+    let result = backend.create_keypair(&id);
+
+    ensure!(
+        matches!(result, Err(SecureEnvError::UnableToGenerateKey(_))),
+        "Expected `UnableToGenerateKey`, received: {result:?}"
+    );
+    ensure!(
+        key.get_public_key()? == backend.load_keypair(&id)?.get_public_key()?,
+        "Existing keypair has been replaced"
+    );
+
+    Ok(())
+}
+
+/// A deleted keypair can not be loaded anymore and its id can be used again
+pub fn delete_keypair(backend: &dyn KeyBackend) -> CheckResult {
+    let mut ids = Ids::new(backend);
+    let id = ids.next("delete");
+
+    let key = backend.create_keypair(&id)?;
+    backend.delete_keypair(&id)?;
+
+    let result = backend.load_keypair(&id);
+    ensure!(
+        matches!(result, Err(SecureEnvError::UnableToGetKeyPairById(_))),
+        "Expected `UnableToGetKeyPairById` after deletion, received: {result:?}"
+    );
+
+    let new_key = backend.create_keypair(&id)?;
+    ensure!(
+        key.get_public_key()? != new_key.get_public_key()?,
+        "Keypair that is created with the id of a deleted keypair has the same public key"
+    );
+
+    Ok(())
+}
+
+/// Deleting an unknown id, or an id that has already been deleted, fails with `UnableToDeleteKey`
+pub fn delete_keypair_that_does_not_exist(backend: &dyn KeyBackend) -> CheckResult {
+    let mut ids = Ids::new(backend);
+
+    let result = backend.delete_keypair(&ids.next("delete-missing"));
+    ensure!(
+        matches!(result, Err(SecureEnvError::UnableToDeleteKey(_))),
+        "Expected `UnableToDeleteKey`, received: {result:?}"
+    );
+
+    let id = ids.next("delete-twice");
+    backend.create_keypair(&id)?;
+    backend.delete_keypair(&id)?;
+
+    let result = backend.delete_keypair(&id);
+    ensure!(
+        matches!(result, Err(SecureEnvError::UnableToDeleteKey(_))),
+        "Expected `UnableToDeleteKey` when deleting twice, received: {result:?}"
+    );
+
+    Ok(())
+}
+
+/// Ephemeral keypairs can be used right away, and every ephemeral keypair is unique
+pub fn ephemeral_keypair(backend: &dyn KeyBackend) -> CheckResult {
+    let options = KeyGenerationOptions::default();
+
+    let key = backend.generate_ephemeral_keypair(&options)?;
+    let other_key = backend.generate_ephemeral_keypair(&options)?;
+
+    let public_key = key.get_public_key()?;
+    sign_and_verify(&key, &public_key, b"Hello World!")?;
+
+    ensure!(
+        public_key != other_key.get_public_key()?,
+        "Two ephemeral keypairs have the same public key"
+    );
+
+    Ok(())
+}
+
+/// Signatures are 64 byte `r || s` ECDSA P-256 SHA-256 signatures over the message
+pub fn signature_is_valid(backend: &dyn KeyBackend) -> CheckResult {
+    let mut ids = Ids::new(backend);
+
+    let key = backend.create_keypair(&ids.next("signature"))?;
+    let public_key = key.get_public_key()?;
+
+    for len in [0, 1, 32, 1000, 64 * 1024] {
+        sign_and_verify(&key, &public_key, &vec![0xa5; len])?;
+    }
+
+    let signature = key.sign(b"Hello World!")?;
+    ensure!(
+        !verify(&public_key, b"Hello World?", &signature)?,
+        "Signature verifies for a different message"
+    );
+
+    Ok(())
+}
+
+/// Public keys are 33 byte compressed SEC1 encoded P-256 points that do not change
+pub fn public_key_encoding(backend: &dyn KeyBackend) -> CheckResult {
+    let mut ids = Ids::new(backend);
+    let id = ids.next("public-key");
+
+    let key = backend.create_keypair(&id)?;
+    let public_key = key.get_public_key()?;
+
+    ensure!(
+        public_key.len() == 33,
+        "Public key must be 33 bytes, received {} bytes",
+        public_key.len()
+    );
+    ensure!(
+        public_key[0] == 0x02 || public_key[0] == 0x03,
+        "Public key must be compressed, received tag {:#04x}",
+        public_key[0]
+    );
+
+    let point = p256::PublicKey::from_sec1_bytes(&public_key)
+        .map_err(|e| Failure(format!("Public key is not a point on P-256: {e}")))?;
+    ensure!(
+        point.to_encoded_point(true).as_bytes() == public_key.as_slice(),
+        "Public key is not canonically encoded"
+    );
+
+    ensure!(
+        public_key == key.get_public_key()?,
+        "Public key changes between calls"
+    );
+    ensure!(
+        public_key == backend.load_keypair(&id)?.get_public_key()?,
+        "Public key changes after loading the keypair"
+    );
+
+    Ok(())
+}
+
+/// Keypairs can be created from multiple threads at the same time
+pub fn concurrent_generation(backend: &dyn KeyBackend) -> CheckResult {
+    let mut ids = Ids::new(backend);
+    let thread_ids: Vec<KeyId> = (0..THREADS).map(|_| ids.next("concurrent")).collect();
+
+    let public_keys = thread::scope(|scope| {
+        let handles: Vec<_> = thread_ids
+            .iter()
+            .map(|id| scope.spawn(move || backend.create_keypair(id)?.get_public_key()))
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .map_err(|_| Failure("Thread panicked while creating a keypair".to_owned()))?
+                    .map_err(Failure::from)
+            })
+            .collect::<Result<Vec<_>, Failure>>()
+    })?;
+
+    ensure!(
+        public_keys.iter().collect::<HashSet<_>>().len() == THREADS,
+        "Keypairs that are created concurrently share a public key"
+    );
+
+    for (id, public_key) in thread_ids.iter().zip(&public_keys) {
+        ensure!(
+            backend.load_keypair(id)?.get_public_key()? == *public_key,
+            "Keypair '{id}' that has been created concurrently can not be loaded"
+        );
+    }
+
+    Ok(())
+}
+
+/// A single keypair can sign from multiple threads at the same time
+pub fn concurrent_signing(backend: &dyn KeyBackend) -> CheckResult {
+    let mut ids = Ids::new(backend);
+
+    let key = backend.create_keypair(&ids.next("concurrent-signing"))?;
+    let public_key = key.get_public_key()?;
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..THREADS)
+            .map(|i| {
+                let key = &key;
+                let public_key = &public_key;
+
+                scope.spawn(move || sign_and_verify(key, public_key, &[i as u8; 32]))
+            })
+            .collect();
+
+        handles.into_iter().try_for_each(|handle| {
+            handle
+                .join()
+                .map_err(|_| Failure("Thread panicked while signing".to_owned()))?
+        })
+    })
+}
+
+/// Stored keypairs are listed by id, deleted and ephemeral keypairs are not
+pub fn list_keys(backend: &dyn KeyBackend) -> CheckResult {
+    let mut ids = Ids::new(backend);
+    let listed = ids.next("listed");
+    let deleted = ids.next("list-deleted");
+
+    backend.create_keypair(&listed)?;
+    backend.create_keypair(&deleted)?;
+    backend.delete_keypair(&deleted)?;
+    let ephemeral = backend.generate_ephemeral_keypair(&KeyGenerationOptions::default())?;
+
+    // A persistent keystore may contain keypairs of earlier runs
+    let keys = backend.list_keys()?;
+    ensure!(keys.contains(&listed), "Keypair '{listed}' is not listed");
+    ensure!(
+        !keys.contains(&deleted),
+        "Deleted keypair '{deleted}' is listed"
+    );
+    ensure!(
+        keys.iter().all(|id| backend.load_keypair(id).is_ok()),
+        "Listed ids can not all be loaded: {keys:?}"
+    );
+
+    drop(ephemeral);
+
+    Ok(())
+}
+
+/// Wiping deletes every keypair of the namespace, and wiping again deletes nothing
+///
+/// This check deletes every keypair in the namespace of the backend, including keypairs that have
+/// not been created by the suite.
+pub fn wipe(backend: &dyn KeyBackend) -> CheckResult {
+    let mut ids = Ids::new(backend);
+    let created: Vec<KeyId> = (0..3).map(|_| ids.next("wipe")).collect();
+
+    for id in &created {
+        backend.create_keypair(id)?;
+    }
+
+    let keys = backend.list_keys()?;
+    ensure!(
+        created.iter().all(|id| keys.contains(id)),
+        "Created keypairs are not listed before wiping: {keys:?}"
+    );
+
+    let report = backend.wipe()?;
+    ensure!(
+        report.is_complete(),
+        "Wiping failed for some keypairs: {report:?}"
+    );
+    ensure!(
+        created.iter().all(|id| report.deleted.contains(id)),
+        "Created keypairs are not reported as deleted: {:?}",
+        report.deleted
+    );
+
+    // Verify that no keys remain
+    let keys = backend.list_keys()?;
+    ensure!(keys.is_empty(), "Keypairs remain after wiping: {keys:?}");
+
+    for id in &created {
+        let result = backend.load_keypair(id);
+        ensure!(
+            matches!(result, Err(SecureEnvError::UnableToGetKeyPairById(_))),
+            "Expected `UnableToGetKeyPairById` for '{id}' after wiping, received: {result:?}"
+        );
+    }
+
+    let report = backend.wipe()?;
+    ensure!(
+        report.deleted.is_empty() && report.is_complete(),
+        "Wiping an empty namespace is not a no-op: {report:?}"
+    );
+
+    Ok(())
+}
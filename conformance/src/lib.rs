@@ -0,0 +1,237 @@
+//! Conformance test suite for secure-env backends
+//!
+//! Every [`KeyBackend`] has to behave the same, so an application can switch backends without
+//! noticing. This crate checks the behaviour that all backends share: generating and loading
+//! keypairs, ephemeral keypairs, duplicate ids, deletion, signatures, public key encodings,
+//! concurrent use, listing and wiping keypairs and the kinds of errors that are returned. Third
+//! party backends can use it to prove that they are compatible.
+//!
+//! Every check creates keypairs with random ids and deletes them again, so the suite can be run
+//! against a keystore that persists its keys. The `wipe` check is the exception: it deletes every
+//! keypair in the namespace of the backend, so run the suite in a namespace of its own. When the
+//! backends of the tests share a keystore, give every test its own namespace with the
+//! `|check| ...` form of [`conformance_tests!`], as the tests run in parallel.
+//!
+//! # Examples
+//!
+//! ## Run the suite with `cargo test`
+//!
+//! [`conformance_tests!`] generates a `#[test]` for every check. The expression is evaluated for
+//! every test, so each test gets a new backend.
+//!
+//! ```
+//! use secure_env::{SecureEnvironmentConfig, SoftwareBackend};
+//!
+//! secure_env_conformance::conformance_tests!(SoftwareBackend::new(
+//!     SecureEnvironmentConfig::default()
+//! ));
+//! # fn main() {}
+//! ```
+//!
+//! ## Run the suite on a device
+//!
+//! On targets where `cargo test` is not available, [`run`] executes every check and returns a
+//! [`Report`].
+//!
+//! ```
+//! use secure_env::{SecureEnvironmentConfig, SoftwareBackend};
+//!
+//! let report = secure_env_conformance::run(&SoftwareBackend::new(
+//!     SecureEnvironmentConfig::default(),
+//! ));
+//!
+//! println!("{report}");
+//! assert!(report.passed());
+//! ```
+
+use secure_env::{error::SecureEnvError, KeyBackend};
+use std::fmt::{self, Display};
+
+pub mod checks;
+
+/// Reason why a check failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure(pub String);
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+impl From<SecureEnvError> for Failure {
+    fn from(e: SecureEnvError) -> Self {
+        Self(format!("Unexpected error: {e}"))
+    }
+}
+
+pub type CheckResult = Result<(), Failure>;
+
+/// Single check of the suite
+#[derive(Debug, Clone, Copy)]
+pub struct Check {
+    pub name: &'static str,
+    pub run: fn(&dyn KeyBackend) -> CheckResult,
+}
+
+/// Every check of the suite
+///
+/// Keep in sync with [`conformance_tests!`].
+pub const CHECKS: &[Check] = &[
+    Check {
+        name: "generate_keypair",
+        run: checks::generate_keypair,
+    },
+    Check {
+        name: "load_keypair",
+        run: checks::load_keypair,
+    },
+    Check {
+        name: "load_keypair_that_does_not_exist",
+        run: checks::load_keypair_that_does_not_exist,
+    },
+    Check {
+        name: "duplicate_id",
+        run: checks::duplicate_id,
+    },
+    Check {
+        name: "delete_keypair",
+        run: checks::delete_keypair,
+    },
+    Check {
+        name: "delete_keypair_that_does_not_exist",
+        run: checks::delete_keypair_that_does_not_exist,
+    },
+    Check {
+        name: "ephemeral_keypair",
+        run: checks::ephemeral_keypair,
+    },
+    Check {
+        name: "signature_is_valid",
+        run: checks::signature_is_valid,
+    },
+    Check {
+        name: "public_key_encoding",
+        run: checks::public_key_encoding,
+    },
+    Check {
+        name: "concurrent_generation",
+        run: checks::concurrent_generation,
+    },
+    Check {
+        name: "concurrent_signing",
+        run: checks::concurrent_signing,
+    },
+    Check {
+        name: "list_keys",
+        run: checks::list_keys,
+    },
+    // Runs last, as it deletes the keypairs of the namespace
+    Check {
+        name: "wipe",
+        run: checks::wipe,
+    },
+];
+
+/// Result of running the suite via [`run`]
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub results: Vec<(&'static str, CheckResult)>,
+}
+
+impl Report {
+    /// Whether every check has passed
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|(_, result)| result.is_ok())
+    }
+
+    /// Checks that have failed, with the reason why
+    pub fn failures(&self) -> impl Iterator<Item = (&'static str, &Failure)> {
+        self.results
+            .iter()
+            .filter_map(|(name, result)| result.as_ref().err().map(|failure| (*name, failure)))
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, result) in &self.results {
+            match result {
+                Ok(()) => writeln!(f, "{name} ... ok")?,
+                Err(failure) => writeln!(f, "{name} ... FAILED: {failure}")?,
+            }
+        }
+
+        let failed = self.failures().count();
+        write!(f, "{} passed; {failed} failed", self.results.len() - failed)
+    }
+}
+
+/// Run every check against `backend`
+pub fn run(backend: &dyn KeyBackend) -> Report {
+    Report {
+        results: CHECKS
+            .iter()
+            .map(|check| (check.name, (check.run)(backend)))
+            .collect(),
+    }
+}
+
+/// Generate a `#[test]` for every check of the suite
+///
+/// The expression must evaluate to a type that implements [`KeyBackend`]. It is evaluated once
+/// for every test. With the `|check| ...` form, the name of the check is bound as a `&str`, e.g.
+/// to give every test its own namespace.
+///
+/// ```
+/// use secure_env::{SecureEnvironmentConfig, SoftwareBackend};
+///
+/// secure_env_conformance::conformance_tests!(|check| SoftwareBackend::new(
+///     SecureEnvironmentConfig {
+///         namespace: Some(format!("conformance-{check}")),
+///         ..Default::default()
+///     }
+/// ));
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! conformance_tests {
+    (|$name:ident| $backend:expr) => {
+        $crate::conformance_tests!(
+            @tests $name, $backend;
+            generate_keypair,
+            load_keypair,
+            load_keypair_that_does_not_exist,
+            duplicate_id,
+            delete_keypair,
+            delete_keypair_that_does_not_exist,
+            ephemeral_keypair,
+            signature_is_valid,
+            public_key_encoding,
+            concurrent_generation,
+            concurrent_signing,
+            list_keys,
+            wipe
+        );
+    };
+
+    ($backend:expr) => {
+        $crate::conformance_tests!(|_check| $backend);
+    };
+
+    (@tests $name:ident, $backend:expr; $($check:ident),*) => {
+        $(
+            #[test]
+            fn $check() {
+                let $name = stringify!($check);
+                let backend = $backend;
+
+                if let Err(failure) = $crate::checks::$check(&backend) {
+                    panic!("{}", failure);
+                }
+            }
+        )*
+    };
+}
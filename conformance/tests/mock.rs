@@ -0,0 +1,13 @@
+//! Runs the suite against the `MockBackend`, with random and with seeded keys
+
+use secure_env::{MockBackend, SecureEnvironmentConfig};
+
+secure_env_conformance::conformance_tests!(MockBackend::new(SecureEnvironmentConfig::default()));
+
+mod seeded {
+    use super::*;
+
+    secure_env_conformance::conformance_tests!(
+        MockBackend::new(SecureEnvironmentConfig::default()).with_seed(42)
+    );
+}
@@ -0,0 +1,16 @@
+//! Runs the suite against the `SoftwareBackend`, with and without a namespace
+
+use secure_env::{SecureEnvironmentConfig, SoftwareBackend};
+
+secure_env_conformance::conformance_tests!(
+    SoftwareBackend::new(SecureEnvironmentConfig::default())
+);
+
+mod namespaced {
+    use super::*;
+
+    secure_env_conformance::conformance_tests!(SoftwareBackend::new(SecureEnvironmentConfig {
+        namespace: Some("conformance".to_owned()),
+        ..Default::default()
+    }));
+}
@@ -1,136 +1,95 @@
-use askar_crypto::{alg::p256::P256KeyPair, repr::KeyPublicBytes};
-use secure_env::{KeyOps, SecureEnvironment, SecureEnvironmentOps};
-use std::{
-    panic::catch_unwind,
-    process::exit,
-    ptr::{addr_of, null},
-};
+//! Runs the conformance suite, and the checks that only apply to the platform backends, on a
+//! device. A failure panics, so it shows up as "panicked" in the device log.
+
+use secure_env::{KeyOps, SecureEnvironment, SecureEnvironmentConfig, SecureEnvironmentOps};
+use secure_env_conformance::{run, CheckResult, Failure};
 use uuid::Uuid;
 
-macro_rules! run_tests {
-    ($($test:ident),*) => {
-        $(
-            let result = catch_unwind(|| {
-                $test();
-            });
-            let test_name = stringify!($test);
-            match result {
-                Ok(_) => {
-                    println!("{test_name} passed!");
-                }
-                Err(e) => {
-                    eprintln!("{test_name} failed!");
-                    eprintln!("{e:?}");
-                    exit(1)
-                }
-            }
-        )*
-    }
-}
+/// Check that only applies to the platform backends
+type PlatformCheck = (&'static str, fn() -> CheckResult);
 
 pub fn run_tests() {
-    run_tests!(
-        test_generate_keypair,
-        test_get_keypair_by_id,
-        test_get_public_key,
-        test_generate_and_sign,
-        test_generate_and_sign_and_verify_with_askar,
-
-        test_get_by_id_and_get_public_key,
-        test_get_by_id_and_sign,
-        test_get_by_id_and_sign_and_verify_with_askar
-    );
-}
-
-fn test_generate_keypair() {
-    let id = Uuid::new_v4();
-    let key = SecureEnvironment::generate_keypair(id, false).unwrap();
-
-    assert!((addr_of!(key) != null()));
-}
-
-fn test_get_keypair_by_id() {
-    let id = Uuid::new_v4();
-
-    SecureEnvironment::generate_keypair(id, false).unwrap();
-    let key = SecureEnvironment::get_keypair_by_id(id).unwrap();
-
-    assert!((addr_of!(key) != null()));
-}
-
-fn test_get_public_key() {
-    let id = Uuid::new_v4();
-    let key = SecureEnvironment::generate_keypair(id, false).unwrap();
-
-    let public_key = key.get_public_key().unwrap();
-
-    assert_eq!(public_key.len(), 33);
-}
-
-fn test_get_by_id_and_get_public_key() {
-    let id = Uuid::new_v4();
-
-    SecureEnvironment::generate_keypair(id, false).unwrap();
-    let key = SecureEnvironment::get_keypair_by_id(id).unwrap();
-
-    let public_key = key.get_public_key().unwrap();
-
-    assert!(p256::PublicKey::from_sec1_bytes(&public_key).is_ok());
-    assert_eq!(public_key.len(), 33);
-}
-
-fn test_generate_and_sign() {
-    let id = Uuid::new_v4();
-    let key = SecureEnvironment::generate_keypair(id,false).unwrap();
-    let msg  = b"Hello World!";
-
-    let signature = key.sign(msg).unwrap();
-
-    assert!(p256::ecdsa::Signature::from_slice(&signature).is_ok());
-    assert_eq!(signature.len(), 64);
-}
-
-fn test_get_by_id_and_sign() {
-    let id = Uuid::new_v4();
-    SecureEnvironment::generate_keypair(id,false).unwrap();
-    let key = SecureEnvironment::get_keypair_by_id(id).unwrap();
-    let msg  = b"Hello World!";
-
-    let signature = key.sign(msg).unwrap();
+    let namespaced = SecureEnvironment::new(SecureEnvironmentConfig {
+        namespace: Some("mobile-tests".to_owned()),
+        ..Default::default()
+    });
+
+    let reports = [
+        ("default environment", run(&SecureEnvironment::default())),
+        ("namespaced environment", run(&namespaced)),
+    ];
+
+    let platform_checks: [PlatformCheck; 2] = [
+        ("static_api", test_static_api),
+        (
+            "namespaced_environments_are_isolated",
+            test_namespaced_environments_are_isolated,
+        ),
+    ];
+
+    let mut passed = true;
+
+    for (name, report) in &reports {
+        println!("Conformance suite with the {name}:\n{report}");
+        passed &= report.passed();
+    }
 
+    for (name, check) in platform_checks {
+        match check() {
+            Ok(()) => println!("{name} ... ok"),
+            Err(failure) => {
+                println!("{name} ... FAILED: {failure}");
+                passed = false;
+            }
+        }
+    }
 
-    assert_eq!(signature.len(), 64);
+    assert!(passed, "Mobile tests failed");
 }
 
-fn test_generate_and_sign_and_verify_with_askar() {
-    let id = Uuid::new_v4();
-    let key = SecureEnvironment::generate_keypair(id,false).unwrap();
-    let public_key = key.get_public_key().unwrap();
-    let msg  = b"Hello World!";
+fn test_static_api() -> CheckResult {
+    let id = Uuid::new_v4().to_string();
 
-    let signature = key.sign(msg).unwrap();
+    let key = SecureEnvironment::generate_keypair(&id, false)?;
+    let key_from_id = SecureEnvironment::get_keypair_by_id(&id)?;
 
-    let keypair = P256KeyPair::from_public_bytes(&public_key).unwrap();
+    let result = if key.get_public_key()? == key_from_id.get_public_key()? {
+        Ok(())
+    } else {
+        Err(Failure(
+            "Loaded keypair has a different public key".to_owned(),
+        ))
+    };
 
-    let is_valid = keypair.verify_signature(msg, &signature);
-
-    assert!(is_valid);
+    SecureEnvironment::default().delete_keypair(&id)?;
 
+    result
 }
 
-fn test_get_by_id_and_sign_and_verify_with_askar() {
-    let id = Uuid::new_v4();
-    SecureEnvironment::generate_keypair(id,false).unwrap();
-    let key = SecureEnvironment::get_keypair_by_id(id).unwrap();
-    let public_key = key.get_public_key().unwrap();
-    let msg  = b"Hello World!";
-
-    let signature = key.sign(msg).unwrap();
-
-    let keypair = P256KeyPair::from_public_bytes(&public_key).unwrap();
-
-    let is_valid = keypair.verify_signature(msg, &signature);
-
-    assert!(is_valid);
-
+fn test_namespaced_environments_are_isolated() -> CheckResult {
+    let id = Uuid::new_v4().to_string();
+    let environment = SecureEnvironment::new(SecureEnvironmentConfig {
+        namespace: Some("mobile-tests".to_owned()),
+        ..Default::default()
+    });
+    let other_environment = SecureEnvironment::new(SecureEnvironmentConfig {
+        namespace: Some("other-mobile-tests".to_owned()),
+        ..Default::default()
+    });
+
+    environment.create_keypair(&id)?;
+
+    let result = if other_environment.load_keypair(&id).is_ok() {
+        Err(Failure(
+            "Keypair is visible in another namespace".to_owned(),
+        ))
+    } else if SecureEnvironment::get_keypair_by_id(&id).is_ok() {
+        Err(Failure("Keypair is visible without a namespace".to_owned()))
+    } else {
+        Ok(())
+    };
+
+    environment.delete_keypair(&id)?;
+
+    result
 }
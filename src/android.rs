@@ -1,38 +1,126 @@
 use crate::{
     error::{SecureEnvError, SecureEnvResult},
     jni_tokens::*,
-    KeyOps, SecureEnvironmentOps,
+    jwk_thumbprint,
+    wipe::wipe_keys,
+    EphemeralWipeFailure, IntoKeyId, KeyGenerationOptions, KeyId, KeyMetadata, KeyMetadataHandle,
+    KeyOps, PlatformHandles, SecureEnvironment, WipeReport,
 };
+#[cfg(feature = "jni_onload")]
+use jni::sys::{jint, JNI_ERR, JNI_VERSION_1_6};
 use jni::{
-    objects::{JByteArray, JClass, JObject, JString, JValue},
-    sys::jobject,
-    JNIEnv,
+    objects::{GlobalRef, JByteArray, JClass, JObject, JObjectArray, JString, JValue},
+    signature::{Primitive, ReturnType},
+    JNIEnv, JavaVM,
+};
+use once_cell::sync::OnceCell;
+use p256::{
+    ecdsa::{signature::Keypair, Signature, VerifyingKey},
+    elliptic_curve::sec1::ToEncodedPoint,
 };
-use lazy_static::lazy_static;
-use libc::c_void;
-use p256::{ecdsa::Signature, elliptic_curve::sec1::ToEncodedPoint};
 use paste::paste;
-use std::sync::{Arc, Mutex};
+use rand_core::{OsRng, RngCore};
+use std::sync::Arc;
 use x509_parser::{prelude::FromDer, x509::SubjectPublicKeyInfo};
 
-pub struct AndroidContext(*mut c_void);
+/// Number of local references that is reserved for a single operation
+const LOCAL_FRAME_CAPACITY: i32 = 32;
+
+/// Prefix of the alias of every key, the same as the application tag of keys on iOS
+///
+/// The `AndroidKeyStore` is shared with the other libraries of the application, so without the
+/// prefix their keys could collide with keys of this library, and would be listed and wiped as
+/// keys of this library when no namespace has been configured.
+const ALIAS_PREFIX: &str = "id.animo.secure-env:";
+
+/// Aliases under which the key with `scoped_id` may be stored, in order of preference
+///
+/// Keys that have been created before the prefix was added are stored under `scoped_id` itself.
+/// They are used when no prefixed key exists, so existing keys keep working.
+fn candidate_aliases(scoped_id: &str) -> [String; 2] {
+    [format!("{ALIAS_PREFIX}{scoped_id}"), scoped_id.to_owned()]
+}
+
+/// `JavaVM` that has been provided via `init_with_java_vm` or `init_with_context`
+static DEFAULT_JVM: OnceCell<Arc<Jvm>> = OnceCell::new();
+
+/// Context that has been provided via `init_with_context`
+static DEFAULT_CONTEXT: OnceCell<Arc<AndroidContext>> = OnceCell::new();
+
+/// Application that has been looked up via `android.app.ActivityThread`
+static ACTIVITY_THREAD_CONTEXT: OnceCell<Arc<AndroidContext>> = OnceCell::new();
+
+/// Initialize the library with the `JavaVM` of the process
+///
+/// This, or [`init_with_context`], has to be called once before any keys can be used, unless the
+/// `jni_onload` feature is enabled. A process only has a single `JavaVM`, so only the first call
+/// has an effect and later calls return `Ok(())`.
+pub fn init_with_java_vm(java_vm: JavaVM) -> SecureEnvResult<()> {
+    default_jvm(java_vm).map(|_| ())
+}
+
+/// Initialize the library with the `JavaVM` of the process and an `android.content.Context`
+///
+/// A global reference to the context is kept, so a local reference may be passed in. The context
+/// is used to query the capabilities of the device instead of reflecting on
+/// `android.app.ActivityThread`. Only the first context that is provided is used, also when the
+/// `JavaVM` has already been provided via [`init_with_java_vm`].
+pub fn init_with_context(java_vm: JavaVM, context: &JObject) -> SecureEnvResult<()> {
+    let jvm = default_jvm(java_vm)?;
+
+    DEFAULT_CONTEXT.get_or_try_init(|| AndroidContext::new(jvm, context).map(Arc::new))?;
+
+    Ok(())
+}
+
+fn default_jvm(java_vm: JavaVM) -> SecureEnvResult<&'static Jvm> {
+    DEFAULT_JVM
+        .get_or_try_init(|| Jvm::new(java_vm).map(Arc::new))
+        .map(|jvm| jvm.as_ref())
+}
 
-unsafe impl Send for AndroidContext {}
-unsafe impl Sync for AndroidContext {}
+/// Entry point that is called by the JVM when the library is loaded via `System.loadLibrary`
+///
+/// Only available with the `jni_onload` feature, as an application can only export a single
+/// `JNI_OnLoad`.
+///
+/// # Safety
+///
+/// `vm` must be a valid pointer to the `JavaVM` that loads the library
+#[cfg(feature = "jni_onload")]
+#[no_mangle]
+pub unsafe extern "system" fn JNI_OnLoad(
+    vm: *mut jni::sys::JavaVM,
+    _reserved: *mut std::ffi::c_void,
+) -> jint {
+    let java_vm = match JavaVM::from_raw(vm) {
+        Ok(java_vm) => java_vm,
+        Err(_) => return JNI_ERR,
+    };
 
-lazy_static! {
-    static ref JAVA_VM: Arc<Mutex<Option<jni::JavaVM>>> = Arc::new(Mutex::new(None));
+    match init_with_java_vm(java_vm) {
+        Ok(()) => JNI_VERSION_1_6,
+        Err(_) => JNI_ERR,
+    }
 }
 
 // Entry point that can be used to set the pointer to the jvm. It has to be called manually from a
-// Java environment,
+// Java environment from the `id.animo.SecureEnvironment` class. Prefer `init_with_java_vm` or
+// `init_with_context` when the library is not loaded via that class.
 #[no_mangle]
 pub extern "system" fn Java_id_animo_SecureEnvironment_set_1env<'local>(
-    env: JNIEnv<'local>,
+    mut env: JNIEnv<'local>,
     _class: JClass<'local>,
 ) {
-    let vm = env.get_java_vm().unwrap();
-    *JAVA_VM.lock().unwrap() = Some(vm);
+    let result = env
+        .get_java_vm()
+        .map_err(|e| SecureEnvError::NotInitialized(e.to_string()))
+        .and_then(init_with_java_vm);
+
+    if let Err(e) = result {
+        // If throwing fails as well there is no way left to report the error to the caller
+        let _ = env.throw_new(ILLEGAL_STATE_EXCEPTION_CLS, e.to_string());
+    }
 }
 
 macro_rules! jni_handle_error {
@@ -116,15 +204,6 @@ macro_rules! jni_get_static_field {
     };
 }
 
-macro_rules! jni_new_object {
-    ($env:expr, $cls:ident, $args:expr, $err:ident) => {
-        paste! {{
-            $env.new_object([<$cls _CLS>], [<$cls _CTOR_SIG>], $args)
-                .map_err(|e| jni_handle_error!($env, $err, e))
-        }}
-    };
-}
-
 macro_rules! jni_find_class {
     ($env:expr, $cls:ident, $err:ident) => {
         paste! {{
@@ -134,112 +213,487 @@ macro_rules! jni_find_class {
     };
 }
 
+macro_rules! jni_return_type {
+    (l) => {
+        ReturnType::Object
+    };
+    (z) => {
+        ReturnType::Primitive(Primitive::Boolean)
+    };
+    (i) => {
+        ReturnType::Primitive(Primitive::Int)
+    };
+    (v) => {
+        ReturnType::Primitive(Primitive::Void)
+    };
+}
+
+// The `*_by_id` macros call methods via the ids in `JniCache`. The arguments must match the
+// signature of the method, which is not checked by the JVM.
+
+macro_rules! jni_call_method_by_id {
+    ($env:expr, $obj:expr, $method_id:expr, [$($arg:expr),*], $ret_typ:ident, $err:ident) => {
+        // SAFETY: the method id has been resolved from the class of `$obj` and the arguments
+        // match its signature in `jni_tokens`
+        unsafe {
+            $env.call_method_unchecked(
+                $obj,
+                $method_id,
+                jni_return_type!($ret_typ),
+                &[$(JValue::from($arg).as_jni()),*],
+            )
+        }
+        .and_then(|v| v.$ret_typ())
+        .map_err(|e| jni_handle_error!($env, $err, e))
+    };
+
+    ($env:expr, $obj:expr, $method_id:expr, $ret_typ:ident, $err:ident) => {
+        jni_call_method_by_id!($env, $obj, $method_id, [], $ret_typ, $err)
+    };
+}
+
+macro_rules! jni_call_static_method_by_id {
+    ($env:expr, $cls:expr, $method_id:expr, [$($arg:expr),*], $ret_typ:ident, $err:ident) => {
+        // SAFETY: the method id has been resolved from `$cls` and the arguments match its
+        // signature in `jni_tokens`
+        unsafe {
+            $env.call_static_method_unchecked(
+                $cls,
+                $method_id,
+                jni_return_type!($ret_typ),
+                &[$(JValue::from($arg).as_jni()),*],
+            )
+        }
+        .and_then(|v| v.$ret_typ())
+        .map_err(|e| jni_handle_error!($env, $err, e))
+    };
+}
+
+macro_rules! jni_new_object_by_id {
+    ($env:expr, $cls:expr, $ctor_id:expr, [$($arg:expr),*], $err:ident) => {
+        // SAFETY: the constructor id has been resolved from `$cls` and the arguments match its
+        // signature in `jni_tokens`
+        unsafe {
+            $env.new_object_unchecked($cls, $ctor_id, &[$(JValue::from($arg).as_jni()),*])
+        }
+        .map_err(|e| jni_handle_error!($env, $err, e))
+    };
+}
+
+mod jni_cache;
+use jni_cache::JniCache;
+
+/// `JavaVM` together with the lookups that have been resolved on it
 #[derive(Debug)]
-pub struct SecureEnvironment;
+pub(crate) struct Jvm {
+    java_vm: JavaVM,
+    cache: JniCache,
+}
 
-impl SecureEnvironmentOps<Key> for SecureEnvironment {
-    fn generate_keypair(id: impl Into<String>, backed_by_biometrics: bool) -> SecureEnvResult<Key> {
-        let jvm = JAVA_VM.lock().map_err(|_| {
-            SecureEnvError::UnableToAttachJVMToThread("Could not acquire lock on JVM".to_owned())
-        })?;
+impl Jvm {
+    fn new(java_vm: JavaVM) -> SecureEnvResult<Self> {
+        let cache = {
+            let mut env = java_vm
+                .attach_current_thread_as_daemon()
+                .map_err(|e| SecureEnvError::UnableToAttachJVMToThread(e.to_string()))?;
+
+            env.with_local_frame(LOCAL_FRAME_CAPACITY, |env| {
+                Ok::<_, jni::errors::Error>(JniCache::new(env))
+            })
+            .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))??
+        };
+
+        Ok(Self { java_vm, cache })
+    }
+}
+
+/// Keystore support of the device, as reported by the `PackageManager`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeystoreSupport {
+    StrongBox,
+    TrustedExecutionEnvironment,
+    Insufficient,
+}
+
+/// `android.content.Context` together with the keystore support that has been queried through it
+///
+/// The keystore support of a device does not change while the process is running, so it is only
+/// queried once per context.
+#[derive(Debug)]
+pub(crate) struct AndroidContext {
+    object: GlobalRef,
+    keystore_support: OnceCell<KeystoreSupport>,
+}
+
+impl AndroidContext {
+    fn new(jvm: &Jvm, context: &JObject) -> SecureEnvResult<Self> {
+        let object = jvm
+            .java_vm
+            .attach_current_thread_as_daemon()
+            .and_then(|env| env.new_global_ref(context))
+            .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
 
-        let jvm = jvm
-            .as_ref()
-            .ok_or(SecureEnvError::UnableToAttachJVMToThread(
-                "JVM has not been set".to_owned(),
-            ))?;
+        Ok(Self {
+            object,
+            keystore_support: OnceCell::new(),
+        })
+    }
+
+    fn keystore_support(
+        &self,
+        env: &mut JNIEnv,
+        cache: &JniCache,
+    ) -> SecureEnvResult<KeystoreSupport> {
+        self.keystore_support
+            .get_or_try_init(|| {
+                let package_manager = jni_call_method_by_id!(
+                    env,
+                    &self.object,
+                    cache.context_get_package_manager,
+                    l,
+                    UnableToGenerateKey
+                )?;
+
+                // This has not been documented anywhere that I could find.
+                // After some debugging with emulators and multiple real device
+                // (some with a Secure Element (Pixel 6a) and some without (OnePlus Nord))
+                // 300 seems to be the correct cut-off.
+                let required_hardware_keystore_version = 300;
+
+                let has_strongbox_support = jni_call_method_by_id!(
+                    env,
+                    &package_manager,
+                    cache.package_manager_has_system_feature,
+                    [
+                        &cache.hardware_keystore_feature,
+                        required_hardware_keystore_version
+                    ],
+                    z,
+                    UnableToGenerateKey
+                )?;
+
+                if has_strongbox_support {
+                    return Ok(KeystoreSupport::StrongBox);
+                }
+
+                // 41: Hardware enforcement of device-unlocked keys
+                // TODO: check the exact meaning behind this
+                //       Maybe there is number that corrolates to TEE?
+                //       This seems to work best with testing
+                let required_device_unlocked_keystore_version = 41;
+
+                let has_device_unlocked_keystore_support = jni_call_method_by_id!(
+                    env,
+                    &package_manager,
+                    cache.package_manager_has_system_feature,
+                    [
+                        &cache.hardware_keystore_feature,
+                        required_device_unlocked_keystore_version
+                    ],
+                    z,
+                    UnableToGenerateKey
+                )?;
+
+                if has_device_unlocked_keystore_support {
+                    Ok(KeystoreSupport::TrustedExecutionEnvironment)
+                } else {
+                    Ok(KeystoreSupport::Insufficient)
+                }
+            })
+            .copied()
+    }
+}
+
+impl PlatformHandles {
+    /// Use the provided `JavaVM` instead of the one that has been set via [`init_with_java_vm`]
+    ///
+    /// The classes and methods that are used by the keystore operations are looked up once, here.
+    pub fn with_java_vm(java_vm: JavaVM) -> SecureEnvResult<Self> {
+        Ok(Self {
+            jvm: Some(Arc::new(Jvm::new(java_vm)?)),
+            context: None,
+        })
+    }
+
+    /// Use the provided `JavaVM` and `android.content.Context` instead of the ones that have been
+    /// set via [`init_with_context`]
+    ///
+    /// A global reference to the context is kept, so a local reference may be passed in.
+    pub fn with_context(java_vm: JavaVM, context: &JObject) -> SecureEnvResult<Self> {
+        let jvm = Jvm::new(java_vm)?;
+        let context = AndroidContext::new(&jvm, context)?;
+
+        Ok(Self {
+            jvm: Some(Arc::new(jvm)),
+            context: Some(Arc::new(context)),
+        })
+    }
+
+    fn jvm(&self) -> SecureEnvResult<&Jvm> {
+        self.jvm
+            .as_deref()
+            .or_else(|| DEFAULT_JVM.get().map(|jvm| jvm.as_ref()))
+            .ok_or(SecureEnvError::NotInitialized(
+                "No JavaVM has been provided. Call `init_with_java_vm` or `init_with_context` first"
+                    .to_owned(),
+            ))
+    }
+
+    /// Run `f` with the JNI environment of the current thread and the cached lookups
+    ///
+    /// The thread is attached to the JVM when necessary. Local references that are created by `f`
+    /// are released when it returns, so anything that outlives `f` must be a global reference.
+    fn with_env<T>(
+        &self,
+        f: impl FnOnce(&mut JNIEnv, &JniCache) -> SecureEnvResult<T>,
+    ) -> SecureEnvResult<T> {
+        let jvm = self.jvm()?;
 
         let mut env = jvm
+            .java_vm
             .attach_current_thread_as_daemon()
             .map_err(|e| SecureEnvError::UnableToAttachJVMToThread(e.to_string()))?;
 
-        let id = id.into();
+        env.with_local_frame(LOCAL_FRAME_CAPACITY, |env| {
+            Ok::<_, jni::errors::Error>(f(env, &jvm.cache))
+        })
+        .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?
+    }
 
-        let id = env
-            .new_string(id)
-            .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
+    /// Get the application context of the process
+    ///
+    /// A context that has been provided by the integrator is preferred. Only when none has been
+    /// provided, it is looked up via `android.app.ActivityThread`. This is a hidden API that is
+    /// not accessible from every process, e.g. isolated processes.
+    fn application_context(&self, env: &mut JNIEnv) -> SecureEnvResult<&AndroidContext> {
+        if let Some(context) = self
+            .context
+            .as_deref()
+            .or(DEFAULT_CONTEXT.get().map(Arc::as_ref))
+        {
+            return Ok(context);
+        }
 
-        let purpose_sign = jni_get_static_field!(
-            env,
-            KEY_PROPERTIES_CLS,
-            KEY_PROPERTIES_PURPOSE_SIGN,
-            i,
-            UnableToGenerateKey
-        )?;
+        ACTIVITY_THREAD_CONTEXT
+            .get_or_try_init(|| {
+                let current_activity_thread = jni_call_static_method!(
+                    env,
+                    ACTIVITY_THREAD,
+                    ACTIVITY_THREAD_GET_CURRENT_ACTIVITY_THREAD,
+                    l,
+                    NotInitialized
+                )?;
+
+                if current_activity_thread.is_null() {
+                    return Err(SecureEnvError::NotInitialized(
+                        "No context has been provided and the current activity thread is not available. Call `init_with_context` first"
+                            .to_owned(),
+                    ));
+                }
+
+                let application = jni_call_method!(
+                    env,
+                    current_activity_thread,
+                    ACTIVITY_THREAD_GET_APPLICATION,
+                    l,
+                    NotInitialized
+                )?;
+
+                let object = env
+                    .new_global_ref(application)
+                    .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
+
+                Ok(Arc::new(AndroidContext {
+                    object,
+                    keystore_support: OnceCell::new(),
+                }))
+            })
+            .map(|context| context.as_ref())
+    }
+}
 
-        let builder = jni_new_object!(
-            env,
-            KEY_GEN_PARAMETER_SPEC_BUILDER,
-            &[(&id).into(), JValue::from(purpose_sign)],
-            UnableToGenerateKey
-        )?;
+impl SecureEnvironment {
+    /// Create and store a keypair in the `AndroidKeyStore`
+    pub fn create_keypair_with_options(
+        &self,
+        id: impl IntoKeyId,
+        options: &KeyGenerationOptions,
+    ) -> SecureEnvResult<Key> {
+        let key_id = id.into_key_id()?;
+        let scoped_id = self.config.scoped_id(&key_id)?;
+        options.validate_usage_limits()?;
+
+        let [alias, legacy_alias] = candidate_aliases(&scoped_id);
+
+        let key = self.config.platform.with_env(|env, cache| {
+            // The `AndroidKeyStore` silently replaces an existing key with the same alias
+            let key_store = key_store(env, cache)?;
+            for alias in [&alias, &legacy_alias] {
+                let alias = env
+                    .new_string(alias)
+                    .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
+
+                let exists = jni_call_method_by_id!(
+                    env,
+                    key_store,
+                    cache.key_store_contains_alias,
+                    [&alias],
+                    z,
+                    UnableToGenerateKey
+                )?;
+
+                if exists {
+                    return Err(SecureEnvError::UnableToGenerateKey(format!(
+                        "Key with id: '{scoped_id}' already exists."
+                    )));
+                }
+            }
+
+            let id = env
+                .new_string(&alias)
+                .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
+
+            let object = self.generate_keypair(env, cache, &id, options)?;
+            let verifying_key = verifying_key(env, cache, &object)?;
+
+            Ok(Key {
+                object,
+                alias: alias.clone(),
+                platform: self.config.platform.clone(),
+                metadata: self
+                    .config
+                    .metadata_handle(Some(&key_id))
+                    .with_usage_limits(options),
+                has_usage_limit: options.max_usage_count.is_some(),
+                verifying_key,
+                _ephemeral: None,
+            })
+        })?;
+
+        self.config.store_created_metadata(&key_id, &key, options)?;
 
-        let kp_cls = jni_find_class!(env, KEY_PROPERTIES, UnableToGenerateKey)?;
+        Ok(key)
+    }
+
+    /// Create a keypair in the `AndroidKeyStore` that can not be loaded by id
+    ///
+    /// The `AndroidKeyStore` can only generate keys under an alias, so the key is stored under a
+    /// random alias that is deleted when the last clone of the key is dropped.
+    pub fn generate_ephemeral_keypair(
+        &self,
+        options: &KeyGenerationOptions,
+    ) -> SecureEnvResult<Key> {
+        let mut random = [0u8; 16];
+        OsRng.fill_bytes(&mut random);
+        let random: String = random.iter().map(|b| format!("{b:02x}")).collect();
+
+        let alias = format!("{}{random}", self.ephemeral_alias_prefix()?);
+        options.validate_usage_limits()?;
+
+        self.config.platform.with_env(|env, cache| {
+            let id = env
+                .new_string(&alias)
+                .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
+
+            let object = self.generate_keypair(env, cache, &id, options)?;
+            let ephemeral = Arc::new(EphemeralAlias {
+                alias: alias.clone(),
+                platform: self.config.platform.clone(),
+            });
+            let verifying_key = verifying_key(env, cache, &object)?;
+
+            Ok(Key {
+                object,
+                alias,
+                platform: self.config.platform.clone(),
+                metadata: self.config.metadata_handle(None).with_usage_limits(options),
+                has_usage_limit: options.max_usage_count.is_some(),
+                verifying_key,
+                _ephemeral: Some(ephemeral),
+            })
+        })
+    }
 
-        let digest_sha256 = jni_get_static_field!(
+    /// Prefix of the aliases of the ephemeral keys of the namespace
+    ///
+    /// The `:` after `ephemeral` can not be part of an id, so the alias never collides with the
+    /// alias of a stored key and is never listed as one.
+    fn ephemeral_alias_prefix(&self) -> SecureEnvResult<String> {
+        Ok(format!(
+            "{ALIAS_PREFIX}{}:",
+            self.config.scoped_id(&KeyId::new("ephemeral")?)?
+        ))
+    }
+
+    /// Generate a keypair under the alias `id` and return a global reference to the
+    /// `java.security.KeyPair`
+    fn generate_keypair(
+        &self,
+        env: &mut JNIEnv,
+        cache: &JniCache,
+        id: &JString,
+        options: &KeyGenerationOptions,
+    ) -> SecureEnvResult<GlobalRef> {
+        let builder = jni_new_object_by_id!(
             env,
-            &kp_cls,
-            KEY_PROPERTIES_DIGEST_SHA256,
-            l,
+            &cache.key_gen_parameter_spec_builder_cls,
+            cache.key_gen_parameter_spec_builder_ctor,
+            [id, cache.purpose_sign],
             UnableToGenerateKey
         )?;
 
-        let string_cls = jni_find_class!(env, STRING, UnableToGenerateKey)?;
-
-        let args = env
-            .new_object_array(1, string_cls, &digest_sha256)
-            .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
-
-        let builder = jni_call_method!(
+        let builder = jni_call_method_by_id!(
             env,
-            builder,
-            KEY_GEN_PARAMETER_SPEC_BUILDER_SET_DIGESTS,
-            &[(&args).into()],
+            &builder,
+            cache.key_gen_parameter_spec_builder_set_digests,
+            [&cache.digests],
             l,
             UnableToGenerateKey
         )?;
 
-        let builder = jni_call_method!(
+        let builder = jni_call_method_by_id!(
             env,
-            builder,
-            KEY_GEN_PARAMETER_SPEC_BUILDER_SET_KEY_SIZE,
-            &[JValue::from(256)],
+            &builder,
+            cache.key_gen_parameter_spec_builder_set_key_size,
+            [256],
             l,
             UnableToGenerateKey
         )?;
 
-        let builder = if backed_by_biometrics {
-            let auth_biometric_strong = jni_get_static_field!(
-                env,
-                &kp_cls,
-                KEY_PROPERTIES_AUTH_BIOMETRIC_STRONG,
-                i,
-                UnableToGenerateKey
-            )?;
+        let builder = if options.backed_by_biometrics {
+            let (Some(set_user_authentication_parameters), Some(auth_biometric_strong)) = (
+                cache.key_gen_parameter_spec_builder_set_user_authentication_parameters,
+                cache.auth_biometric_strong,
+            ) else {
+                return Err(SecureEnvError::UnableToGenerateKey(
+                    "A key that is backed by biometrics requires Android 11 (API level 30)"
+                        .to_owned(),
+                ));
+            };
 
-            let builder = jni_call_method!(
+            let builder = jni_call_method_by_id!(
                 env,
-                builder,
-                KEY_GEN_PARAMETER_SPEC_BUILDER_SET_USER_AUTHENTICATION_REQUIRED,
-                &[JValue::Bool(1)],
+                &builder,
+                cache.key_gen_parameter_spec_builder_set_user_authentication_required,
+                [true],
                 l,
                 UnableToGenerateKey
             )?;
 
-            let builder = jni_call_method!(
+            let builder = jni_call_method_by_id!(
                 env,
-                builder,
-                KEY_GEN_PARAMETER_SPEC_BUILDER_SET_INVALIDATED_BY_BIOMETRIC_ENROLLMENT,
-                &[JValue::Bool(1)],
+                &builder,
+                cache.key_gen_parameter_spec_builder_set_invalidated_by_biometric_enrollment,
+                [true],
                 l,
                 UnableToGenerateKey
             )?;
 
-            jni_call_method!(
+            jni_call_method_by_id!(
                 env,
-                builder,
-                KEY_GEN_PARAMETER_SPEC_BUILDER_SET_USER_AUTHENTICATION_PARAMETERS,
-                &[JValue::from(0), auth_biometric_strong.into()],
+                &builder,
+                set_user_authentication_parameters,
+                [0, auth_biometric_strong],
                 l,
                 UnableToGenerateKey
             )?
@@ -247,302 +701,674 @@ impl SecureEnvironmentOps<Key> for SecureEnvironment {
             builder
         };
 
-        let current_activity_thread = jni_call_static_method!(
-            env,
-            ACTIVITY_THREAD,
-            ACTIVITY_THREAD_GET_CURRENT_ACTIVITY_THREAD,
-            l,
-            UnableToGenerateKey
-        )?;
-        let ctx = jni_call_method!(
-            env,
-            current_activity_thread,
-            ACTIVITY_THREAD_GET_APPLICATION,
-            l,
-            UnableToGenerateKey
-        )?;
-
-        let package_manager = jni_call_method!(
-            env,
-            ctx,
-            CONTEXT_GET_PACKAGE_MANAGER,
-            l,
-            UnableToGenerateKey
-        )?;
-
-        let hardware_keystore_token = env
-            .new_string("android.hardware.hardware_keystore")
-            .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
-
-        // This has not been documented anywhere that I could find.
-        // After some debugging with emulators and multiple real device
-        // (some with a Secure Element (Pixel 6a) and some without (OnePlus Nord))
-        // 300 seems to be the correct cut-off.
-        let required_hardware_keystore_version = 300;
+        // The `AndroidKeyStore` enforces the validity period and the usage limit itself
+        let builder = match options.valid_from {
+            Some(valid_from) => {
+                let date = java_date(env, cache, valid_from)?;
+
+                jni_call_method_by_id!(
+                    env,
+                    &builder,
+                    cache.key_gen_parameter_spec_builder_set_key_validity_start,
+                    [&date],
+                    l,
+                    UnableToGenerateKey
+                )?
+            }
+            None => builder,
+        };
 
-        let has_strongbox_support = jni_call_method!(
-            env,
-            &package_manager,
-            PACKAGE_MANAGER_HAS_SYSTEM_FEATURE,
-            &[
-                (&hardware_keystore_token).into(),
-                required_hardware_keystore_version.into()
-            ],
-            z,
-            UnableToGenerateKey
-        )?;
+        let builder = match options.valid_until {
+            Some(valid_until) => {
+                let date = java_date(env, cache, valid_until)?;
+
+                jni_call_method_by_id!(
+                    env,
+                    &builder,
+                    cache.key_gen_parameter_spec_builder_set_key_validity_end,
+                    [&date],
+                    l,
+                    UnableToGenerateKey
+                )?
+            }
+            None => builder,
+        };
 
-        let builder = if has_strongbox_support {
-            jni_call_method!(
-                env,
-                &builder,
-                KEY_GEN_PARAMETER_SPEC_BUILDER_SET_IS_STRONG_BOX_BACKED,
-                &[JValue::Bool(1)],
-                l,
-                UnableToGenerateKey
-            )?
-        } else {
-            // 41: Hardware enforcement of device-unlocked keys
-            // TODO: check the exact meaning behind this
-            //       Maybe there is number that corrolates to TEE?
-            //       This seems to work best with testing
-            let required_device_unlocked_keystore_version = 41;
+        let builder = match options.max_usage_count {
+            Some(max_usage_count) => {
+                let set_max_usage_count = cache
+                    .key_gen_parameter_spec_builder_set_max_usage_count
+                    .ok_or(SecureEnvError::UnableToGenerateKey(
+                        "A usage limit requires Android 12 (API level 31)".to_owned(),
+                    ))?;
+
+                let max_usage_count = i32::try_from(max_usage_count)
+                    .map_err(|e| SecureEnvError::UnableToGenerateKey(e.to_string()))?;
+
+                jni_call_method_by_id!(
+                    env,
+                    &builder,
+                    set_max_usage_count,
+                    [max_usage_count],
+                    l,
+                    UnableToGenerateKey
+                )?
+            }
+            None => builder,
+        };
 
-            let has_device_unlocked_keystore_support = jni_call_method!(
-                env,
-                &package_manager,
-                PACKAGE_MANAGER_HAS_SYSTEM_FEATURE,
-                &[
-                    (&hardware_keystore_token).into(),
-                    required_device_unlocked_keystore_version.into()
-                ],
-                z,
-                UnableToGenerateKey
-            )?;
+        let builder = match &options.attestation_challenge {
+            Some(attestation_challenge) => {
+                let set_attestation_challenge = cache
+                    .key_gen_parameter_spec_builder_set_attestation_challenge
+                    .ok_or(SecureEnvError::UnableToGenerateKey(
+                        "Key attestation requires Android 7 (API level 24)".to_owned(),
+                    ))?;
+
+                let attestation_challenge = env
+                    .byte_array_from_slice(attestation_challenge)
+                    .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
+
+                jni_call_method_by_id!(
+                    env,
+                    &builder,
+                    set_attestation_challenge,
+                    [&attestation_challenge],
+                    l,
+                    UnableToGenerateKey
+                )?
+            }
+            None => builder,
+        };
 
-            if !has_device_unlocked_keystore_support {
+        let keystore_support = self
+            .config
+            .platform
+            .application_context(env)?
+            .keystore_support(env, cache)?;
+
+        let builder = match keystore_support {
+            KeystoreSupport::StrongBox => {
+                let set_is_strong_box_backed = cache
+                    .key_gen_parameter_spec_builder_set_is_strong_box_backed
+                    .ok_or(SecureEnvError::UnableToGenerateKey(
+                        "A key that is backed by StrongBox requires Android 9 (API level 28)"
+                            .to_owned(),
+                    ))?;
+
+                jni_call_method_by_id!(
+                    env,
+                    &builder,
+                    set_is_strong_box_backed,
+                    [true],
+                    l,
+                    UnableToGenerateKey
+                )?
+            }
+            KeystoreSupport::TrustedExecutionEnvironment => builder,
+            KeystoreSupport::Insufficient => {
                 return Err(SecureEnvError::UnableToGenerateKey(
                     "Unable to generate keypair. Device has insufficient keystore support"
                         .to_owned(),
-                ));
+                ))
             }
-
-            builder
         };
 
-        let algorithm = env
-            .new_string(EC_ALGORITHM)
-            .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
-
-        let provider = env
-            .new_string(ANDROID_KEY_STORE_PROVIDER)
-            .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
-
-        let key_pair_generator = jni_call_static_method!(
+        let key_pair_generator = jni_call_static_method_by_id!(
             env,
-            KEY_PAIR_GENERATOR,
-            KEY_PAIR_GENERATOR_GET_INSTANCE,
-            &[(&algorithm).into(), (&provider).into()],
+            &cache.key_pair_generator_cls,
+            cache.key_pair_generator_get_instance,
+            [&cache.ec_algorithm, &cache.android_key_store_provider],
             l,
             UnableToGenerateKey
         )?;
 
-        let params = jni_call_method!(
+        let params = jni_call_method_by_id!(
             env,
             &builder,
-            KEY_GEN_PARAMETER_SPEC_BUILDER_BUILD,
+            cache.key_gen_parameter_spec_builder_build,
             l,
             UnableToGenerateKey
         )?;
 
-        jni_call_method!(
+        jni_call_method_by_id!(
             env,
             &key_pair_generator,
-            KEY_PAIR_GENERATOR_INITIALIZE,
-            &[(&params).into()],
+            cache.key_pair_generator_initialize,
+            [&params],
             v,
             UnableToGenerateKey
         )?;
 
-        let key = jni_call_method!(
+        let key = jni_call_method_by_id!(
             env,
             &key_pair_generator,
-            KEY_PAIR_GENERATOR_GENERATE_KEY_PAIR,
+            cache.key_pair_generator_generate_key_pair,
             l,
             UnableToGenerateKey
         )?;
 
-        Ok(Key(Arc::new(Mutex::new(*key))))
+        env.new_global_ref(key)
+            .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))
     }
 
-    fn get_keypair_by_id(id: impl Into<String>) -> SecureEnvResult<Key> {
-        let jvm = JAVA_VM.lock().map_err(|_| {
-            SecureEnvError::UnableToAttachJVMToThread("Could not acquire lock on JVM".to_owned())
-        })?;
+    /// Get a keypair that has been stored in the `AndroidKeyStore`
+    pub fn load_keypair(&self, id: impl IntoKeyId) -> SecureEnvResult<Key> {
+        let key_id = id.into_key_id()?;
+        let scoped_id = self.config.scoped_id(&key_id)?;
+
+        self.config.platform.with_env(|env, cache| {
+            let key_store = key_store(env, cache)?;
+
+            let mut stored_entry = None;
+            for alias in candidate_aliases(&scoped_id) {
+                let id = env
+                    .new_string(&alias)
+                    .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
+
+                let entry = jni_call_method_by_id!(
+                    env,
+                    key_store,
+                    cache.key_store_get_entry,
+                    [&id, &JObject::null()],
+                    l,
+                    UnableToGetKeyPairById
+                )?;
+
+                // The methods are called without null checks, so a missing entry is handled here
+                if !entry.is_null() {
+                    stored_entry = Some((alias, entry));
+                    break;
+                }
+            }
 
-        let jvm = jvm
-            .as_ref()
-            .ok_or(SecureEnvError::UnableToAttachJVMToThread(
-                "JVM has not been set".to_owned(),
-            ))?;
+            let Some((alias, entry)) = stored_entry else {
+                return Err(SecureEnvError::UnableToGetKeyPairById(format!(
+                    "Key reference with id: '{scoped_id}' not found."
+                )));
+            };
 
-        let mut env = jvm
-            .attach_current_thread_as_daemon()
-            .map_err(|e| SecureEnvError::UnableToAttachJVMToThread(e.to_string()))?;
+            let private_key = jni_call_method_by_id!(
+                env,
+                &entry,
+                cache.key_store_entry_get_private_key,
+                l,
+                UnableToGetKeyPairById
+            )?;
 
-        let provider = env
-            .new_string(ANDROID_KEY_STORE_PROVIDER)
-            .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
+            let has_usage_limit = has_usage_limit(env, cache, &private_key)?;
 
-        let id = id.into();
-        let id = env
-            .new_string(id)
-            .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
+            let certificate = jni_call_method_by_id!(
+                env,
+                &entry,
+                cache.key_store_entry_get_certificate,
+                l,
+                UnableToGetKeyPairById
+            )?;
+
+            let public_key = jni_call_method_by_id!(
+                env,
+                &certificate,
+                cache.certificate_get_public_key,
+                l,
+                UnableToGetKeyPairById
+            )?;
+
+            let key_pair = jni_new_object_by_id!(
+                env,
+                &cache.key_pair_cls,
+                cache.key_pair_ctor,
+                [&public_key, &private_key],
+                UnableToGetKeyPairById
+            )?;
+
+            let object = env
+                .new_global_ref(key_pair)
+                .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
+            let verifying_key = verifying_key(env, cache, &object)?;
+
+            Ok(Key {
+                object,
+                alias,
+                platform: self.config.platform.clone(),
+                metadata: self.config.metadata_handle(Some(&key_id)),
+                has_usage_limit,
+                verifying_key,
+                _ephemeral: None,
+            })
+        })
+    }
+
+    /// Get the keypair with the SEC1 encoded public key `public_key`
+    ///
+    /// The `AndroidKeyStore` can not be searched by public key, so the thumbprints in the
+    /// [`crate::MetadataStore`] of the configuration are used as a local index.
+    pub fn find_keypair_by_public_key(&self, public_key: &[u8]) -> SecureEnvResult<Option<Key>> {
+        self.find_keypair_by_thumbprint(&jwk_thumbprint(public_key)?)
+    }
+
+    /// Get the keypair whose public key has the JWK thumbprint `thumbprint`, via the index in the
+    /// [`crate::MetadataStore`] of the configuration
+    pub fn find_keypair_by_thumbprint(&self, thumbprint: &str) -> SecureEnvResult<Option<Key>> {
+        self.config
+            .find_key_by_thumbprint(thumbprint)?
+            .map(|id| self.load_keypair(id))
+            .transpose()
+    }
+
+    /// Ids of every keypair that is stored in the `AndroidKeyStore` in the namespace
+    ///
+    /// Keys are recognized by the prefix of their alias. Legacy keys without the prefix are only
+    /// listed with a namespace, as the `AndroidKeyStore` is shared with the other libraries of the
+    /// application and an alias without a namespace can belong to any of them.
+    pub fn list_keys(&self) -> SecureEnvResult<Vec<KeyId>> {
+        let mut ids: Vec<KeyId> = self
+            .aliases()?
+            .iter()
+            .filter_map(|alias| match alias.strip_prefix(ALIAS_PREFIX) {
+                Some(scoped_id) => self.config.unscoped_id(scoped_id),
+                None if self.config.namespace.is_some() => self.config.unscoped_id(alias),
+                None => None,
+            })
+            .collect();
+
+        // A legacy key and a prefixed key may share an id
+        ids.sort();
+        ids.dedup();
+
+        Ok(ids)
+    }
+
+    /// Delete every keypair in the configured namespace, together with its metadata, see
+    /// [`crate::KeyBackend::wipe`]
+    ///
+    /// Only keys that are listed by [`SecureEnvironment::list_keys`] are deleted, so the keys of
+    /// other libraries in the `AndroidKeyStore` are kept, also without a namespace.
+    ///
+    /// The ephemeral keys of the namespace are deleted as well, including keys that are still in
+    /// use and keys that have been left behind when the process was terminated. Ephemeral keys
+    /// that can not be deleted are reported in [`WipeReport::ephemeral_failures`].
+    pub fn wipe(&self) -> SecureEnvResult<WipeReport> {
+        let ephemeral_alias_prefix = self.ephemeral_alias_prefix()?;
+        let ephemeral_aliases: Vec<String> = self
+            .aliases()?
+            .into_iter()
+            .filter(|alias| alias.starts_with(&ephemeral_alias_prefix))
+            .collect();
+
+        let mut report = wipe_keys(self)?;
+
+        for alias in ephemeral_aliases {
+            if let Err(error) = delete_alias(&self.config.platform, &alias) {
+                report
+                    .ephemeral_failures
+                    .push(EphemeralWipeFailure { alias, error });
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Every alias in the `AndroidKeyStore`, of every namespace
+    fn aliases(&self) -> SecureEnvResult<Vec<String>> {
+        self.config.platform.with_env(|env, cache| {
+            let key_store = key_store(env, cache)?;
+
+            let aliases = jni_call_method_by_id!(
+                env,
+                key_store,
+                cache.key_store_aliases,
+                l,
+                UnableToListKeys
+            )?;
+
+            let mut result = vec![];
+
+            while jni_call_method_by_id!(
+                env,
+                &aliases,
+                cache.enumeration_has_more_elements,
+                z,
+                UnableToListKeys
+            )? {
+                let alias = JString::from(jni_call_method_by_id!(
+                    env,
+                    &aliases,
+                    cache.enumeration_next_element,
+                    l,
+                    UnableToListKeys
+                )?);
+
+                let value: String = env
+                    .get_string(&alias)
+                    .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?
+                    .into();
+
+                // The local frame only has room for a few references, the keystore may contain
+                // many aliases
+                env.delete_local_ref(alias)
+                    .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
+
+                result.push(value);
+            }
+
+            Ok(result)
+        })
+    }
+
+    /// Delete a keypair from the `AndroidKeyStore`, together with its metadata
+    pub fn delete_keypair(&self, id: impl IntoKeyId) -> SecureEnvResult<()> {
+        let key_id = id.into_key_id()?;
+        let scoped_id = self.config.scoped_id(&key_id)?;
+
+        self.config.platform.with_env(|env, cache| {
+            let key_store = key_store(env, cache)?;
+
+            for alias in candidate_aliases(&scoped_id) {
+                let id = env
+                    .new_string(&alias)
+                    .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
+
+                // Deleting an alias that does not exist is not an error for the `AndroidKeyStore`
+                let exists = jni_call_method_by_id!(
+                    env,
+                    key_store,
+                    cache.key_store_contains_alias,
+                    [&id],
+                    z,
+                    UnableToDeleteKey
+                )?;
+
+                if exists {
+                    return jni_call_method_by_id!(
+                        env,
+                        key_store,
+                        cache.key_store_delete_entry,
+                        [&id],
+                        v,
+                        UnableToDeleteKey
+                    );
+                }
+            }
+
+            Err(SecureEnvError::UnableToDeleteKey(format!(
+                "Key reference with id: '{scoped_id}' not found."
+            )))
+        })?;
 
-        let key_store = jni_call_static_method!(
+        self.config
+            .remove_metadata(&key_id)
+            .map_err(|e| SecureEnvError::UnableToDeleteKey(e.to_string()))
+    }
+}
+
+/// Get the loaded `AndroidKeyStore`, which is created on first use
+fn key_store<'a>(env: &mut JNIEnv, cache: &'a JniCache) -> SecureEnvResult<&'a GlobalRef> {
+    cache.key_store.get_or_try_init(|| {
+        let key_store = jni_call_static_method_by_id!(
             env,
-            KEY_STORE,
-            KEY_STORE_GET_INSTANCE,
-            &[(&provider).into()],
+            &cache.key_store_cls,
+            cache.key_store_get_instance,
+            [&cache.android_key_store_provider],
             l,
             UnableToGetKeyPairById
         )?;
 
-        jni_call_method!(
+        jni_call_method_by_id!(
             env,
             &key_store,
-            KEY_STORE_LOAD,
-            &[(&JObject::null()).into()],
+            cache.key_store_load,
+            [&JObject::null()],
             v,
             UnableToGetKeyPairById
         )?;
 
-        let entry = jni_call_method!(
-            env,
-            &key_store,
-            KEY_STORE_GET_ENTRY,
-            &[(&id).into(), (&JObject::null()).into()],
-            l,
-            UnableToGetKeyPairById
-        )?;
+        env.new_global_ref(key_store)
+            .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))
+    })
+}
 
-        let private_key = jni_call_method!(
-            env,
-            &entry,
-            KEY_STORE_ENTRY_GET_PRIVATE_KEY,
-            l,
-            UnableToGetKeyPairById
-        )?;
+/// Whether a private key of the `AndroidKeyStore` has a usage limit, according to its `KeyInfo`
+///
+/// The limit is read from the keystore instead of the metadata, so it is known for keys that are
+/// loaded without a [`crate::MetadataStore`] as well.
+fn has_usage_limit(
+    env: &mut JNIEnv,
+    cache: &JniCache,
+    private_key: &JObject,
+) -> SecureEnvResult<bool> {
+    // Usage limits are only supported since Android 12 (API level 31)
+    let Some(get_remaining_usage_count) = cache.key_info_get_remaining_usage_count else {
+        return Ok(false);
+    };
 
-        let certificate = jni_call_method!(
-            env,
-            &entry,
-            KEY_STORE_ENTRY_GET_CERTIFICATE,
-            l,
-            UnableToGetKeyPairById
-        )?;
+    let key_factory = jni_call_static_method_by_id!(
+        env,
+        &cache.key_factory_cls,
+        cache.key_factory_get_instance,
+        [&cache.ec_algorithm, &cache.android_key_store_provider],
+        l,
+        UnableToGetKeyPairById
+    )?;
+
+    let key_info = jni_call_method_by_id!(
+        env,
+        &key_factory,
+        cache.key_factory_get_key_spec,
+        [private_key, &cache.key_info_cls],
+        l,
+        UnableToGetKeyPairById
+    )?;
+
+    let remaining_usage_count = jni_call_method_by_id!(
+        env,
+        &key_info,
+        get_remaining_usage_count,
+        i,
+        UnableToGetKeyPairById
+    )?;
+
+    // `KeyProperties.UNRESTRICTED_USAGE_COUNT` is -1
+    Ok(remaining_usage_count >= 0)
+}
 
-        let public_key = jni_call_method!(
-            env,
-            &certificate,
-            CERTIFICATE_GET_PUBLIC_KEY,
-            l,
-            UnableToGetKeyPairById
-        )?;
+/// Key that is stored in the `AndroidKeyStore`
+///
+/// The key holds a global reference to its `java.security.KeyPair`, so it can be used from any
+/// thread. The reference is released when the last clone of the key is dropped.
+#[derive(Debug, Clone)]
+pub struct Key {
+    object: GlobalRef,
+    /// Alias of the key in the `AndroidKeyStore`
+    alias: String,
+    platform: PlatformHandles,
+    metadata: KeyMetadataHandle,
+    /// Whether the `AndroidKeyStore` limits the number of uses of the key
+    has_usage_limit: bool,
+    /// Retrieved when the key is created or loaded, as [`Keypair::verifying_key`] can not fail
+    verifying_key: VerifyingKey,
+    /// Only held so the alias is deleted when the last clone of the key is dropped
+    _ephemeral: Option<Arc<EphemeralAlias>>,
+}
 
-        let key_pair = jni_new_object!(
-            env,
-            KEY_PAIR,
-            &[(&public_key).into(), (&private_key).into()],
-            UnableToGetKeyPairById
-        )?;
+/// Random alias of an ephemeral key, which is deleted from the `AndroidKeyStore` on drop
+#[derive(Debug)]
+struct EphemeralAlias {
+    alias: String,
+    platform: PlatformHandles,
+}
 
-        Ok(Key(Arc::new(Mutex::new(*key_pair))))
+impl Drop for EphemeralAlias {
+    fn drop(&mut self) {
+        // Errors can not be reported here. An alias that is left behind is never loaded again,
+        // and is deleted, or reported as a failure, by the next wipe of the namespace.
+        let _ = delete_alias(&self.platform, &self.alias);
     }
 }
 
-#[derive(Debug)]
-pub struct Key(Arc<Mutex<jobject>>);
+/// `java.util.Date` of a time in seconds since the Unix epoch
+fn java_date<'local>(
+    env: &mut JNIEnv<'local>,
+    cache: &JniCache,
+    seconds: u64,
+) -> SecureEnvResult<JObject<'local>> {
+    let milliseconds = i64::try_from(seconds.saturating_mul(1000)).unwrap_or(i64::MAX);
+
+    jni_new_object_by_id!(
+        env,
+        &cache.date_cls,
+        cache.date_ctor,
+        [milliseconds],
+        UnableToGenerateKey
+    )
+}
+
+/// Delete the entry of `alias`, which does not fail when the alias does not exist
+fn delete_alias(platform: &PlatformHandles, alias: &str) -> SecureEnvResult<()> {
+    platform.with_env(|env, cache| {
+        let alias = env
+            .new_string(alias)
+            .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
 
-unsafe impl Send for Key {}
-unsafe impl Sync for Key {}
+        let key_store = key_store(env, cache)?;
+
+        jni_call_method_by_id!(
+            env,
+            key_store,
+            cache.key_store_delete_entry,
+            [&alias],
+            v,
+            UnableToDeleteKey
+        )
+    })
+}
 
 impl Key {
-    unsafe fn get_object(&self) -> JObject {
-        let raw = self.0.lock().unwrap();
-        JObject::from_raw(*raw)
+    /// Typed error for a signature that has been refused because of the validity period or the
+    /// usage limit of the key, which are enforced by the `AndroidKeyStore`
+    fn signature_error(&self, error: SecureEnvError) -> SecureEnvError {
+        let SecureEnvError::UnableToCreateSignature(message) = &error else {
+            return error;
+        };
+
+        if message.contains("KeyExpiredException") || message.contains("KeyNotYetValidException") {
+            return SecureEnvError::KeyExpired(message.clone());
+        }
+
+        // The `AndroidKeyStore` deletes a key after its last use
+        if self.has_usage_limit && !self.alias_exists().unwrap_or(true) {
+            return SecureEnvError::UsageLimitExceeded(message.clone());
+        }
+
+        error
     }
-}
 
-impl KeyOps for Key {
-    fn get_public_key(&self) -> SecureEnvResult<Vec<u8>> {
-        let jvm = JAVA_VM.lock().map_err(|_| {
-            SecureEnvError::UnableToAttachJVMToThread("Could not acquire lock on JVM".to_owned())
-        })?;
+    fn alias_exists(&self) -> SecureEnvResult<bool> {
+        self.platform.with_env(|env, cache| {
+            let alias = env
+                .new_string(&self.alias)
+                .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
 
-        let jvm = jvm
-            .as_ref()
-            .ok_or(SecureEnvError::UnableToAttachJVMToThread(
-                "JVM has not been set".to_owned(),
-            ))?;
+            let key_store = key_store(env, cache)?;
 
-        let mut env = jvm
-            .attach_current_thread_as_daemon()
-            .map_err(|e| SecureEnvError::UnableToAttachJVMToThread(e.to_string()))?;
+            jni_call_method_by_id!(
+                env,
+                key_store,
+                cache.key_store_contains_alias,
+                [&alias],
+                z,
+                UnableToCreateSignature
+            )
+        })
+    }
+}
 
-        let key = unsafe { self.get_object() };
+/// Compressed SEC1 encoding of the public key of a `java.security.KeyPair`
+fn public_key(env: &mut JNIEnv, cache: &JniCache, key_pair: &JObject) -> SecureEnvResult<Vec<u8>> {
+    let public_key = jni_call_method_by_id!(
+        env,
+        key_pair,
+        cache.key_pair_get_public,
+        l,
+        UnableToGetPublicKey
+    )?;
+
+    let public_key_encoded = jni_call_method_by_id!(
+        env,
+        &public_key,
+        cache.public_key_get_encoded,
+        l,
+        UnableToGetPublicKey
+    )?;
+
+    let format = jni_call_method_by_id!(
+        env,
+        &public_key,
+        cache.public_key_get_format,
+        l,
+        UnableToGetPublicKey
+    )?;
+
+    let format = JString::from(format);
+    let format = env
+        .get_string(&format)
+        .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
+    let format = format
+        .to_str()
+        .map_err(|e| SecureEnvError::UnableToGetPublicKey(e.to_string()))?;
+
+    if format != "X.509" {
+        return Err(SecureEnvError::UnableToGetPublicKey(format!(
+            "Unexpected key format. Expected 'X.509', received: '{format}'"
+        )));
+    }
 
-        let public_key = jni_call_method!(env, &key, KEY_PAIR_GET_PUBLIC, l, UnableToGetPublicKey)?;
+    let public_key: JByteArray = public_key_encoded.into();
 
-        let public_key_encoded = jni_call_method!(
-            env,
-            &public_key,
-            PUBLIC_KEY_GET_ENCODED,
-            l,
-            UnableToGetPublicKey
-        )?;
+    let public_key = env
+        .convert_byte_array(public_key)
+        .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
 
-        let format = jni_call_method!(
-            env,
-            &public_key,
-            PUBLIC_KEY_GET_FORMAT,
-            l,
-            UnableToGetPublicKey
-        )?;
+    let spki = SubjectPublicKeyInfo::from_der(&public_key)
+        .map_err(|e| SecureEnvError::UnableToGetPublicKey(e.to_string()))?;
 
-        let format = JString::from(format);
-        let format = env
-            .get_string(&format)
-            .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
-        let format = format
-            .to_str()
-            .map_err(|e| SecureEnvError::UnableToGetPublicKey(e.to_string()))?;
-
-        if format != "X.509" {
-            return Err(SecureEnvError::UnableToGetPublicKey(format!(
-                "Unexpected key format. Expected 'X.509', received: '{format}'"
-            )));
-        }
+    let spki_data = spki.1.subject_public_key.data;
 
-        let public_key: JByteArray = public_key_encoded.into();
+    let public_key = p256::PublicKey::from_sec1_bytes(&spki_data)
+        .map_err(|e| SecureEnvError::UnableToGetPublicKey(e.to_string()))?;
 
-        let public_key = env
-            .convert_byte_array(public_key)
-            .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
+    let encoded_point = public_key.to_encoded_point(true);
 
-        let spki = SubjectPublicKeyInfo::from_der(&public_key)
-            .map_err(|e| SecureEnvError::UnableToGetPublicKey(e.to_string()))?;
+    let public_key = encoded_point.to_bytes().to_vec();
+
+    Ok(public_key)
+}
 
-        let spki_data = spki.1.subject_public_key.data;
+/// Verifying key of the public key of a `java.security.KeyPair`
+fn verifying_key(
+    env: &mut JNIEnv,
+    cache: &JniCache,
+    key_pair: &JObject,
+) -> SecureEnvResult<VerifyingKey> {
+    let public_key = public_key(env, cache, key_pair)?;
 
-        let public_key = p256::PublicKey::from_sec1_bytes(&spki_data)
-            .map_err(|e| SecureEnvError::UnableToGetPublicKey(e.to_string()))?;
+    VerifyingKey::from_sec1_bytes(&public_key)
+        .map_err(|e| SecureEnvError::UnableToGetPublicKey(e.to_string()))
+}
 
-        let encoded_point = public_key.to_encoded_point(true);
+impl Keypair for Key {
+    type VerifyingKey = VerifyingKey;
 
-        let public_key = encoded_point.to_bytes().to_vec();
+    /// Verifying key that has been retrieved when the key was created or loaded
+    fn verifying_key(&self) -> VerifyingKey {
+        self.verifying_key
+    }
+}
 
-        Ok(public_key)
+impl KeyOps for Key {
+    fn get_public_key(&self) -> SecureEnvResult<Vec<u8>> {
+        self.platform
+            .with_env(|env, cache| public_key(env, cache, &self.object))
     }
 
     /**
@@ -552,81 +1378,147 @@ impl KeyOps for Key {
      *
      */
     fn sign(&self, msg: &[u8]) -> SecureEnvResult<Vec<u8>> {
-        let jvm = JAVA_VM.lock().map_err(|_| {
-            SecureEnvError::UnableToAttachJVMToThread("Could not acquire lock on JVM".to_owned())
-        })?;
-
-        let jvm = jvm
-            .as_ref()
-            .ok_or(SecureEnvError::UnableToAttachJVMToThread(
-                "JVM has not been set".to_owned(),
-            ))?;
-
-        let mut env = jvm
-            .attach_current_thread_as_daemon()
-            .map_err(|e| SecureEnvError::UnableToAttachJVMToThread(e.to_string()))?;
-
-        let key = unsafe { self.get_object() };
-
-        let algorithm = env
-            .new_string(SHA256_WITH_ECDSA_ALGO)
-            .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
-
-        let private_key =
-            jni_call_method!(env, &key, KEY_PAIR_GET_PRIVATE, l, UnableToCreateSignature)?;
-
-        let signature_instance = jni_call_static_method!(
-            env,
-            SIGNATURE,
-            SIGNATURE_GET_INSTANCE,
-            &[(&algorithm).into()],
-            l,
-            UnableToCreateSignature
-        )?;
+        self.platform
+            .with_env(|env, cache| {
+                let key = &self.object;
+
+                let private_key = jni_call_method_by_id!(
+                    env,
+                    key,
+                    cache.key_pair_get_private,
+                    l,
+                    UnableToCreateSignature
+                )?;
+
+                // `Signature` instances are stateful, so a new one is required for every operation
+                let signature_instance = jni_call_static_method_by_id!(
+                    env,
+                    &cache.signature_cls,
+                    cache.signature_get_instance,
+                    [&cache.sha256_with_ecdsa],
+                    l,
+                    UnableToCreateSignature
+                )?;
+
+                jni_call_method_by_id!(
+                    env,
+                    &signature_instance,
+                    cache.signature_init_sign,
+                    [&private_key],
+                    v,
+                    UnableToCreateSignature
+                )?;
+
+                let b_arr = env
+                    .byte_array_from_slice(msg)
+                    .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
+
+                jni_call_method_by_id!(
+                    env,
+                    &signature_instance,
+                    cache.signature_update,
+                    [&b_arr],
+                    v,
+                    UnableToCreateSignature
+                )?;
+
+                let signature = jni_call_method_by_id!(
+                    env,
+                    &signature_instance,
+                    cache.signature_sign,
+                    l,
+                    UnableToCreateSignature
+                )?;
+
+                let signature: JByteArray = signature.into();
+
+                let signature = env
+                    .convert_byte_array(signature)
+                    .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
+
+                let signature = Signature::from_der(&signature)
+                    .map_err(|e| SecureEnvError::UnableToCreateSignature(e.to_string()))?;
+
+                let r = signature.r();
+                let s = signature.s();
+                let compact_signature = [r.to_bytes(), s.to_bytes()].concat();
+
+                Ok(compact_signature)
+            })
+            .map_err(|e| self.signature_error(e))
+    }
 
-        jni_call_method!(
-            env,
-            &signature_instance,
-            SIGNATURE_INIT_SIGN,
-            &[(&private_key).into()],
-            v,
-            UnableToCreateSignature
-        )?;
+    fn metadata(&self) -> SecureEnvResult<Option<KeyMetadata>> {
+        self.metadata.metadata()
+    }
 
-        let b_arr = env
-            .byte_array_from_slice(msg)
-            .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
+    fn set_metadata(&self, metadata: &KeyMetadata) -> SecureEnvResult<()> {
+        self.metadata.set_metadata(metadata)
+    }
 
-        jni_call_method!(
-            env,
-            &signature_instance,
-            SIGNATURE_UPDATE,
-            &[(&b_arr).into()],
-            v,
-            UnableToCreateSignature
-        )?;
+    /// Certificate chain of the alias in the `AndroidKeyStore`
+    ///
+    /// Without an attestation challenge, the `AndroidKeyStore` only stores a self-signed
+    /// certificate of the key, which is not an attestation, so `None` is returned for it.
+    fn attestation_chain(&self) -> SecureEnvResult<Option<Vec<Vec<u8>>>> {
+        self.platform.with_env(|env, cache| {
+            let alias = env
+                .new_string(&self.alias)
+                .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
 
-        let signature = jni_call_method!(
-            env,
-            &signature_instance,
-            SIGNATURE_SIGN,
-            l,
-            UnableToCreateSignature
-        )?;
+            let key_store = key_store(env, cache)?;
 
-        let signature: JByteArray = signature.into();
+            let chain = JObjectArray::from(jni_call_method_by_id!(
+                env,
+                key_store,
+                cache.key_store_get_certificate_chain,
+                [&alias],
+                l,
+                UnableToGetAttestationChain
+            )?);
+
+            if chain.is_null() {
+                return Err(SecureEnvError::UnableToGetAttestationChain(format!(
+                    "Key reference with alias: '{}' not found.",
+                    self.alias
+                )));
+            }
 
-        let signature = env
-            .convert_byte_array(signature)
-            .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
+            let length = env
+                .get_array_length(&chain)
+                .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
 
-        let signature = Signature::from_der(&signature)
-            .map_err(|e| SecureEnvError::UnableToCreateSignature(e.to_string()))?;
+            if length < 2 {
+                return Ok(None);
+            }
 
-        let r = signature.r();
-        let s = signature.s();
-        let compact_signature = [r.to_bytes(), s.to_bytes()].concat();
+            let mut result = vec![];
+
+            for index in 0..length {
+                let certificate = env
+                    .get_object_array_element(&chain, index)
+                    .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
+
+                let encoded = JByteArray::from(jni_call_method_by_id!(
+                    env,
+                    &certificate,
+                    cache.certificate_get_encoded,
+                    l,
+                    UnableToGetAttestationChain
+                )?);
+
+                result.push(
+                    env.convert_byte_array(&encoded)
+                        .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?,
+                );
+
+                // The local frame only has room for a few references
+                env.delete_local_ref(encoded)
+                    .and_then(|_| env.delete_local_ref(certificate))
+                    .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
+            }
 
-        Ok(compact_signature)
+            Ok(Some(result))
+        })
     }
 }
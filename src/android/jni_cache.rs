@@ -0,0 +1,395 @@
+use crate::{
+    error::{SecureEnvError, SecureEnvResult},
+    jni_tokens::*,
+};
+use jni::{
+    objects::{GlobalRef, JMethodID, JObject, JStaticMethodID},
+    JNIEnv,
+};
+use once_cell::sync::OnceCell;
+use paste::paste;
+
+macro_rules! jni_method_id {
+    ($env:expr, $cls:expr, $method:ident) => {
+        paste! {
+            $env.get_method_id($cls, $method, [<$method _SIG>])
+                .map_err(|e| jni_handle_error!($env, NotInitialized, e))
+        }
+    };
+}
+
+macro_rules! jni_static_method_id {
+    ($env:expr, $cls:expr, $method:ident) => {
+        paste! {
+            $env.get_static_method_id($cls, $method, [<$method _SIG>])
+                .map_err(|e| jni_handle_error!($env, NotInitialized, e))
+        }
+    };
+}
+
+macro_rules! jni_constructor_id {
+    ($env:expr, $cls:expr, $cls_name:ident) => {
+        paste! {
+            $env.get_method_id($cls, CONSTRUCTOR, [<$cls_name _CTOR_SIG>])
+                .map_err(|e| jni_handle_error!($env, NotInitialized, e))
+        }
+    };
+}
+
+/// Classes, method ids and constants that are used by the keystore operations
+///
+/// Resolving these by name is a large part of the cost of a single operation, so they are
+/// resolved once when a `JavaVM` is provided. Every class is held via a global reference or is
+/// part of the platform, so the class can not be unloaded and its method ids stay valid.
+#[derive(Debug)]
+pub(crate) struct JniCache {
+    pub(crate) purpose_sign: i32,
+    /// Only available since Android 11 (API level 30)
+    pub(crate) auth_biometric_strong: Option<i32>,
+    pub(crate) digests: GlobalRef,
+    pub(crate) ec_algorithm: GlobalRef,
+    pub(crate) android_key_store_provider: GlobalRef,
+    pub(crate) sha256_with_ecdsa: GlobalRef,
+    pub(crate) hardware_keystore_feature: GlobalRef,
+
+    pub(crate) context_get_package_manager: JMethodID,
+    pub(crate) package_manager_has_system_feature: JMethodID,
+
+    pub(crate) key_gen_parameter_spec_builder_cls: GlobalRef,
+    pub(crate) key_gen_parameter_spec_builder_ctor: JMethodID,
+    pub(crate) key_gen_parameter_spec_builder_set_digests: JMethodID,
+    pub(crate) key_gen_parameter_spec_builder_set_key_size: JMethodID,
+    pub(crate) key_gen_parameter_spec_builder_set_user_authentication_required: JMethodID,
+    pub(crate) key_gen_parameter_spec_builder_set_invalidated_by_biometric_enrollment: JMethodID,
+    /// Only available since Android 11 (API level 30)
+    pub(crate) key_gen_parameter_spec_builder_set_user_authentication_parameters: Option<JMethodID>,
+    /// Only available since Android 9 (API level 28)
+    pub(crate) key_gen_parameter_spec_builder_set_is_strong_box_backed: Option<JMethodID>,
+    pub(crate) key_gen_parameter_spec_builder_set_key_validity_start: JMethodID,
+    pub(crate) key_gen_parameter_spec_builder_set_key_validity_end: JMethodID,
+    /// Only available since Android 12 (API level 31)
+    pub(crate) key_gen_parameter_spec_builder_set_max_usage_count: Option<JMethodID>,
+    /// Only available since Android 7 (API level 24)
+    pub(crate) key_gen_parameter_spec_builder_set_attestation_challenge: Option<JMethodID>,
+    pub(crate) key_gen_parameter_spec_builder_build: JMethodID,
+
+    pub(crate) date_cls: GlobalRef,
+    pub(crate) date_ctor: JMethodID,
+
+    pub(crate) key_pair_generator_cls: GlobalRef,
+    pub(crate) key_pair_generator_get_instance: JStaticMethodID,
+    pub(crate) key_pair_generator_initialize: JMethodID,
+    pub(crate) key_pair_generator_generate_key_pair: JMethodID,
+
+    pub(crate) key_factory_cls: GlobalRef,
+    pub(crate) key_factory_get_instance: JStaticMethodID,
+    pub(crate) key_factory_get_key_spec: JMethodID,
+    pub(crate) key_info_cls: GlobalRef,
+    /// Only available since Android 12 (API level 31)
+    pub(crate) key_info_get_remaining_usage_count: Option<JMethodID>,
+
+    pub(crate) key_store_cls: GlobalRef,
+    pub(crate) key_store_get_instance: JStaticMethodID,
+    pub(crate) key_store_load: JMethodID,
+    pub(crate) key_store_get_entry: JMethodID,
+    pub(crate) key_store_contains_alias: JMethodID,
+    pub(crate) key_store_delete_entry: JMethodID,
+    pub(crate) key_store_aliases: JMethodID,
+    pub(crate) key_store_get_certificate_chain: JMethodID,
+    pub(crate) enumeration_has_more_elements: JMethodID,
+    pub(crate) enumeration_next_element: JMethodID,
+    pub(crate) key_store_entry_get_private_key: JMethodID,
+    pub(crate) key_store_entry_get_certificate: JMethodID,
+    pub(crate) certificate_get_public_key: JMethodID,
+    pub(crate) certificate_get_encoded: JMethodID,
+
+    pub(crate) key_pair_cls: GlobalRef,
+    pub(crate) key_pair_ctor: JMethodID,
+    pub(crate) key_pair_get_public: JMethodID,
+    pub(crate) key_pair_get_private: JMethodID,
+
+    pub(crate) public_key_get_encoded: JMethodID,
+    pub(crate) public_key_get_format: JMethodID,
+
+    pub(crate) signature_cls: GlobalRef,
+    pub(crate) signature_get_instance: JStaticMethodID,
+    pub(crate) signature_init_sign: JMethodID,
+    pub(crate) signature_update: JMethodID,
+    pub(crate) signature_sign: JMethodID,
+
+    /// Loaded `AndroidKeyStore`. It is only created when it is first used, as the keystore is not
+    /// required for generating keys.
+    pub(crate) key_store: OnceCell<GlobalRef>,
+}
+
+fn global_ref<'local>(
+    env: &mut JNIEnv<'local>,
+    object: impl AsRef<JObject<'local>>,
+) -> SecureEnvResult<GlobalRef> {
+    env.new_global_ref(object)
+        .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))
+}
+
+fn global_string(env: &mut JNIEnv, value: &str) -> SecureEnvResult<GlobalRef> {
+    let value = env
+        .new_string(value)
+        .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
+
+    global_ref(env, value)
+}
+
+impl JniCache {
+    /// Resolve every lookup. Local references that are created here are not released, so this
+    /// should be called within a local frame.
+    pub(crate) fn new(env: &mut JNIEnv) -> SecureEnvResult<Self> {
+        let key_properties_cls = jni_find_class!(env, KEY_PROPERTIES, NotInitialized)?;
+
+        let purpose_sign = jni_get_static_field!(
+            env,
+            &key_properties_cls,
+            KEY_PROPERTIES_PURPOSE_SIGN,
+            i,
+            NotInitialized
+        )?;
+
+        // The `NoSuchFieldError` of older versions is cleared by `jni_handle_error!`
+        let auth_biometric_strong = jni_get_static_field!(
+            env,
+            &key_properties_cls,
+            KEY_PROPERTIES_AUTH_BIOMETRIC_STRONG,
+            i,
+            NotInitialized
+        )
+        .ok();
+
+        let digest_sha256 = jni_get_static_field!(
+            env,
+            &key_properties_cls,
+            KEY_PROPERTIES_DIGEST_SHA256,
+            l,
+            NotInitialized
+        )?;
+
+        let string_cls = jni_find_class!(env, STRING, NotInitialized)?;
+
+        let digests = env
+            .new_object_array(1, string_cls, digest_sha256)
+            .map_err(|e| SecureEnvError::UnableToCreateJavaValue(e.to_string()))?;
+
+        let context_cls = jni_find_class!(env, CONTEXT, NotInitialized)?;
+        let package_manager_cls = jni_find_class!(env, PACKAGE_MANAGER, NotInitialized)?;
+
+        let builder_cls = jni_find_class!(env, KEY_GEN_PARAMETER_SPEC_BUILDER, NotInitialized)?;
+        let key_pair_generator_cls = jni_find_class!(env, KEY_PAIR_GENERATOR, NotInitialized)?;
+        let key_factory_cls = jni_find_class!(env, KEY_FACTORY, NotInitialized)?;
+        let key_info_cls = jni_find_class!(env, KEY_INFO, NotInitialized)?;
+        let key_store_cls = jni_find_class!(env, KEY_STORE, NotInitialized)?;
+        let key_store_entry_cls = jni_find_class!(env, KEY_STORE_ENTRY, NotInitialized)?;
+        let enumeration_cls = jni_find_class!(env, ENUMERATION, NotInitialized)?;
+        let date_cls = jni_find_class!(env, DATE, NotInitialized)?;
+        let certificate_cls = jni_find_class!(env, CERTIFICATE, NotInitialized)?;
+        let key_pair_cls = jni_find_class!(env, KEY_PAIR, NotInitialized)?;
+        let public_key_cls = jni_find_class!(env, PUBLIC_KEY, NotInitialized)?;
+        let signature_cls = jni_find_class!(env, SIGNATURE, NotInitialized)?;
+
+        Ok(Self {
+            purpose_sign,
+            auth_biometric_strong,
+            digests: global_ref(env, digests)?,
+            ec_algorithm: global_string(env, EC_ALGORITHM)?,
+            android_key_store_provider: global_string(env, ANDROID_KEY_STORE_PROVIDER)?,
+            sha256_with_ecdsa: global_string(env, SHA256_WITH_ECDSA_ALGO)?,
+            hardware_keystore_feature: global_string(env, HARDWARE_KEYSTORE_FEATURE)?,
+
+            context_get_package_manager: jni_method_id!(
+                env,
+                &context_cls,
+                CONTEXT_GET_PACKAGE_MANAGER
+            )?,
+            package_manager_has_system_feature: jni_method_id!(
+                env,
+                &package_manager_cls,
+                PACKAGE_MANAGER_HAS_SYSTEM_FEATURE
+            )?,
+
+            key_gen_parameter_spec_builder_ctor: jni_constructor_id!(
+                env,
+                &builder_cls,
+                KEY_GEN_PARAMETER_SPEC_BUILDER
+            )?,
+            key_gen_parameter_spec_builder_set_digests: jni_method_id!(
+                env,
+                &builder_cls,
+                KEY_GEN_PARAMETER_SPEC_BUILDER_SET_DIGESTS
+            )?,
+            key_gen_parameter_spec_builder_set_key_size: jni_method_id!(
+                env,
+                &builder_cls,
+                KEY_GEN_PARAMETER_SPEC_BUILDER_SET_KEY_SIZE
+            )?,
+            key_gen_parameter_spec_builder_set_user_authentication_required: jni_method_id!(
+                env,
+                &builder_cls,
+                KEY_GEN_PARAMETER_SPEC_BUILDER_SET_USER_AUTHENTICATION_REQUIRED
+            )?,
+            key_gen_parameter_spec_builder_set_invalidated_by_biometric_enrollment: jni_method_id!(
+                env,
+                &builder_cls,
+                KEY_GEN_PARAMETER_SPEC_BUILDER_SET_INVALIDATED_BY_BIOMETRIC_ENROLLMENT
+            )?,
+            // The `NoSuchMethodError` of older versions is cleared by `jni_handle_error!`
+            key_gen_parameter_spec_builder_set_user_authentication_parameters: jni_method_id!(
+                env,
+                &builder_cls,
+                KEY_GEN_PARAMETER_SPEC_BUILDER_SET_USER_AUTHENTICATION_PARAMETERS
+            )
+            .ok(),
+            key_gen_parameter_spec_builder_set_is_strong_box_backed: jni_method_id!(
+                env,
+                &builder_cls,
+                KEY_GEN_PARAMETER_SPEC_BUILDER_SET_IS_STRONG_BOX_BACKED
+            )
+            .ok(),
+            key_gen_parameter_spec_builder_set_key_validity_start: jni_method_id!(
+                env,
+                &builder_cls,
+                KEY_GEN_PARAMETER_SPEC_BUILDER_SET_KEY_VALIDITY_START
+            )?,
+            key_gen_parameter_spec_builder_set_key_validity_end: jni_method_id!(
+                env,
+                &builder_cls,
+                KEY_GEN_PARAMETER_SPEC_BUILDER_SET_KEY_VALIDITY_END
+            )?,
+            // The `NoSuchMethodError` of older versions is cleared by `jni_handle_error!`
+            key_gen_parameter_spec_builder_set_max_usage_count: jni_method_id!(
+                env,
+                &builder_cls,
+                KEY_GEN_PARAMETER_SPEC_BUILDER_SET_MAX_USAGE_COUNT
+            )
+            .ok(),
+            key_gen_parameter_spec_builder_set_attestation_challenge: jni_method_id!(
+                env,
+                &builder_cls,
+                KEY_GEN_PARAMETER_SPEC_BUILDER_SET_ATTESTATION_CHALLENGE
+            )
+            .ok(),
+            key_gen_parameter_spec_builder_build: jni_method_id!(
+                env,
+                &builder_cls,
+                KEY_GEN_PARAMETER_SPEC_BUILDER_BUILD
+            )?,
+            key_gen_parameter_spec_builder_cls: global_ref(env, builder_cls)?,
+
+            date_ctor: jni_constructor_id!(env, &date_cls, DATE)?,
+            date_cls: global_ref(env, date_cls)?,
+
+            key_pair_generator_get_instance: jni_static_method_id!(
+                env,
+                &key_pair_generator_cls,
+                KEY_PAIR_GENERATOR_GET_INSTANCE
+            )?,
+            key_pair_generator_initialize: jni_method_id!(
+                env,
+                &key_pair_generator_cls,
+                KEY_PAIR_GENERATOR_INITIALIZE
+            )?,
+            key_pair_generator_generate_key_pair: jni_method_id!(
+                env,
+                &key_pair_generator_cls,
+                KEY_PAIR_GENERATOR_GENERATE_KEY_PAIR
+            )?,
+            key_pair_generator_cls: global_ref(env, key_pair_generator_cls)?,
+
+            key_factory_get_instance: jni_static_method_id!(
+                env,
+                &key_factory_cls,
+                KEY_FACTORY_GET_INSTANCE
+            )?,
+            key_factory_get_key_spec: jni_method_id!(
+                env,
+                &key_factory_cls,
+                KEY_FACTORY_GET_KEY_SPEC
+            )?,
+            key_factory_cls: global_ref(env, key_factory_cls)?,
+            // The `NoSuchMethodError` of older versions is cleared by `jni_handle_error!`
+            key_info_get_remaining_usage_count: jni_method_id!(
+                env,
+                &key_info_cls,
+                KEY_INFO_GET_REMAINING_USAGE_COUNT
+            )
+            .ok(),
+            key_info_cls: global_ref(env, key_info_cls)?,
+
+            key_store_get_instance: jni_static_method_id!(
+                env,
+                &key_store_cls,
+                KEY_STORE_GET_INSTANCE
+            )?,
+            key_store_load: jni_method_id!(env, &key_store_cls, KEY_STORE_LOAD)?,
+            key_store_get_entry: jni_method_id!(env, &key_store_cls, KEY_STORE_GET_ENTRY)?,
+            key_store_contains_alias: jni_method_id!(
+                env,
+                &key_store_cls,
+                KEY_STORE_CONTAINS_ALIAS
+            )?,
+            key_store_delete_entry: jni_method_id!(env, &key_store_cls, KEY_STORE_DELETE_ENTRY)?,
+            key_store_aliases: jni_method_id!(env, &key_store_cls, KEY_STORE_ALIASES)?,
+            key_store_get_certificate_chain: jni_method_id!(
+                env,
+                &key_store_cls,
+                KEY_STORE_GET_CERTIFICATE_CHAIN
+            )?,
+            enumeration_has_more_elements: jni_method_id!(
+                env,
+                &enumeration_cls,
+                ENUMERATION_HAS_MORE_ELEMENTS
+            )?,
+            enumeration_next_element: jni_method_id!(
+                env,
+                &enumeration_cls,
+                ENUMERATION_NEXT_ELEMENT
+            )?,
+            key_store_cls: global_ref(env, key_store_cls)?,
+            key_store_entry_get_private_key: jni_method_id!(
+                env,
+                &key_store_entry_cls,
+                KEY_STORE_ENTRY_GET_PRIVATE_KEY
+            )?,
+            key_store_entry_get_certificate: jni_method_id!(
+                env,
+                &key_store_entry_cls,
+                KEY_STORE_ENTRY_GET_CERTIFICATE
+            )?,
+            certificate_get_public_key: jni_method_id!(
+                env,
+                &certificate_cls,
+                CERTIFICATE_GET_PUBLIC_KEY
+            )?,
+            certificate_get_encoded: jni_method_id!(
+                env,
+                &certificate_cls,
+                CERTIFICATE_GET_ENCODED
+            )?,
+
+            key_pair_ctor: jni_constructor_id!(env, &key_pair_cls, KEY_PAIR)?,
+            key_pair_get_public: jni_method_id!(env, &key_pair_cls, KEY_PAIR_GET_PUBLIC)?,
+            key_pair_get_private: jni_method_id!(env, &key_pair_cls, KEY_PAIR_GET_PRIVATE)?,
+            key_pair_cls: global_ref(env, key_pair_cls)?,
+
+            public_key_get_encoded: jni_method_id!(env, &public_key_cls, PUBLIC_KEY_GET_ENCODED)?,
+            public_key_get_format: jni_method_id!(env, &public_key_cls, PUBLIC_KEY_GET_FORMAT)?,
+
+            signature_get_instance: jni_static_method_id!(
+                env,
+                &signature_cls,
+                SIGNATURE_GET_INSTANCE
+            )?,
+            signature_init_sign: jni_method_id!(env, &signature_cls, SIGNATURE_INIT_SIGN)?,
+            signature_update: jni_method_id!(env, &signature_cls, SIGNATURE_UPDATE)?,
+            signature_sign: jni_method_id!(env, &signature_cls, SIGNATURE_SIGN)?,
+            signature_cls: global_ref(env, signature_cls)?,
+
+            key_store: OnceCell::new(),
+        })
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{
+    error::SecureEnvResult, jwk_thumbprint, wipe::wipe_keys, BoxedKey, KeyGenerationOptions, KeyId,
+    SecureEnvironmentConfig, WipeReport,
+};
+use std::fmt::Debug;
+
+/// Object safe backend that creates and loads keys
+///
+/// Every backend implements this trait, so the backend can be selected at runtime and used via
+/// `dyn KeyBackend`. All backends behave the same:
+///
+/// - ids are validated [`KeyId`]s and are prefixed with the namespace of the configuration
+/// - creating a keypair with an id that is already in use fails with
+///   [`SecureEnvError::UnableToGenerateKey`](crate::error::SecureEnvError::UnableToGenerateKey)
+/// - loading a keypair that does not exist fails with [`SecureEnvError::UnableToGetKeyPairById`](crate::error::SecureEnvError::UnableToGetKeyPairById)
+/// - deleting a keypair that does not exist fails with [`SecureEnvError::UnableToDeleteKey`](crate::error::SecureEnvError::UnableToDeleteKey)
+/// - only the keypairs of the namespace are listed and wiped
+/// - with a [`crate::MetadataStore`], creating a keypair stores its creation time and the
+///   thumbprint of its public key, and deleting it removes its metadata
+///
+/// The `animo-secure-env-conformance` crate verifies this for any implementation.
+///
+/// # Examples
+///
+/// ## Select a backend at runtime
+///
+/// ```
+/// use secure_env::{BoxedKey, KeyBackend, KeyId, KeyOps, SecureEnvironmentConfig, SoftwareBackend};
+/// use std::sync::Arc;
+///
+/// let backend: Arc<dyn KeyBackend> = Arc::new(SoftwareBackend::new(SecureEnvironmentConfig::default()));
+///
+/// let keys: Vec<BoxedKey> = vec![
+///     backend.create_keypair(&KeyId::new("first-key").unwrap()).unwrap(),
+///     backend.create_keypair(&KeyId::new("second-key").unwrap()).unwrap(),
+/// ];
+///
+/// for key in keys {
+///     assert_eq!(key.sign(b"Hello World!").unwrap().len(), 64);
+/// }
+/// ```
+pub trait KeyBackend: Send + Sync + Debug {
+    /// Configuration of the backend
+    fn config(&self) -> &SecureEnvironmentConfig;
+
+    /// Create and store a keypair
+    fn create_keypair_with_options(
+        &self,
+        id: &KeyId,
+        options: &KeyGenerationOptions,
+    ) -> SecureEnvResult<BoxedKey>;
+
+    /// Get a keypair that has been stored by this backend
+    fn load_keypair(&self, id: &KeyId) -> SecureEnvResult<BoxedKey>;
+
+    /// Delete a keypair that has been stored by this backend
+    fn delete_keypair(&self, id: &KeyId) -> SecureEnvResult<()>;
+
+    /// Ids of every keypair that is stored in the namespace of the backend
+    ///
+    /// Ephemeral keypairs are never listed.
+    fn list_keys(&self) -> SecureEnvResult<Vec<KeyId>>;
+
+    /// Create a keypair that is not stored and can not be loaded by id, e.g. for a single session
+    ///
+    /// The key is destroyed when the last reference to it is dropped.
+    fn generate_ephemeral_keypair(
+        &self,
+        options: &KeyGenerationOptions,
+    ) -> SecureEnvResult<BoxedKey>;
+
+    /// Create and store a keypair with the default options of the backend
+    fn create_keypair(&self, id: &KeyId) -> SecureEnvResult<BoxedKey> {
+        self.create_keypair_with_options(id, &self.config().default_options)
+    }
+
+    /// Ids of the keypairs in the namespace of the backend whose metadata has every tag of `tags`
+    ///
+    /// Requires a [`crate::MetadataStore`] in the configuration.
+    fn find_keys(&self, tags: &[(&str, &str)]) -> SecureEnvResult<Vec<KeyId>> {
+        self.config().find_keys(tags)
+    }
+
+    /// Delete every keypair in the namespace of the backend, together with its metadata
+    ///
+    /// The namespace is the one of the configuration that the backend was created with. To wipe
+    /// another namespace, create a backend with that namespace in its configuration.
+    ///
+    /// A keypair that can not be deleted does not stop the others from being deleted, it is
+    /// reported in the [`WipeReport`] instead. Wiping an empty namespace succeeds, so it can be
+    /// repeated until the report is complete.
+    fn wipe(&self) -> SecureEnvResult<WipeReport> {
+        wipe_keys(self)
+    }
+
+    /// Get the keypair in the namespace of the backend with the SEC1 encoded public key
+    /// `public_key`, or `None` when no keypair has this public key
+    fn find_keypair_by_public_key(&self, public_key: &[u8]) -> SecureEnvResult<Option<BoxedKey>> {
+        self.find_keypair_by_thumbprint(&jwk_thumbprint(public_key)?)
+    }
+
+    /// Get the keypair in the namespace of the backend whose public key has the JWK thumbprint
+    /// `thumbprint`, see [`crate::Jwk::thumbprint`], or `None` when no keypair matches
+    ///
+    /// By default the thumbprints in the [`crate::MetadataStore`] of the configuration are used
+    /// as the index, so a store is required.
+    fn find_keypair_by_thumbprint(&self, thumbprint: &str) -> SecureEnvResult<Option<BoxedKey>> {
+        self.config()
+            .find_key_by_thumbprint(thumbprint)?
+            .map(|id| self.load_keypair(&id))
+            .transpose()
+    }
+}
+
+#[cfg(any(
+    target_os = "android",
+    target_os = "ios",
+    feature = "android_host_testing"
+))]
+impl KeyBackend for crate::SecureEnvironment {
+    fn config(&self) -> &SecureEnvironmentConfig {
+        &self.config
+    }
+
+    fn create_keypair_with_options(
+        &self,
+        id: &KeyId,
+        options: &KeyGenerationOptions,
+    ) -> SecureEnvResult<BoxedKey> {
+        Ok(Box::new(
+            crate::SecureEnvironment::create_keypair_with_options(self, id, options)?,
+        ))
+    }
+
+    fn load_keypair(&self, id: &KeyId) -> SecureEnvResult<BoxedKey> {
+        Ok(Box::new(crate::SecureEnvironment::load_keypair(self, id)?))
+    }
+
+    fn delete_keypair(&self, id: &KeyId) -> SecureEnvResult<()> {
+        crate::SecureEnvironment::delete_keypair(self, id)
+    }
+
+    fn list_keys(&self) -> SecureEnvResult<Vec<KeyId>> {
+        crate::SecureEnvironment::list_keys(self)
+    }
+
+    fn wipe(&self) -> SecureEnvResult<WipeReport> {
+        crate::SecureEnvironment::wipe(self)
+    }
+
+    fn generate_ephemeral_keypair(
+        &self,
+        options: &KeyGenerationOptions,
+    ) -> SecureEnvResult<BoxedKey> {
+        Ok(Box::new(
+            crate::SecureEnvironment::generate_ephemeral_keypair(self, options)?,
+        ))
+    }
+
+    fn find_keypair_by_public_key(&self, public_key: &[u8]) -> SecureEnvResult<Option<BoxedKey>> {
+        Ok(
+            crate::SecureEnvironment::find_keypair_by_public_key(self, public_key)?
+                .map(|key| Box::new(key) as BoxedKey),
+        )
+    }
+
+    fn find_keypair_by_thumbprint(&self, thumbprint: &str) -> SecureEnvResult<Option<BoxedKey>> {
+        Ok(
+            crate::SecureEnvironment::find_keypair_by_thumbprint(self, thumbprint)?
+                .map(|key| Box::new(key) as BoxedKey),
+        )
+    }
+}
@@ -0,0 +1,365 @@
+use crate::{
+    error::{SecureEnvError, SecureEnvResult},
+    metadata::now,
+    x509::{
+        extension, key_identifier, parse_name, sign, signature_algorithm,
+        subject_alt_name_extension, subject_public_key_info, time,
+    },
+    KeyOps, SubjectAltName, X509Extension,
+};
+use p256::{ecdsa::VerifyingKey, pkcs8::EncodePublicKey};
+use rand_core::{OsRng, RngCore};
+use x509_cert::{
+    der::{
+        asn1::OctetString,
+        oid::db::rfc5912::{
+            ID_CE_AUTHORITY_KEY_IDENTIFIER, ID_CE_BASIC_CONSTRAINTS, ID_CE_KEY_USAGE,
+            ID_CE_SUBJECT_KEY_IDENTIFIER,
+        },
+        pem::LineEnding,
+        Decode, DecodePem, Encode, EncodePem,
+    },
+    ext::pkix::{self, AuthorityKeyIdentifier, KeyUsages},
+    name::Name,
+    serial_number::SerialNumber,
+    spki::SubjectPublicKeyInfoOwned,
+    time::{Time, Validity},
+    TbsCertificate, Version,
+};
+
+/// Usage of the certified key, for the key usage extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyUsage {
+    DigitalSignature,
+    NonRepudiation,
+    KeyEncipherment,
+    DataEncipherment,
+    KeyAgreement,
+    KeyCertSign,
+    CrlSign,
+}
+
+impl From<KeyUsage> for KeyUsages {
+    fn from(usage: KeyUsage) -> Self {
+        match usage {
+            KeyUsage::DigitalSignature => KeyUsages::DigitalSignature,
+            KeyUsage::NonRepudiation => KeyUsages::NonRepudiation,
+            KeyUsage::KeyEncipherment => KeyUsages::KeyEncipherment,
+            KeyUsage::DataEncipherment => KeyUsages::DataEncipherment,
+            KeyUsage::KeyAgreement => KeyUsages::KeyAgreement,
+            KeyUsage::KeyCertSign => KeyUsages::KeyCertSign,
+            KeyUsage::CrlSign => KeyUsages::CRLSign,
+        }
+    }
+}
+
+/// Basic constraints extension, which is always marked as critical
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BasicConstraints {
+    /// Whether the certified key may sign certificates
+    pub ca: bool,
+
+    /// Maximum number of intermediate certificates that may follow this certificate in a path
+    pub path_len: Option<u8>,
+}
+
+/// Contents of a certificate
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CertificateParams {
+    /// Big endian serial number, of at most 20 bytes
+    ///
+    /// When `None`, a random serial number of 16 bytes is used.
+    pub serial_number: Option<Vec<u8>>,
+
+    /// Distinguished name of the subject in the string representation of
+    /// [RFC 4514](https://www.rfc-editor.org/rfc/rfc4514), e.g. `CN=Device,O=Animo`
+    ///
+    /// An empty string results in an empty subject, in which case the subject alternative names
+    /// identify the subject.
+    pub subject: String,
+
+    /// Names for the subject alternative name extension, which is omitted when empty
+    pub subject_alt_names: Vec<SubjectAltName>,
+
+    /// Start of the validity period in seconds since the Unix epoch
+    ///
+    /// When `None`, the certificate is valid from the moment it is created.
+    pub valid_from: Option<u64>,
+
+    /// End of the validity period in seconds since the Unix epoch
+    ///
+    /// When `None`, the certificate has no well-defined expiration date (`99991231235959Z`).
+    pub valid_until: Option<u64>,
+
+    /// Basic constraints extension, which is omitted when `None`
+    pub basic_constraints: Option<BasicConstraints>,
+
+    /// Key usage extension, which is omitted when empty
+    pub key_usage: Vec<KeyUsage>,
+
+    /// Additional extensions
+    pub extensions: Vec<X509Extension>,
+}
+
+/// X.509 v3 certificate ([RFC 5280](https://www.rfc-editor.org/rfc/rfc5280)) that is signed by a
+/// key of this crate
+///
+/// Certificates are signed with `ecdsa-with-SHA256`. The subject key identifier is always
+/// included, as is the authority key identifier, which is the subject key identifier of the
+/// issuer. This allows a key of the secure element to act as a small local certificate
+/// authority, or to be pinned for mTLS with a self-signed certificate.
+///
+/// # Examples
+///
+/// ```
+/// use secure_env::{
+///     BasicConstraints, Certificate, CertificateParams, KeyOps, KeyUsage,
+///     SecureEnvironmentConfig, SoftwareBackend,
+/// };
+///
+/// let backend = SoftwareBackend::new(SecureEnvironmentConfig::default());
+/// let ca_key = backend.create_keypair("ca").unwrap();
+/// let leaf_key = backend.generate_ephemeral_keypair(&Default::default()).unwrap();
+///
+/// let ca = Certificate::self_signed(
+///     &ca_key,
+///     &CertificateParams {
+///         subject: "CN=Device CA".to_owned(),
+///         basic_constraints: Some(BasicConstraints { ca: true, path_len: Some(0) }),
+///         key_usage: vec![KeyUsage::KeyCertSign],
+///         ..Default::default()
+///     },
+/// )
+/// .unwrap();
+///
+/// let leaf = Certificate::issue(
+///     &ca_key,
+///     &ca,
+///     &leaf_key.get_public_key().unwrap(),
+///     &CertificateParams {
+///         subject: "CN=Session".to_owned(),
+///         key_usage: vec![KeyUsage::DigitalSignature],
+///         ..Default::default()
+///     },
+/// )
+/// .unwrap();
+///
+/// assert!(leaf.to_pem().unwrap().starts_with("-----BEGIN CERTIFICATE-----"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Certificate {
+    inner: x509_cert::Certificate,
+}
+
+impl Certificate {
+    /// Build a self-signed certificate for the public key of `key` and sign it with `key`
+    pub fn self_signed(key: &impl KeyOps, params: &CertificateParams) -> SecureEnvResult<Self> {
+        let subject_public_key_info = subject_public_key_info(key)?;
+        let key_identifier = key_identifier(&subject_public_key_info).map_err(error)?;
+        let subject = parse_name(&params.subject).map_err(error)?;
+
+        Self::build(
+            key,
+            subject.clone(),
+            key_identifier,
+            subject,
+            subject_public_key_info,
+            params,
+        )
+    }
+
+    /// Build a certificate for the SEC1 encoded P-256 `public_key` of another key, e.g. an
+    /// ephemeral key, and sign it with `issuer_key`
+    ///
+    /// `issuer` is the certificate of `issuer_key`, which provides the issuer name and the
+    /// authority key identifier.
+    pub fn issue(
+        issuer_key: &impl KeyOps,
+        issuer: &Certificate,
+        public_key: &[u8],
+        params: &CertificateParams,
+    ) -> SecureEnvResult<Self> {
+        let issuer_tbs = &issuer.inner.tbs_certificate;
+        if issuer_tbs.subject_public_key_info != subject_public_key_info(issuer_key)? {
+            return Err(SecureEnvError::UnableToCreateCertificate(
+                "Issuer certificate does not certify the public key of the issuer key".to_owned(),
+            ));
+        }
+
+        let authority_key_identifier = match issuer.subject_key_identifier()? {
+            Some(key_identifier) => key_identifier,
+            None => key_identifier(&issuer_tbs.subject_public_key_info).map_err(error)?,
+        };
+
+        let public_key = VerifyingKey::from_sec1_bytes(public_key)
+            .map_err(|e| SecureEnvError::InvalidPublicKey(e.to_string()))?;
+        let subject_public_key_info = public_key
+            .to_public_key_der()
+            .and_then(|der| Ok(SubjectPublicKeyInfoOwned::from_der(der.as_bytes())?))
+            .map_err(|e| SecureEnvError::InvalidPublicKey(e.to_string()))?;
+
+        Self::build(
+            issuer_key,
+            issuer_tbs.subject.clone(),
+            authority_key_identifier,
+            parse_name(&params.subject).map_err(error)?,
+            subject_public_key_info,
+            params,
+        )
+    }
+
+    fn build(
+        issuer_key: &impl KeyOps,
+        issuer: Name,
+        authority_key_identifier: OctetString,
+        subject: Name,
+        subject_public_key_info: SubjectPublicKeyInfoOwned,
+        params: &CertificateParams,
+    ) -> SecureEnvResult<Self> {
+        let serial_number = match &params.serial_number {
+            Some(serial_number) => SerialNumber::new(serial_number),
+            None => {
+                let mut serial_number = [0u8; 16];
+                OsRng.fill_bytes(&mut serial_number);
+                // Keep the serial number positive and 16 bytes long
+                serial_number[0] = (serial_number[0] & 0x7f) | 0x40;
+                SerialNumber::new(&serial_number)
+            }
+        }
+        .map_err(error)?;
+
+        let valid_from = match params.valid_from {
+            Some(valid_from) => valid_from,
+            None => now()?,
+        };
+        if matches!(params.valid_until, Some(valid_until) if valid_until < valid_from) {
+            return Err(SecureEnvError::UnableToCreateCertificate(
+                "End of the validity period is before its start".to_owned(),
+            ));
+        }
+        let validity = Validity {
+            not_before: time(valid_from).map_err(error)?,
+            not_after: match params.valid_until {
+                Some(valid_until) => time(valid_until).map_err(error)?,
+                None => Time::INFINITY,
+            },
+        };
+
+        let mut extensions = Vec::new();
+        if let Some(constraints) = params.basic_constraints {
+            let constraints = pkix::BasicConstraints {
+                ca: constraints.ca,
+                path_len_constraint: constraints.path_len,
+            };
+            extensions.push(extension(ID_CE_BASIC_CONSTRAINTS, true, &constraints).map_err(error)?);
+        }
+        if !params.key_usage.is_empty() {
+            let usage = params
+                .key_usage
+                .iter()
+                .fold(pkix::KeyUsage(Default::default()), |usage, flag| {
+                    pkix::KeyUsage(usage.0 | KeyUsages::from(*flag))
+                });
+            extensions.push(extension(ID_CE_KEY_USAGE, true, &usage).map_err(error)?);
+        }
+        let subject_key_identifier = key_identifier(&subject_public_key_info).map_err(error)?;
+        extensions.push(
+            extension(
+                ID_CE_SUBJECT_KEY_IDENTIFIER,
+                false,
+                &pkix::SubjectKeyIdentifier(subject_key_identifier),
+            )
+            .map_err(error)?,
+        );
+        let authority_key_identifier = AuthorityKeyIdentifier {
+            key_identifier: Some(authority_key_identifier),
+            authority_cert_issuer: None,
+            authority_cert_serial_number: None,
+        };
+        extensions.push(
+            extension(
+                ID_CE_AUTHORITY_KEY_IDENTIFIER,
+                false,
+                &authority_key_identifier,
+            )
+            .map_err(error)?,
+        );
+        if !params.subject_alt_names.is_empty() {
+            extensions.push(
+                subject_alt_name_extension(&subject, &params.subject_alt_names).map_err(error)?,
+            );
+        }
+        for extension in &params.extensions {
+            extensions.push(extension.to_extension().map_err(error)?);
+        }
+
+        let tbs_certificate = TbsCertificate {
+            version: Version::V3,
+            serial_number,
+            signature: signature_algorithm(),
+            issuer,
+            validity,
+            subject,
+            subject_public_key_info,
+            issuer_unique_id: None,
+            subject_unique_id: None,
+            extensions: Some(extensions),
+        };
+
+        let signature = sign(issuer_key, &tbs_certificate.to_der().map_err(error)?)?;
+
+        Ok(Self {
+            inner: x509_cert::Certificate {
+                tbs_certificate,
+                signature_algorithm: signature_algorithm(),
+                signature,
+            },
+        })
+    }
+
+    /// Certificate from its DER encoding, e.g. to issue certificates with a stored certificate
+    /// authority
+    pub fn from_der(der: &[u8]) -> SecureEnvResult<Self> {
+        x509_cert::Certificate::from_der(der)
+            .map(|inner| Self { inner })
+            .map_err(|e| SecureEnvError::InvalidCertificate(e.to_string()))
+    }
+
+    /// Certificate from its PEM encoding, with the `CERTIFICATE` label
+    pub fn from_pem(pem: &str) -> SecureEnvResult<Self> {
+        x509_cert::Certificate::from_pem(pem)
+            .map(|inner| Self { inner })
+            .map_err(|e| SecureEnvError::InvalidCertificate(e.to_string()))
+    }
+
+    /// DER encoding of the certificate
+    pub fn to_der(&self) -> SecureEnvResult<Vec<u8>> {
+        self.inner.to_der().map_err(error)
+    }
+
+    /// PEM encoding of the certificate, with the `CERTIFICATE` label
+    pub fn to_pem(&self) -> SecureEnvResult<String> {
+        self.inner.to_pem(LineEnding::LF).map_err(error)
+    }
+
+    /// Value of the subject key identifier extension, if the certificate has one
+    fn subject_key_identifier(&self) -> SecureEnvResult<Option<OctetString>> {
+        let Some(extensions) = &self.inner.tbs_certificate.extensions else {
+            return Ok(None);
+        };
+
+        extensions
+            .iter()
+            .find(|extension| extension.extn_id == ID_CE_SUBJECT_KEY_IDENTIFIER)
+            .map(|extension| {
+                pkix::SubjectKeyIdentifier::from_der(extension.extn_value.as_bytes())
+                    .map(|key_identifier| key_identifier.0)
+                    .map_err(|e| SecureEnvError::InvalidCertificate(e.to_string()))
+            })
+            .transpose()
+    }
+}
+
+fn error(e: x509_cert::der::Error) -> SecureEnvError {
+    SecureEnvError::UnableToCreateCertificate(e.to_string())
+}
@@ -0,0 +1,133 @@
+use crate::{
+    error::{SecureEnvError, SecureEnvResult},
+    KeyId, MetadataStore,
+};
+use std::sync::Arc;
+
+/// Options that are used when generating a new keypair
+///
+/// The validity period and the usage limit are enforced by the `AndroidKeyStore` on Android. On
+/// the other backends they are enforced by this library, via the [`MetadataStore`] of the
+/// configuration, which is then required for keys that are stored. Signing outside of the validity
+/// period fails with [`crate::error::SecureEnvError::KeyExpired`] and signing beyond the usage
+/// limit fails with [`crate::error::SecureEnvError::UsageLimitExceeded`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyGenerationOptions {
+    /// Require biometric authentication before the key can be used for signing
+    ///
+    /// Requires Android 11 (API level 30) on Android.
+    pub backed_by_biometrics: bool,
+
+    /// Time from which the key can be used, in seconds since the Unix epoch
+    pub valid_from: Option<u64>,
+
+    /// Time until which the key can be used, in seconds since the Unix epoch
+    pub valid_until: Option<u64>,
+
+    /// Number of signatures that can be created with the key
+    ///
+    /// Requires Android 12 (API level 31) on Android. The `AndroidKeyStore` deletes the key after
+    /// its last use.
+    pub max_usage_count: Option<u32>,
+
+    /// Challenge that is included in the key attestation, e.g. a nonce of the party that verifies
+    /// the attestation
+    ///
+    /// Only supported on Android, from Android 7 (API level 24). The attestation chain can then be
+    /// retrieved via [`crate::KeyOps::attestation_chain`].
+    pub attestation_challenge: Option<Vec<u8>>,
+}
+
+impl KeyGenerationOptions {
+    /// Whether a validity period or a usage limit has been set
+    pub(crate) fn has_usage_limits(&self) -> bool {
+        self.valid_from.is_some() || self.valid_until.is_some() || self.max_usage_count.is_some()
+    }
+
+    /// Reject usage limits that can never be met
+    pub(crate) fn validate_usage_limits(&self) -> SecureEnvResult<()> {
+        if let (Some(valid_from), Some(valid_until)) = (self.valid_from, self.valid_until) {
+            if valid_until < valid_from {
+                return Err(SecureEnvError::UnableToGenerateKey(format!(
+                    "Key would never be valid, as it is valid until {valid_until} but only from {valid_from}"
+                )));
+            }
+        }
+
+        if self.max_usage_count == Some(0) {
+            return Err(SecureEnvError::UnableToGenerateKey(
+                "Key would never be usable, as its usage limit is 0".to_owned(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Handles to the platform that are required to talk to the secure element
+///
+/// On iOS no handles are required. On Android a `JavaVM` and an `android.content.Context` can be
+/// provided. When they are omitted, the handles that have been provided via `init_with_java_vm` or
+/// `init_with_context` are used.
+#[derive(Debug, Clone, Default)]
+pub struct PlatformHandles {
+    #[cfg(any(target_os = "android", feature = "android_host_testing"))]
+    pub(crate) jvm: Option<Arc<crate::android::Jvm>>,
+
+    #[cfg(any(target_os = "android", feature = "android_host_testing"))]
+    pub(crate) context: Option<Arc<crate::android::AndroidContext>>,
+}
+
+/// Configuration of a [`crate::SecureEnvironment`] instance
+#[derive(Debug, Clone, Default)]
+pub struct SecureEnvironmentConfig {
+    /// Namespace that is prepended to every key id, so multiple configurations can use the same
+    /// keystore without their ids colliding
+    ///
+    /// It has the same restrictions as a [`KeyId`], but may be at most
+    /// [`KeyId::MAX_NAMESPACE_LEN`] characters long.
+    pub namespace: Option<String>,
+
+    /// Options that are used when no explicit options are provided during key generation
+    pub default_options: KeyGenerationOptions,
+
+    /// Handles to the platform
+    pub platform: PlatformHandles,
+
+    /// Store for the [`crate::KeyMetadata`] of the keys, which is required for
+    /// [`crate::KeyOps::metadata`] and for finding keys by their tags
+    pub metadata_store: Option<Arc<dyn MetadataStore>>,
+}
+
+impl SecureEnvironmentConfig {
+    /// Id under which the key is stored in the keystore of the platform
+    ///
+    /// The namespace is validated here, as the configuration can be constructed freely.
+    pub(crate) fn scoped_id(&self, id: &KeyId) -> SecureEnvResult<String> {
+        match &self.namespace {
+            Some(namespace) => {
+                KeyId::validate_namespace(namespace)?;
+                Ok(format!("{namespace}:{id}"))
+            }
+            None => Ok(id.to_string()),
+        }
+    }
+
+    /// Id of a key that is stored under `scoped_id`, or `None` when it belongs to another
+    /// namespace
+    pub(crate) fn unscoped_id(&self, scoped_id: &str) -> Option<KeyId> {
+        unscoped_id(self.namespace.as_deref(), scoped_id)
+    }
+}
+
+/// Id of a key that is stored under `scoped_id`, or `None` when it does not belong to `namespace`
+///
+/// Ids can not contain a `:`, so ids of other namespaces are not valid ids.
+pub(crate) fn unscoped_id(namespace: Option<&str>, scoped_id: &str) -> Option<KeyId> {
+    let id = match namespace {
+        Some(namespace) => scoped_id.strip_prefix(namespace)?.strip_prefix(':')?,
+        None => scoped_id,
+    };
+
+    KeyId::new(id).ok()
+}
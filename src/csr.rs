@@ -0,0 +1,118 @@
+use crate::{
+    error::{SecureEnvError, SecureEnvResult},
+    x509::{
+        parse_name, sign, signature_algorithm, subject_alt_name_extension, subject_public_key_info,
+    },
+    KeyOps, SubjectAltName, X509Extension,
+};
+use x509_cert::{
+    der::{pem::LineEnding, Encode, EncodePem},
+    request::{CertReq, CertReqInfo, ExtensionReq, Version},
+};
+
+/// Contents of a certificate signing request
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CertificateRequestParams {
+    /// Distinguished name of the subject in the string representation of
+    /// [RFC 4514](https://www.rfc-editor.org/rfc/rfc4514), e.g. `CN=Device,O=Animo`
+    ///
+    /// An empty string results in an empty subject, in which case the subject alternative names
+    /// identify the subject.
+    pub subject: String,
+
+    /// Names for the subject alternative name extension, which is omitted when empty
+    pub subject_alt_names: Vec<SubjectAltName>,
+
+    /// Additional extensions that are requested for the certificate
+    pub extensions: Vec<X509Extension>,
+}
+
+/// PKCS#10 certificate signing request ([RFC 2986](https://www.rfc-editor.org/rfc/rfc2986)) that
+/// is signed by a key of this crate
+///
+/// The request is signed with `ecdsa-with-SHA256` and the requested extensions are included in
+/// the `extensionRequest` attribute.
+///
+/// # Examples
+///
+/// ```
+/// use secure_env::{
+///     CertificateRequest, CertificateRequestParams, SecureEnvironmentConfig, SoftwareBackend,
+///     SubjectAltName,
+/// };
+///
+/// let backend = SoftwareBackend::new(SecureEnvironmentConfig::default());
+/// let key = backend.create_keypair("my-unique-id").unwrap();
+///
+/// let request = CertificateRequest::new(
+///     &key,
+///     &CertificateRequestParams {
+///         subject: "CN=Device,O=Animo".to_owned(),
+///         subject_alt_names: vec![SubjectAltName::Dns("device.example.com".to_owned())],
+///         ..Default::default()
+///     },
+/// )
+/// .unwrap();
+///
+/// assert!(request.to_pem().unwrap().starts_with("-----BEGIN CERTIFICATE REQUEST-----"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateRequest {
+    inner: CertReq,
+}
+
+impl CertificateRequest {
+    /// Build the certificate signing request for the public key of `key` and sign it with `key`
+    pub fn new(key: &impl KeyOps, params: &CertificateRequestParams) -> SecureEnvResult<Self> {
+        let error = |e: x509_cert::der::Error| {
+            SecureEnvError::UnableToCreateCertificateRequest(e.to_string())
+        };
+
+        let subject = parse_name(&params.subject).map_err(error)?;
+
+        let mut extensions = Vec::new();
+        if !params.subject_alt_names.is_empty() {
+            extensions.push(
+                subject_alt_name_extension(&subject, &params.subject_alt_names).map_err(error)?,
+            );
+        }
+        for extension in &params.extensions {
+            extensions.push(extension.to_extension().map_err(error)?);
+        }
+
+        let mut info = CertReqInfo {
+            version: Version::V1,
+            subject,
+            public_key: subject_public_key_info(key)?,
+            attributes: Default::default(),
+        };
+        if !extensions.is_empty() {
+            let attribute = ExtensionReq(extensions).try_into().map_err(error)?;
+            info.attributes.insert(attribute).map_err(error)?;
+        }
+
+        let signature = sign(key, &info.to_der().map_err(error)?)?;
+
+        Ok(Self {
+            inner: CertReq {
+                info,
+                algorithm: signature_algorithm(),
+                signature,
+            },
+        })
+    }
+
+    /// DER encoding of the certificate signing request
+    pub fn to_der(&self) -> SecureEnvResult<Vec<u8>> {
+        self.inner
+            .to_der()
+            .map_err(|e| SecureEnvError::UnableToCreateCertificateRequest(e.to_string()))
+    }
+
+    /// PEM encoding of the certificate signing request, with the `CERTIFICATE REQUEST` label
+    pub fn to_pem(&self) -> SecureEnvResult<String> {
+        self.inner
+            .to_pem(LineEnding::LF)
+            .map_err(|e| SecureEnvError::UnableToCreateCertificateRequest(e.to_string()))
+    }
+}
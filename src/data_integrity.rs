@@ -0,0 +1,363 @@
+use crate::{
+    error::{SecureEnvError, SecureEnvResult},
+    metadata::now,
+    rust_crypto::verify_signature,
+    KeyOps,
+};
+use p256::ecdsa::VerifyingKey;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use x509_cert::der::DateTime;
+
+/// `type` of a Data Integrity proof
+const PROOF_TYPE: &str = "DataIntegrityProof";
+
+/// Cryptosuite of the proofs of this crate
+const CRYPTOSUITE: &str = "ecdsa-jcs-2019";
+
+/// Multicodec prefix of a compressed P-256 public key (`p256-pub`, `0x1200` as varint)
+const P256_PUB_MULTICODEC: [u8; 2] = [0x80, 0x24];
+
+/// Proof options of a Data Integrity proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataIntegrityProofOptions {
+    /// Reason the proof is created, `assertionMethod` by default
+    pub proof_purpose: String,
+
+    /// Security domain that the proof is restricted to, e.g. the verifier
+    pub domain: Option<String>,
+
+    /// Challenge that was provided by the verifier
+    pub challenge: Option<String>,
+}
+
+impl Default for DataIntegrityProofOptions {
+    fn default() -> Self {
+        Self {
+            proof_purpose: "assertionMethod".to_owned(),
+            domain: None,
+            challenge: None,
+        }
+    }
+}
+
+/// Data Integrity proof with the `ecdsa-jcs-2019` cryptosuite
+/// ([VC Data Integrity ECDSA Cryptosuites](https://www.w3.org/TR/vc-di-ecdsa/#ecdsa-jcs-2019))
+///
+/// The document and proof options are canonicalized with JCS, hashed with SHA-256 and signed with
+/// the key. The `verificationMethod` is the `did:key` of the key and the `proofValue` is the
+/// base58btc multibase encoded `r || s` signature.
+///
+/// # Examples
+///
+/// ```
+/// use secure_env::{
+///     DataIntegrityProof, DataIntegrityProofOptions, SecureEnvironmentConfig, SoftwareBackend,
+/// };
+/// use serde_json::json;
+///
+/// let backend = SoftwareBackend::new(SecureEnvironmentConfig::default());
+/// let key = backend.create_keypair("my-unique-id").unwrap();
+///
+/// let credential = json!({
+///     "@context": ["https://www.w3.org/ns/credentials/v2"],
+///     "type": ["VerifiableCredential"],
+///     "credentialSubject": { "name": "Alice" },
+/// });
+///
+/// let secured = DataIntegrityProof::sign(&key, &credential, &Default::default()).unwrap();
+///
+/// let proof = DataIntegrityProof::verify(&secured).unwrap();
+/// assert!(proof.verification_method.starts_with("did:key:zDn"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataIntegrityProof {
+    /// `DataIntegrityProof`
+    #[serde(rename = "type")]
+    pub proof_type: String,
+
+    /// `ecdsa-jcs-2019`
+    pub cryptosuite: String,
+
+    /// Creation time as an XML Schema `dateTime`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+
+    /// `did:key` URL of the key that created the proof
+    pub verification_method: String,
+
+    /// Reason the proof was created, e.g. `assertionMethod`
+    pub proof_purpose: String,
+
+    /// Security domain that the proof is restricted to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+
+    /// Challenge that was provided by the verifier
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub challenge: Option<String>,
+
+    /// Base58btc multibase encoded signature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof_value: Option<String>,
+}
+
+impl DataIntegrityProof {
+    /// Add a proof created with `key` to the JSON object `document` and return the secured
+    /// document
+    ///
+    /// `created` is set to the current time.
+    pub fn sign(
+        key: &impl KeyOps,
+        document: &Value,
+        options: &DataIntegrityProofOptions,
+    ) -> SecureEnvResult<Value> {
+        let error = |message: &str| SecureEnvError::UnableToCreateSignature(message.to_owned());
+
+        let Value::Object(document) = document else {
+            return Err(error("Document is not a JSON object"));
+        };
+        if document.contains_key("proof") {
+            return Err(error("Document already contains a proof"));
+        }
+
+        let created = DateTime::from_unix_duration(Duration::from_secs(now()?))
+            .map_err(|_| error("System time can not be represented as a dateTime"))?;
+        let did = did_key(&key.get_public_key()?)?;
+
+        let proof = Self {
+            proof_type: PROOF_TYPE.to_owned(),
+            cryptosuite: CRYPTOSUITE.to_owned(),
+            created: Some(created.to_string()),
+            verification_method: format!("{did}#{}", &did["did:key:".len()..]),
+            proof_purpose: options.proof_purpose.clone(),
+            domain: options.domain.clone(),
+            challenge: options.challenge.clone(),
+            proof_value: None,
+        };
+        let Value::Object(proof_options) =
+            serde_json::to_value(&proof).map_err(|e| error(&e.to_string()))?
+        else {
+            return Err(error("Proof options are not a JSON object"));
+        };
+
+        let signature = key.sign(&hash_data(document, proof_options)?)?;
+
+        let mut secured = document.clone();
+        secured.insert(
+            "proof".to_owned(),
+            serde_json::to_value(Self {
+                proof_value: Some(format!("z{}", bs58::encode(signature).into_string())),
+                ..proof
+            })
+            .map_err(|e| error(&e.to_string()))?,
+        );
+
+        Ok(Value::Object(secured))
+    }
+
+    /// Verify the proof of a secured document with the key of its `did:key` verification method
+    ///
+    /// Checking the `created`, `domain`, `challenge` and `proofPurpose` of the returned proof is
+    /// left to the verifier.
+    pub fn verify(secured_document: &Value) -> SecureEnvResult<Self> {
+        let error = |message: &str| SecureEnvError::InvalidSignature(message.to_owned());
+
+        let Value::Object(document) = secured_document else {
+            return Err(error("Document is not a JSON object"));
+        };
+        let mut document = document.clone();
+        let Some(Value::Object(mut proof_options)) = document.remove("proof") else {
+            return Err(error("Document does not contain a single proof"));
+        };
+
+        let proof: Self = serde_json::from_value(Value::Object(proof_options.clone()))
+            .map_err(|e| error(&e.to_string()))?;
+        if proof.proof_type != PROOF_TYPE || proof.cryptosuite != CRYPTOSUITE {
+            return Err(error("Proof is not an ecdsa-jcs-2019 Data Integrity proof"));
+        }
+        proof_options.remove("proofValue");
+
+        // A proof with its own `@context` was created for a document with these contexts first
+        if let Some(context) = proof_options.get("@context") {
+            let contexts = |context: &Value| match context {
+                Value::Array(contexts) => contexts.clone(),
+                context => vec![context.clone()],
+            };
+            if !document
+                .get("@context")
+                .map(contexts)
+                .unwrap_or_default()
+                .starts_with(&contexts(context))
+            {
+                return Err(error(
+                    "Document does not start with the contexts of the proof",
+                ));
+            }
+            document.insert("@context".to_owned(), context.clone());
+        }
+
+        let signature = proof
+            .proof_value
+            .as_deref()
+            .and_then(|proof_value| proof_value.strip_prefix('z'))
+            .and_then(|proof_value| bs58::decode(proof_value).into_vec().ok())
+            .ok_or_else(|| error("Proof value is not base58btc multibase encoded"))?;
+
+        let (did, fragment) = proof
+            .verification_method
+            .split_once('#')
+            .unwrap_or((proof.verification_method.as_str(), ""));
+        let public_key = did_key_public_key(did)?;
+        if !fragment.is_empty() && did.strip_prefix("did:key:") != Some(fragment) {
+            return Err(error("Verification method is not the key of the did:key"));
+        }
+
+        verify_signature(
+            &public_key,
+            &hash_data(&document, proof_options)?,
+            &signature,
+        )?;
+
+        Ok(proof)
+    }
+}
+
+/// `did:key` of a SEC1 encoded P-256 public key
+/// ([did:key](https://w3c-ccg.github.io/did-method-key/)), e.g. `did:key:zDnae...`
+pub fn did_key(public_key: &[u8]) -> SecureEnvResult<String> {
+    let verifying_key = VerifyingKey::from_sec1_bytes(public_key)
+        .map_err(|e| SecureEnvError::InvalidPublicKey(e.to_string()))?;
+
+    let mut multikey = P256_PUB_MULTICODEC.to_vec();
+    multikey.extend(verifying_key.to_encoded_point(true).as_bytes());
+
+    Ok(format!("did:key:z{}", bs58::encode(multikey).into_string()))
+}
+
+/// Compressed SEC1 encoded P-256 public key of a `did:key`
+fn did_key_public_key(did: &str) -> SecureEnvResult<Vec<u8>> {
+    let multikey = did
+        .strip_prefix("did:key:z")
+        .and_then(|multikey| bs58::decode(multikey).into_vec().ok())
+        .ok_or_else(|| {
+            SecureEnvError::InvalidPublicKey("Verification method is not a did:key".to_owned())
+        })?;
+
+    match multikey.strip_prefix(&P256_PUB_MULTICODEC) {
+        Some(public_key) if public_key.len() == 33 => Ok(public_key.to_vec()),
+        _ => Err(SecureEnvError::InvalidPublicKey(
+            "Only compressed P-256 did:key keys are supported".to_owned(),
+        )),
+    }
+}
+
+/// Hash of the proof configuration followed by the hash of the document, which is signed
+fn hash_data(
+    document: &Map<String, Value>,
+    mut proof_config: Map<String, Value>,
+) -> SecureEnvResult<Vec<u8>> {
+    if let Some(context) = document.get("@context") {
+        proof_config.insert("@context".to_owned(), context.clone());
+    }
+
+    let proof_config = canonicalize_json(&Value::Object(proof_config))?;
+    let document = canonicalize_json(&Value::Object(document.clone()))?;
+
+    let mut hash_data = Sha256::digest(proof_config.as_bytes()).to_vec();
+    hash_data.extend(Sha256::digest(document.as_bytes()));
+
+    Ok(hash_data)
+}
+
+/// Canonical serialization of `value` with the JSON Canonicalization Scheme
+/// ([RFC 8785](https://www.rfc-editor.org/rfc/rfc8785))
+///
+/// Object members are sorted by the UTF-16 code units of their names and numbers are serialized as
+/// ECMAScript does. JSON text should be parsed with the `float_roundtrip` feature of `serde_json`,
+/// as its default float parsing is not exact.
+pub fn canonicalize_json(value: &Value) -> SecureEnvResult<String> {
+    let mut canonical = String::new();
+    write_canonical(value, &mut canonical)?;
+
+    Ok(canonical)
+}
+
+fn write_canonical(value: &Value, canonical: &mut String) -> SecureEnvResult<()> {
+    match value {
+        Value::Null | Value::Bool(_) | Value::String(_) => canonical.push_str(
+            &serde_json::to_string(value)
+                .map_err(|e| SecureEnvError::UnableToCreateSignature(e.to_string()))?,
+        ),
+        Value::Number(number) => {
+            let number = number.as_f64().ok_or_else(|| {
+                SecureEnvError::UnableToCreateSignature(format!(
+                    "Number {number} is not an IEEE 754 double"
+                ))
+            })?;
+            canonical.push_str(&ecmascript_number(number));
+        }
+        Value::Array(values) => {
+            canonical.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    canonical.push(',');
+                }
+                write_canonical(value, canonical)?;
+            }
+            canonical.push(']');
+        }
+        Value::Object(members) => {
+            let mut members = members.iter().collect::<Vec<_>>();
+            members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+
+            canonical.push('{');
+            for (i, (name, value)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    canonical.push(',');
+                }
+                write_canonical(&Value::String(name.clone()), canonical)?;
+                canonical.push(':');
+                write_canonical(value, canonical)?;
+            }
+            canonical.push('}');
+        }
+    }
+
+    Ok(())
+}
+
+/// Serialization of a finite number by ECMAScript's `Number.prototype.toString`
+fn ecmascript_number(number: f64) -> String {
+    if number == 0.0 {
+        return "0".to_owned();
+    }
+    if number < 0.0 {
+        return format!("-{}", ecmascript_number(-number));
+    }
+
+    // The shortest digits that round trip, as `d.ddde<exponent>`
+    let scientific = format!("{number:e}");
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let digits = mantissa.replace('.', "");
+    let k = digits.len() as i32;
+    let n = exponent.parse::<i32>().unwrap_or_default() + 1;
+
+    if k <= n && n <= 21 {
+        format!("{digits}{}", "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{digits}", "0".repeat(-n as usize))
+    } else {
+        let sign = if n - 1 < 0 { '-' } else { '+' };
+        let exponent = (n - 1).abs();
+        match digits.split_at(1) {
+            (first, "") => format!("{first}e{sign}{exponent}"),
+            (first, rest) => format!("{first}.{rest}e{sign}{exponent}"),
+        }
+    }
+}
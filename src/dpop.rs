@@ -0,0 +1,215 @@
+use crate::{
+    error::{SecureEnvError, SecureEnvResult},
+    jws::{self, UnverifiedJws},
+    metadata::now,
+    Jwk, KeyOps,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+/// `typ` header parameter of a DPoP proof
+const DPOP_TYPE: &str = "dpop+jwt";
+
+/// Claims of a DPoP proof ([RFC 9449 section 4.2](https://www.rfc-editor.org/rfc/rfc9449#section-4.2))
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DpopClaims {
+    /// Unique identifier of the proof, which lets the server detect replays
+    pub jti: String,
+
+    /// HTTP method of the request
+    pub htm: String,
+
+    /// HTTP target URI of the request, without query and fragment
+    pub htu: String,
+
+    /// Creation time in seconds since the Unix epoch
+    pub iat: u64,
+
+    /// Nonce that was provided by the server in the `DPoP-Nonce` header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+
+    /// Base64url encoded SHA-256 hash of the access token that is sent with the proof
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ath: Option<String>,
+}
+
+/// Request that a DPoP proof is created for
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DpopProofParams {
+    /// HTTP method of the request, e.g. `POST`
+    pub htm: String,
+
+    /// HTTP target URI of the request; the query and fragment are removed
+    pub htu: String,
+
+    /// Nonce that was provided by the server in the `DPoP-Nonce` header
+    pub nonce: Option<String>,
+
+    /// Access token that is sent with the proof, whose hash is included as `ath`
+    pub access_token: Option<String>,
+}
+
+/// Expectations of a resource or authorization server for a DPoP proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DpopValidation {
+    /// HTTP method of the request that the proof was received with
+    pub htm: String,
+
+    /// HTTP target URI of the request that the proof was received with; the query and fragment are
+    /// ignored
+    pub htu: String,
+
+    /// Nonce that the server provided, which the proof must contain
+    pub nonce: Option<String>,
+
+    /// Access token that the proof was received with, whose hash must match `ath`
+    pub access_token: Option<String>,
+
+    /// JWK thumbprint that the access token is bound to (`cnf.jkt`), which must match the key of
+    /// the proof
+    pub jkt: Option<String>,
+
+    /// Maximum age of the proof in seconds, 300 by default
+    pub max_age: u64,
+
+    /// Number of seconds that `iat` may be in the future to allow for clock skew, 60 by default
+    pub leeway: u64,
+}
+
+impl Default for DpopValidation {
+    fn default() -> Self {
+        Self {
+            htm: String::new(),
+            htu: String::new(),
+            nonce: None,
+            access_token: None,
+            jkt: None,
+            max_age: jws::DEFAULT_MAX_AGE,
+            leeway: jws::DEFAULT_LEEWAY,
+        }
+    }
+}
+
+/// DPoP proof ([RFC 9449](https://www.rfc-editor.org/rfc/rfc9449)), a JWT that binds a request to
+/// a key of this crate
+///
+/// Proofs are signed with `ES256` and carry the public key in the `jwk` header parameter.
+///
+/// # Examples
+///
+/// ```
+/// use secure_env::{
+///     DpopProof, DpopProofParams, DpopValidation, SecureEnvironmentConfig, SoftwareBackend,
+/// };
+///
+/// let backend = SoftwareBackend::new(SecureEnvironmentConfig::default());
+/// let key = backend.create_keypair("my-unique-id").unwrap();
+///
+/// let token = DpopProof::create(
+///     &key,
+///     &DpopProofParams {
+///         htm: "POST".to_owned(),
+///         htu: "https://server.example.com/token".to_owned(),
+///         ..Default::default()
+///     },
+/// )
+/// .unwrap();
+///
+/// let proof = DpopProof::validate(
+///     &token,
+///     &DpopValidation {
+///         htm: "POST".to_owned(),
+///         htu: "https://server.example.com/token".to_owned(),
+///         ..Default::default()
+///     },
+/// )
+/// .unwrap();
+/// assert_eq!(proof.claims.htm, "POST");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DpopProof {
+    /// Public key of the proof, from the `jwk` header parameter
+    pub jwk: Jwk,
+
+    /// Claims of the proof
+    pub claims: DpopClaims,
+}
+
+impl DpopProof {
+    /// Create a proof for the request with `key` and return the compact token, which is sent in
+    /// the `DPoP` header
+    ///
+    /// A random `jti` is generated and `iat` is set to the current time.
+    pub fn create(key: &impl KeyOps, params: &DpopProofParams) -> SecureEnvResult<String> {
+        let mut jti = [0u8; 16];
+        OsRng.fill_bytes(&mut jti);
+
+        let claims = DpopClaims {
+            jti: URL_SAFE_NO_PAD.encode(jti),
+            htm: params.htm.clone(),
+            htu: without_query_and_fragment(&params.htu).to_owned(),
+            iat: now()?,
+            nonce: params.nonce.clone(),
+            ath: params.access_token.as_deref().map(access_token_hash),
+        };
+        let header = json!({
+            "typ": DPOP_TYPE,
+            "alg": jws::ALGORITHM,
+            "jwk": Jwk::from_public_key(&key.get_public_key()?)?,
+        });
+
+        jws::sign(key, &header, &claims)
+    }
+
+    /// Validate a proof as described in
+    /// [RFC 9449 section 4.3](https://www.rfc-editor.org/rfc/rfc9449#section-4.3)
+    ///
+    /// The signature is verified with the key in the `jwk` header parameter. Detecting a replayed
+    /// `jti` is left to the server, as it requires storage.
+    pub fn validate(token: &str, validation: &DpopValidation) -> SecureEnvResult<Self> {
+        let error = |message: &str| SecureEnvError::InvalidToken(message.to_owned());
+
+        let jws = UnverifiedJws::<DpopClaims>::decode(token, DPOP_TYPE)?;
+        let jwk = jws.jwk()?;
+        let claims = jws.verify(&jwk.to_public_key()?)?;
+
+        if claims.htm != validation.htm {
+            return Err(error("Proof is for another HTTP method"));
+        }
+        if without_query_and_fragment(&claims.htu) != without_query_and_fragment(&validation.htu) {
+            return Err(error("Proof is for another HTTP target URI"));
+        }
+        if validation.nonce.is_some() && claims.nonce != validation.nonce {
+            return Err(error("Proof does not contain the expected nonce"));
+        }
+        if let Some(access_token) = &validation.access_token {
+            if claims.ath.as_deref() != Some(access_token_hash(access_token).as_str()) {
+                return Err(error("Proof is not bound to the access token"));
+            }
+        }
+        if let Some(jkt) = &validation.jkt {
+            if jwk.thumbprint() != *jkt {
+                return Err(error(
+                    "Proof is signed by another key than the access token is bound to",
+                ));
+            }
+        }
+
+        jws::check_issued_at(claims.iat, validation.max_age, validation.leeway)?;
+
+        Ok(Self { jwk, claims })
+    }
+}
+
+/// Value of the `ath` claim, the base64url encoded SHA-256 hash of the access token
+pub fn access_token_hash(access_token: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(access_token.as_bytes()))
+}
+
+fn without_query_and_fragment(uri: &str) -> &str {
+    uri.split(['?', '#']).next().unwrap_or_default()
+}
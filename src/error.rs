@@ -12,15 +12,68 @@ pub enum SecureEnvError {
     #[error("Unable to get public key. Additional info: {0}")]
     UnableToGetPublicKey(String),
 
-    #[cfg(target_os = "android")]
+    #[error("Unable to delete key. Additional info: {0}")]
+    UnableToDeleteKey(String),
+
+    #[error("Unable to list keys. Additional info: {0}")]
+    UnableToListKeys(String),
+
+    #[error("Unable to get attestation chain. Additional info: {0}")]
+    UnableToGetAttestationChain(String),
+
+    #[error("Invalid key id. Additional info: {0}")]
+    InvalidKeyId(String),
+
+    #[error("Unable to access the metadata of the key. Additional info: {0}")]
+    UnableToAccessMetadata(String),
+
+    #[error("Invalid public key. Additional info: {0}")]
+    InvalidPublicKey(String),
+
+    #[error("Key is outside of its validity period. Additional info: {0}")]
+    KeyExpired(String),
+
+    #[error("Key has reached its usage limit. Additional info: {0}")]
+    UsageLimitExceeded(String),
+
+    #[error("Invalid system time. Additional info: {0}")]
+    InvalidSystemTime(String),
+
+    #[error("Unable to create certificate signing request. Additional info: {0}")]
+    UnableToCreateCertificateRequest(String),
+
+    #[error("Unable to create certificate. Additional info: {0}")]
+    UnableToCreateCertificate(String),
+
+    #[error("Invalid certificate. Additional info: {0}")]
+    InvalidCertificate(String),
+
+    #[error("Invalid signature. Additional info: {0}")]
+    InvalidSignature(String),
+
+    #[error("Invalid token. Additional info: {0}")]
+    InvalidToken(String),
+
+    #[error("Invalid CBOR. Additional info: {0}")]
+    InvalidCbor(String),
+
+    #[cfg(any(target_os = "android", feature = "android_host_testing"))]
+    #[error("Secure environment has not been initialized. Additional info: {0}")]
+    NotInitialized(String),
+
+    #[cfg(any(target_os = "android", feature = "android_host_testing"))]
     #[error("Unable to attach JVM to thread. Additional info: {0}")]
     UnableToAttachJVMToThread(String),
 
-    #[cfg(target_os = "android")]
+    #[cfg(any(target_os = "android", feature = "android_host_testing"))]
     #[error("Unable to create java value. Additional info: {0}")]
     UnableToCreateJavaValue(String),
 
-    #[cfg(target_os = "android")]
+    #[cfg(any(
+        target_os = "android",
+        feature = "android_host_testing",
+        feature = "mock"
+    ))]
     #[error("Device does not support hardware backed keys. Additional info: {0}")]
     HardwareBackedKeysAreNotSupported(String),
 }
@@ -0,0 +1,545 @@
+//! HTTP Message Signatures ([RFC 9421](https://www.rfc-editor.org/rfc/rfc9421)) that are created
+//! with the keys of this crate
+//!
+//! The signatures work on an [`HttpRequest`], a plain description of a request, so they can be
+//! used with any HTTP client. Every key is a P-256 key, so the algorithm is always
+//! `ecdsa-p256-sha256`.
+
+use crate::{
+    error::{SecureEnvError, SecureEnvResult},
+    metadata::now,
+    rust_crypto::verify_signature,
+    KeyOps,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sha2::{Digest, Sha256};
+
+/// Algorithm of every signature ([RFC 9421 section 3.3.4](https://www.rfc-editor.org/rfc/rfc9421#section-3.3.4))
+const ALGORITHM: &str = "ecdsa-p256-sha256";
+
+/// Description of an HTTP request, independent of any HTTP client
+///
+/// # Examples
+///
+/// ```
+/// use secure_env::{
+///     HttpRequest, HttpSignatureParams, KeyOps, SecureEnvironmentConfig, SoftwareBackend,
+/// };
+///
+/// let backend = SoftwareBackend::new(SecureEnvironmentConfig::default());
+/// let key = backend.create_keypair("my-unique-id").unwrap();
+///
+/// let mut request = HttpRequest {
+///     method: "POST".to_owned(),
+///     target_uri: "https://example.com/foo?param=value".to_owned(),
+///     headers: vec![("Content-Type".to_owned(), "application/json".to_owned())],
+/// };
+/// request.set_content_digest(br#"{"hello": "world"}"#);
+///
+/// let headers = request
+///     .sign(
+///         &key,
+///         &HttpSignatureParams {
+///             components: vec![
+///                 "@method".to_owned(),
+///                 "@target-uri".to_owned(),
+///                 "content-type".to_owned(),
+///                 "content-digest".to_owned(),
+///             ],
+///             key_id: Some("my-unique-id".to_owned()),
+///             ..Default::default()
+///         },
+///     )
+///     .unwrap();
+/// request.headers.push(("Signature-Input".to_owned(), headers.signature_input));
+/// request.headers.push(("Signature".to_owned(), headers.signature));
+///
+/// let params = request
+///     .verify_signature(&key.get_public_key().unwrap(), "sig1")
+///     .unwrap();
+/// assert_eq!(params.key_id.as_deref(), Some("my-unique-id"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HttpRequest {
+    /// Method of the request, e.g. `POST`
+    pub method: String,
+
+    /// Absolute URI of the request, e.g. `https://example.com/foo?param=value`
+    pub target_uri: String,
+
+    /// Header fields in the order in which they are sent, whose names are matched
+    /// case-insensitively
+    pub headers: Vec<(String, String)>,
+}
+
+/// Parameters of a signature, which are included in the `Signature-Input` header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpSignatureParams {
+    /// Label of the signature in the `Signature-Input` and `Signature` headers, `sig1` by default
+    pub label: String,
+
+    /// Covered components: derived components like `@method`, `@target-uri`, `@authority`,
+    /// `@scheme`, `@request-target`, `@path` and `@query`, or header names like `content-digest`
+    pub components: Vec<String>,
+
+    /// Creation time in seconds since the Unix epoch
+    ///
+    /// [`HttpRequest::sign`] uses the current time when it is `None`.
+    pub created: Option<u64>,
+
+    /// Expiration time in seconds since the Unix epoch
+    pub expires: Option<u64>,
+
+    /// Identifier of the key, which lets the verifier select the public key
+    pub key_id: Option<String>,
+
+    /// Nonce, e.g. one that is provided by the verifier to prevent replays
+    pub nonce: Option<String>,
+
+    /// Application specific tag of the signature
+    pub tag: Option<String>,
+}
+
+impl Default for HttpSignatureParams {
+    fn default() -> Self {
+        Self {
+            label: "sig1".to_owned(),
+            components: vec!["@method".to_owned(), "@target-uri".to_owned()],
+            created: None,
+            expires: None,
+            key_id: None,
+            nonce: None,
+            tag: None,
+        }
+    }
+}
+
+/// Values of the headers that carry a signature
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpSignatureHeaders {
+    /// Value of the `Signature-Input` header
+    pub signature_input: String,
+
+    /// Value of the `Signature` header
+    pub signature: String,
+}
+
+impl HttpRequest {
+    /// Combined value of every header field with `name`, separated by `, `
+    pub fn header(&self, name: &str) -> Option<String> {
+        let values = self
+            .headers
+            .iter()
+            .filter(|(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim())
+            .collect::<Vec<_>>();
+
+        (!values.is_empty()).then(|| values.join(", "))
+    }
+
+    /// Set the `Content-Digest` header ([RFC 9530](https://www.rfc-editor.org/rfc/rfc9530)) to the
+    /// SHA-256 digest of `body`, so the body can be covered by the signature via `content-digest`
+    pub fn set_content_digest(&mut self, body: &[u8]) {
+        self.headers
+            .retain(|(field, _)| !field.eq_ignore_ascii_case("content-digest"));
+        self.headers.push((
+            "Content-Digest".to_owned(),
+            format!("sha-256=:{}:", STANDARD.encode(Sha256::digest(body))),
+        ));
+    }
+
+    /// Check that the SHA-256 digest in the `Content-Digest` header matches `body`
+    pub fn verify_content_digest(&self, body: &[u8]) -> SecureEnvResult<()> {
+        let content_digest = self.header("content-digest").ok_or_else(|| {
+            SecureEnvError::InvalidSignature("Request has no Content-Digest header".to_owned())
+        })?;
+        let digest = dictionary_member(&content_digest, "sha-256")
+            .and_then(byte_sequence)
+            .ok_or_else(|| {
+                SecureEnvError::InvalidSignature(
+                    "Content-Digest header has no sha-256 digest".to_owned(),
+                )
+            })?;
+
+        if digest != Sha256::digest(body).as_slice() {
+            return Err(SecureEnvError::InvalidSignature(
+                "Content digest does not match the body".to_owned(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Signature base ([RFC 9421 section 2.5](https://www.rfc-editor.org/rfc/rfc9421#section-2.5))
+    /// of the request for `params`, which is the message that is signed
+    pub fn signature_base(&self, params: &HttpSignatureParams) -> SecureEnvResult<String> {
+        self.signature_base_with_input(&params.components, &params.serialize()?)
+    }
+
+    /// Sign the request with `key` and return the values of the `Signature-Input` and `Signature`
+    /// headers, which are not added to the request
+    pub fn sign(
+        &self,
+        key: &impl KeyOps,
+        params: &HttpSignatureParams,
+    ) -> SecureEnvResult<HttpSignatureHeaders> {
+        let mut params = params.clone();
+        if params.created.is_none() {
+            params.created = Some(now()?);
+        }
+
+        let signature_params = params.serialize()?;
+        let signature_base =
+            self.signature_base_with_input(&params.components, &signature_params)?;
+        let signature = key.sign(signature_base.as_bytes())?;
+
+        Ok(HttpSignatureHeaders {
+            signature_input: format!("{}={signature_params}", params.label),
+            signature: format!("{}=:{}:", params.label, STANDARD.encode(signature)),
+        })
+    }
+
+    /// Verify the signature with `label` in the `Signature-Input` and `Signature` headers with the
+    /// SEC1 encoded P-256 `public_key`
+    ///
+    /// The parameters of the signature are returned, so the verifier can check that the expected
+    /// components are covered and that the `created`, `key_id` and `nonce` are acceptable. An
+    /// expired signature or one with another algorithm than `ecdsa-p256-sha256` is rejected.
+    pub fn verify_signature(
+        &self,
+        public_key: &[u8],
+        label: &str,
+    ) -> SecureEnvResult<HttpSignatureParams> {
+        let error = |message: &str| SecureEnvError::InvalidSignature(message.to_owned());
+
+        let signature_input = self
+            .header("signature-input")
+            .ok_or_else(|| error("Request has no Signature-Input header"))?;
+        let signature_params = dictionary_member(&signature_input, label)
+            .ok_or_else(|| error("Signature-Input header has no signature with the label"))?;
+        let (params, algorithm) = HttpSignatureParams::parse(label, signature_params)?;
+
+        if matches!(&algorithm, Some(algorithm) if algorithm != ALGORITHM) {
+            return Err(error("Signature algorithm is not ecdsa-p256-sha256"));
+        }
+        if let Some(expires) = params.expires {
+            if expires < now()? {
+                return Err(error("Signature has expired"));
+            }
+        }
+
+        let signature = self
+            .header("signature")
+            .ok_or_else(|| error("Request has no Signature header"))?;
+        let signature = dictionary_member(&signature, label)
+            .and_then(byte_sequence)
+            .ok_or_else(|| error("Signature header has no signature with the label"))?;
+
+        let signature_base =
+            self.signature_base_with_input(&params.components, signature_params)?;
+        verify_signature(public_key, signature_base.as_bytes(), &signature)?;
+
+        Ok(params)
+    }
+
+    /// Signature base with the serialized `@signature-params`, which the verifier takes verbatim
+    /// from the `Signature-Input` header
+    fn signature_base_with_input(
+        &self,
+        components: &[String],
+        signature_params: &str,
+    ) -> SecureEnvResult<String> {
+        let mut lines = Vec::with_capacity(components.len() + 1);
+        for (index, component) in components.iter().enumerate() {
+            let component = component.to_ascii_lowercase();
+            if components[..index]
+                .iter()
+                .any(|other| other.eq_ignore_ascii_case(&component))
+            {
+                return Err(SecureEnvError::UnableToCreateSignature(format!(
+                    "Component '{component}' is covered more than once"
+                )));
+            }
+
+            let value = self.component_value(&component)?;
+            lines.push(format!("\"{component}\": {value}"));
+        }
+        lines.push(format!("\"@signature-params\": {signature_params}"));
+
+        Ok(lines.join("\n"))
+    }
+
+    /// Value of a derived component or header field
+    fn component_value(&self, component: &str) -> SecureEnvResult<String> {
+        let uri = || {
+            TargetUri::parse(&self.target_uri).ok_or_else(|| {
+                SecureEnvError::UnableToCreateSignature(format!(
+                    "Target URI '{}' is not an absolute URI",
+                    self.target_uri
+                ))
+            })
+        };
+
+        let value = match component {
+            "@method" => self.method.clone(),
+            "@target-uri" => self.target_uri.clone(),
+            "@authority" => uri()?.authority(),
+            "@scheme" => uri()?.scheme.to_ascii_lowercase(),
+            "@request-target" => {
+                let uri = uri()?;
+                match uri.query {
+                    Some(query) => format!("{}?{query}", uri.path()),
+                    None => uri.path().to_owned(),
+                }
+            }
+            "@path" => uri()?.path().to_owned(),
+            "@query" => format!("?{}", uri()?.query.unwrap_or_default()),
+            derived if derived.starts_with('@') => {
+                return Err(SecureEnvError::UnableToCreateSignature(format!(
+                    "Derived component '{derived}' is not supported"
+                )))
+            }
+            header => self.header(header).ok_or_else(|| {
+                SecureEnvError::UnableToCreateSignature(format!("Request has no '{header}' header"))
+            })?,
+        };
+
+        Ok(value)
+    }
+}
+
+impl HttpSignatureParams {
+    /// Serialization of the parameters as an inner list
+    /// ([RFC 8941 section 3.1.1](https://www.rfc-editor.org/rfc/rfc8941#section-3.1.1)), as used
+    /// in the signature base and the `Signature-Input` header
+    fn serialize(&self) -> SecureEnvResult<String> {
+        let components = self
+            .components
+            .iter()
+            .map(|component| string(&component.to_ascii_lowercase()))
+            .collect::<SecureEnvResult<Vec<_>>>()?;
+
+        let mut serialized = format!("({})", components.join(" "));
+        if let Some(created) = self.created {
+            serialized.push_str(&format!(";created={created}"));
+        }
+        if let Some(expires) = self.expires {
+            serialized.push_str(&format!(";expires={expires}"));
+        }
+        if let Some(key_id) = &self.key_id {
+            serialized.push_str(&format!(";keyid={}", string(key_id)?));
+        }
+        if let Some(nonce) = &self.nonce {
+            serialized.push_str(&format!(";nonce={}", string(nonce)?));
+        }
+        if let Some(tag) = &self.tag {
+            serialized.push_str(&format!(";tag={}", string(tag)?));
+        }
+
+        Ok(serialized)
+    }
+
+    /// Parse the serialized parameters with `label`, and return them with the `alg` parameter
+    fn parse(label: &str, serialized: &str) -> SecureEnvResult<(Self, Option<String>)> {
+        let error = |message: String| SecureEnvError::InvalidSignature(message);
+
+        let (components, parameters) = serialized
+            .strip_prefix('(')
+            .and_then(|serialized| serialized.split_once(')'))
+            .ok_or_else(|| {
+                error(format!(
+                    "Signature input '{serialized}' is not an inner list"
+                ))
+            })?;
+
+        if !parameters.is_empty() && !parameters.starts_with(';') {
+            return Err(error(format!(
+                "Signature input '{serialized}' has invalid parameters"
+            )));
+        }
+
+        let components = components
+            .split(' ')
+            .filter(|component| !component.is_empty())
+            .map(|component| {
+                parse_string(component)
+                    .ok_or_else(|| error(format!("Component '{component}' is not supported")))
+            })
+            .collect::<SecureEnvResult<Vec<_>>>()?;
+
+        let mut params = Self {
+            label: label.to_owned(),
+            components,
+            created: None,
+            expires: None,
+            key_id: None,
+            nonce: None,
+            tag: None,
+        };
+        let mut algorithm = None;
+
+        for parameter in split_top_level(parameters, ';').into_iter().skip(1) {
+            let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+            let integer = || {
+                value
+                    .parse::<u64>()
+                    .map_err(|_| error(format!("Parameter '{name}' is not an integer")))
+            };
+            let text = || {
+                parse_string(value)
+                    .ok_or_else(|| error(format!("Parameter '{name}' is not a string")))
+            };
+
+            match name {
+                "created" => params.created = Some(integer()?),
+                "expires" => params.expires = Some(integer()?),
+                "keyid" => params.key_id = Some(text()?),
+                "nonce" => params.nonce = Some(text()?),
+                "tag" => params.tag = Some(text()?),
+                "alg" => algorithm = Some(text()?),
+                // Unknown parameters are covered by the signature, but otherwise ignored
+                _ => {}
+            }
+        }
+
+        Ok((params, algorithm))
+    }
+}
+
+/// Components of an absolute URI that are used by the derived components
+struct TargetUri<'a> {
+    scheme: &'a str,
+    authority: &'a str,
+    path: &'a str,
+    query: Option<&'a str>,
+}
+
+impl<'a> TargetUri<'a> {
+    fn parse(uri: &'a str) -> Option<Self> {
+        let (scheme, rest) = uri.split_once("://")?;
+        let rest = rest.split('#').next().unwrap_or_default();
+        let (rest, query) = match rest.split_once('?') {
+            Some((rest, query)) => (rest, Some(query)),
+            None => (rest, None),
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, ""),
+        };
+
+        if scheme.is_empty() || authority.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            scheme,
+            authority,
+            path,
+            query,
+        })
+    }
+
+    /// Lowercase authority without the default port of the scheme
+    fn authority(&self) -> String {
+        let authority = self.authority.to_ascii_lowercase();
+        let default_port = match self.scheme.to_ascii_lowercase().as_str() {
+            "https" => ":443",
+            "http" => ":80",
+            _ => return authority,
+        };
+
+        authority
+            .strip_suffix(default_port)
+            .map(ToOwned::to_owned)
+            .unwrap_or(authority)
+    }
+
+    /// Absolute path, which is `/` when the URI has no path
+    fn path(&self) -> &str {
+        if self.path.is_empty() {
+            "/"
+        } else {
+            self.path
+        }
+    }
+}
+
+/// Serialization of a structured field string, which only allows printable ASCII characters
+fn string(value: &str) -> SecureEnvResult<String> {
+    if !value.bytes().all(|byte| (0x20..0x7f).contains(&byte)) {
+        return Err(SecureEnvError::UnableToCreateSignature(format!(
+            "'{value}' contains characters that are not printable ASCII"
+        )));
+    }
+
+    Ok(format!(
+        "\"{}\"",
+        value.replace('\\', "\\\\").replace('"', "\\\"")
+    ))
+}
+
+/// Parse a structured field string, without parameters
+fn parse_string(serialized: &str) -> Option<String> {
+    let inner = serialized.strip_prefix('"')?.strip_suffix('"')?;
+
+    let mut value = String::with_capacity(inner.len());
+    let mut characters = inner.chars();
+    while let Some(character) = characters.next() {
+        match character {
+            '\\' => match characters.next()? {
+                escaped @ ('\\' | '"') => value.push(escaped),
+                _ => return None,
+            },
+            '"' => return None,
+            character => value.push(character),
+        }
+    }
+
+    Some(value)
+}
+
+/// Parse a structured field byte sequence, ignoring its parameters
+fn byte_sequence(serialized: &str) -> Option<Vec<u8>> {
+    let serialized = serialized.split(';').next()?.trim();
+    let encoded = serialized.strip_prefix(':')?.strip_suffix(':')?;
+
+    STANDARD.decode(encoded).ok()
+}
+
+/// Raw value of the member with `key` of a structured field dictionary
+fn dictionary_member<'a>(dictionary: &'a str, key: &str) -> Option<&'a str> {
+    split_top_level(dictionary, ',')
+        .into_iter()
+        .filter_map(|member| member.split_once('='))
+        // The last member with a key wins
+        .rfind(|(name, _)| *name == key)
+        .map(|(_, value)| value)
+}
+
+/// Split `value` at every `separator` that is not part of a string or an inner list, trimming
+/// the whitespace around the parts
+fn split_top_level(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut depth = 0usize;
+
+    for (index, character) in value.char_indices() {
+        match character {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '(' if !in_string => depth += 1,
+            ')' if !in_string => depth = depth.saturating_sub(1),
+            _ if character == separator && !in_string && depth == 0 => {
+                parts.push(value[start..index].trim());
+                start = index + character.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(value[start..].trim());
+
+    parts
+}
@@ -1,127 +1,375 @@
 use crate::{
     error::{SecureEnvError, SecureEnvResult},
-    KeyOps, SecureEnvironmentOps,
+    wipe::wipe_keys,
+    IntoKeyId, KeyGenerationOptions, KeyId, KeyMetadata, KeyMetadataHandle, KeyOps,
+    SecureEnvironment, WipeReport,
+};
+use core_foundation::{
+    base::{CFType, TCFType, ToVoid},
+    data::CFData,
+    dictionary::CFMutableDictionary,
+    string::{CFString, CFStringRef},
+};
+use p256::{
+    ecdsa::{signature::Keypair, Signature, VerifyingKey},
+    elliptic_curve::{group::GroupEncoding, sec1::ToEncodedPoint},
 };
-use p256::{ecdsa::Signature, elliptic_curve::group::GroupEncoding};
 use security_framework::{
     access_control::{ProtectionMode, SecAccessControl},
-    item::{ItemClass, ItemSearchOptions, KeyClass, Location, SearchResult},
+    item::{ItemClass, ItemSearchOptions, KeyClass, Limit, Location, SearchResult},
     key::{Algorithm, GenerateKeyOptions, KeyType, SecKey, Token},
     passwords_options::AccessControlOptions,
 };
+use security_framework_sys::{
+    base::errSecItemNotFound,
+    item::{kSecAttrLabel, kSecValueRef},
+};
+use sha1::{Digest, Sha1};
 
-/// Unit struct that can be used to create and get keypairs by id
-///
-/// # Examples
-///
-/// ## Generate a keypair
-///
-/// ```
-/// use secure_env::{SecureEnvironment, SecureEnvironmentOps};
-///
-/// let key = SecureEnvironment::generate_keypair("my-unique-id").unwrap();
-/// ```
-///
-/// ## Get a keypair from the keychain
-///
-/// ```
-/// use secure_env::{SecureEnvironment, SecureEnvironmentOps};
-///
-/// {
-///     SecureEnvironment::generate_keypair("my-unique-id").unwrap();
-/// }
-///
-/// let key = SecureEnvironment::get_keypair_by_id("my-unique-id").unwrap();
-/// ```
-#[derive(Debug, Clone, Eq, PartialEq, Copy)]
-pub struct SecureEnvironment;
-
-impl SecureEnvironmentOps<Key> for SecureEnvironment {
-    fn generate_keypair(id: impl Into<String>, backed_by_biometrics: bool) -> SecureEnvResult<Key> {
-        // Create a dictionary with the following options:
-        let mut opts = GenerateKeyOptions::default();
-
-        // Set the key type to `ec` (Elliptic Curve)
-        let opts = opts.set_key_type(KeyType::ec());
-
-        // Set the a token of `SecureEnclave`.
-        // Meaning Apple will store the key in a secure element
-        let opts = opts.set_token(Token::SecureEnclave);
-
-        let opts = if backed_by_biometrics {
-            // Set the access control so that biometrics via LocalAuthentication.framework is required
-            let access_control = SecAccessControl::create_with_protection(
-                Some(ProtectionMode::AccessibleWhenUnlockedThisDeviceOnly),
-                AccessControlOptions::BIOMETRY_CURRENT_SET.bits(),
-            )
-            .map_err(|_| {
-                SecureEnvError::UnableToGenerateKey(
-                    "Unable to create access control flags".to_owned(),
-                )
-            })?;
-
-            opts.set_access_control(access_control)
-        } else {
-            opts
-        };
+/// Prefix of the `kSecAttrApplicationTag` of every key, so keys of other libraries that use the
+/// same label are never matched
+const APPLICATION_TAG_PREFIX: &str = "id.animo.secure-env:";
+
+extern "C" {
+    // Not exposed by `security-framework-sys`
+    static kSecAttrApplicationTag: CFStringRef;
+}
+
+fn application_tag(scoped_id: &str) -> Vec<u8> {
+    format!("{APPLICATION_TAG_PREFIX}{scoped_id}").into_bytes()
+}
+
+impl SecureEnvironment {
+    /// Create and store a keypair in the Secure Enclave
+    pub fn create_keypair_with_options(
+        &self,
+        id: impl IntoKeyId,
+        options: &KeyGenerationOptions,
+    ) -> SecureEnvResult<Key> {
+        let id = id.into_key_id()?;
+        let scoped_id = self.config.scoped_id(&id)?;
+
+        // The keychain has no usage limits, so they are kept in the metadata
+        self.config.check_usage_limits(options, false)?;
+
+        // The keychain would store a second key under the same label
+        if find_private_key(&scoped_id)?.is_some() {
+            return Err(SecureEnvError::UnableToGenerateKey(format!(
+                "Key with id: '{scoped_id}' already exists."
+            )));
+        }
+
+        let mut opts = secure_enclave_key_options(options)?;
 
         // Store the key in the keychain
         let opts = opts.set_location(Location::DataProtectionKeychain);
 
         // Give the key a label so we can retrieve it later
-        // with the `SecureEnvironment::get_keypair_by_id` method
-        let opts = opts.set_label(id);
+        // with the `SecureEnvironment::load_keypair` method
+        let opts = opts.set_label(scoped_id.as_str());
 
-        let dict = opts.to_dictionary();
+        // Tag the key as well, so the lookup only matches keys of this library.
+        // `GenerateKeyOptions` does not support an application tag, so it is added afterwards
+        let mut dict = CFMutableDictionary::from(&opts.to_dictionary());
+        let tag = CFData::from_buffer(&application_tag(&scoped_id));
+        dict.set(unsafe { kSecAttrApplicationTag }.to_void(), tag.to_void());
 
         // Generate a key using the dictionary
         // This also passes along any information the OS provides when an error occurs
+        let key = SecKey::generate(dict.to_immutable())
+            .map_err(|e| SecureEnvError::UnableToGenerateKey(e.to_string()))?;
+
+        let key = Key::new(
+            key,
+            self.config
+                .metadata_handle(Some(&id))
+                .with_usage_limits(options),
+        )?;
+
+        self.config.store_created_metadata(&id, &key, options)?;
+
+        Ok(key)
+    }
+
+    /// Create a keypair in the Secure Enclave that is not stored in the keychain
+    ///
+    /// The key can not be loaded by id and is destroyed when the last clone of it is dropped.
+    pub fn generate_ephemeral_keypair(
+        &self,
+        options: &KeyGenerationOptions,
+    ) -> SecureEnvResult<Key> {
+        self.config.check_usage_limits(options, true)?;
+
+        // Without a location `kSecAttrIsPermanent` is false, so the key is not stored in the
+        // keychain. A label is not required, as the key can not be searched for.
+        let dict = secure_enclave_key_options(options)?.to_dictionary();
+
         let key = SecKey::generate(dict)
             .map_err(|e| SecureEnvError::UnableToGenerateKey(e.to_string()))?;
 
-        Ok(Key(key))
+        Key::new(
+            key,
+            self.config.metadata_handle(None).with_usage_limits(options),
+        )
+    }
+
+    /// Get a keypair that has been stored in the keychain
+    pub fn load_keypair(&self, id: impl IntoKeyId) -> SecureEnvResult<Key> {
+        let id = id.into_key_id()?;
+        let scoped_id = self.config.scoped_id(&id)?;
+
+        let key = find_private_key(&scoped_id)?.ok_or(SecureEnvError::UnableToGetKeyPairById(
+            format!("Key reference with id: '{scoped_id}' not found."),
+        ))?;
+
+        Key::new(key, self.config.metadata_handle(Some(&id)))
     }
 
-    fn get_keypair_by_id(id: impl Into<String>) -> SecureEnvResult<Key> {
-        let id = id.into();
+    /// Get the keypair with the SEC1 encoded public key `public_key`
+    ///
+    /// The keychain indexes every key by its `kSecAttrApplicationLabel`, the SHA-1 hash of the
+    /// uncompressed public key, so no metadata store is required.
+    pub fn find_keypair_by_public_key(&self, public_key: &[u8]) -> SecureEnvResult<Option<Key>> {
+        let public_key = p256::PublicKey::from_sec1_bytes(public_key)
+            .map_err(|e| SecureEnvError::InvalidPublicKey(e.to_string()))?;
+        let application_label = Sha1::digest(public_key.to_encoded_point(false).as_bytes());
 
         let search_result = ItemSearchOptions::new()
-            // Search by the provided label
-            .label(&id)
-            // Load the reference, not the actual data
+            .application_label(&application_label)
             .load_refs(true)
-            // Looking for a `Key` instance
+            .load_attributes(true)
+            .class(ItemClass::key())
+            .key_class(KeyClass::private())
+            // The same key can not be stored twice, but keys of other namespaces may match
+            .limit(Limit::All)
+            .search();
+
+        let search_result = match search_result {
+            Ok(search_result) => search_result,
+            Err(e) if e.code() == errSecItemNotFound => return Ok(None),
+            Err(e) => return Err(SecureEnvError::UnableToGetKeyPairById(e.to_string())),
+        };
+
+        for result in search_result {
+            let SearchResult::Dict(attributes) = result else {
+                continue;
+            };
+
+            let Some(key) = attributes
+                .find(unsafe { kSecValueRef }.to_void())
+                .and_then(|key| unsafe { CFType::wrap_under_get_rule(*key) }.downcast::<SecKey>())
+            else {
+                continue;
+            };
+
+            let Some(scoped_id) = attributes
+                .find(unsafe { kSecAttrLabel }.to_void())
+                .and_then(|label| {
+                    unsafe { CFType::wrap_under_get_rule(*label) }.downcast::<CFString>()
+                })
+                .map(|label| label.to_string())
+            else {
+                continue;
+            };
+
+            // Keys of other libraries have another tag, legacy keys of this library have none
+            let is_other_library = attributes
+                .find(unsafe { kSecAttrApplicationTag }.to_void())
+                .and_then(|tag| unsafe { CFType::wrap_under_get_rule(*tag) }.downcast::<CFData>())
+                .map_or(false, |tag| {
+                    tag.bytes() != application_tag(&scoped_id).as_slice()
+                });
+
+            if is_other_library {
+                continue;
+            }
+
+            if let Some(id) = self.config.unscoped_id(&scoped_id) {
+                return Key::new(key, self.config.metadata_handle(Some(&id))).map(Some);
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Get the keypair whose public key has the JWK thumbprint `thumbprint`
+    ///
+    /// The thumbprint can not be mapped to the `kSecAttrApplicationLabel`, so the thumbprints in
+    /// the [`crate::MetadataStore`] of the configuration are used as the index.
+    pub fn find_keypair_by_thumbprint(&self, thumbprint: &str) -> SecureEnvResult<Option<Key>> {
+        self.config
+            .find_key_by_thumbprint(thumbprint)?
+            .map(|id| self.load_keypair(id))
+            .transpose()
+    }
+
+    /// Ids of every keypair that is stored in the keychain in the namespace
+    ///
+    /// Keys are recognized by their application tag. Legacy keys without a tag are only listed
+    /// with a namespace, as a label without a namespace can belong to any other library.
+    pub fn list_keys(&self) -> SecureEnvResult<Vec<KeyId>> {
+        let search_result = ItemSearchOptions::new()
+            .load_attributes(true)
             .class(ItemClass::key())
-            // We want access to the private key
             .key_class(KeyClass::private())
-            // Limit to 1 output key
-            .limit(1)
-            // Search the keychain
-            .search()
-            .map_err(|_| {
-                SecureEnvError::UnableToGetKeyPairById(format!(
-                    "Key reference with id: '{id}' not found."
-                ))
-            })?;
-
-        let result = search_result
-            .first()
-            .ok_or(SecureEnvError::UnableToGetKeyPairById(format!(
-                "Key reference with id: '{id}' not found."
-            )))?;
-
-        match result {
-            SearchResult::Ref(r) => match r {
-                security_framework::item::Reference::Key(k) => Ok(Key(k.to_owned())),
-                _ => Err(SecureEnvError::UnableToGetKeyPairById(
-                    "Found Reference, but not of key instance".to_owned(),
-                )),
-            },
-            _ => Err(SecureEnvError::UnableToGetKeyPairById(
-                "Did not find search reference".to_owned(),
-            )),
+            .limit(Limit::All)
+            .search();
+
+        let search_result = match search_result {
+            Ok(search_result) => search_result,
+            Err(e) if e.code() == errSecItemNotFound => return Ok(vec![]),
+            Err(e) => return Err(SecureEnvError::UnableToListKeys(e.to_string())),
+        };
+
+        let mut ids = vec![];
+
+        for result in search_result {
+            let SearchResult::Dict(attributes) = result else {
+                continue;
+            };
+
+            let scoped_id = match attributes.find(unsafe { kSecAttrApplicationTag }.to_void()) {
+                Some(tag) => unsafe { CFType::wrap_under_get_rule(*tag) }
+                    .downcast::<CFData>()
+                    .and_then(|tag| String::from_utf8(tag.bytes().to_vec()).ok())
+                    .and_then(|tag| {
+                        tag.strip_prefix(APPLICATION_TAG_PREFIX)
+                            .map(ToOwned::to_owned)
+                    }),
+                None if self.config.namespace.is_some() => attributes
+                    .find(unsafe { kSecAttrLabel }.to_void())
+                    .and_then(|label| {
+                        unsafe { CFType::wrap_under_get_rule(*label) }.downcast::<CFString>()
+                    })
+                    .map(|label| label.to_string()),
+                None => None,
+            };
+
+            if let Some(id) = scoped_id.and_then(|scoped_id| self.config.unscoped_id(&scoped_id)) {
+                ids.push(id);
+            }
+        }
+
+        // A legacy key and a tagged key may share an id
+        ids.sort();
+        ids.dedup();
+
+        Ok(ids)
+    }
+
+    /// Delete every keypair in the configured namespace, together with its metadata, see
+    /// [`crate::KeyBackend::wipe`]
+    pub fn wipe(&self) -> SecureEnvResult<WipeReport> {
+        wipe_keys(self)
+    }
+
+    /// Delete a keypair from the keychain, together with its metadata
+    pub fn delete_keypair(&self, id: impl IntoKeyId) -> SecureEnvResult<()> {
+        let id = id.into_key_id()?;
+        let key = self
+            .load_keypair(&id)
+            .map_err(|e| SecureEnvError::UnableToDeleteKey(e.to_string()))?;
+
+        key.key
+            .delete()
+            .map_err(|e| SecureEnvError::UnableToDeleteKey(e.to_string()))?;
+
+        self.config
+            .remove_metadata(&id)
+            .map_err(|e| SecureEnvError::UnableToDeleteKey(e.to_string()))
+    }
+}
+
+/// Find the private key that is stored under the label and application tag of `scoped_id`
+///
+/// Keys that have been created before the application tag was stored only have a label. They are
+/// used when no tagged key exists, so existing keys keep working.
+fn find_private_key(scoped_id: &str) -> SecureEnvResult<Option<SecKey>> {
+    let search_result = ItemSearchOptions::new()
+        // Search by the provided label
+        .label(scoped_id)
+        // Load the reference and the attributes, which contain the application tag
+        .load_refs(true)
+        .load_attributes(true)
+        // Looking for a `Key` instance
+        .class(ItemClass::key())
+        // We want access to the private key
+        .key_class(KeyClass::private())
+        // Other libraries may use the same label
+        .limit(Limit::All)
+        // Search the keychain
+        .search();
+
+    let search_result = match search_result {
+        Ok(search_result) => search_result,
+        Err(e) if e.code() == errSecItemNotFound => return Ok(None),
+        Err(e) => return Err(SecureEnvError::UnableToGetKeyPairById(e.to_string())),
+    };
+
+    let tag = application_tag(scoped_id);
+    let mut untagged_key = None;
+
+    for result in search_result {
+        let SearchResult::Dict(attributes) = result else {
+            continue;
+        };
+
+        let Some(key) = attributes
+            .find(unsafe { kSecValueRef }.to_void())
+            .and_then(|key| unsafe { CFType::wrap_under_get_rule(*key) }.downcast::<SecKey>())
+        else {
+            continue;
+        };
+
+        match attributes.find(unsafe { kSecAttrApplicationTag }.to_void()) {
+            Some(value) => {
+                let value = unsafe { CFType::wrap_under_get_rule(*value) }.downcast::<CFData>();
+
+                if value.map_or(false, |value| value.bytes() == tag.as_slice()) {
+                    return Ok(Some(key));
+                }
+            }
+            None => untagged_key = untagged_key.or(Some(key)),
         }
     }
+
+    Ok(untagged_key)
+}
+
+/// Options for a key in the Secure Enclave, without a location in the keychain
+fn secure_enclave_key_options(
+    options: &KeyGenerationOptions,
+) -> SecureEnvResult<GenerateKeyOptions> {
+    // The Secure Enclave does not attest its keys
+    if options.attestation_challenge.is_some() {
+        return Err(SecureEnvError::UnableToGenerateKey(
+            "Key attestation is not supported by the Secure Enclave".to_owned(),
+        ));
+    }
+
+    let mut opts = GenerateKeyOptions::default();
+
+    // Set the key type to `ec` (Elliptic Curve)
+    opts.set_key_type(KeyType::ec());
+
+    // Set the a token of `SecureEnclave`.
+    // Meaning Apple will store the key in a secure element
+    opts.set_token(Token::SecureEnclave);
+
+    if options.backed_by_biometrics {
+        // Set the access control so that biometrics via LocalAuthentication.framework is required
+        let access_control = SecAccessControl::create_with_protection(
+            Some(ProtectionMode::AccessibleWhenUnlockedThisDeviceOnly),
+            AccessControlOptions::BIOMETRY_CURRENT_SET.bits(),
+        )
+        .map_err(|_| {
+            SecureEnvError::UnableToGenerateKey("Unable to create access control flags".to_owned())
+        })?;
+
+        opts.set_access_control(access_control);
+    }
+
+    Ok(opts)
 }
 
 /// Key structure which allows for signing and retrieval of the public key
@@ -167,36 +415,75 @@ impl SecureEnvironmentOps<Key> for SecureEnvironment {
 ///
 /// assert!(is_signature_valid);
 /// ```
-#[derive(Debug, Clone, Eq, PartialEq)]
-pub struct Key(SecKey);
+#[derive(Debug, Clone)]
+pub struct Key {
+    key: SecKey,
+    metadata: KeyMetadataHandle,
+    /// Retrieved when the key is created or loaded, as [`Keypair::verifying_key`] can not fail
+    verifying_key: VerifyingKey,
+}
 
-impl KeyOps for Key {
-    fn get_public_key(&self) -> SecureEnvResult<Vec<u8>> {
-        // Retrieve the internal representation of the public key of the `SecKey`
-        let public_key = self
-            .0
-            .public_key()
-            .ok_or(SecureEnvError::UnableToGetPublicKey(
-                "No public key reference found on the internal `SecKey`".to_owned(),
-            ))?;
-
-        // Convert the public key reference to the `sec1` format in bytes
-        let sec1_bytes = public_key
-            .external_representation()
-            .ok_or(SecureEnvError::UnableToGetPublicKey(
-                "Could not create an external representation for the public key on the `SecKey`"
-                    .to_owned(),
-            ))?
-            .to_vec();
-
-        // Instantiate a P256 public key from the `sec1` bytes
-        let public_key = p256::PublicKey::from_sec1_bytes(&sec1_bytes)
+impl Key {
+    fn new(key: SecKey, metadata: KeyMetadataHandle) -> SecureEnvResult<Self> {
+        let verifying_key = VerifyingKey::from_sec1_bytes(&public_key(&key)?)
             .map_err(|e| SecureEnvError::UnableToGetPublicKey(e.to_string()))?;
 
-        // Get the affine point of the public key and convert this into a byte representation
-        let public_key = public_key.as_affine().to_bytes().to_vec();
+        Ok(Self {
+            key,
+            metadata,
+            verifying_key,
+        })
+    }
+}
+
+impl PartialEq for Key {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for Key {}
+
+/// Compressed SEC1 encoding of the public key of a `SecKey`
+fn public_key(key: &SecKey) -> SecureEnvResult<Vec<u8>> {
+    // Retrieve the internal representation of the public key of the `SecKey`
+    let public_key = key
+        .public_key()
+        .ok_or(SecureEnvError::UnableToGetPublicKey(
+            "No public key reference found on the internal `SecKey`".to_owned(),
+        ))?;
+
+    // Convert the public key reference to the `sec1` format in bytes
+    let sec1_bytes = public_key
+        .external_representation()
+        .ok_or(SecureEnvError::UnableToGetPublicKey(
+            "Could not create an external representation for the public key on the `SecKey`"
+                .to_owned(),
+        ))?
+        .to_vec();
+
+    // Instantiate a P256 public key from the `sec1` bytes
+    let public_key = p256::PublicKey::from_sec1_bytes(&sec1_bytes)
+        .map_err(|e| SecureEnvError::UnableToGetPublicKey(e.to_string()))?;
 
-        Ok(public_key)
+    // Get the affine point of the public key and convert this into a byte representation
+    let public_key = public_key.as_affine().to_bytes().to_vec();
+
+    Ok(public_key)
+}
+
+impl Keypair for Key {
+    type VerifyingKey = VerifyingKey;
+
+    /// Verifying key that has been retrieved when the key was created or loaded
+    fn verifying_key(&self) -> VerifyingKey {
+        self.verifying_key
+    }
+}
+
+impl KeyOps for Key {
+    fn get_public_key(&self) -> SecureEnvResult<Vec<u8>> {
+        public_key(&self.key)
     }
 
     /**
@@ -206,9 +493,13 @@ impl KeyOps for Key {
      *
      */
     fn sign(&self, msg: &[u8]) -> SecureEnvResult<Vec<u8>> {
+        // The Secure Enclave has no validity period or usage limit, so they are enforced here. A
+        // use is only counted once the signature has been made, so a cancelled prompt is free.
+        self.metadata.check_usage()?;
+
         // Sign the message with the `der` format
         let der_sig = self
-            .0
+            .key
             .create_signature(Algorithm::ECDSASignatureMessageX962SHA256, msg)
             .map_err(|e| SecureEnvError::UnableToCreateSignature(e.to_string()))?;
 
@@ -219,6 +510,16 @@ impl KeyOps for Key {
         // Convert the signature to a byte representation
         let signature = signature.to_vec();
 
+        self.metadata.record_usage()?;
+
         Ok(signature)
     }
+
+    fn metadata(&self) -> SecureEnvResult<Option<KeyMetadata>> {
+        self.metadata.metadata()
+    }
+
+    fn set_metadata(&self, metadata: &KeyMetadata) -> SecureEnvResult<()> {
+        self.metadata.set_metadata(metadata)
+    }
 }
@@ -1,19 +1,28 @@
 pub static STRING_CLS: &str = "java/lang/String";
 
+pub static ILLEGAL_STATE_EXCEPTION_CLS: &str = "java/lang/IllegalStateException";
+
 pub static EXCEPTION_TO_STRING: &str = "toString";
 pub static EXCEPTION_TO_STRING_SIG: &str = "()Ljava/lang/String;";
 
 pub static EC_ALGORITHM: &str = "EC";
 pub static ANDROID_KEY_STORE_PROVIDER: &str = "AndroidKeyStore";
 pub static SHA256_WITH_ECDSA_ALGO: &str = "SHA256withECDSA";
+pub static HARDWARE_KEYSTORE_FEATURE: &str = "android.hardware.hardware_keystore";
+
+pub static CONSTRUCTOR: &str = "<init>";
 
 // Context
 
+pub static CONTEXT_CLS: &str = "android/content/Context";
+
 pub static CONTEXT_GET_PACKAGE_MANAGER: &str = "getPackageManager";
 pub static CONTEXT_GET_PACKAGE_MANAGER_SIG: &str = "()Landroid/content/pm/PackageManager;";
 
 // Package manager
 
+pub static PACKAGE_MANAGER_CLS: &str = "android/content/pm/PackageManager";
+
 pub static PACKAGE_MANAGER_HAS_SYSTEM_FEATURE: &str = "hasSystemFeature";
 pub static PACKAGE_MANAGER_HAS_SYSTEM_FEATURE_SIG: &str = "(Ljava/lang/String;I)Z";
 
@@ -64,6 +73,23 @@ pub static KEY_GEN_PARAMETER_SPEC_BUILDER_SET_IS_STRONG_BOX_BACKED: &str = "setI
 pub static KEY_GEN_PARAMETER_SPEC_BUILDER_SET_IS_STRONG_BOX_BACKED_SIG: &str =
     "(Z)Landroid/security/keystore/KeyGenParameterSpec$Builder;";
 
+pub static KEY_GEN_PARAMETER_SPEC_BUILDER_SET_KEY_VALIDITY_START: &str = "setKeyValidityStart";
+pub static KEY_GEN_PARAMETER_SPEC_BUILDER_SET_KEY_VALIDITY_START_SIG: &str =
+    "(Ljava/util/Date;)Landroid/security/keystore/KeyGenParameterSpec$Builder;";
+
+pub static KEY_GEN_PARAMETER_SPEC_BUILDER_SET_KEY_VALIDITY_END: &str = "setKeyValidityEnd";
+pub static KEY_GEN_PARAMETER_SPEC_BUILDER_SET_KEY_VALIDITY_END_SIG: &str =
+    "(Ljava/util/Date;)Landroid/security/keystore/KeyGenParameterSpec$Builder;";
+
+pub static KEY_GEN_PARAMETER_SPEC_BUILDER_SET_MAX_USAGE_COUNT: &str = "setMaxUsageCount";
+pub static KEY_GEN_PARAMETER_SPEC_BUILDER_SET_MAX_USAGE_COUNT_SIG: &str =
+    "(I)Landroid/security/keystore/KeyGenParameterSpec$Builder;";
+
+pub static KEY_GEN_PARAMETER_SPEC_BUILDER_SET_ATTESTATION_CHALLENGE: &str =
+    "setAttestationChallenge";
+pub static KEY_GEN_PARAMETER_SPEC_BUILDER_SET_ATTESTATION_CHALLENGE_SIG: &str =
+    "([B)Landroid/security/keystore/KeyGenParameterSpec$Builder;";
+
 pub static KEY_GEN_PARAMETER_SPEC_BUILDER_BUILD: &str = "build";
 pub static KEY_GEN_PARAMETER_SPEC_BUILDER_BUILD_SIG: &str =
     "()Landroid/security/keystore/KeyGenParameterSpec;";
@@ -96,8 +122,58 @@ pub static KEY_STORE_LOAD_SIG: &str = "(Ljava/security/KeyStore$LoadStoreParamet
 pub static KEY_STORE_GET_ENTRY: &str = "getEntry";
 pub static KEY_STORE_GET_ENTRY_SIG: &str = "(Ljava/lang/String;Ljava/security/KeyStore$ProtectionParameter;)Ljava/security/KeyStore$Entry;";
 
+pub static KEY_STORE_CONTAINS_ALIAS: &str = "containsAlias";
+pub static KEY_STORE_CONTAINS_ALIAS_SIG: &str = "(Ljava/lang/String;)Z";
+
+pub static KEY_STORE_DELETE_ENTRY: &str = "deleteEntry";
+pub static KEY_STORE_DELETE_ENTRY_SIG: &str = "(Ljava/lang/String;)V";
+
+pub static KEY_STORE_ALIASES: &str = "aliases";
+pub static KEY_STORE_ALIASES_SIG: &str = "()Ljava/util/Enumeration;";
+
+pub static KEY_STORE_GET_CERTIFICATE_CHAIN: &str = "getCertificateChain";
+pub static KEY_STORE_GET_CERTIFICATE_CHAIN_SIG: &str =
+    "(Ljava/lang/String;)[Ljava/security/cert/Certificate;";
+
+// Key Factory
+
+pub static KEY_FACTORY_CLS: &str = "java/security/KeyFactory";
+
+pub static KEY_FACTORY_GET_INSTANCE: &str = "getInstance";
+pub static KEY_FACTORY_GET_INSTANCE_SIG: &str =
+    "(Ljava/lang/String;Ljava/lang/String;)Ljava/security/KeyFactory;";
+
+pub static KEY_FACTORY_GET_KEY_SPEC: &str = "getKeySpec";
+pub static KEY_FACTORY_GET_KEY_SPEC_SIG: &str =
+    "(Ljava/security/Key;Ljava/lang/Class;)Ljava/security/spec/KeySpec;";
+
+// Key Info
+
+pub static KEY_INFO_CLS: &str = "android/security/keystore/KeyInfo";
+
+pub static KEY_INFO_GET_REMAINING_USAGE_COUNT: &str = "getRemainingUsageCount";
+pub static KEY_INFO_GET_REMAINING_USAGE_COUNT_SIG: &str = "()I";
+
+// Date
+
+pub static DATE_CLS: &str = "java/util/Date";
+
+pub static DATE_CTOR_SIG: &str = "(J)V";
+
+// Enumeration
+
+pub static ENUMERATION_CLS: &str = "java/util/Enumeration";
+
+pub static ENUMERATION_HAS_MORE_ELEMENTS: &str = "hasMoreElements";
+pub static ENUMERATION_HAS_MORE_ELEMENTS_SIG: &str = "()Z";
+
+pub static ENUMERATION_NEXT_ELEMENT: &str = "nextElement";
+pub static ENUMERATION_NEXT_ELEMENT_SIG: &str = "()Ljava/lang/Object;";
+
 // Key Store Entry
 
+pub static KEY_STORE_ENTRY_CLS: &str = "java/security/KeyStore$PrivateKeyEntry";
+
 pub static KEY_STORE_ENTRY_GET_PRIVATE_KEY: &str = "getPrivateKey";
 pub static KEY_STORE_ENTRY_GET_PRIVATE_KEY_SIG: &str = "()Ljava/security/PrivateKey;";
 
@@ -106,9 +182,14 @@ pub static KEY_STORE_ENTRY_GET_CERTIFICATE_SIG: &str = "()Ljava/security/cert/Ce
 
 // Certificate
 
+pub static CERTIFICATE_CLS: &str = "java/security/cert/Certificate";
+
 pub static CERTIFICATE_GET_PUBLIC_KEY: &str = "getPublicKey";
 pub static CERTIFICATE_GET_PUBLIC_KEY_SIG: &str = "()Ljava/security/PublicKey;";
 
+pub static CERTIFICATE_GET_ENCODED: &str = "getEncoded";
+pub static CERTIFICATE_GET_ENCODED_SIG: &str = "()[B";
+
 // Key Pair
 
 pub static KEY_PAIR_CLS: &str = "java/security/KeyPair";
@@ -122,6 +203,8 @@ pub static KEY_PAIR_GET_PRIVATE_SIG: &str = "()Ljava/security/PrivateKey;";
 
 // Public Key
 
+pub static PUBLIC_KEY_CLS: &str = "java/security/PublicKey";
+
 pub static PUBLIC_KEY_GET_ENCODED: &str = "getEncoded";
 pub static PUBLIC_KEY_GET_ENCODED_SIG: &str = "()[B";
 
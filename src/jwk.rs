@@ -0,0 +1,99 @@
+use crate::error::{SecureEnvError, SecureEnvResult};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use p256::{elliptic_curve::sec1::ToEncodedPoint, PublicKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// Public key of a keypair as a JSON Web Key ([RFC 7517](https://www.rfc-editor.org/rfc/rfc7517))
+///
+/// Every key of this crate is a P-256 key, so the JWK is always an `EC` key on the `P-256` curve.
+///
+/// # Examples
+///
+/// ```
+/// use secure_env::{Jwk, KeyOps, SecureEnvironmentConfig, SoftwareBackend};
+///
+/// let backend = SoftwareBackend::new(SecureEnvironmentConfig::default());
+/// let key = backend.create_keypair("my-unique-id").unwrap();
+///
+/// let jwk = Jwk::from_public_key(&key.get_public_key().unwrap()).unwrap();
+/// assert_eq!(jwk.crv, "P-256");
+/// assert_eq!(jwk.thumbprint().len(), 43);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Jwk {
+    pub kty: String,
+    pub crv: String,
+    /// Base64url encoded x coordinate
+    pub x: String,
+    /// Base64url encoded y coordinate
+    pub y: String,
+}
+
+impl Jwk {
+    /// JWK of a compressed or uncompressed SEC1 encoded P-256 public key, as returned by
+    /// [`crate::KeyOps::get_public_key`]
+    pub fn from_public_key(public_key: &[u8]) -> SecureEnvResult<Self> {
+        let public_key = PublicKey::from_sec1_bytes(public_key)
+            .map_err(|e| SecureEnvError::InvalidPublicKey(e.to_string()))?;
+        let point = public_key.to_encoded_point(false);
+
+        // An uncompressed point always has both coordinates
+        let (Some(x), Some(y)) = (point.x(), point.y()) else {
+            return Err(SecureEnvError::InvalidPublicKey(
+                "Public key is the identity point".to_owned(),
+            ));
+        };
+
+        Ok(Self {
+            kty: "EC".to_owned(),
+            crv: "P-256".to_owned(),
+            x: URL_SAFE_NO_PAD.encode(x),
+            y: URL_SAFE_NO_PAD.encode(y),
+        })
+    }
+
+    /// Compressed SEC1 encoding of the public key, the same encoding that is returned by
+    /// [`crate::KeyOps::get_public_key`]
+    pub fn to_public_key(&self) -> SecureEnvResult<Vec<u8>> {
+        if self.kty != "EC" || self.crv != "P-256" {
+            return Err(SecureEnvError::InvalidPublicKey(format!(
+                "Only P-256 keys are supported, received kty '{}' and crv '{}'",
+                self.kty, self.crv
+            )));
+        }
+
+        let coordinate = |value: &str| {
+            URL_SAFE_NO_PAD
+                .decode(value)
+                .map_err(|e| SecureEnvError::InvalidPublicKey(e.to_string()))
+        };
+
+        let uncompressed = [vec![0x04], coordinate(&self.x)?, coordinate(&self.y)?].concat();
+        let public_key = PublicKey::from_sec1_bytes(&uncompressed)
+            .map_err(|e| SecureEnvError::InvalidPublicKey(e.to_string()))?;
+
+        Ok(public_key.to_encoded_point(true).as_bytes().to_vec())
+    }
+
+    /// JWK thumbprint ([RFC 7638](https://www.rfc-editor.org/rfc/rfc7638)), the base64url encoded
+    /// SHA-256 hash of the required members in lexicographic order
+    pub fn thumbprint(&self) -> String {
+        // A `BTreeMap` is serialized in lexicographic order and without whitespace
+        let members = BTreeMap::from([
+            ("crv", &self.crv),
+            ("kty", &self.kty),
+            ("x", &self.x),
+            ("y", &self.y),
+        ]);
+        let canonical = serde_json::to_vec(&members).expect("string map to be serializable");
+
+        URL_SAFE_NO_PAD.encode(Sha256::digest(canonical))
+    }
+}
+
+/// JWK thumbprint of a SEC1 encoded P-256 public key, see [`Jwk::thumbprint`]
+pub fn jwk_thumbprint(public_key: &[u8]) -> SecureEnvResult<String> {
+    Ok(Jwk::from_public_key(public_key)?.thumbprint())
+}
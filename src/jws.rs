@@ -0,0 +1,131 @@
+//! Compact JSON Web Signatures ([RFC 7515](https://www.rfc-editor.org/rfc/rfc7515)) with `ES256`,
+//! which the JWTs of this crate are built on
+
+use crate::{
+    error::{SecureEnvError, SecureEnvResult},
+    metadata::now,
+    rust_crypto::verify_signature,
+    Jwk, KeyOps,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+
+/// Algorithm of every JWS, as the keys of this crate are P-256 keys
+pub(crate) const ALGORITHM: &str = "ES256";
+
+/// Default maximum age in seconds of a token that is validated via [`check_issued_at`]
+pub(crate) const DEFAULT_MAX_AGE: u64 = 300;
+
+/// Default number of seconds that the `iat` of a token may be in the future, to allow for clock
+/// skew
+pub(crate) const DEFAULT_LEEWAY: u64 = 60;
+
+/// Check that a token with the `iat` claim `iat` has not been issued more than `leeway` seconds
+/// in the future and is not older than `max_age` seconds
+pub(crate) fn check_issued_at(iat: u64, max_age: u64, leeway: u64) -> SecureEnvResult<()> {
+    let now = now()?;
+
+    if iat > now.saturating_add(leeway) {
+        return Err(SecureEnvError::InvalidToken(
+            "Token was issued in the future".to_owned(),
+        ));
+    }
+    if iat.saturating_add(max_age) < now {
+        return Err(SecureEnvError::InvalidToken("Token is too old".to_owned()));
+    }
+
+    Ok(())
+}
+
+/// Sign `header` and `claims` with `key` and return the compact serialization
+///
+/// The `r || s` encoding of [`KeyOps::sign`] is the JWS encoding of an `ES256` signature.
+pub(crate) fn sign(
+    key: &impl KeyOps,
+    header: &impl Serialize,
+    claims: &impl Serialize,
+) -> SecureEnvResult<String> {
+    let signing_input = format!("{}.{}", encode(header)?, encode(claims)?);
+    let signature = key.sign(signing_input.as_bytes())?;
+
+    Ok(format!(
+        "{signing_input}.{}",
+        URL_SAFE_NO_PAD.encode(signature)
+    ))
+}
+
+fn encode(value: &impl Serialize) -> SecureEnvResult<String> {
+    serde_json::to_vec(value)
+        .map(|json| URL_SAFE_NO_PAD.encode(json))
+        .map_err(|e| SecureEnvError::UnableToCreateSignature(e.to_string()))
+}
+
+/// Compact JWS whose signature has not been verified yet
+pub(crate) struct UnverifiedJws<C> {
+    pub(crate) header: Map<String, Value>,
+    pub(crate) claims: C,
+    signing_input: String,
+    signature: Vec<u8>,
+}
+
+impl<C: DeserializeOwned> UnverifiedJws<C> {
+    /// Decode a compact JWS with `ES256` and the `typ` header parameter `typ`
+    pub(crate) fn decode(token: &str, typ: &str) -> SecureEnvResult<Self> {
+        let error = |message: String| SecureEnvError::InvalidToken(message);
+
+        let mut parts = token.split('.');
+        let (Some(header), Some(claims), Some(signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(error("Token is not a compact JWS".to_owned()));
+        };
+
+        let decode = |part: &str| {
+            URL_SAFE_NO_PAD
+                .decode(part)
+                .map_err(|e| error(e.to_string()))
+        };
+        let header: Map<String, Value> =
+            serde_json::from_slice(&decode(header)?).map_err(|e| error(e.to_string()))?;
+
+        if header.get("typ").and_then(Value::as_str) != Some(typ) {
+            return Err(error(format!("Token type is not '{typ}'")));
+        }
+        if header.get("alg").and_then(Value::as_str) != Some(ALGORITHM) {
+            return Err(error(format!("Token algorithm is not '{ALGORITHM}'")));
+        }
+
+        Ok(Self {
+            header,
+            claims: serde_json::from_slice(&decode(claims)?).map_err(|e| error(e.to_string()))?,
+            signing_input: token[..token.rfind('.').unwrap_or_default()].to_owned(),
+            signature: decode(signature)?,
+        })
+    }
+}
+
+impl<C> UnverifiedJws<C> {
+    /// Public key in the `jwk` header parameter, which must not contain a private key
+    pub(crate) fn jwk(&self) -> SecureEnvResult<Jwk> {
+        let jwk = self
+            .header
+            .get("jwk")
+            .ok_or_else(|| SecureEnvError::InvalidToken("Token has no jwk header".to_owned()))?;
+
+        if jwk.get("d").is_some() {
+            return Err(SecureEnvError::InvalidToken(
+                "Token contains a private key in the jwk header".to_owned(),
+            ));
+        }
+
+        serde_json::from_value(jwk.clone()).map_err(|e| SecureEnvError::InvalidToken(e.to_string()))
+    }
+
+    /// Verify the signature with the SEC1 encoded P-256 `public_key` and return the claims
+    pub(crate) fn verify(self, public_key: &[u8]) -> SecureEnvResult<C> {
+        verify_signature(public_key, self.signing_input.as_bytes(), &self.signature)?;
+
+        Ok(self.claims)
+    }
+}
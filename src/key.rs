@@ -1,7 +1,112 @@
-use crate::error::SecureEnvResult;
+use crate::{
+    error::{SecureEnvError, SecureEnvResult},
+    KeyMetadata,
+};
+use std::{fmt::Debug, sync::Arc};
 
 pub trait KeyOps {
     fn get_public_key(&self) -> SecureEnvResult<Vec<u8>>;
 
     fn sign(&self, msg: &[u8]) -> SecureEnvResult<Vec<u8>>;
+
+    /// Metadata of the key, from the [`crate::MetadataStore`] of the configuration
+    ///
+    /// The keys of every backend of this crate support metadata, except for ephemeral keys.
+    fn metadata(&self) -> SecureEnvResult<Option<KeyMetadata>> {
+        Err(SecureEnvError::UnableToAccessMetadata(
+            "Metadata is not supported by this key".to_owned(),
+        ))
+    }
+
+    /// Replace the metadata of the key
+    fn set_metadata(&self, _metadata: &KeyMetadata) -> SecureEnvResult<()> {
+        Err(SecureEnvError::UnableToAccessMetadata(
+            "Metadata is not supported by this key".to_owned(),
+        ))
+    }
+
+    /// DER encoded attestation certificate chain of the key, leaf certificate first
+    ///
+    /// Only keys of the `AndroidKeyStore` that have been generated with
+    /// [`crate::KeyGenerationOptions::attestation_challenge`] have an attestation chain, whose
+    /// leaf certifies the key and contains the attestation. Every other key returns `None`.
+    fn attestation_chain(&self) -> SecureEnvResult<Option<Vec<Vec<u8>>>> {
+        Ok(None)
+    }
+}
+
+/// Object safe key, implemented by the keys of every backend
+///
+/// This allows keys of different backends to be stored in a single collection, see [`BoxedKey`].
+pub trait SecureKey: KeyOps + Send + Sync + Debug {}
+
+impl<K: KeyOps + Send + Sync + Debug> SecureKey for K {}
+
+/// Key of any backend, e.g. returned by a [`crate::KeyBackend`]
+pub type BoxedKey = Box<dyn SecureKey>;
+
+impl<K: KeyOps + ?Sized> KeyOps for Box<K> {
+    fn get_public_key(&self) -> SecureEnvResult<Vec<u8>> {
+        (**self).get_public_key()
+    }
+
+    fn sign(&self, msg: &[u8]) -> SecureEnvResult<Vec<u8>> {
+        (**self).sign(msg)
+    }
+
+    fn metadata(&self) -> SecureEnvResult<Option<KeyMetadata>> {
+        (**self).metadata()
+    }
+
+    fn set_metadata(&self, metadata: &KeyMetadata) -> SecureEnvResult<()> {
+        (**self).set_metadata(metadata)
+    }
+
+    fn attestation_chain(&self) -> SecureEnvResult<Option<Vec<Vec<u8>>>> {
+        (**self).attestation_chain()
+    }
+}
+
+impl<K: KeyOps + ?Sized> KeyOps for Arc<K> {
+    fn get_public_key(&self) -> SecureEnvResult<Vec<u8>> {
+        (**self).get_public_key()
+    }
+
+    fn sign(&self, msg: &[u8]) -> SecureEnvResult<Vec<u8>> {
+        (**self).sign(msg)
+    }
+
+    fn metadata(&self) -> SecureEnvResult<Option<KeyMetadata>> {
+        (**self).metadata()
+    }
+
+    fn set_metadata(&self, metadata: &KeyMetadata) -> SecureEnvResult<()> {
+        (**self).set_metadata(metadata)
+    }
+
+    fn attestation_chain(&self) -> SecureEnvResult<Option<Vec<Vec<u8>>>> {
+        (**self).attestation_chain()
+    }
+}
+
+impl<K: KeyOps + ?Sized> KeyOps for &K {
+    fn get_public_key(&self) -> SecureEnvResult<Vec<u8>> {
+        (**self).get_public_key()
+    }
+
+    fn sign(&self, msg: &[u8]) -> SecureEnvResult<Vec<u8>> {
+        (**self).sign(msg)
+    }
+
+    fn metadata(&self) -> SecureEnvResult<Option<KeyMetadata>> {
+        (**self).metadata()
+    }
+
+    fn set_metadata(&self, metadata: &KeyMetadata) -> SecureEnvResult<()> {
+        (**self).set_metadata(metadata)
+    }
+
+    fn attestation_chain(&self) -> SecureEnvResult<Option<Vec<Vec<u8>>>> {
+        (**self).attestation_chain()
+    }
 }
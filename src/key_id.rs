@@ -0,0 +1,137 @@
+use crate::error::{SecureEnvError, SecureEnvResult};
+use std::fmt::{self, Display};
+
+/// Id of a keypair, which has been validated
+///
+/// An id is between 1 and [`KeyId::MAX_LEN`] characters long and only contains ASCII letters,
+/// digits, `-`, `_` and `.`. The `:` is reserved to separate the namespace from the id, so ids of
+/// different namespaces can never collide in the keystore of the platform.
+///
+/// Every method that takes an id accepts anything that implements [`IntoKeyId`], e.g. a `&str`
+/// or a `String`, and validates it before the keystore is accessed.
+///
+/// # Examples
+///
+/// ```
+/// use secure_env::KeyId;
+///
+/// let id = KeyId::new("my-unique-id").unwrap();
+/// assert_eq!(id.as_str(), "my-unique-id");
+///
+/// assert!(KeyId::new("").is_err());
+/// assert!(KeyId::new("my-namespace:my-unique-id").is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct KeyId(String);
+
+impl KeyId {
+    /// Maximum number of characters of an id
+    pub const MAX_LEN: usize = 128;
+
+    /// Maximum number of characters of a namespace
+    pub const MAX_NAMESPACE_LEN: usize = 64;
+
+    pub fn new(id: impl Into<String>) -> SecureEnvResult<Self> {
+        let id = id.into();
+        validate("Key id", &id, Self::MAX_LEN)?;
+
+        Ok(Self(id))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+
+    /// Validate a namespace, which has the same restrictions as an id
+    pub(crate) fn validate_namespace(namespace: &str) -> SecureEnvResult<()> {
+        validate("Namespace", namespace, Self::MAX_NAMESPACE_LEN)
+    }
+}
+
+fn validate(name: &str, value: &str, max_len: usize) -> SecureEnvResult<()> {
+    // The charset is checked first, so the length in bytes is also the number of characters
+    if let Some(c) = value
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+    {
+        return Err(SecureEnvError::InvalidKeyId(format!(
+            "{name} '{value}' contains '{c}'. Only ASCII letters, digits, '-', '_' and '.' are allowed"
+        )));
+    }
+
+    if value.is_empty() || value.len() > max_len {
+        return Err(SecureEnvError::InvalidKeyId(format!(
+            "{name} must be between 1 and {max_len} characters long, received {} characters",
+            value.len()
+        )));
+    }
+
+    Ok(())
+}
+
+impl Display for KeyId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for KeyId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<&str> for KeyId {
+    type Error = SecureEnvError;
+
+    fn try_from(id: &str) -> SecureEnvResult<Self> {
+        Self::new(id)
+    }
+}
+
+impl TryFrom<String> for KeyId {
+    type Error = SecureEnvError;
+
+    fn try_from(id: String) -> SecureEnvResult<Self> {
+        Self::new(id)
+    }
+}
+
+/// Conversion into a validated [`KeyId`]
+pub trait IntoKeyId {
+    fn into_key_id(self) -> SecureEnvResult<KeyId>;
+}
+
+impl IntoKeyId for KeyId {
+    fn into_key_id(self) -> SecureEnvResult<KeyId> {
+        Ok(self)
+    }
+}
+
+impl IntoKeyId for &KeyId {
+    fn into_key_id(self) -> SecureEnvResult<KeyId> {
+        Ok(self.clone())
+    }
+}
+
+impl IntoKeyId for &str {
+    fn into_key_id(self) -> SecureEnvResult<KeyId> {
+        KeyId::new(self)
+    }
+}
+
+impl IntoKeyId for String {
+    fn into_key_id(self) -> SecureEnvResult<KeyId> {
+        KeyId::new(self)
+    }
+}
+
+impl IntoKeyId for &String {
+    fn into_key_id(self) -> SecureEnvResult<KeyId> {
+        KeyId::new(self.as_str())
+    }
+}
@@ -1,23 +1,96 @@
-#[cfg(not(any(target_os = "android", target_os = "ios")))]
-compile_error!("Only Android and iOS are supported targets");
+#[cfg(all(target_os = "ios", feature = "android_host_testing"))]
+compile_error!("The `android_host_testing` feature can not be used when targeting iOS");
 
 pub mod error;
 
+mod config;
+pub use config::*;
+
 mod key;
 pub use key::*;
 
+mod key_id;
+pub use key_id::*;
+
+mod jwk;
+pub use jwk::*;
+
+mod metadata;
+pub use metadata::*;
+
 mod secure_environment;
 pub use secure_environment::*;
 
+mod backend;
+pub use backend::*;
+
+mod wipe;
+pub use wipe::*;
+
+mod software;
+pub use software::*;
+
+#[cfg(feature = "mock")]
+mod mock;
+#[cfg(feature = "mock")]
+pub use mock::*;
+
 #[cfg(target_os = "ios")]
 mod ios;
 #[cfg(target_os = "ios")]
 pub use ios::*;
 
-#[cfg(target_os = "android")]
+#[cfg(any(target_os = "android", feature = "android_host_testing"))]
 mod android;
-#[cfg(target_os = "android")]
+#[cfg(any(target_os = "android", feature = "android_host_testing"))]
 pub use android::*;
 
-#[cfg(target_os = "android")]
+mod rust_crypto;
+pub use rust_crypto::*;
+
+#[cfg(feature = "x509")]
+mod x509;
+#[cfg(feature = "x509")]
+pub use x509::*;
+
+#[cfg(feature = "x509")]
+mod csr;
+#[cfg(feature = "x509")]
+pub use csr::*;
+
+#[cfg(feature = "x509")]
+mod certificate;
+#[cfg(feature = "x509")]
+pub use certificate::*;
+
+mod http_signature;
+pub use http_signature::*;
+
+mod jws;
+
+mod dpop;
+pub use dpop::*;
+
+mod openid4vci;
+pub use openid4vci::*;
+
+mod sd_jwt;
+pub use sd_jwt::*;
+
+#[cfg(feature = "mdoc")]
+mod mdoc;
+#[cfg(feature = "mdoc")]
+pub use mdoc::*;
+
+#[cfg(feature = "data-integrity")]
+mod data_integrity;
+#[cfg(feature = "data-integrity")]
+pub use data_integrity::*;
+
+#[cfg(feature = "rustls")]
+mod tls;
+#[cfg(feature = "rustls")]
+pub use tls::*;
+
+#[cfg(any(target_os = "android", feature = "android_host_testing"))]
 mod jni_tokens;
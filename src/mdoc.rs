@@ -0,0 +1,309 @@
+use crate::{
+    error::{SecureEnvError, SecureEnvResult},
+    rust_crypto::verify_signature,
+    KeyOps,
+};
+use coset::{
+    cbor::{self, value::Value},
+    iana, AsCborValue, CborSerializable, CoseKey, CoseKeyBuilder, CoseSign1, CoseSign1Builder,
+    HeaderBuilder, KeyType, Label, RegisteredLabelWithPrivate,
+};
+use p256::ecdsa::VerifyingKey;
+
+/// Context string of the `DeviceAuthentication` structure
+const DEVICE_AUTHENTICATION: &str = "DeviceAuthentication";
+
+/// CBOR tag of encoded CBOR data items, which wraps `DeviceAuthenticationBytes` and
+/// `DeviceNameSpacesBytes`
+const ENCODED_CBOR_TAG: u64 = 24;
+
+/// `DeviceAuthentication` structure of an mdoc presentation
+/// ([ISO/IEC 18013-5](https://www.iso.org/standard/69084.html) section 9.1.3), which the device
+/// key signs to authenticate the document
+///
+/// The session transcript and the device name spaces are kept as encoded CBOR, so that the signed
+/// bytes are exactly those that the reader reconstructs.
+///
+/// # Examples
+///
+/// ```
+/// use secure_env::{DeviceAuthentication, SecureEnvironmentConfig, SoftwareBackend};
+///
+/// let backend = SoftwareBackend::new(SecureEnvironmentConfig::default());
+/// let key = backend.create_keypair("my-unique-id").unwrap();
+///
+/// // Encoded `SessionTranscript` of the session with the reader, here without engagement and
+/// // handover
+/// let session_transcript = vec![0x83, 0xf6, 0xf6, 0xf6];
+/// let device_authentication =
+///     DeviceAuthentication::new(session_transcript, "org.iso.18013.5.1.mDL");
+///
+/// let device_signature = device_authentication.sign(&key).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceAuthentication {
+    /// Encoded `SessionTranscript` of the session with the reader
+    pub session_transcript: Vec<u8>,
+
+    /// Document type of the presented document, e.g. `org.iso.18013.5.1.mDL`
+    pub doc_type: String,
+
+    /// Encoded `DeviceNameSpaces`, the data elements that are returned signed by the device,
+    /// an empty map by default
+    pub device_name_spaces: Vec<u8>,
+}
+
+impl DeviceAuthentication {
+    /// `DeviceAuthentication` for the document `doc_type` without device signed data elements
+    pub fn new(session_transcript: Vec<u8>, doc_type: impl Into<String>) -> Self {
+        Self {
+            session_transcript,
+            doc_type: doc_type.into(),
+            device_name_spaces: vec![0xa0],
+        }
+    }
+
+    /// Encoded `DeviceAuthentication` array of the context string, session transcript, document
+    /// type and `DeviceNameSpacesBytes`
+    pub fn to_cbor(&self) -> SecureEnvResult<Vec<u8>> {
+        let session_transcript = decode(&self.session_transcript)?;
+        if !matches!(session_transcript, Value::Array(_)) {
+            return Err(SecureEnvError::InvalidCbor(
+                "Session transcript is not an array".to_owned(),
+            ));
+        }
+        if !matches!(decode(&self.device_name_spaces)?, Value::Map(_)) {
+            return Err(SecureEnvError::InvalidCbor(
+                "Device name spaces are not a map".to_owned(),
+            ));
+        }
+
+        // The session transcript is copied as is instead of re-encoding the decoded value
+        let mut device_authentication = vec![0x84];
+        device_authentication.extend(encode(&Value::Text(DEVICE_AUTHENTICATION.to_owned()))?);
+        device_authentication.extend(&self.session_transcript);
+        device_authentication.extend(encode(&Value::Text(self.doc_type.clone()))?);
+        device_authentication.extend(encode(&Value::Tag(
+            ENCODED_CBOR_TAG,
+            Box::new(Value::Bytes(self.device_name_spaces.clone())),
+        ))?);
+
+        Ok(device_authentication)
+    }
+
+    /// Encoded `DeviceAuthenticationBytes`, the tagged `DeviceAuthentication` that is the detached
+    /// payload of the device signature
+    pub fn to_bytes(&self) -> SecureEnvResult<Vec<u8>> {
+        encode(&Value::Tag(
+            ENCODED_CBOR_TAG,
+            Box::new(Value::Bytes(self.to_cbor()?)),
+        ))
+    }
+
+    /// Sign `DeviceAuthenticationBytes` with `key` and return the encoded `DeviceSignature`, an
+    /// untagged `COSE_Sign1` with `ES256` and a detached payload
+    pub fn sign(&self, key: &impl KeyOps) -> SecureEnvResult<Vec<u8>> {
+        let payload = self.to_bytes()?;
+
+        let mut device_signature = CoseSign1Builder::new()
+            .protected(
+                HeaderBuilder::new()
+                    .algorithm(iana::Algorithm::ES256)
+                    .build(),
+            )
+            .build();
+        // `KeyOps::sign` hashes with SHA-256 and returns `r || s`, the COSE encoding of `ES256`
+        device_signature.signature =
+            key.sign(&device_signature.tbs_detached_data(&payload, &[]))?;
+
+        device_signature
+            .to_vec()
+            .map_err(|e| SecureEnvError::UnableToCreateSignature(e.to_string()))
+    }
+
+    /// Verify the encoded `DeviceSignature` with the SEC1 encoded P-256 `public_key` of the device
+    /// key
+    pub fn verify(&self, device_signature: &[u8], public_key: &[u8]) -> SecureEnvResult<()> {
+        let device_signature = CoseSign1::from_slice(device_signature)
+            .map_err(|e| SecureEnvError::InvalidSignature(e.to_string()))?;
+
+        if device_signature.protected.header.alg
+            != Some(RegisteredLabelWithPrivate::Assigned(iana::Algorithm::ES256))
+        {
+            return Err(SecureEnvError::InvalidSignature(
+                "Device signature algorithm is not ES256".to_owned(),
+            ));
+        }
+        if device_signature.payload.is_some() {
+            return Err(SecureEnvError::InvalidSignature(
+                "Device signature does not have a detached payload".to_owned(),
+            ));
+        }
+
+        device_signature.verify_detached_signature(&self.to_bytes()?, &[], |signature, data| {
+            verify_signature(public_key, data, signature)
+        })
+    }
+}
+
+/// `DeviceKeyInfo` of a mobile security object
+/// ([ISO/IEC 18013-5](https://www.iso.org/standard/69084.html) section 9.1.2.4), which binds a
+/// document to a device key
+///
+/// # Examples
+///
+/// ```
+/// use secure_env::{DeviceKeyInfo, SecureEnvironmentConfig, SoftwareBackend};
+///
+/// let backend = SoftwareBackend::new(SecureEnvironmentConfig::default());
+/// let key = backend.create_keypair("my-unique-id").unwrap();
+///
+/// let device_key_info = DeviceKeyInfo::from_key(&key).unwrap();
+/// let cbor = device_key_info.to_cbor().unwrap();
+///
+/// assert_eq!(DeviceKeyInfo::from_cbor(&cbor).unwrap(), device_key_info);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceKeyInfo {
+    /// Compressed SEC1 encoded P-256 public key of the device key
+    pub public_key: Vec<u8>,
+}
+
+impl DeviceKeyInfo {
+    /// `DeviceKeyInfo` with the public key of `key`
+    pub fn from_key(key: &impl KeyOps) -> SecureEnvResult<Self> {
+        Self::from_public_key(&key.get_public_key()?)
+    }
+
+    /// `DeviceKeyInfo` with the SEC1 encoded P-256 `public_key`
+    pub fn from_public_key(public_key: &[u8]) -> SecureEnvResult<Self> {
+        let verifying_key = VerifyingKey::from_sec1_bytes(public_key)
+            .map_err(|e| SecureEnvError::InvalidPublicKey(e.to_string()))?;
+
+        Ok(Self {
+            public_key: verifying_key.to_encoded_point(true).as_bytes().to_vec(),
+        })
+    }
+
+    /// Encoded `DeviceKey`, the public key as an EC2 `COSE_Key`
+    /// ([RFC 9052 section 7](https://www.rfc-editor.org/rfc/rfc9052#section-7))
+    pub fn to_cose_key(&self) -> SecureEnvResult<Vec<u8>> {
+        self.cose_key()?
+            .to_vec()
+            .map_err(|e| SecureEnvError::InvalidPublicKey(e.to_string()))
+    }
+
+    /// Encoded `DeviceKeyInfo` map with the `deviceKey`
+    pub fn to_cbor(&self) -> SecureEnvResult<Vec<u8>> {
+        let device_key = self
+            .cose_key()?
+            .to_cbor_value()
+            .map_err(|e| SecureEnvError::InvalidPublicKey(e.to_string()))?;
+
+        encode(&Value::Map(vec![(
+            Value::Text("deviceKey".to_owned()),
+            device_key,
+        )]))
+    }
+
+    /// Decode an EC2 P-256 `COSE_Key`
+    pub fn from_cose_key(cose_key: &[u8]) -> SecureEnvResult<Self> {
+        let cose_key = CoseKey::from_slice(cose_key)
+            .map_err(|e| SecureEnvError::InvalidCbor(e.to_string()))?;
+
+        Self::from_decoded_cose_key(cose_key)
+    }
+
+    /// Decode a `DeviceKeyInfo` map, ignoring its optional key authorizations and key info
+    pub fn from_cbor(device_key_info: &[u8]) -> SecureEnvResult<Self> {
+        let Value::Map(entries) = decode(device_key_info)? else {
+            return Err(SecureEnvError::InvalidCbor(
+                "Device key info is not a map".to_owned(),
+            ));
+        };
+
+        let device_key = entries
+            .into_iter()
+            .find(|(name, _)| name.as_text() == Some("deviceKey"))
+            .map(|(_, device_key)| device_key)
+            .ok_or_else(|| {
+                SecureEnvError::InvalidCbor("Device key info has no deviceKey".to_owned())
+            })?;
+        let cose_key = CoseKey::from_cbor_value(device_key)
+            .map_err(|e| SecureEnvError::InvalidCbor(e.to_string()))?;
+
+        Self::from_decoded_cose_key(cose_key)
+    }
+
+    fn cose_key(&self) -> SecureEnvResult<CoseKey> {
+        let verifying_key = VerifyingKey::from_sec1_bytes(&self.public_key)
+            .map_err(|e| SecureEnvError::InvalidPublicKey(e.to_string()))?;
+        let point = verifying_key.to_encoded_point(false);
+        let (Some(x), Some(y)) = (point.x(), point.y()) else {
+            return Err(SecureEnvError::InvalidPublicKey(
+                "Public key is the identity point".to_owned(),
+            ));
+        };
+
+        Ok(
+            CoseKeyBuilder::new_ec2_pub_key(iana::EllipticCurve::P_256, x.to_vec(), y.to_vec())
+                .build(),
+        )
+    }
+
+    fn from_decoded_cose_key(cose_key: CoseKey) -> SecureEnvResult<Self> {
+        let error = |message: &str| SecureEnvError::InvalidPublicKey(message.to_owned());
+
+        if cose_key.kty != KeyType::Assigned(iana::KeyType::EC2) {
+            return Err(error("Only EC2 keys are supported"));
+        }
+
+        let parameter = |parameter: iana::Ec2KeyParameter| {
+            cose_key
+                .params
+                .iter()
+                .find(|(label, _)| *label == Label::Int(parameter as i64))
+                .map(|(_, value)| value)
+        };
+        if parameter(iana::Ec2KeyParameter::Crv)
+            != Some(&Value::from(iana::EllipticCurve::P_256 as u64))
+        {
+            return Err(error("Only P-256 keys are supported"));
+        }
+        let (Some(Value::Bytes(x)), Some(Value::Bytes(y))) = (
+            parameter(iana::Ec2KeyParameter::X),
+            parameter(iana::Ec2KeyParameter::Y),
+        ) else {
+            return Err(error("Key does not have x and y coordinates"));
+        };
+
+        let mut public_key = vec![0x04];
+        public_key.extend(x);
+        public_key.extend(y);
+
+        Self::from_public_key(&public_key)
+    }
+}
+
+/// Decode a single CBOR data item
+fn decode(bytes: &[u8]) -> SecureEnvResult<Value> {
+    let mut reader = bytes;
+    let value = cbor::de::from_reader(&mut reader)
+        .map_err(|e| SecureEnvError::InvalidCbor(e.to_string()))?;
+
+    if !reader.is_empty() {
+        return Err(SecureEnvError::InvalidCbor(
+            "Unexpected data after the CBOR data item".to_owned(),
+        ));
+    }
+
+    Ok(value)
+}
+
+fn encode(value: &Value) -> SecureEnvResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    cbor::ser::into_writer(value, &mut bytes)
+        .map_err(|e| SecureEnvError::UnableToCreateSignature(e.to_string()))?;
+
+    Ok(bytes)
+}
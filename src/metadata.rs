@@ -0,0 +1,551 @@
+use crate::{
+    config::unscoped_id,
+    error::{SecureEnvError, SecureEnvResult},
+    jwk_thumbprint, KeyGenerationOptions, KeyId, KeyOps, SecureEnvironmentConfig,
+};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    fs, io,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Metadata of a keypair, which is kept in a [`MetadataStore`] next to the keystore
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyMetadata {
+    /// Time at which the keypair has been created, in seconds since the Unix epoch. It is set by
+    /// the backend when the keypair is created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
+
+    /// JWK thumbprint of the public key, see [`crate::Jwk::thumbprint`]. It is set by the backend
+    /// when the keypair is created and indexes the keypair for
+    /// [`crate::KeyBackend::find_keypair_by_thumbprint`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbprint: Option<String>,
+
+    /// Tags of the application, e.g. the purpose of the key or the credential it is bound to
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+
+    /// Time from which the keypair can be used, see [`KeyGenerationOptions::valid_from`]. It is
+    /// set by the backend when the keypair is created and can not be changed afterwards.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_from: Option<u64>,
+
+    /// Time until which the keypair can be used, see [`KeyGenerationOptions::valid_until`]. It is
+    /// set by the backend when the keypair is created and can not be changed afterwards.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_until: Option<u64>,
+
+    /// Number of signatures that can be created with the keypair, see
+    /// [`KeyGenerationOptions::max_usage_count`]. It is set by the backend when the keypair is
+    /// created and can not be changed afterwards.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_usage_count: Option<u32>,
+
+    /// Number of signatures that have been created with a keypair that has a usage limit
+    ///
+    /// It is counted by the backends that enforce the limit in this library. On Android the
+    /// `AndroidKeyStore` counts the signatures instead, so it stays 0.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub usage_count: u32,
+}
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+/// Validity period and usage limit of a keypair
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct UsageLimits {
+    pub(crate) valid_from: Option<u64>,
+    pub(crate) valid_until: Option<u64>,
+    pub(crate) max_usage_count: Option<u32>,
+}
+
+impl UsageLimits {
+    fn from_options(options: &KeyGenerationOptions) -> Self {
+        Self {
+            valid_from: options.valid_from,
+            valid_until: options.valid_until,
+            max_usage_count: options.max_usage_count,
+        }
+    }
+
+    fn from_metadata(metadata: &KeyMetadata) -> Self {
+        Self {
+            valid_from: metadata.valid_from,
+            valid_until: metadata.valid_until,
+            max_usage_count: metadata.max_usage_count,
+        }
+    }
+
+    fn check_validity_period(&self, now: u64) -> SecureEnvResult<()> {
+        match (self.valid_from, self.valid_until) {
+            (Some(valid_from), _) if now < valid_from => Err(SecureEnvError::KeyExpired(format!(
+                "Key is valid from {valid_from}, it is {now}"
+            ))),
+            (_, Some(valid_until)) if now > valid_until => Err(SecureEnvError::KeyExpired(
+                format!("Key was valid until {valid_until}, it is {now}"),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Fail when a keypair with a usage limit of `max_usage_count` can not be used again
+fn check_usage_count(usage_count: u32, max_usage_count: u32) -> SecureEnvResult<()> {
+    if usage_count >= max_usage_count {
+        return Err(SecureEnvError::UsageLimitExceeded(format!(
+            "Key has been used {usage_count} of {max_usage_count} times"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Count a use of a keypair with a usage limit of `max_usage_count`
+fn count_usage(usage_count: &mut u32, max_usage_count: u32) -> SecureEnvResult<()> {
+    check_usage_count(*usage_count, max_usage_count)?;
+
+    *usage_count += 1;
+    Ok(())
+}
+
+/// Current time in seconds since the Unix epoch, or an error when the system time is before it
+pub(crate) fn now() -> SecureEnvResult<u64> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .map_err(|e| SecureEnvError::InvalidSystemTime(e.to_string()))
+}
+
+/// Serializes the updates of the metadata that read it first, so a concurrent signature or
+/// [`KeyMetadataHandle::set_metadata`] does not lose a use of a keypair
+static METADATA_UPDATE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Storage for the [`KeyMetadata`] of the keypairs of every namespace
+///
+/// The backends create, remove and query the metadata together with the keypairs, so it does not
+/// drift out of sync with the keystore.
+pub trait MetadataStore: Send + Sync + Debug {
+    /// Metadata of a keypair, if any has been stored
+    fn load(&self, namespace: Option<&str>, id: &KeyId) -> SecureEnvResult<Option<KeyMetadata>>;
+
+    /// Store the metadata of a keypair, replacing any existing metadata
+    fn store(
+        &self,
+        namespace: Option<&str>,
+        id: &KeyId,
+        metadata: &KeyMetadata,
+    ) -> SecureEnvResult<()>;
+
+    /// Remove the metadata of a keypair. Removing metadata that does not exist is not an error.
+    fn remove(&self, namespace: Option<&str>, id: &KeyId) -> SecureEnvResult<()>;
+
+    /// Metadata of every keypair in the namespace
+    fn list(&self, namespace: Option<&str>) -> SecureEnvResult<Vec<(KeyId, KeyMetadata)>>;
+}
+
+/// [`MetadataStore`] that keeps the metadata of every namespace in a single JSON file
+///
+/// The file is rewritten atomically on every change, via a temporary file next to it. It must
+/// only be used by a single process, as concurrent writers are only synchronized within the
+/// process.
+///
+/// # Examples
+///
+/// ```
+/// use secure_env::{
+///     FileMetadataStore, KeyBackend, KeyId, KeyMetadata, KeyOps, SecureEnvironmentConfig,
+///     SoftwareBackend,
+/// };
+/// use std::sync::Arc;
+///
+/// # let path = std::env::temp_dir().join(format!("secure-env-doc-{}.json", std::process::id()));
+/// let backend = SoftwareBackend::new(SecureEnvironmentConfig {
+///     metadata_store: Some(Arc::new(FileMetadataStore::new(&path))),
+///     ..Default::default()
+/// });
+///
+/// let key = backend.create_keypair("my-unique-id").unwrap();
+///
+/// let mut metadata = key.metadata().unwrap().unwrap();
+/// metadata.tags.insert("purpose".to_owned(), "dpop".to_owned());
+/// key.set_metadata(&metadata).unwrap();
+///
+/// assert_eq!(
+///     backend.find_keys(&[("purpose", "dpop")]).unwrap(),
+///     vec![KeyId::new("my-unique-id").unwrap()]
+/// );
+/// # std::fs::remove_file(path).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct FileMetadataStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileMetadataStore {
+    /// Store the metadata in the file at `path`, which is created when it does not exist
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Mutex::default(),
+        }
+    }
+
+    fn read(&self) -> SecureEnvResult<BTreeMap<String, KeyMetadata>> {
+        let contents = match fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(SecureEnvError::UnableToAccessMetadata(e.to_string())),
+        };
+
+        serde_json::from_slice(&contents)
+            .map_err(|e| SecureEnvError::UnableToAccessMetadata(e.to_string()))
+    }
+
+    fn write(&self, entries: &BTreeMap<String, KeyMetadata>) -> SecureEnvResult<()> {
+        let contents = serde_json::to_vec_pretty(entries)
+            .map_err(|e| SecureEnvError::UnableToAccessMetadata(e.to_string()))?;
+
+        let mut temporary_path = self.path.clone().into_os_string();
+        temporary_path.push(".tmp");
+
+        fs::write(&temporary_path, contents)
+            .and_then(|_| fs::rename(&temporary_path, &self.path))
+            .map_err(|e| SecureEnvError::UnableToAccessMetadata(e.to_string()))
+    }
+
+    fn update(&self, f: impl FnOnce(&mut BTreeMap<String, KeyMetadata>)) -> SecureEnvResult<()> {
+        let _lock = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        let mut entries = self.read()?;
+        f(&mut entries);
+        self.write(&entries)
+    }
+}
+
+/// Key under which the metadata is stored, the same as the alias in the keystore
+fn entry_key(namespace: Option<&str>, id: &KeyId) -> String {
+    match namespace {
+        Some(namespace) => format!("{namespace}:{id}"),
+        None => id.to_string(),
+    }
+}
+
+impl MetadataStore for FileMetadataStore {
+    fn load(&self, namespace: Option<&str>, id: &KeyId) -> SecureEnvResult<Option<KeyMetadata>> {
+        let _lock = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        Ok(self.read()?.remove(&entry_key(namespace, id)))
+    }
+
+    fn store(
+        &self,
+        namespace: Option<&str>,
+        id: &KeyId,
+        metadata: &KeyMetadata,
+    ) -> SecureEnvResult<()> {
+        self.update(|entries| {
+            entries.insert(entry_key(namespace, id), metadata.clone());
+        })
+    }
+
+    fn remove(&self, namespace: Option<&str>, id: &KeyId) -> SecureEnvResult<()> {
+        self.update(|entries| {
+            entries.remove(&entry_key(namespace, id));
+        })
+    }
+
+    fn list(&self, namespace: Option<&str>) -> SecureEnvResult<Vec<(KeyId, KeyMetadata)>> {
+        let _lock = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        Ok(self
+            .read()?
+            .into_iter()
+            .filter_map(|(key, metadata)| Some((unscoped_id(namespace, &key)?, metadata)))
+            .collect())
+    }
+}
+
+/// Access to the metadata of a single key, which is held by the key of every backend
+#[derive(Debug, Clone)]
+pub(crate) struct KeyMetadataHandle {
+    namespace: Option<String>,
+    /// `None` for ephemeral keys, which have no metadata
+    id: Option<KeyId>,
+    store: Option<Arc<dyn MetadataStore>>,
+    /// Validity period and usage limit, which never change after the key has been created
+    usage_limits: Arc<OnceCell<UsageLimits>>,
+    /// Number of signatures of an ephemeral key, which has no metadata to count them in
+    ephemeral_usage_count: Arc<Mutex<u32>>,
+}
+
+impl KeyMetadataHandle {
+    /// Use the usage limits of the options of a key that has just been created, so they do not
+    /// have to be loaded from the metadata
+    pub(crate) fn with_usage_limits(self, options: &KeyGenerationOptions) -> Self {
+        let _ = self.usage_limits.set(UsageLimits::from_options(options));
+        self
+    }
+
+    /// Validity period and usage limit of the key, which are loaded from the metadata once
+    ///
+    /// Without metadata, e.g. without a store, the key has no limits.
+    pub(crate) fn usage_limits(&self) -> SecureEnvResult<UsageLimits> {
+        self.usage_limits
+            .get_or_try_init(|| {
+                let Ok((store, id)) = self.store() else {
+                    return Ok(UsageLimits::default());
+                };
+
+                Ok(store
+                    .load(self.namespace.as_deref(), id)?
+                    .map(|metadata| UsageLimits::from_metadata(&metadata))
+                    .unwrap_or_default())
+            })
+            .copied()
+    }
+
+    /// Fail when the key is outside of its validity period or has reached its usage limit
+    ///
+    /// Called by the backends that enforce the limits in this library before every signature, so
+    /// the user is not prompted for a signature that is refused afterwards.
+    pub(crate) fn check_usage(&self) -> SecureEnvResult<()> {
+        let usage_limits = self.usage_limits()?;
+        usage_limits.check_validity_period(now()?)?;
+
+        let Some(max_usage_count) = usage_limits.max_usage_count else {
+            return Ok(());
+        };
+
+        let usage_count = if self.id.is_none() {
+            *self
+                .ephemeral_usage_count
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+        } else {
+            let (store, id) = self.store()?;
+            store
+                .load(self.namespace.as_deref(), id)?
+                .ok_or(SecureEnvError::UnableToAccessMetadata(format!(
+                    "No usage count has been stored for key '{id}'"
+                )))?
+                .usage_count
+        };
+
+        check_usage_count(usage_count, max_usage_count)
+    }
+
+    /// Count a use of the key, or fail when it has reached its usage limit in the meantime
+    ///
+    /// Called by the backends that enforce the limits in this library after every signature that
+    /// has been made, so a signature that fails or is cancelled does not use up the key. The
+    /// signature must be discarded when this fails.
+    pub(crate) fn record_usage(&self) -> SecureEnvResult<()> {
+        let Some(max_usage_count) = self.usage_limits()?.max_usage_count else {
+            return Ok(());
+        };
+
+        if self.id.is_none() {
+            let mut usage_count = self
+                .ephemeral_usage_count
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+
+            return count_usage(&mut usage_count, max_usage_count);
+        }
+
+        let (store, id) = self.store()?;
+        let namespace = self.namespace.as_deref();
+        let _lock = METADATA_UPDATE_LOCK
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+
+        // The metadata is removed together with the key
+        let mut metadata =
+            store
+                .load(namespace, id)?
+                .ok_or(SecureEnvError::UnableToAccessMetadata(format!(
+                    "No usage count has been stored for key '{id}'"
+                )))?;
+
+        count_usage(&mut metadata.usage_count, max_usage_count)?;
+        store.store(namespace, id, &metadata)
+    }
+
+    fn store(&self) -> SecureEnvResult<(&Arc<dyn MetadataStore>, &KeyId)> {
+        let store = self
+            .store
+            .as_ref()
+            .ok_or(SecureEnvError::UnableToAccessMetadata(
+                "No metadata store has been configured".to_owned(),
+            ))?;
+
+        let id = self
+            .id
+            .as_ref()
+            .ok_or(SecureEnvError::UnableToAccessMetadata(
+                "Ephemeral keys have no metadata".to_owned(),
+            ))?;
+
+        Ok((store, id))
+    }
+
+    pub(crate) fn metadata(&self) -> SecureEnvResult<Option<KeyMetadata>> {
+        let (store, id) = self.store()?;
+
+        store.load(self.namespace.as_deref(), id)
+    }
+
+    /// Replace the metadata, but keep the fields that are set by the backend when they are not
+    /// provided, so the index is not lost by accident
+    ///
+    /// The usage limits and the usage count are always kept, so they can not be lifted.
+    pub(crate) fn set_metadata(&self, metadata: &KeyMetadata) -> SecureEnvResult<()> {
+        let (store, id) = self.store()?;
+        let namespace = self.namespace.as_deref();
+        let _lock = METADATA_UPDATE_LOCK
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+
+        let stored = store.load(namespace, id)?.unwrap_or_default();
+        let metadata = KeyMetadata {
+            created_at: metadata.created_at.or(stored.created_at),
+            thumbprint: metadata.thumbprint.clone().or(stored.thumbprint),
+            tags: metadata.tags.clone(),
+            valid_from: stored.valid_from,
+            valid_until: stored.valid_until,
+            max_usage_count: stored.max_usage_count,
+            usage_count: stored.usage_count,
+        };
+
+        store.store(namespace, id, &metadata)
+    }
+}
+
+impl SecureEnvironmentConfig {
+    /// Metadata handle for the key with `id`, or for an ephemeral key when `id` is `None`
+    pub(crate) fn metadata_handle(&self, id: Option<&KeyId>) -> KeyMetadataHandle {
+        KeyMetadataHandle {
+            namespace: self.namespace.clone(),
+            id: id.cloned(),
+            store: self.metadata_store.clone(),
+            usage_limits: Arc::default(),
+            ephemeral_usage_count: Arc::default(),
+        }
+    }
+
+    /// Check the usage limits of the options before a keypair is created, for the backends that
+    /// enforce the limits in this library
+    ///
+    /// A stored keypair keeps its usage count in the metadata, so a store is required.
+    pub(crate) fn check_usage_limits(
+        &self,
+        options: &KeyGenerationOptions,
+        ephemeral: bool,
+    ) -> SecureEnvResult<()> {
+        options.validate_usage_limits()?;
+
+        if options.has_usage_limits() && !ephemeral && self.metadata_store.is_none() {
+            return Err(SecureEnvError::UnableToGenerateKey(
+                "A validity period or usage limit requires a metadata store".to_owned(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Store the metadata of a keypair that has just been created
+    pub(crate) fn store_created_metadata(
+        &self,
+        id: &KeyId,
+        key: &impl KeyOps,
+        options: &KeyGenerationOptions,
+    ) -> SecureEnvResult<()> {
+        let Some(store) = &self.metadata_store else {
+            return Ok(());
+        };
+
+        let thumbprint = key
+            .get_public_key()
+            .and_then(|public_key| jwk_thumbprint(&public_key))
+            .map_err(|e| SecureEnvError::UnableToAccessMetadata(e.to_string()))?;
+
+        store.store(
+            self.namespace.as_deref(),
+            id,
+            &KeyMetadata {
+                created_at: now().ok(),
+                thumbprint: Some(thumbprint),
+                valid_from: options.valid_from,
+                valid_until: options.valid_until,
+                max_usage_count: options.max_usage_count,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Remove the metadata of a keypair that has been deleted
+    pub(crate) fn remove_metadata(&self, id: &KeyId) -> SecureEnvResult<()> {
+        match &self.metadata_store {
+            Some(store) => store.remove(self.namespace.as_deref(), id),
+            None => Ok(()),
+        }
+    }
+
+    fn required_metadata_store(&self) -> SecureEnvResult<&Arc<dyn MetadataStore>> {
+        self.metadata_store
+            .as_ref()
+            .ok_or(SecureEnvError::UnableToAccessMetadata(
+                "No metadata store has been configured".to_owned(),
+            ))
+    }
+
+    /// Ids of the keypairs in the namespace that have metadata, which is empty without a store
+    pub(crate) fn metadata_ids(&self) -> SecureEnvResult<Vec<KeyId>> {
+        match &self.metadata_store {
+            Some(store) => Ok(store
+                .list(self.namespace.as_deref())?
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect()),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Ids of the keypairs in the namespace that have every tag of `tags`
+    pub(crate) fn find_keys(&self, tags: &[(&str, &str)]) -> SecureEnvResult<Vec<KeyId>> {
+        Ok(self
+            .required_metadata_store()?
+            .list(self.namespace.as_deref())?
+            .into_iter()
+            .filter(|(_, metadata)| {
+                tags.iter().all(|(name, value)| {
+                    metadata.tags.get(*name).map(String::as_str) == Some(*value)
+                })
+            })
+            .map(|(id, _)| id)
+            .collect())
+    }
+
+    /// Id of the keypair in the namespace whose public key has the JWK thumbprint `thumbprint`
+    pub(crate) fn find_key_by_thumbprint(
+        &self,
+        thumbprint: &str,
+    ) -> SecureEnvResult<Option<KeyId>> {
+        Ok(self
+            .required_metadata_store()?
+            .list(self.namespace.as_deref())?
+            .into_iter()
+            .find(|(_, metadata)| metadata.thumbprint.as_deref() == Some(thumbprint))
+            .map(|(id, _)| id))
+    }
+}
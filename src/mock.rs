@@ -0,0 +1,685 @@
+use crate::{
+    error::{SecureEnvError, SecureEnvResult},
+    jwk_thumbprint, BoxedKey, IntoKeyId, KeyBackend, KeyGenerationOptions, KeyId, KeyMetadata,
+    KeyMetadataHandle, KeyOps, SecureEnvironmentConfig,
+};
+use p256::{
+    ecdsa::{
+        signature::{Keypair, Signer},
+        Signature, SigningKey, VerifyingKey,
+    },
+    elliptic_curve::rand_core::OsRng,
+};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    sync::{Arc, Mutex, MutexGuard},
+    thread,
+    time::Duration,
+};
+
+/// Operation of a [`MockBackend`] or a [`MockKey`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MockOperation {
+    CreateKeypair,
+    GenerateEphemeralKeypair,
+    LoadKeypair,
+    /// Lookup by public key or thumbprint, which is logged with the thumbprint as its id
+    FindKeypair,
+    ListKeys,
+    DeleteKeypair,
+    GetPublicKey,
+    Sign,
+}
+
+/// Failure of the platform that can be simulated by a [`MockFaultPlan`]
+///
+/// The error that is returned is the error of the failing operation, e.g.
+/// [`SecureEnvError::UnableToCreateSignature`] for [`MockOperation::Sign`], with the [`Display`]
+/// output of the fault as its message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockFault {
+    /// The user cancelled the biometric prompt
+    UserCancelled,
+
+    /// The key has been permanently invalidated, e.g. because new biometrics have been enrolled
+    KeyInvalidated,
+
+    /// The device has no StrongBox. When creating a keypair this fails with
+    /// [`SecureEnvError::HardwareBackedKeysAreNotSupported`]
+    StrongBoxUnavailable,
+
+    /// Any other failure, with the message of the error
+    Other(String),
+}
+
+impl Display for MockFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MockFault::UserCancelled => f.write_str("User cancelled the biometric prompt"),
+            MockFault::KeyInvalidated => f.write_str("Key has been permanently invalidated"),
+            MockFault::StrongBoxUnavailable => f.write_str("StrongBox is not available"),
+            MockFault::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl MockFault {
+    fn error(&self, operation: MockOperation) -> SecureEnvError {
+        let message = self.to_string();
+
+        match (operation, self) {
+            (
+                MockOperation::CreateKeypair | MockOperation::GenerateEphemeralKeypair,
+                MockFault::StrongBoxUnavailable,
+            ) => SecureEnvError::HardwareBackedKeysAreNotSupported(message),
+            (MockOperation::CreateKeypair | MockOperation::GenerateEphemeralKeypair, _) => {
+                SecureEnvError::UnableToGenerateKey(message)
+            }
+            (MockOperation::LoadKeypair | MockOperation::FindKeypair, _) => {
+                SecureEnvError::UnableToGetKeyPairById(message)
+            }
+            (MockOperation::ListKeys, _) => SecureEnvError::UnableToListKeys(message),
+            (MockOperation::DeleteKeypair, _) => SecureEnvError::UnableToDeleteKey(message),
+            (MockOperation::GetPublicKey, _) => SecureEnvError::UnableToGetPublicKey(message),
+            (MockOperation::Sign, _) => SecureEnvError::UnableToCreateSignature(message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Calls {
+    Nth(usize),
+    From(usize),
+}
+
+impl Calls {
+    fn contains(&self, call: usize) -> bool {
+        match *self {
+            Calls::Nth(n) => call == n,
+            Calls::From(n) => call >= n,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ScheduledFault {
+    operation: MockOperation,
+    calls: Calls,
+    fault: MockFault,
+}
+
+/// Script of the faults and latency that a [`MockBackend`] simulates
+///
+/// Calls are counted per [`MockOperation`] over every key of the backend, starting at 1. When
+/// multiple faults apply to a call, the one that has been added first is used.
+///
+/// # Examples
+///
+/// ```
+/// use secure_env::{MockFault, MockFaultPlan, MockOperation};
+/// use std::time::Duration;
+///
+/// let plan = MockFaultPlan::new()
+///     // The user cancels the biometric prompt of the second signature
+///     .fail(MockOperation::Sign, 2, MockFault::UserCancelled)
+///     // Every signature from the fifth on fails, as the key has been invalidated
+///     .fail_from(MockOperation::Sign, 5, MockFault::KeyInvalidated)
+///     // Slow hardware
+///     .latency(MockOperation::Sign, Duration::from_millis(50));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MockFaultPlan {
+    faults: Vec<ScheduledFault>,
+    latency: HashMap<MockOperation, Duration>,
+}
+
+impl MockFaultPlan {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail the `nth` call of `operation`
+    pub fn fail(mut self, operation: MockOperation, nth: usize, fault: MockFault) -> Self {
+        self.faults.push(ScheduledFault {
+            operation,
+            calls: Calls::Nth(nth),
+            fault,
+        });
+        self
+    }
+
+    /// Fail the `nth` and every later call of `operation`
+    pub fn fail_from(mut self, operation: MockOperation, nth: usize, fault: MockFault) -> Self {
+        self.faults.push(ScheduledFault {
+            operation,
+            calls: Calls::From(nth),
+            fault,
+        });
+        self
+    }
+
+    /// Delay every call of `operation` by `latency`
+    pub fn latency(mut self, operation: MockOperation, latency: Duration) -> Self {
+        self.latency.insert(operation, latency);
+        self
+    }
+
+    fn fault(&self, operation: MockOperation, call: usize) -> Option<&MockFault> {
+        self.faults
+            .iter()
+            .find(|scheduled| scheduled.operation == operation && scheduled.calls.contains(call))
+            .map(|scheduled| &scheduled.fault)
+    }
+}
+
+/// Call that has been made to a [`MockBackend`] or one of its keys
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockCall {
+    pub operation: MockOperation,
+
+    /// Id of the key, as it has been provided by the caller. Empty for ephemeral keys and when
+    /// listing the keys.
+    pub id: String,
+
+    /// Message of the error when the call has failed
+    pub result: Result<(), String>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    seed: Option<u64>,
+    keys: HashMap<String, Arc<SigningKey>>,
+    /// Number of keypairs that have been created per id, so a recreated id gets a new key
+    generations: HashMap<String, u64>,
+    ephemeral_keys: u64,
+    plan: MockFaultPlan,
+    calls_per_operation: HashMap<MockOperation, usize>,
+    calls: Vec<MockCall>,
+}
+
+#[derive(Debug, Default)]
+struct Shared(Mutex<MockState>);
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, MockState> {
+        // A panic while the lock is held can not leave the state inconsistent
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Count the call and apply the latency and fault of the plan
+    fn begin(&self, operation: MockOperation, id: &str) -> SecureEnvResult<()> {
+        let (latency, fault) = {
+            let mut state = self.lock();
+
+            let call = state.calls_per_operation.entry(operation).or_default();
+            *call += 1;
+            let call = *call;
+
+            (
+                state.plan.latency.get(&operation).copied(),
+                state.plan.fault(operation, call).cloned(),
+            )
+        };
+
+        if let Some(latency) = latency {
+            thread::sleep(latency);
+        }
+
+        match fault {
+            Some(fault) => self.finish(operation, id, Err(fault.error(operation))),
+            None => Ok(()),
+        }
+    }
+
+    /// Record the call in the log
+    fn finish<T>(
+        &self,
+        operation: MockOperation,
+        id: &str,
+        result: SecureEnvResult<T>,
+    ) -> SecureEnvResult<T> {
+        self.lock().calls.push(MockCall {
+            operation,
+            id: id.to_owned(),
+            result: result.as_ref().map(|_| ()).map_err(|e| e.to_string()),
+        });
+
+        result
+    }
+}
+
+/// Backend that simulates a secure element in memory, for testing applications
+///
+/// Keys are kept in memory like the [`crate::SoftwareBackend`], but the backend can be scripted
+/// to fail or to be slow via a [`MockFaultPlan`], so the error handling of an application can be
+/// tested without a device. With a seed the generated keys are deterministic. Every call is
+/// recorded and can be inspected via [`MockBackend::calls`].
+///
+/// Clones share their keys, fault plan and call log, so a clone can be handed to the code under
+/// test while the test inspects the original.
+///
+/// Only available with the `mock` feature.
+///
+/// # Examples
+///
+/// ## Simulate a cancelled biometric prompt
+///
+/// ```
+/// use secure_env::{
+///     error::SecureEnvError, KeyOps, MockBackend, MockFault, MockFaultPlan, MockOperation,
+///     SecureEnvironmentConfig,
+/// };
+///
+/// let backend = MockBackend::new(SecureEnvironmentConfig::default())
+///     .with_seed(42)
+///     .with_fault_plan(MockFaultPlan::new().fail(MockOperation::Sign, 1, MockFault::UserCancelled));
+///
+/// let key = backend.create_keypair("my-unique-id").unwrap();
+///
+/// assert!(matches!(
+///     key.sign(b"Hello World!"),
+///     Err(SecureEnvError::UnableToCreateSignature(_))
+/// ));
+/// assert_eq!(key.sign(b"Hello World!").unwrap().len(), 64);
+///
+/// let signatures = backend
+///     .calls()
+///     .into_iter()
+///     .filter(|call| call.operation == MockOperation::Sign)
+///     .count();
+/// assert_eq!(signatures, 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockBackend {
+    config: SecureEnvironmentConfig,
+    shared: Arc<Shared>,
+}
+
+impl MockBackend {
+    pub fn new(config: SecureEnvironmentConfig) -> Self {
+        Self {
+            config,
+            shared: Arc::default(),
+        }
+    }
+
+    /// Derive the keys from `seed`, instead of generating them randomly
+    ///
+    /// The key only depends on the seed, the id and how often a keypair has been created with
+    /// that id before, so it does not depend on the order in which keypairs are created.
+    pub fn with_seed(self, seed: u64) -> Self {
+        self.shared.lock().seed = Some(seed);
+        self
+    }
+
+    /// Simulate the faults and latency of `plan`
+    pub fn with_fault_plan(self, plan: MockFaultPlan) -> Self {
+        self.set_fault_plan(plan);
+        self
+    }
+
+    /// Replace the fault plan and start counting the calls from 1 again
+    pub fn set_fault_plan(&self, plan: MockFaultPlan) {
+        let mut state = self.shared.lock();
+
+        state.plan = plan;
+        state.calls_per_operation.clear();
+    }
+
+    /// Every call that has been made to the backend and its keys, in the order they finished
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.shared.lock().calls.clone()
+    }
+
+    /// Clear the call log
+    pub fn clear_calls(&self) {
+        self.shared.lock().calls.clear();
+    }
+
+    /// Create and store a keypair with the default options of this backend
+    pub fn create_keypair(&self, id: impl IntoKeyId) -> SecureEnvResult<MockKey> {
+        self.create_keypair_with_options(id, &self.config.default_options)
+    }
+
+    /// Create and store a keypair in memory
+    ///
+    /// Keys that are backed by biometrics are supported, failures of the biometric prompt can be
+    /// simulated with [`MockFault::UserCancelled`].
+    pub fn create_keypair_with_options(
+        &self,
+        id: impl IntoKeyId,
+        options: &KeyGenerationOptions,
+    ) -> SecureEnvResult<MockKey> {
+        let id = id.into_key_id()?;
+        let scoped_id = self.config.scoped_id(&id)?;
+        self.config.check_usage_limits(options, false)?;
+
+        self.shared
+            .begin(MockOperation::CreateKeypair, id.as_str())?;
+
+        let result = {
+            let mut state = self.shared.lock();
+
+            if state.keys.contains_key(&scoped_id) {
+                Err(SecureEnvError::UnableToGenerateKey(format!(
+                    "Key with id: '{scoped_id}' already exists."
+                )))
+            } else {
+                let seed = state.seed;
+                let generation = state.generations.entry(scoped_id.clone()).or_default();
+                let signing_key = match seed {
+                    Some(seed) => derive_signing_key(seed, Some(&scoped_id), *generation),
+                    None => SigningKey::random(&mut OsRng),
+                };
+                *generation += 1;
+
+                let signing_key = Arc::new(signing_key);
+                state.keys.insert(scoped_id.clone(), signing_key.clone());
+
+                Ok(self
+                    .key(Some(&id), Some(scoped_id), signing_key)
+                    .with_usage_limits(options))
+            }
+        };
+        let result = result.and_then(|key| {
+            self.config.store_created_metadata(&id, &key, options)?;
+            Ok(key)
+        });
+
+        self.shared
+            .finish(MockOperation::CreateKeypair, id.as_str(), result)
+    }
+
+    /// Create a keypair that is not stored and can not be loaded by id
+    ///
+    /// With a seed, the key depends on the number of ephemeral keypairs that have been created
+    /// before.
+    pub fn generate_ephemeral_keypair(
+        &self,
+        options: &KeyGenerationOptions,
+    ) -> SecureEnvResult<MockKey> {
+        self.config.check_usage_limits(options, true)?;
+        self.shared
+            .begin(MockOperation::GenerateEphemeralKeypair, "")?;
+
+        let signing_key = {
+            let mut state = self.shared.lock();
+
+            let signing_key = match state.seed {
+                Some(seed) => derive_signing_key(seed, None, state.ephemeral_keys),
+                None => SigningKey::random(&mut OsRng),
+            };
+            state.ephemeral_keys += 1;
+
+            signing_key
+        };
+
+        let key = self
+            .key(None, None, Arc::new(signing_key))
+            .with_usage_limits(options);
+
+        self.shared
+            .finish(MockOperation::GenerateEphemeralKeypair, "", Ok(key))
+    }
+
+    /// Get a keypair that has been stored in memory
+    pub fn load_keypair(&self, id: impl IntoKeyId) -> SecureEnvResult<MockKey> {
+        let id = id.into_key_id()?;
+        let scoped_id = self.config.scoped_id(&id)?;
+
+        self.shared.begin(MockOperation::LoadKeypair, id.as_str())?;
+        let signing_key = self.shared.lock().keys.get(&scoped_id).cloned();
+
+        let result = signing_key
+            .map(|signing_key| self.key(Some(&id), Some(scoped_id.clone()), signing_key))
+            .ok_or(SecureEnvError::UnableToGetKeyPairById(format!(
+                "Key reference with id: '{scoped_id}' not found."
+            )));
+
+        self.shared
+            .finish(MockOperation::LoadKeypair, id.as_str(), result)
+    }
+
+    /// Ids of every keypair that is stored in memory
+    pub fn list_keys(&self) -> SecureEnvResult<Vec<KeyId>> {
+        self.shared.begin(MockOperation::ListKeys, "")?;
+
+        let mut ids: Vec<KeyId> = self
+            .shared
+            .lock()
+            .keys
+            .keys()
+            .filter_map(|scoped_id| self.config.unscoped_id(scoped_id))
+            .collect();
+        ids.sort();
+
+        self.shared.finish(MockOperation::ListKeys, "", Ok(ids))
+    }
+
+    /// Get the stored keypair with the SEC1 encoded public key `public_key`
+    pub fn find_keypair_by_public_key(
+        &self,
+        public_key: &[u8],
+    ) -> SecureEnvResult<Option<MockKey>> {
+        self.find_keypair_by_thumbprint(&jwk_thumbprint(public_key)?)
+    }
+
+    /// Get the stored keypair whose public key has the JWK thumbprint `thumbprint`
+    pub fn find_keypair_by_thumbprint(&self, thumbprint: &str) -> SecureEnvResult<Option<MockKey>> {
+        self.shared.begin(MockOperation::FindKeypair, thumbprint)?;
+
+        let found = self
+            .shared
+            .lock()
+            .keys
+            .iter()
+            .find(|(_, signing_key)| {
+                // The public key of a signing key is always a valid point
+                let public_key = signing_key.verifying_key().to_encoded_point(true);
+                jwk_thumbprint(public_key.as_bytes()).map_or(false, |other| other == thumbprint)
+            })
+            .map(|(scoped_id, signing_key)| (scoped_id.clone(), signing_key.clone()));
+
+        let result = Ok(found.and_then(|(scoped_id, signing_key)| {
+            let id = self.config.unscoped_id(&scoped_id)?;
+            Some(self.key(Some(&id), Some(scoped_id), signing_key))
+        }));
+
+        self.shared
+            .finish(MockOperation::FindKeypair, thumbprint, result)
+    }
+
+    /// Delete a keypair from memory, together with its metadata
+    ///
+    /// Signing with a key that has been deleted fails afterwards.
+    pub fn delete_keypair(&self, id: impl IntoKeyId) -> SecureEnvResult<()> {
+        let id = id.into_key_id()?;
+        let scoped_id = self.config.scoped_id(&id)?;
+
+        self.shared
+            .begin(MockOperation::DeleteKeypair, id.as_str())?;
+        let removed = self.shared.lock().keys.remove(&scoped_id);
+
+        let result = removed
+            .ok_or(SecureEnvError::UnableToDeleteKey(format!(
+                "Key reference with id: '{scoped_id}' not found."
+            )))
+            .and_then(|_| {
+                self.config
+                    .remove_metadata(&id)
+                    .map_err(|e| SecureEnvError::UnableToDeleteKey(e.to_string()))
+            });
+
+        self.shared
+            .finish(MockOperation::DeleteKeypair, id.as_str(), result)
+    }
+
+    fn key(
+        &self,
+        id: Option<&KeyId>,
+        scoped_id: Option<String>,
+        signing_key: Arc<SigningKey>,
+    ) -> MockKey {
+        MockKey {
+            id: id.map(KeyId::to_string).unwrap_or_default(),
+            scoped_id,
+            signing_key,
+            metadata: self.config.metadata_handle(id),
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+/// Derive a valid P-256 scalar from the seed. Hashes that are not a valid scalar are so unlikely
+/// that retrying with a counter is enough.
+fn derive_signing_key(seed: u64, scoped_id: Option<&str>, generation: u64) -> SigningKey {
+    (0u8..=u8::MAX)
+        .find_map(|attempt| {
+            let hash = Sha256::new()
+                .chain_update(b"secure-env mock")
+                .chain_update(seed.to_be_bytes())
+                .chain_update(generation.to_be_bytes())
+                .chain_update([attempt])
+                .chain_update(match scoped_id {
+                    Some(scoped_id) => [b"stored:", scoped_id.as_bytes()].concat(),
+                    None => b"ephemeral".to_vec(),
+                })
+                .finalize();
+
+            SigningKey::from_slice(&hash).ok()
+        })
+        .expect("SHA-256 output to be a valid P-256 scalar")
+}
+
+impl KeyBackend for MockBackend {
+    fn config(&self) -> &SecureEnvironmentConfig {
+        &self.config
+    }
+
+    fn create_keypair_with_options(
+        &self,
+        id: &KeyId,
+        options: &KeyGenerationOptions,
+    ) -> SecureEnvResult<BoxedKey> {
+        Ok(Box::new(MockBackend::create_keypair_with_options(
+            self, id, options,
+        )?))
+    }
+
+    fn load_keypair(&self, id: &KeyId) -> SecureEnvResult<BoxedKey> {
+        Ok(Box::new(MockBackend::load_keypair(self, id)?))
+    }
+
+    fn delete_keypair(&self, id: &KeyId) -> SecureEnvResult<()> {
+        MockBackend::delete_keypair(self, id)
+    }
+
+    fn list_keys(&self) -> SecureEnvResult<Vec<KeyId>> {
+        MockBackend::list_keys(self)
+    }
+
+    fn generate_ephemeral_keypair(
+        &self,
+        options: &KeyGenerationOptions,
+    ) -> SecureEnvResult<BoxedKey> {
+        Ok(Box::new(MockBackend::generate_ephemeral_keypair(
+            self, options,
+        )?))
+    }
+
+    fn find_keypair_by_public_key(&self, public_key: &[u8]) -> SecureEnvResult<Option<BoxedKey>> {
+        Ok(MockBackend::find_keypair_by_public_key(self, public_key)?
+            .map(|key| Box::new(key) as BoxedKey))
+    }
+
+    fn find_keypair_by_thumbprint(&self, thumbprint: &str) -> SecureEnvResult<Option<BoxedKey>> {
+        Ok(MockBackend::find_keypair_by_thumbprint(self, thumbprint)?
+            .map(|key| Box::new(key) as BoxedKey))
+    }
+}
+
+impl MockKey {
+    fn with_usage_limits(self, options: &KeyGenerationOptions) -> Self {
+        Self {
+            metadata: self.metadata.with_usage_limits(options),
+            ..self
+        }
+    }
+}
+
+/// Key that is generated by the [`MockBackend`]
+#[derive(Debug, Clone)]
+pub struct MockKey {
+    id: String,
+    /// Id under which the key is stored, `None` for ephemeral keys
+    scoped_id: Option<String>,
+    signing_key: Arc<SigningKey>,
+    metadata: KeyMetadataHandle,
+    shared: Arc<Shared>,
+}
+
+impl Keypair for MockKey {
+    type VerifyingKey = VerifyingKey;
+
+    /// Verifying key of the signing key
+    ///
+    /// Unlike [`KeyOps::get_public_key`], this is not recorded as a call and faults are not
+    /// injected, as it can not fail.
+    fn verifying_key(&self) -> VerifyingKey {
+        *self.signing_key.verifying_key()
+    }
+}
+
+impl KeyOps for MockKey {
+    fn get_public_key(&self) -> SecureEnvResult<Vec<u8>> {
+        self.shared.begin(MockOperation::GetPublicKey, &self.id)?;
+
+        let public_key = self.signing_key.verifying_key().to_encoded_point(true);
+
+        self.shared.finish(
+            MockOperation::GetPublicKey,
+            &self.id,
+            Ok(public_key.as_bytes().to_vec()),
+        )
+    }
+
+    fn sign(&self, msg: &[u8]) -> SecureEnvResult<Vec<u8>> {
+        self.shared.begin(MockOperation::Sign, &self.id)?;
+
+        // Ephemeral keys are never stored, so they can not be deleted
+        let deleted_id = self.scoped_id.as_ref().filter(|scoped_id| {
+            self.shared
+                .lock()
+                .keys
+                .get(*scoped_id)
+                .map_or(true, |stored| !Arc::ptr_eq(stored, &self.signing_key))
+        });
+
+        let result = match deleted_id {
+            None => self.metadata.check_usage().and_then(|_| {
+                let signature: Signature = self
+                    .signing_key
+                    .try_sign(msg)
+                    .map_err(|e| SecureEnvError::UnableToCreateSignature(e.to_string()))?;
+
+                self.metadata.record_usage()?;
+                Ok(signature.to_vec())
+            }),
+            Some(scoped_id) => Err(SecureEnvError::UnableToCreateSignature(format!(
+                "Key with id: '{scoped_id}' has been deleted."
+            ))),
+        };
+
+        self.shared.finish(MockOperation::Sign, &self.id, result)
+    }
+
+    fn metadata(&self) -> SecureEnvResult<Option<KeyMetadata>> {
+        self.metadata.metadata()
+    }
+
+    fn set_metadata(&self, metadata: &KeyMetadata) -> SecureEnvResult<()> {
+        self.metadata.set_metadata(metadata)
+    }
+}
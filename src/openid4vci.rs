@@ -0,0 +1,344 @@
+use crate::{
+    error::{SecureEnvError, SecureEnvResult},
+    jws::{self, UnverifiedJws},
+    metadata::now,
+    Jwk, KeyOps,
+};
+#[cfg(feature = "x509")]
+use base64::{engine::general_purpose::STANDARD, Engine};
+use p256::ecdsa::VerifyingKey;
+#[cfg(feature = "x509")]
+use p256::pkcs8::DecodePublicKey;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+#[cfg(feature = "x509")]
+use x509_cert::der::{Decode, Encode};
+
+/// `typ` header parameter of an OpenID4VCI proof
+const PROOF_TYPE: &str = "openid4vci-proof+jwt";
+
+/// How the key of a proof is referenced in its header
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Openid4vciProofKey {
+    /// The public key is included as a `jwk`
+    #[default]
+    Jwk,
+
+    /// Key id that the issuer can resolve to the public key, e.g. a DID URL
+    Kid(String),
+
+    /// DER encoded certificate chain, leaf certificate first, whose leaf certifies the public key,
+    /// e.g. a certificate that has been issued for the key
+    ///
+    /// The leaf certificate is checked to certify the key when the proof is created. Requires the
+    /// `x509` feature, without it a proof with an `x5c` is rejected.
+    #[cfg(feature = "x509")]
+    X5c(Vec<Vec<u8>>),
+
+    /// The attestation chain of the key, see [`KeyOps::attestation_chain`], as `x5c`
+    ///
+    /// Creating the proof fails when the key has no attestation chain. A verified proof has the
+    /// chain as [`Openid4vciProofKey::X5c`].
+    #[cfg(feature = "x509")]
+    AttestationChain,
+}
+
+/// Claims of an OpenID4VCI proof
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Openid4vciProofClaims {
+    /// Client id of the wallet, omitted for anonymous access
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+
+    /// Identifier of the credential issuer
+    pub aud: String,
+
+    /// Creation time in seconds since the Unix epoch
+    pub iat: u64,
+
+    /// `c_nonce` that was provided by the credential issuer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+}
+
+/// Contents of an OpenID4VCI proof
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Openid4vciProofParams {
+    /// Identifier of the credential issuer
+    pub aud: String,
+
+    /// `c_nonce` that was provided by the credential issuer
+    pub nonce: Option<String>,
+
+    /// Client id of the wallet, omitted for anonymous access
+    pub iss: Option<String>,
+
+    /// How the key is referenced in the header, as a `jwk` by default
+    pub key: Openid4vciProofKey,
+}
+
+/// Expectations of a credential issuer for an OpenID4VCI proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Openid4vciProofValidation {
+    /// Identifier of the credential issuer
+    pub aud: String,
+
+    /// `c_nonce` that the credential issuer provided, which the proof must contain
+    pub nonce: Option<String>,
+
+    /// SEC1 encoded P-256 public key that the proof must be signed with
+    ///
+    /// Required for a proof with a `kid`, which is resolved by the issuer.
+    pub public_key: Option<Vec<u8>>,
+
+    /// Maximum age of the proof in seconds, 300 by default
+    pub max_age: u64,
+
+    /// Number of seconds that `iat` may be in the future to allow for clock skew, 60 by default
+    pub leeway: u64,
+}
+
+impl Default for Openid4vciProofValidation {
+    fn default() -> Self {
+        Self {
+            aud: String::new(),
+            nonce: None,
+            public_key: None,
+            max_age: jws::DEFAULT_MAX_AGE,
+            leeway: jws::DEFAULT_LEEWAY,
+        }
+    }
+}
+
+/// Proof of possession of a key for a credential request of
+/// [OpenID for Verifiable Credential Issuance](https://openid.net/specs/openid-4-verifiable-credential-issuance-1_0.html),
+/// a JWT with the `openid4vci-proof+jwt` type
+///
+/// Proofs are signed with `ES256`. The key is referenced in the header as a `jwk`, a `kid` or, with
+/// the `x509` feature, an `x5c` certificate chain, such as the attestation chain of the key.
+///
+/// # Examples
+///
+/// ```
+/// use secure_env::{
+///     Openid4vciProof, Openid4vciProofParams, Openid4vciProofValidation,
+///     SecureEnvironmentConfig, SoftwareBackend,
+/// };
+///
+/// let backend = SoftwareBackend::new(SecureEnvironmentConfig::default());
+/// let key = backend.create_keypair("my-unique-id").unwrap();
+///
+/// let token = Openid4vciProof::create(
+///     &key,
+///     &Openid4vciProofParams {
+///         aud: "https://issuer.example.com".to_owned(),
+///         nonce: Some("tZignsnFbp".to_owned()),
+///         ..Default::default()
+///     },
+/// )
+/// .unwrap();
+///
+/// let proof = Openid4vciProof::verify(
+///     &token,
+///     &Openid4vciProofValidation {
+///         aud: "https://issuer.example.com".to_owned(),
+///         nonce: Some("tZignsnFbp".to_owned()),
+///         ..Default::default()
+///     },
+/// )
+/// .unwrap();
+/// assert_eq!(proof.claims.nonce.as_deref(), Some("tZignsnFbp"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Openid4vciProof {
+    /// How the key is referenced in the header
+    ///
+    /// The certificate chain of an `x5c` is returned as is, validating it against the trust anchors
+    /// of the issuer is left to the issuer.
+    pub key: Openid4vciProofKey,
+
+    /// SEC1 encoded P-256 public key that the proof is signed with
+    pub public_key: Vec<u8>,
+
+    /// Claims of the proof
+    pub claims: Openid4vciProofClaims,
+}
+
+impl Openid4vciProof {
+    /// Create a proof with `key` and return the compact token
+    ///
+    /// `iat` is set to the current time.
+    pub fn create(key: &impl KeyOps, params: &Openid4vciProofParams) -> SecureEnvResult<String> {
+        let claims = Openid4vciProofClaims {
+            iss: params.iss.clone(),
+            aud: params.aud.clone(),
+            iat: now()?,
+            nonce: params.nonce.clone(),
+        };
+
+        let mut header = Map::new();
+        header.insert("typ".to_owned(), json!(PROOF_TYPE));
+        header.insert("alg".to_owned(), json!(jws::ALGORITHM));
+        match &params.key {
+            Openid4vciProofKey::Jwk => {
+                let jwk = Jwk::from_public_key(&key.get_public_key()?)?;
+                header.insert("jwk".to_owned(), json!(jwk));
+            }
+            Openid4vciProofKey::Kid(kid) => {
+                header.insert("kid".to_owned(), json!(kid));
+            }
+            #[cfg(feature = "x509")]
+            Openid4vciProofKey::X5c(chain) => {
+                header.insert("x5c".to_owned(), x5c(key, chain)?);
+            }
+            #[cfg(feature = "x509")]
+            Openid4vciProofKey::AttestationChain => {
+                let chain = key.attestation_chain()?.ok_or_else(|| {
+                    SecureEnvError::UnableToCreateSignature(
+                        "Key has no attestation chain".to_owned(),
+                    )
+                })?;
+                header.insert("x5c".to_owned(), x5c(key, &chain)?);
+            }
+        }
+
+        jws::sign(key, &header, &claims)
+    }
+
+    /// Verify a proof as a credential issuer
+    ///
+    /// The signature is verified with the key of the header, or with
+    /// [`Openid4vciProofValidation::public_key`] for a proof with a `kid`.
+    pub fn verify(token: &str, validation: &Openid4vciProofValidation) -> SecureEnvResult<Self> {
+        let error = |message: &str| SecureEnvError::InvalidToken(message.to_owned());
+
+        let jws = UnverifiedJws::<Openid4vciProofClaims>::decode(token, PROOF_TYPE)?;
+
+        let references = ["jwk", "kid", "x5c"]
+            .iter()
+            .filter(|name| jws.header.contains_key(**name))
+            .count();
+        if references != 1 {
+            return Err(error(
+                "Proof header must contain exactly one of jwk, kid and x5c",
+            ));
+        }
+
+        let (key, header_public_key) = if jws.header.contains_key("jwk") {
+            (Openid4vciProofKey::Jwk, Some(jws.jwk()?.to_public_key()?))
+        } else if let Some(kid) = jws.header.get("kid") {
+            let kid = kid.as_str().ok_or_else(|| error("kid is not a string"))?;
+            (Openid4vciProofKey::Kid(kid.to_owned()), None)
+        } else {
+            x5c_key(&jws.header)?
+        };
+
+        let public_key = match (header_public_key, &validation.public_key) {
+            (Some(header), Some(expected)) if !same_public_key(&header, expected) => {
+                return Err(error("Proof is signed by another key than expected"));
+            }
+            (Some(header), _) => header,
+            (None, Some(expected)) => expected.clone(),
+            (None, None) => {
+                return Err(error(
+                    "Public key of the kid must be provided to verify the proof",
+                ))
+            }
+        };
+
+        let claims = jws.verify(&public_key)?;
+
+        if claims.aud != validation.aud {
+            return Err(error("Proof is for another credential issuer"));
+        }
+        if validation.nonce.is_some() && claims.nonce != validation.nonce {
+            return Err(error("Proof does not contain the expected nonce"));
+        }
+        jws::check_issued_at(claims.iat, validation.max_age, validation.leeway)?;
+
+        Ok(Self {
+            key,
+            public_key,
+            claims,
+        })
+    }
+}
+
+/// Key of a proof with an `x5c` header parameter, with the public key of the leaf certificate
+#[cfg(feature = "x509")]
+fn x5c_key(header: &Map<String, Value>) -> SecureEnvResult<(Openid4vciProofKey, Option<Vec<u8>>)> {
+    let error = |message: &str| SecureEnvError::InvalidToken(message.to_owned());
+
+    let chain = header
+        .get("x5c")
+        .and_then(Value::as_array)
+        .filter(|chain| !chain.is_empty())
+        .ok_or_else(|| error("x5c is not a non-empty array"))?
+        .iter()
+        .map(|certificate| {
+            certificate
+                .as_str()
+                .and_then(|certificate| STANDARD.decode(certificate).ok())
+                .ok_or_else(|| error("x5c contains an invalid certificate"))
+        })
+        .collect::<SecureEnvResult<Vec<_>>>()?;
+    let public_key = leaf_public_key(&chain[0])?;
+
+    Ok((Openid4vciProofKey::X5c(chain), Some(public_key)))
+}
+
+/// Certificate chains can not be parsed without the `x509` feature
+#[cfg(not(feature = "x509"))]
+fn x5c_key(_header: &Map<String, Value>) -> SecureEnvResult<(Openid4vciProofKey, Option<Vec<u8>>)> {
+    Err(SecureEnvError::InvalidToken(
+        "x5c requires the x509 feature".to_owned(),
+    ))
+}
+
+/// `x5c` header parameter of a DER encoded certificate chain, whose leaf must certify `key`
+#[cfg(feature = "x509")]
+fn x5c(key: &impl KeyOps, chain: &[Vec<u8>]) -> SecureEnvResult<Value> {
+    let leaf = chain.first().ok_or_else(|| {
+        SecureEnvError::UnableToCreateSignature("Certificate chain is empty".to_owned())
+    })?;
+    if leaf_public_key(leaf)? != key.get_public_key()? {
+        return Err(SecureEnvError::UnableToCreateSignature(
+            "Leaf certificate does not certify the key".to_owned(),
+        ));
+    }
+
+    let chain = chain
+        .iter()
+        .map(|certificate| STANDARD.encode(certificate))
+        .collect::<Vec<_>>();
+
+    Ok(json!(chain))
+}
+
+/// Compressed SEC1 encoding of the P-256 public key of a DER encoded certificate
+#[cfg(feature = "x509")]
+fn leaf_public_key(certificate: &[u8]) -> SecureEnvResult<Vec<u8>> {
+    let certificate = x509_cert::Certificate::from_der(certificate)
+        .map_err(|e| SecureEnvError::InvalidCertificate(e.to_string()))?;
+    let public_key = certificate
+        .tbs_certificate
+        .subject_public_key_info
+        .to_der()
+        .map_err(|e| SecureEnvError::InvalidCertificate(e.to_string()))?;
+
+    let verifying_key = VerifyingKey::from_public_key_der(&public_key)
+        .map_err(|e| SecureEnvError::InvalidPublicKey(e.to_string()))?;
+
+    Ok(verifying_key.to_encoded_point(true).as_bytes().to_vec())
+}
+
+/// Whether two SEC1 encoded public keys, compressed or not, are the same key
+fn same_public_key(first: &[u8], second: &[u8]) -> bool {
+    match (
+        VerifyingKey::from_sec1_bytes(first),
+        VerifyingKey::from_sec1_bytes(second),
+    ) {
+        (Ok(first), Ok(second)) => first == second,
+        _ => false,
+    }
+}
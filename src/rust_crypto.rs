@@ -0,0 +1,168 @@
+//! Implementations of the RustCrypto traits for the keys of every backend
+//!
+//! Crates like `x509-cert`, `jsonwebtoken` and `ssh-key` accept any [`Signer`] of P-256
+//! signatures, so the keys of this crate can be used with them directly. The traits are the
+//! versions that are re-exported by `p256`: `signature` 2 and `spki` 0.7.
+
+use crate::{
+    error::{SecureEnvError, SecureEnvResult},
+    BoxedKey, KeyOps,
+};
+use p256::{
+    ecdsa::{
+        signature::{Error, Keypair, Signer, Verifier},
+        Signature, VerifyingKey,
+    },
+    pkcs8::{spki, Document, EncodePublicKey},
+};
+
+/// Verifying key of a SEC1 encoded P-256 public key, as returned by [`KeyOps::get_public_key`]
+pub(crate) fn verifying_key(key: &impl KeyOps) -> Result<VerifyingKey, SecureEnvError> {
+    let public_key = key.get_public_key()?;
+
+    VerifyingKey::from_sec1_bytes(&public_key)
+        .map_err(|e| SecureEnvError::InvalidPublicKey(e.to_string()))
+}
+
+/// Verify the `r || s` encoded `signature`, as returned by [`KeyOps::sign`], of `msg` with a SEC1
+/// encoded P-256 public key
+pub(crate) fn verify_signature(
+    public_key: &[u8],
+    msg: &[u8],
+    signature: &[u8],
+) -> Result<(), SecureEnvError> {
+    let verifying_key = VerifyingKey::from_sec1_bytes(public_key)
+        .map_err(|e| SecureEnvError::InvalidPublicKey(e.to_string()))?;
+    let signature = Signature::from_slice(signature)
+        .map_err(|e| SecureEnvError::InvalidSignature(e.to_string()))?;
+
+    verifying_key
+        .verify(msg, &signature)
+        .map_err(|e| SecureEnvError::InvalidSignature(e.to_string()))
+}
+
+/// Key with its public key, which implements [`Keypair`] as well
+///
+/// The keys of the backends implement [`Keypair`] themselves. [`BoxedKey`] and other
+/// implementations of [`KeyOps`] do not, as [`Keypair::verifying_key`] can not fail while
+/// retrieving the public key can, so this wrapper retrieves the public key once when the key is
+/// wrapped.
+///
+/// # Examples
+///
+/// ```
+/// use p256::ecdsa::{
+///     signature::{Keypair, Signer, Verifier},
+///     Signature,
+/// };
+/// use secure_env::{BoxedKey, RustCryptoKey, SecureEnvironmentConfig, SoftwareBackend};
+///
+/// let backend = SoftwareBackend::new(SecureEnvironmentConfig::default());
+/// let key: BoxedKey = Box::new(backend.create_keypair("my-unique-id").unwrap());
+/// let key = RustCryptoKey::new(key).unwrap();
+///
+/// let signature: Signature = key.sign(b"Hello World!");
+/// assert!(key.verifying_key().verify(b"Hello World!", &signature).is_ok());
+/// ```
+#[derive(Debug, Clone)]
+pub struct RustCryptoKey<K> {
+    key: K,
+    verifying_key: VerifyingKey,
+}
+
+impl<K: KeyOps> RustCryptoKey<K> {
+    /// Wrap `key`, or fail when its public key can not be retrieved
+    pub fn new(key: K) -> SecureEnvResult<Self> {
+        let verifying_key = verifying_key(&key)?;
+
+        Ok(Self { key, verifying_key })
+    }
+
+    /// The wrapped key
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Unwrap the key
+    pub fn into_inner(self) -> K {
+        self.key
+    }
+}
+
+impl<K: KeyOps> Signer<Signature> for RustCryptoKey<K> {
+    /// Sign `msg` with ECDSA P-256 and SHA-256
+    fn try_sign(&self, msg: &[u8]) -> Result<Signature, Error> {
+        let signature = self.key.sign(msg).map_err(Error::from_source)?;
+
+        Signature::from_slice(&signature)
+    }
+}
+
+impl<K> Keypair for RustCryptoKey<K> {
+    type VerifyingKey = VerifyingKey;
+
+    fn verifying_key(&self) -> VerifyingKey {
+        self.verifying_key
+    }
+}
+
+impl<K> EncodePublicKey for RustCryptoKey<K> {
+    /// DER encoded `SubjectPublicKeyInfo` of the public key
+    fn to_public_key_der(&self) -> spki::Result<Document> {
+        self.verifying_key.to_public_key_der()
+    }
+}
+
+/// Implement [`Signer`] via [`KeyOps`] and [`EncodePublicKey`] via [`Keypair`], for every key of a
+/// backend that is listed
+macro_rules! impl_rust_crypto_traits {
+    ($($key:ty),* $(,)?) => {
+        $(
+            impl Signer<Signature> for $key {
+                /// Sign `msg` with ECDSA P-256 and SHA-256
+                fn try_sign(&self, msg: &[u8]) -> Result<Signature, Error> {
+                    let signature = KeyOps::sign(self, msg).map_err(Error::from_source)?;
+
+                    Signature::from_slice(&signature)
+                }
+            }
+
+            impl EncodePublicKey for $key {
+                /// DER encoded `SubjectPublicKeyInfo` of the public key
+                fn to_public_key_der(&self) -> spki::Result<Document> {
+                    Keypair::verifying_key(self).to_public_key_der()
+                }
+            }
+        )*
+    };
+}
+
+impl Signer<Signature> for BoxedKey {
+    /// Sign `msg` with ECDSA P-256 and SHA-256
+    fn try_sign(&self, msg: &[u8]) -> Result<Signature, Error> {
+        let signature = KeyOps::sign(self, msg).map_err(Error::from_source)?;
+
+        Signature::from_slice(&signature)
+    }
+}
+
+impl EncodePublicKey for BoxedKey {
+    /// DER encoded `SubjectPublicKeyInfo` of the public key
+    fn to_public_key_der(&self) -> spki::Result<Document> {
+        verifying_key(self)
+            .map_err(|_| spki::Error::KeyMalformed)?
+            .to_public_key_der()
+    }
+}
+
+impl_rust_crypto_traits!(crate::SoftwareKey);
+
+#[cfg(feature = "mock")]
+impl_rust_crypto_traits!(crate::MockKey);
+
+#[cfg(any(
+    target_os = "android",
+    target_os = "ios",
+    feature = "android_host_testing"
+))]
+impl_rust_crypto_traits!(crate::Key);
@@ -0,0 +1,142 @@
+use crate::{
+    error::{SecureEnvError, SecureEnvResult},
+    jws,
+    metadata::now,
+    Jwk, KeyOps,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+/// `typ` header parameter of a key binding JWT
+const KEY_BINDING_TYPE: &str = "kb+jwt";
+
+/// Claims of a key binding JWT
+/// ([SD-JWT section 4.3](https://datatracker.ietf.org/doc/html/draft-ietf-oauth-selective-disclosure-jwt#section-4.3))
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyBindingClaims {
+    /// Creation time in seconds since the Unix epoch
+    pub iat: u64,
+
+    /// Identifier of the verifier that the presentation is for
+    pub aud: String,
+
+    /// Nonce that was provided by the verifier
+    pub nonce: String,
+
+    /// Base64url encoded hash of the presented SD-JWT and disclosures
+    pub sd_hash: String,
+}
+
+/// Verifier that a key binding JWT is created for
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyBindingParams {
+    /// Identifier of the verifier, e.g. its client id
+    pub aud: String,
+
+    /// Nonce that was provided by the verifier
+    pub nonce: String,
+}
+
+/// Sign a key binding JWT over an SD-JWT `presentation` with `key` and return the presentation
+/// with the key binding JWT appended
+///
+/// `presentation` is the issuer-signed JWT followed by the presented disclosures, each terminated
+/// by `~`. The key binding JWT proves possession of the key in the `cnf` claim of the credential;
+/// when `cnf` contains a `jwk`, it must be the public key of `key`. `iat` is set to the current
+/// time.
+///
+/// # Examples
+///
+/// ```
+/// use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+/// use secure_env::{
+///     append_key_binding_jwt, Jwk, KeyBindingParams, KeyOps, SecureEnvironmentConfig,
+///     SoftwareBackend,
+/// };
+/// use serde_json::json;
+///
+/// let backend = SoftwareBackend::new(SecureEnvironmentConfig::default());
+/// let key = backend.create_keypair("my-unique-id").unwrap();
+///
+/// // Issuer-signed JWT of a credential bound to `key`, followed by the presented disclosures
+/// let jwk = Jwk::from_public_key(&key.get_public_key().unwrap()).unwrap();
+/// let payload = json!({ "_sd_alg": "sha-256", "cnf": { "jwk": jwk } });
+/// let presentation = format!(
+///     "eyJhbGciOiJFUzI1NiIsInR5cCI6ImRjK3NkLWp3dCJ9.{}.c2lnbmF0dXJl~WyJzYWx0IiwibmFtZSIsIkFsaWNlIl0~",
+///     URL_SAFE_NO_PAD.encode(payload.to_string()),
+/// );
+///
+/// let presentation = append_key_binding_jwt(
+///     &key,
+///     &presentation,
+///     &KeyBindingParams {
+///         aud: "https://verifier.example.org".to_owned(),
+///         nonce: "1234567890".to_owned(),
+///     },
+/// )
+/// .unwrap();
+/// assert!(!presentation.ends_with('~'));
+/// ```
+pub fn append_key_binding_jwt(
+    key: &impl KeyOps,
+    presentation: &str,
+    params: &KeyBindingParams,
+) -> SecureEnvResult<String> {
+    let error = |message: &str| SecureEnvError::InvalidToken(message.to_owned());
+
+    if !presentation.ends_with('~') {
+        return Err(error(
+            "Presentation must end with '~' and not contain a key binding JWT",
+        ));
+    }
+
+    let payload = presentation
+        .split('~')
+        .next()
+        .and_then(|issuer_jwt| issuer_jwt.split('.').nth(1))
+        .and_then(|payload| URL_SAFE_NO_PAD.decode(payload).ok())
+        .and_then(|payload| serde_json::from_slice::<Value>(&payload).ok())
+        .ok_or_else(|| error("Presentation does not start with an issuer-signed JWT"))?;
+
+    if let Some(sd_alg) = payload.get("_sd_alg") {
+        if sd_alg != "sha-256" {
+            return Err(error("Only the sha-256 hash algorithm is supported"));
+        }
+    }
+
+    let cnf = payload
+        .get("cnf")
+        .ok_or_else(|| error("Credential is not bound to a key"))?;
+    if let Some(jwk) = cnf.get("jwk") {
+        let jwk: Jwk = serde_json::from_value(jwk.clone()).map_err(|e| error(&e.to_string()))?;
+        if jwk.to_public_key()? != key.get_public_key()? {
+            return Err(SecureEnvError::UnableToCreateSignature(
+                "Credential is bound to another key".to_owned(),
+            ));
+        }
+    }
+
+    let claims = KeyBindingClaims {
+        iat: now()?,
+        aud: params.aud.clone(),
+        nonce: params.nonce.clone(),
+        sd_hash: sd_hash(presentation),
+    };
+    let header = json!({
+        "typ": KEY_BINDING_TYPE,
+        "alg": jws::ALGORITHM,
+    });
+
+    Ok(format!(
+        "{presentation}{}",
+        jws::sign(key, &header, &claims)?
+    ))
+}
+
+/// Value of the `sd_hash` claim, the base64url encoded SHA-256 hash of the presentation without
+/// its key binding JWT
+pub fn sd_hash(presentation: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(presentation.as_bytes()))
+}
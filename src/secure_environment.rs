@@ -1,7 +1,115 @@
-use crate::{error::SecureEnvResult, key::KeyOps};
+use crate::{error::SecureEnvResult, key::KeyOps, IntoKeyId};
+#[cfg(any(
+    target_os = "android",
+    target_os = "ios",
+    feature = "android_host_testing"
+))]
+use crate::{Key, KeyGenerationOptions, KeyId, SecureEnvironmentConfig};
 
 pub trait SecureEnvironmentOps<K: KeyOps> {
-    fn generate_keypair(id: impl Into<String>, backed_by_biometrics: bool) -> SecureEnvResult<K>;
+    fn generate_keypair(id: impl IntoKeyId, backed_by_biometrics: bool) -> SecureEnvResult<K>;
 
-    fn get_keypair_by_id(id: impl Into<String>) -> SecureEnvResult<K>;
+    fn get_keypair_by_id(id: impl IntoKeyId) -> SecureEnvResult<K>;
+}
+
+/// Environment that can be used to create and get keypairs by id
+///
+/// Every instance carries its own [`SecureEnvironmentConfig`], so multiple configurations, e.g.
+/// with different namespaces, can be used side by side. The static [`SecureEnvironmentOps`] API
+/// uses an instance with the default configuration.
+///
+/// # Examples
+///
+/// The examples require the keystore of a device, so they are only compiled, not run, as tests.
+///
+/// ## Generate a keypair
+///
+/// ```no_run
+/// use secure_env::{SecureEnvironment, SecureEnvironmentOps};
+///
+/// let key = SecureEnvironment::generate_keypair("my-unique-id", false).unwrap();
+/// ```
+///
+/// ## Get a keypair from the keychain
+///
+/// ```no_run
+/// use secure_env::{SecureEnvironment, SecureEnvironmentOps};
+///
+/// {
+///     SecureEnvironment::generate_keypair("my-unique-id", false).unwrap();
+/// }
+///
+/// let key = SecureEnvironment::get_keypair_by_id("my-unique-id").unwrap();
+/// ```
+///
+/// ## Use a namespaced instance
+///
+/// ```no_run
+/// use secure_env::{SecureEnvironment, SecureEnvironmentConfig};
+///
+/// let environment = SecureEnvironment::new(SecureEnvironmentConfig {
+///     namespace: Some("my-library".to_owned()),
+///     ..Default::default()
+/// });
+///
+/// environment.create_keypair("my-unique-id").unwrap();
+/// let key = environment.load_keypair("my-unique-id").unwrap();
+/// ```
+#[cfg(any(
+    target_os = "android",
+    target_os = "ios",
+    feature = "android_host_testing"
+))]
+#[derive(Debug, Clone, Default)]
+pub struct SecureEnvironment {
+    pub(crate) config: SecureEnvironmentConfig,
+}
+
+#[cfg(any(
+    target_os = "android",
+    target_os = "ios",
+    feature = "android_host_testing"
+))]
+impl SecureEnvironment {
+    pub fn new(config: SecureEnvironmentConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &SecureEnvironmentConfig {
+        &self.config
+    }
+
+    /// Create and store a keypair with the default options of this environment
+    pub fn create_keypair(&self, id: impl IntoKeyId) -> SecureEnvResult<Key> {
+        self.create_keypair_with_options(id, &self.config.default_options)
+    }
+
+    /// Ids of the keypairs in the namespace of this environment whose metadata has every tag of
+    /// `tags`
+    ///
+    /// Requires a [`crate::MetadataStore`] in the configuration.
+    pub fn find_keys(&self, tags: &[(&str, &str)]) -> SecureEnvResult<Vec<KeyId>> {
+        self.config.find_keys(tags)
+    }
+}
+
+#[cfg(any(
+    target_os = "android",
+    target_os = "ios",
+    feature = "android_host_testing"
+))]
+impl SecureEnvironmentOps<Key> for SecureEnvironment {
+    fn generate_keypair(id: impl IntoKeyId, backed_by_biometrics: bool) -> SecureEnvResult<Key> {
+        SecureEnvironment::default().create_keypair_with_options(
+            id,
+            &KeyGenerationOptions {
+                backed_by_biometrics,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn get_keypair_by_id(id: impl IntoKeyId) -> SecureEnvResult<Key> {
+        SecureEnvironment::default().load_keypair(id)
+    }
 }
@@ -0,0 +1,314 @@
+use crate::{
+    error::{SecureEnvError, SecureEnvResult},
+    jwk_thumbprint, BoxedKey, IntoKeyId, KeyBackend, KeyGenerationOptions, KeyId, KeyMetadata,
+    KeyMetadataHandle, KeyOps, SecureEnvironmentConfig,
+};
+use p256::{
+    ecdsa::{
+        signature::{Keypair, Signer},
+        Signature, SigningKey, VerifyingKey,
+    },
+    elliptic_curve::rand_core::OsRng,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// Backend that generates and keeps its keys in memory
+///
+/// The keys are not protected by any hardware and do not outlive the backend. It can be used on
+/// hosts without a secure element, e.g. for testing, and behaves the same as the hardware backed
+/// [`KeyBackend`]s.
+///
+/// # Examples
+///
+/// ## Generate a keypair and sign a message
+///
+/// ```
+/// use secure_env::{KeyOps, SecureEnvironmentConfig, SoftwareBackend};
+///
+/// let backend = SoftwareBackend::new(SecureEnvironmentConfig::default());
+///
+/// let key = backend.create_keypair("my-unique-id").unwrap();
+/// let signature = key.sign(b"Hello World!").unwrap();
+///
+/// assert_eq!(signature.len(), 64);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SoftwareBackend {
+    config: SecureEnvironmentConfig,
+    keys: Arc<Mutex<SoftwareKeys>>,
+}
+
+/// Keys of a [`SoftwareBackend`], by id and by the JWK thumbprint of their public key
+#[derive(Debug, Default)]
+struct SoftwareKeys {
+    by_id: HashMap<String, SoftwareKey>,
+    by_thumbprint: HashMap<String, String>,
+}
+
+impl SoftwareBackend {
+    pub fn new(config: SecureEnvironmentConfig) -> Self {
+        Self {
+            config,
+            keys: Arc::default(),
+        }
+    }
+
+    /// Create and store a keypair with the default options of this backend
+    pub fn create_keypair(&self, id: impl IntoKeyId) -> SecureEnvResult<SoftwareKey> {
+        self.create_keypair_with_options(id, &self.config.default_options)
+    }
+
+    /// Create and store a keypair in memory
+    pub fn create_keypair_with_options(
+        &self,
+        id: impl IntoKeyId,
+        options: &KeyGenerationOptions,
+    ) -> SecureEnvResult<SoftwareKey> {
+        check_supported_options(options)?;
+
+        let id = id.into_key_id()?;
+        let scoped_id = self.config.scoped_id(&id)?;
+        self.config.check_usage_limits(options, false)?;
+
+        let key = {
+            let mut keys = self.keys.lock().map_err(|_| {
+                SecureEnvError::UnableToGenerateKey(
+                    "Could not acquire lock on key store".to_owned(),
+                )
+            })?;
+
+            if keys.by_id.contains_key(&scoped_id) {
+                return Err(SecureEnvError::UnableToGenerateKey(format!(
+                    "Key with id: '{scoped_id}' already exists."
+                )));
+            }
+
+            let key = SoftwareKey {
+                signing_key: Arc::new(SigningKey::random(&mut OsRng)),
+                metadata: self
+                    .config
+                    .metadata_handle(Some(&id))
+                    .with_usage_limits(options),
+            };
+            keys.by_thumbprint
+                .insert(jwk_thumbprint(&key.get_public_key()?)?, scoped_id.clone());
+            keys.by_id.insert(scoped_id, key.clone());
+
+            key
+        };
+
+        self.config.store_created_metadata(&id, &key, options)?;
+
+        Ok(key)
+    }
+
+    /// Create a keypair that is not stored and can not be loaded by id
+    pub fn generate_ephemeral_keypair(
+        &self,
+        options: &KeyGenerationOptions,
+    ) -> SecureEnvResult<SoftwareKey> {
+        check_supported_options(options)?;
+
+        self.config.check_usage_limits(options, true)?;
+
+        Ok(SoftwareKey {
+            signing_key: Arc::new(SigningKey::random(&mut OsRng)),
+            metadata: self.config.metadata_handle(None).with_usage_limits(options),
+        })
+    }
+
+    /// Get a keypair that has been stored in memory
+    pub fn load_keypair(&self, id: impl IntoKeyId) -> SecureEnvResult<SoftwareKey> {
+        let id = self.config.scoped_id(&id.into_key_id()?)?;
+
+        let keys = self.keys.lock().map_err(|_| {
+            SecureEnvError::UnableToGetKeyPairById("Could not acquire lock on key store".to_owned())
+        })?;
+
+        keys.by_id
+            .get(&id)
+            .cloned()
+            .ok_or(SecureEnvError::UnableToGetKeyPairById(format!(
+                "Key reference with id: '{id}' not found."
+            )))
+    }
+
+    /// Ids of every keypair that is stored in memory
+    pub fn list_keys(&self) -> SecureEnvResult<Vec<KeyId>> {
+        let keys = self.keys.lock().map_err(|_| {
+            SecureEnvError::UnableToListKeys("Could not acquire lock on key store".to_owned())
+        })?;
+
+        let mut ids: Vec<KeyId> = keys
+            .by_id
+            .keys()
+            .filter_map(|scoped_id| self.config.unscoped_id(scoped_id))
+            .collect();
+        ids.sort();
+
+        Ok(ids)
+    }
+
+    /// Get the stored keypair with the SEC1 encoded public key `public_key`
+    pub fn find_keypair_by_public_key(
+        &self,
+        public_key: &[u8],
+    ) -> SecureEnvResult<Option<SoftwareKey>> {
+        self.find_keypair_by_thumbprint(&jwk_thumbprint(public_key)?)
+    }
+
+    /// Get the stored keypair whose public key has the JWK thumbprint `thumbprint`
+    pub fn find_keypair_by_thumbprint(
+        &self,
+        thumbprint: &str,
+    ) -> SecureEnvResult<Option<SoftwareKey>> {
+        let keys = self.keys.lock().map_err(|_| {
+            SecureEnvError::UnableToGetKeyPairById("Could not acquire lock on key store".to_owned())
+        })?;
+
+        Ok(keys
+            .by_thumbprint
+            .get(thumbprint)
+            .and_then(|id| keys.by_id.get(id))
+            .cloned())
+    }
+
+    /// Delete a keypair from memory, together with its metadata
+    pub fn delete_keypair(&self, id: impl IntoKeyId) -> SecureEnvResult<()> {
+        let id = id.into_key_id()?;
+        let scoped_id = self.config.scoped_id(&id)?;
+
+        {
+            let mut keys = self.keys.lock().map_err(|_| {
+                SecureEnvError::UnableToDeleteKey("Could not acquire lock on key store".to_owned())
+            })?;
+
+            keys.by_id
+                .remove(&scoped_id)
+                .ok_or(SecureEnvError::UnableToDeleteKey(format!(
+                    "Key reference with id: '{scoped_id}' not found."
+                )))?;
+            keys.by_thumbprint.retain(|_, id| *id != scoped_id);
+        }
+
+        self.config
+            .remove_metadata(&id)
+            .map_err(|e| SecureEnvError::UnableToDeleteKey(e.to_string()))
+    }
+}
+
+/// Reject the options that require hardware, which a key in memory can not provide
+fn check_supported_options(options: &KeyGenerationOptions) -> SecureEnvResult<()> {
+    if options.backed_by_biometrics {
+        return Err(SecureEnvError::UnableToGenerateKey(
+            "Biometric protection is not supported by the software backend".to_owned(),
+        ));
+    }
+
+    if options.attestation_challenge.is_some() {
+        return Err(SecureEnvError::UnableToGenerateKey(
+            "Key attestation is not supported by the software backend".to_owned(),
+        ));
+    }
+
+    Ok(())
+}
+
+impl KeyBackend for SoftwareBackend {
+    fn config(&self) -> &SecureEnvironmentConfig {
+        &self.config
+    }
+
+    fn create_keypair_with_options(
+        &self,
+        id: &KeyId,
+        options: &KeyGenerationOptions,
+    ) -> SecureEnvResult<BoxedKey> {
+        Ok(Box::new(SoftwareBackend::create_keypair_with_options(
+            self, id, options,
+        )?))
+    }
+
+    fn load_keypair(&self, id: &KeyId) -> SecureEnvResult<BoxedKey> {
+        Ok(Box::new(SoftwareBackend::load_keypair(self, id)?))
+    }
+
+    fn delete_keypair(&self, id: &KeyId) -> SecureEnvResult<()> {
+        SoftwareBackend::delete_keypair(self, id)
+    }
+
+    fn list_keys(&self) -> SecureEnvResult<Vec<KeyId>> {
+        SoftwareBackend::list_keys(self)
+    }
+
+    fn generate_ephemeral_keypair(
+        &self,
+        options: &KeyGenerationOptions,
+    ) -> SecureEnvResult<BoxedKey> {
+        Ok(Box::new(SoftwareBackend::generate_ephemeral_keypair(
+            self, options,
+        )?))
+    }
+
+    fn find_keypair_by_public_key(&self, public_key: &[u8]) -> SecureEnvResult<Option<BoxedKey>> {
+        Ok(
+            SoftwareBackend::find_keypair_by_public_key(self, public_key)?
+                .map(|key| Box::new(key) as BoxedKey),
+        )
+    }
+
+    fn find_keypair_by_thumbprint(&self, thumbprint: &str) -> SecureEnvResult<Option<BoxedKey>> {
+        Ok(
+            SoftwareBackend::find_keypair_by_thumbprint(self, thumbprint)?
+                .map(|key| Box::new(key) as BoxedKey),
+        )
+    }
+}
+
+/// Key that is generated by the [`SoftwareBackend`]
+#[derive(Debug, Clone)]
+pub struct SoftwareKey {
+    signing_key: Arc<SigningKey>,
+    metadata: KeyMetadataHandle,
+}
+
+impl Keypair for SoftwareKey {
+    type VerifyingKey = VerifyingKey;
+
+    /// Verifying key of the signing key
+    fn verifying_key(&self) -> VerifyingKey {
+        *self.signing_key.verifying_key()
+    }
+}
+
+impl KeyOps for SoftwareKey {
+    fn get_public_key(&self) -> SecureEnvResult<Vec<u8>> {
+        let public_key = self.signing_key.verifying_key().to_encoded_point(true);
+
+        Ok(public_key.as_bytes().to_vec())
+    }
+
+    fn sign(&self, msg: &[u8]) -> SecureEnvResult<Vec<u8>> {
+        self.metadata.check_usage()?;
+
+        let signature: Signature = self
+            .signing_key
+            .try_sign(msg)
+            .map_err(|e| SecureEnvError::UnableToCreateSignature(e.to_string()))?;
+
+        self.metadata.record_usage()?;
+
+        Ok(signature.to_vec())
+    }
+
+    fn metadata(&self) -> SecureEnvResult<Option<KeyMetadata>> {
+        self.metadata.metadata()
+    }
+
+    fn set_metadata(&self, metadata: &KeyMetadata) -> SecureEnvResult<()> {
+        self.metadata.set_metadata(metadata)
+    }
+}
@@ -0,0 +1,170 @@
+//! Signing keys for [rustls](https://docs.rs/rustls), so a key of this crate can authenticate a
+//! TLS connection, e.g. as the client certificate of mTLS, without the private key ever being
+//! available to the application
+
+use crate::{
+    error::{SecureEnvError, SecureEnvResult},
+    SecureKey,
+};
+use p256::{ecdsa::Signature, pkcs8::EncodePublicKey};
+use rustls::{
+    client::ResolvesClientCert,
+    pki_types::{CertificateDer, SubjectPublicKeyInfoDer},
+    server::{ClientHello, ResolvesServerCert},
+    sign::{CertifiedKey, Signer, SigningKey},
+    Error, SignatureAlgorithm, SignatureScheme,
+};
+use std::sync::Arc;
+
+/// Only signature scheme that is supported by the keys of this crate
+const SCHEME: SignatureScheme = SignatureScheme::ECDSA_NISTP256_SHA256;
+
+/// [`SigningKey`] of rustls that signs with a key of this crate
+///
+/// Every key is a P-256 key, so only `ecdsa_secp256r1_sha256` is supported. A peer that does not
+/// offer this scheme can not be authenticated with the key.
+///
+/// # Examples
+///
+/// ```
+/// use secure_env::{RustlsSigningKey, SecureEnvironmentConfig, SoftwareBackend};
+/// use rustls::{sign::SigningKey, SignatureScheme};
+///
+/// let backend = SoftwareBackend::new(SecureEnvironmentConfig::default());
+/// let key = RustlsSigningKey::new(backend.create_keypair("my-unique-id").unwrap()).unwrap();
+///
+/// assert!(key
+///     .choose_scheme(&[SignatureScheme::ECDSA_NISTP256_SHA256])
+///     .is_some());
+/// assert!(key.choose_scheme(&[SignatureScheme::ED25519]).is_none());
+/// ```
+#[derive(Debug)]
+pub struct RustlsSigningKey<K> {
+    key: Arc<K>,
+    /// DER encoded subject public key info of the key
+    public_key: Vec<u8>,
+}
+
+impl<K: SecureKey + 'static> RustlsSigningKey<K> {
+    pub fn new(key: K) -> SecureEnvResult<Self> {
+        let public_key = crate::rust_crypto::verifying_key(&key)?
+            .to_public_key_der()
+            .map_err(|e| SecureEnvError::InvalidPublicKey(e.to_string()))?
+            .into_vec();
+
+        Ok(Self {
+            key: Arc::new(key),
+            public_key,
+        })
+    }
+
+    /// Combine the key with the certificate chain that certifies it, leaf certificate first
+    pub fn into_certified_key(
+        self,
+        certificate_chain: Vec<CertificateDer<'static>>,
+    ) -> CertifiedKey {
+        CertifiedKey::new(certificate_chain, Arc::new(self))
+    }
+}
+
+impl<K: SecureKey + 'static> SigningKey for RustlsSigningKey<K> {
+    fn choose_scheme(&self, offered: &[SignatureScheme]) -> Option<Box<dyn Signer>> {
+        if !offered.contains(&SCHEME) {
+            return None;
+        }
+
+        Some(Box::new(RustlsSigner {
+            key: self.key.clone(),
+        }))
+    }
+
+    fn public_key(&self) -> Option<SubjectPublicKeyInfoDer<'_>> {
+        Some(SubjectPublicKeyInfoDer::from(self.public_key.as_slice()))
+    }
+
+    fn algorithm(&self) -> SignatureAlgorithm {
+        SignatureAlgorithm::ECDSA
+    }
+}
+
+/// [`Signer`] of rustls for the `ecdsa_secp256r1_sha256` scheme, which is created by
+/// [`RustlsSigningKey`]
+#[derive(Debug)]
+pub struct RustlsSigner<K> {
+    key: Arc<K>,
+}
+
+impl<K: SecureKey> Signer for RustlsSigner<K> {
+    /// Sign `message` with the key and encode the signature as DER, as TLS requires
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        let signature = self
+            .key
+            .sign(message)
+            .map_err(|e| Error::General(e.to_string()))?;
+        let signature =
+            Signature::from_slice(&signature).map_err(|e| Error::General(e.to_string()))?;
+
+        Ok(signature.to_der().as_bytes().to_vec())
+    }
+
+    fn scheme(&self) -> SignatureScheme {
+        SCHEME
+    }
+}
+
+/// Resolver that always presents the same certified key, as a client certificate with
+/// `ConfigBuilder::with_client_cert_resolver` or as a server certificate with
+/// `ConfigBuilder::with_cert_resolver`
+///
+/// # Examples
+///
+/// ```
+/// use secure_env::{RustlsCertResolver, RustlsSigningKey, SecureEnvironmentConfig, SoftwareBackend};
+///
+/// # fn certificate_chain() -> Vec<Vec<u8>> { vec![] }
+/// let backend = SoftwareBackend::new(SecureEnvironmentConfig::default());
+/// let key = backend.create_keypair("my-unique-id").unwrap();
+///
+/// // DER encoded certificate chain of the key, e.g. issued for a `CertificateRequest` of the key
+/// let chain = certificate_chain();
+///
+/// let resolver = RustlsCertResolver::new(
+///     RustlsSigningKey::new(key)
+///         .unwrap()
+///         .into_certified_key(chain.into_iter().map(Into::into).collect()),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct RustlsCertResolver {
+    certified_key: Arc<CertifiedKey>,
+}
+
+impl RustlsCertResolver {
+    pub fn new(certified_key: CertifiedKey) -> Self {
+        Self {
+            certified_key: Arc::new(certified_key),
+        }
+    }
+}
+
+impl ResolvesClientCert for RustlsCertResolver {
+    fn resolve(
+        &self,
+        _root_hint_subjects: &[&[u8]],
+        sigschemes: &[SignatureScheme],
+    ) -> Option<Arc<CertifiedKey>> {
+        sigschemes
+            .contains(&SCHEME)
+            .then(|| self.certified_key.clone())
+    }
+
+    fn has_certs(&self) -> bool {
+        true
+    }
+}
+
+impl ResolvesServerCert for RustlsCertResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.certified_key.clone())
+    }
+}
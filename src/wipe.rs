@@ -0,0 +1,73 @@
+use crate::{
+    error::{SecureEnvError, SecureEnvResult},
+    KeyBackend, KeyId,
+};
+
+/// Result of wiping every keypair of the namespace of a backend, see [`KeyBackend::wipe`]
+#[derive(Debug, Default)]
+pub struct WipeReport {
+    /// Ids of the keypairs that have been deleted
+    pub deleted: Vec<KeyId>,
+
+    /// Keypairs, or metadata of keypairs that no longer exist, that could not be deleted
+    pub failures: Vec<WipeFailure>,
+
+    /// Ephemeral keypairs that could not be deleted, which only exist in the keystore on Android
+    pub ephemeral_failures: Vec<EphemeralWipeFailure>,
+}
+
+impl WipeReport {
+    /// Whether nothing has been left behind
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty() && self.ephemeral_failures.is_empty()
+    }
+}
+
+/// Keypair that could not be deleted while wiping, with the reason why
+#[derive(Debug)]
+pub struct WipeFailure {
+    pub id: KeyId,
+    pub error: SecureEnvError,
+}
+
+/// Ephemeral keypair that could not be deleted while wiping, with the reason why
+///
+/// Ephemeral keypairs have no id, so they are identified by their alias in the keystore.
+#[derive(Debug)]
+pub struct EphemeralWipeFailure {
+    pub alias: String,
+    pub error: SecureEnvError,
+}
+
+/// Delete every keypair that is listed by the backend, and the metadata of keypairs that no longer
+/// exist
+///
+/// Everything is enumerated before anything is deleted, so a failure to enumerate leaves the
+/// namespace untouched. A failure to delete a single keypair is reported and the others are
+/// deleted anyway.
+pub(crate) fn wipe_keys<B: KeyBackend + ?Sized>(backend: &B) -> SecureEnvResult<WipeReport> {
+    let ids = backend.list_keys()?;
+    let orphaned_metadata: Vec<KeyId> = backend
+        .config()
+        .metadata_ids()?
+        .into_iter()
+        .filter(|id| !ids.contains(id))
+        .collect();
+
+    let mut report = WipeReport::default();
+
+    for id in ids {
+        match backend.delete_keypair(&id) {
+            Ok(()) => report.deleted.push(id),
+            Err(error) => report.failures.push(WipeFailure { id, error }),
+        }
+    }
+
+    for id in orphaned_metadata {
+        if let Err(error) = backend.config().remove_metadata(&id) {
+            report.failures.push(WipeFailure { id, error });
+        }
+    }
+
+    Ok(report)
+}
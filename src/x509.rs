@@ -0,0 +1,166 @@
+//! Building blocks that are shared by certificate signing requests and certificates
+
+use crate::{error::SecureEnvError, rust_crypto::verifying_key, KeyOps};
+use p256::ecdsa::Signature;
+use sha2::{Digest, Sha256};
+use std::{net::IpAddr, str::FromStr, time::Duration};
+use x509_cert::{
+    der::{
+        asn1::{BitString, GeneralizedTime, Ia5String, OctetString, UtcTime},
+        oid::{
+            db::rfc5912::{ECDSA_WITH_SHA_256, ID_CE_SUBJECT_ALT_NAME},
+            ObjectIdentifier,
+        },
+        Encode,
+    },
+    ext::{
+        pkix::{name::GeneralName, SubjectAltName as SubjectAltNameExtension},
+        Extension,
+    },
+    name::Name,
+    spki::{AlgorithmIdentifierOwned, SubjectPublicKeyInfoOwned},
+    time::Time,
+};
+
+/// Name of the subject that is included in the subject alternative name extension
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubjectAltName {
+    /// DNS name, e.g. `device.example.com`
+    Dns(String),
+    /// URI, e.g. `urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6`
+    Uri(String),
+    /// Email address ([RFC 822](https://www.rfc-editor.org/rfc/rfc822) mailbox)
+    Email(String),
+    /// IPv4 or IPv6 address
+    Ip(IpAddr),
+}
+
+/// Extension of a certificate or certificate signing request that is not covered by the options
+/// of this crate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct X509Extension {
+    /// Dotted object identifier of the extension, e.g. `2.5.29.37` for the extended key usage
+    pub oid: String,
+
+    /// Whether a relying party must reject the certificate when it does not recognize the
+    /// extension
+    pub critical: bool,
+
+    /// DER encoded value of the extension, without the wrapping `OCTET STRING`
+    pub value: Vec<u8>,
+}
+
+impl X509Extension {
+    pub(crate) fn to_extension(&self) -> x509_cert::der::Result<Extension> {
+        Ok(Extension {
+            extn_id: ObjectIdentifier::from_str(&self.oid)?,
+            critical: self.critical,
+            extn_value: OctetString::new(self.value.clone())?,
+        })
+    }
+}
+
+/// Parse a distinguished name in the string representation of
+/// [RFC 4514](https://www.rfc-editor.org/rfc/rfc4514), e.g. `CN=Device,O=Animo`
+///
+/// An empty string is the empty distinguished name, which `Name::from_str` rejects.
+pub(crate) fn parse_name(name: &str) -> x509_cert::der::Result<Name> {
+    if name.is_empty() {
+        return Ok(Name::default());
+    }
+
+    Name::from_str(name)
+}
+
+/// Subject alternative name extension, which is critical when the subject is empty
+/// ([RFC 5280 section 4.2.1.6](https://www.rfc-editor.org/rfc/rfc5280#section-4.2.1.6))
+pub(crate) fn subject_alt_name_extension(
+    subject: &Name,
+    names: &[SubjectAltName],
+) -> x509_cert::der::Result<Extension> {
+    let names = names
+        .iter()
+        .map(|name| {
+            Ok(match name {
+                SubjectAltName::Dns(dns) => GeneralName::DnsName(Ia5String::new(dns)?),
+                SubjectAltName::Uri(uri) => {
+                    GeneralName::UniformResourceIdentifier(Ia5String::new(uri)?)
+                }
+                SubjectAltName::Email(email) => GeneralName::Rfc822Name(Ia5String::new(email)?),
+                SubjectAltName::Ip(ip) => GeneralName::from(*ip),
+            })
+        })
+        .collect::<x509_cert::der::Result<Vec<_>>>()?;
+
+    extension(
+        ID_CE_SUBJECT_ALT_NAME,
+        subject.0.is_empty(),
+        &SubjectAltNameExtension(names),
+    )
+}
+
+/// Extension with the DER encoding of `value`
+pub(crate) fn extension(
+    oid: ObjectIdentifier,
+    critical: bool,
+    value: &impl Encode,
+) -> x509_cert::der::Result<Extension> {
+    Ok(Extension {
+        extn_id: oid,
+        critical,
+        extn_value: OctetString::new(value.to_der()?)?,
+    })
+}
+
+/// Subject public key info of the P-256 public key of `key`
+pub(crate) fn subject_public_key_info(
+    key: &impl KeyOps,
+) -> Result<SubjectPublicKeyInfoOwned, SecureEnvError> {
+    SubjectPublicKeyInfoOwned::from_key(verifying_key(key)?)
+        .map_err(|e| SecureEnvError::InvalidPublicKey(e.to_string()))
+}
+
+/// Key identifier of a public key, which is the SHA-256 hash of the `subjectPublicKey` truncated to
+/// 160 bits ([RFC 7093 section 2](https://www.rfc-editor.org/rfc/rfc7093#section-2))
+pub(crate) fn key_identifier(
+    public_key: &SubjectPublicKeyInfoOwned,
+) -> x509_cert::der::Result<OctetString> {
+    let hash = Sha256::digest(public_key.subject_public_key.raw_bytes());
+
+    OctetString::new(&hash[..20])
+}
+
+/// Time in seconds since the Unix epoch, encoded as `UTCTime` until 2049 and as `GeneralizedTime`
+/// after that ([RFC 5280 section 4.1.2.5](https://www.rfc-editor.org/rfc/rfc5280#section-4.1.2.5))
+pub(crate) fn time(seconds: u64) -> x509_cert::der::Result<Time> {
+    let duration = Duration::from_secs(seconds);
+
+    match UtcTime::from_unix_duration(duration) {
+        Ok(time) => Ok(Time::UtcTime(time)),
+        Err(_) => Ok(Time::GeneralTime(GeneralizedTime::from_unix_duration(
+            duration,
+        )?)),
+    }
+}
+
+/// `ecdsa-with-SHA256` algorithm identifier, which has no parameters
+/// ([RFC 5758 section 3.2](https://www.rfc-editor.org/rfc/rfc5758#section-3.2))
+pub(crate) fn signature_algorithm() -> AlgorithmIdentifierOwned {
+    AlgorithmIdentifierOwned {
+        oid: ECDSA_WITH_SHA_256,
+        parameters: None,
+    }
+}
+
+/// Sign the DER encoding of a to be signed structure with `key`
+///
+/// X.509 uses the DER encoding of the ECDSA signature, not the `r || s` encoding that is returned
+/// by [`KeyOps::sign`].
+pub(crate) fn sign(key: &impl KeyOps, tbs: &[u8]) -> Result<BitString, SecureEnvError> {
+    let signature = key.sign(tbs)?;
+    let signature = Signature::from_slice(&signature)
+        .map_err(|e| SecureEnvError::UnableToCreateSignature(e.to_string()))?;
+
+    BitString::from_bytes(signature.to_der().as_bytes())
+        .map_err(|e| SecureEnvError::UnableToCreateSignature(e.to_string()))
+}
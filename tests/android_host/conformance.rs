@@ -0,0 +1,14 @@
+use crate::jvm;
+use secure_env::{PlatformHandles, SecureEnvironment, SecureEnvironmentConfig};
+
+/// Every check has its own namespace, as the `wipe` check deletes every keypair of its namespace
+/// while the other checks run in parallel
+fn environment(check: &str) -> SecureEnvironment {
+    SecureEnvironment::new(SecureEnvironmentConfig {
+        namespace: Some(format!("conformance-{check}")),
+        platform: PlatformHandles::with_java_vm(jvm::java_vm()).unwrap(),
+        ..Default::default()
+    })
+}
+
+secure_env_conformance::conformance_tests!(|check| environment(check));
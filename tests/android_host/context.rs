@@ -0,0 +1,75 @@
+use crate::jvm;
+use secure_env::{
+    error::SecureEnvError, KeyOps, PlatformHandles, SecureEnvironment, SecureEnvironmentConfig,
+};
+
+fn environment(platform: PlatformHandles) -> SecureEnvironment {
+    SecureEnvironment::new(SecureEnvironmentConfig {
+        namespace: Some("context".to_owned()),
+        platform,
+        ..Default::default()
+    })
+}
+
+#[test]
+fn create_keypair_with_explicit_context() {
+    let context = jvm::context(jvm::STRONGBOX_KEYSTORE_VERSION);
+    let application = jvm::current_application();
+    let application_requests = jvm::package_manager_requests(&application);
+
+    let platform = PlatformHandles::with_context(jvm::java_vm(), context.as_obj()).unwrap();
+    let key = environment(platform)
+        .create_keypair("explicit-context")
+        .unwrap();
+
+    assert_eq!(key.get_public_key().unwrap().len(), 33);
+    assert_eq!(jvm::package_manager_requests(&context), 1);
+    assert_eq!(
+        jvm::package_manager_requests(&application),
+        application_requests
+    );
+}
+
+#[test]
+fn create_keypair_uses_capabilities_of_explicit_context() {
+    // The application reports StrongBox support, so this can only fail when the context is used
+    let context = jvm::context(0);
+
+    let platform = PlatformHandles::with_context(jvm::java_vm(), context.as_obj()).unwrap();
+    let result = environment(platform).create_keypair("insufficient-keystore");
+
+    assert!(matches!(
+        result,
+        Err(SecureEnvError::UnableToGenerateKey(_))
+    ));
+    assert_eq!(jvm::package_manager_requests(&context), 1);
+}
+
+#[test]
+fn create_keypair_falls_back_to_activity_thread() {
+    let application = jvm::current_application();
+
+    let platform = PlatformHandles::with_java_vm(jvm::java_vm()).unwrap();
+    let key = environment(platform)
+        .create_keypair("activity-thread")
+        .unwrap();
+
+    assert_eq!(key.sign(b"Hello World!").unwrap().len(), 64);
+    // The capabilities are queried once per process, possibly already by another test, and only
+    // environments without a context use the application
+    assert_eq!(jvm::package_manager_requests(&application), 1);
+}
+
+#[test]
+fn keystore_support_is_queried_once_per_context() {
+    let context = jvm::context(jvm::STRONGBOX_KEYSTORE_VERSION);
+
+    let platform = PlatformHandles::with_context(jvm::java_vm(), context.as_obj()).unwrap();
+    let environment = environment(platform);
+
+    for id in ["cached-support-1", "cached-support-2", "cached-support-3"] {
+        environment.create_keypair(id).unwrap();
+    }
+
+    assert_eq!(jvm::package_manager_requests(&context), 1);
+}
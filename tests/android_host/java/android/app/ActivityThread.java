@@ -0,0 +1,28 @@
+package android.app;
+
+/**
+ * Host stub of the hidden `android.app.ActivityThread`.
+ *
+ * There is no current activity thread until a test installs an application.
+ */
+public final class ActivityThread {
+    private static ActivityThread current;
+
+    private final Application application;
+
+    private ActivityThread(Application application) {
+        this.application = application;
+    }
+
+    public static synchronized ActivityThread currentActivityThread() {
+        return current;
+    }
+
+    public static synchronized void setCurrentApplication(Application application) {
+        current = application == null ? null : new ActivityThread(application);
+    }
+
+    public Application getApplication() {
+        return application;
+    }
+}
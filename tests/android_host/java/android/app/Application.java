@@ -0,0 +1,13 @@
+package android.app;
+
+import android.content.Context;
+import android.content.pm.PackageManager;
+
+/**
+ * Host stub of `android.app.Application`.
+ */
+public class Application extends Context {
+    public Application(PackageManager packageManager) {
+        super(packageManager);
+    }
+}
@@ -0,0 +1,27 @@
+package android.content;
+
+import android.content.pm.PackageManager;
+
+/**
+ * Host stub of `android.content.Context`.
+ *
+ * Records how often the package manager has been requested, so tests can assert which context has
+ * been used by the library.
+ */
+public class Context {
+    private final PackageManager packageManager;
+    private int packageManagerRequests;
+
+    public Context(PackageManager packageManager) {
+        this.packageManager = packageManager;
+    }
+
+    public synchronized PackageManager getPackageManager() {
+        packageManagerRequests++;
+        return packageManager;
+    }
+
+    public synchronized int getPackageManagerRequests() {
+        return packageManagerRequests;
+    }
+}
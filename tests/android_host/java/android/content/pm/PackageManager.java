@@ -0,0 +1,18 @@
+package android.content.pm;
+
+/**
+ * Host stub of `android.content.pm.PackageManager` that reports a configurable keystore version.
+ */
+public class PackageManager {
+    public static final String FEATURE_HARDWARE_KEYSTORE = "android.hardware.hardware_keystore";
+
+    private final int hardwareKeystoreVersion;
+
+    public PackageManager(int hardwareKeystoreVersion) {
+        this.hardwareKeystoreVersion = hardwareKeystoreVersion;
+    }
+
+    public boolean hasSystemFeature(String featureName, int version) {
+        return FEATURE_HARDWARE_KEYSTORE.equals(featureName) && version <= hardwareKeystoreVersion;
+    }
+}
@@ -0,0 +1,43 @@
+package android.security.keystore;
+
+import java.security.PublicKey;
+import java.security.cert.Certificate;
+import java.security.cert.CertificateEncodingException;
+
+/**
+ * Host stand-in for the self-signed certificate that the `AndroidKeyStore` creates for every key
+ * pair. Only the public key is available.
+ */
+public final class AndroidKeyStoreCertificate extends Certificate {
+    private final PublicKey publicKey;
+
+    AndroidKeyStoreCertificate(PublicKey publicKey) {
+        super("X.509");
+        this.publicKey = publicKey;
+    }
+
+    @Override
+    public byte[] getEncoded() throws CertificateEncodingException {
+        throw new CertificateEncodingException("Not supported by the host AndroidKeyStore");
+    }
+
+    @Override
+    public void verify(PublicKey key) {
+        throw new UnsupportedOperationException("Not supported by the host AndroidKeyStore");
+    }
+
+    @Override
+    public void verify(PublicKey key, String sigProvider) {
+        throw new UnsupportedOperationException("Not supported by the host AndroidKeyStore");
+    }
+
+    @Override
+    public String toString() {
+        return "AndroidKeyStoreCertificate[" + publicKey + "]";
+    }
+
+    @Override
+    public PublicKey getPublicKey() {
+        return publicKey;
+    }
+}
@@ -0,0 +1,40 @@
+package android.security.keystore;
+
+import java.security.PrivateKey;
+
+/**
+ * Host stand-in for a private key that never leaves the `AndroidKeyStore`. The key material is
+ * not exposed, so it can only be used via the `AndroidKeyStore` provider.
+ */
+public final class AndroidKeyStoreECPrivateKey implements PrivateKey {
+    private final String alias;
+    private final transient PrivateKey key;
+
+    AndroidKeyStoreECPrivateKey(String alias, PrivateKey key) {
+        this.alias = alias;
+        this.key = key;
+    }
+
+    String getAlias() {
+        return alias;
+    }
+
+    PrivateKey getKey() {
+        return key;
+    }
+
+    @Override
+    public String getAlgorithm() {
+        return KeyProperties.KEY_ALGORITHM_EC;
+    }
+
+    @Override
+    public String getFormat() {
+        return null;
+    }
+
+    @Override
+    public byte[] getEncoded() {
+        return null;
+    }
+}
@@ -0,0 +1,47 @@
+package android.security.keystore;
+
+import java.security.InvalidKeyException;
+import java.security.Key;
+import java.security.KeyFactorySpi;
+import java.security.PrivateKey;
+import java.security.PublicKey;
+import java.security.spec.InvalidKeySpecException;
+import java.security.spec.KeySpec;
+
+/**
+ * Key factory of the host `AndroidKeyStore` provider, which only returns the `KeyInfo` of its
+ * private keys, like on Android.
+ */
+public final class AndroidKeyStoreKeyFactorySpi extends KeyFactorySpi {
+    @Override
+    protected PublicKey engineGeneratePublic(KeySpec keySpec) throws InvalidKeySpecException {
+        throw new InvalidKeySpecException("Keys can not be imported into the host AndroidKeyStore");
+    }
+
+    @Override
+    protected PrivateKey engineGeneratePrivate(KeySpec keySpec) throws InvalidKeySpecException {
+        throw new InvalidKeySpecException("Keys can not be imported into the host AndroidKeyStore");
+    }
+
+    @Override
+    protected <T extends KeySpec> T engineGetKeySpec(Key key, Class<T> keySpec)
+            throws InvalidKeySpecException {
+        if (!(key instanceof AndroidKeyStoreECPrivateKey) || !KeyInfo.class.equals(keySpec)) {
+            throw new InvalidKeySpecException(
+                    "Only the KeyInfo of AndroidKeyStore keys is supported");
+        }
+
+        AndroidKeyStoreECPrivateKey privateKey = (AndroidKeyStoreECPrivateKey) key;
+        HostKeyStorage.Entry entry = HostKeyStorage.get(privateKey.getAlias());
+        if (entry == null) {
+            throw new InvalidKeySpecException("Key has been deleted");
+        }
+
+        return keySpec.cast(new KeyInfo(entry.remainingUsageCount.get()));
+    }
+
+    @Override
+    protected Key engineTranslateKey(Key key) throws InvalidKeyException {
+        throw new InvalidKeyException("Keys can not be imported into the host AndroidKeyStore");
+    }
+}
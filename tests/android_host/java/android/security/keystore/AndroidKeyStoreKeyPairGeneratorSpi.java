@@ -0,0 +1,71 @@
+package android.security.keystore;
+
+import java.security.InvalidAlgorithmParameterException;
+import java.security.KeyPair;
+import java.security.KeyPairGenerator;
+import java.security.KeyPairGeneratorSpi;
+import java.security.NoSuchAlgorithmException;
+import java.security.SecureRandom;
+import java.security.spec.AlgorithmParameterSpec;
+import java.security.spec.ECGenParameterSpec;
+import java.util.Arrays;
+
+/**
+ * EC key pair generator of the host `AndroidKeyStore` provider, backed by the EC implementation of
+ * the JDK.
+ */
+public final class AndroidKeyStoreKeyPairGeneratorSpi extends KeyPairGeneratorSpi {
+    private KeyGenParameterSpec spec;
+
+    @Override
+    public void initialize(int keysize, SecureRandom random) {
+        throw new IllegalArgumentException("A KeyGenParameterSpec is required");
+    }
+
+    @Override
+    public void initialize(AlgorithmParameterSpec params, SecureRandom random)
+            throws InvalidAlgorithmParameterException {
+        if (!(params instanceof KeyGenParameterSpec)) {
+            throw new InvalidAlgorithmParameterException("A KeyGenParameterSpec is required");
+        }
+
+        KeyGenParameterSpec spec = (KeyGenParameterSpec) params;
+
+        if ((spec.getPurposes() & KeyProperties.PURPOSE_SIGN) == 0) {
+            throw new InvalidAlgorithmParameterException("Key must be usable for signing");
+        }
+        if (spec.getKeySize() != 256) {
+            throw new InvalidAlgorithmParameterException("Only 256 bit keys are supported");
+        }
+        if (!Arrays.asList(spec.getDigests()).contains(KeyProperties.DIGEST_SHA256)) {
+            throw new InvalidAlgorithmParameterException("SHA-256 digest is required");
+        }
+
+        this.spec = spec;
+    }
+
+    @Override
+    public KeyPair generateKeyPair() {
+        if (spec == null) {
+            throw new IllegalStateException("Not initialized");
+        }
+        if (spec.isStrongBoxBacked() && !HostKeyStorage.isStrongBoxAvailable()) {
+            throw new StrongBoxUnavailableException("StrongBox is not available on this device");
+        }
+
+        try {
+            KeyPairGenerator generator = KeyPairGenerator.getInstance("EC", "SunEC");
+            generator.initialize(new ECGenParameterSpec("secp256r1"));
+
+            KeyPair keyPair = generator.generateKeyPair();
+            HostKeyStorage.put(spec.getKeystoreAlias(), keyPair, spec);
+
+            return new KeyPair(
+                    keyPair.getPublic(),
+                    new AndroidKeyStoreECPrivateKey(
+                            spec.getKeystoreAlias(), keyPair.getPrivate()));
+        } catch (Exception e) {
+            throw new IllegalStateException("Unable to generate key pair", e);
+        }
+    }
+}
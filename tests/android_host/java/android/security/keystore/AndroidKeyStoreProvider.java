@@ -0,0 +1,32 @@
+package android.security.keystore;
+
+import java.security.Provider;
+import java.security.Security;
+
+/**
+ * Host stand-in for the `AndroidKeyStore` security provider.
+ */
+public final class AndroidKeyStoreProvider extends Provider {
+    public static final String PROVIDER_NAME = "AndroidKeyStore";
+
+    public AndroidKeyStoreProvider() {
+        super(PROVIDER_NAME, "1.0", "Host stand-in for the Android KeyStore");
+
+        put("KeyPairGenerator.EC", AndroidKeyStoreKeyPairGeneratorSpi.class.getName());
+        put("KeyFactory.EC", AndroidKeyStoreKeyFactorySpi.class.getName());
+        put("KeyStore.AndroidKeyStore", AndroidKeyStoreSpi.class.getName());
+
+        // Only keys of this provider are supported, so `Signature.getInstance` without a provider
+        // selects this implementation for them and the JDK for any other key
+        put("Signature.SHA256withECDSA", AndroidKeyStoreSignatureSpi.class.getName());
+        put(
+                "Signature.SHA256withECDSA SupportedKeyClasses",
+                AndroidKeyStoreECPrivateKey.class.getName());
+    }
+
+    public static synchronized void install() {
+        if (Security.getProvider(PROVIDER_NAME) == null) {
+            Security.addProvider(new AndroidKeyStoreProvider());
+        }
+    }
+}
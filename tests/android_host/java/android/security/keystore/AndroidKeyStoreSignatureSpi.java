@@ -0,0 +1,94 @@
+package android.security.keystore;
+
+import java.security.GeneralSecurityException;
+import java.security.InvalidKeyException;
+import java.security.PrivateKey;
+import java.security.PublicKey;
+import java.security.Signature;
+import java.security.SignatureException;
+import java.security.SignatureSpi;
+import java.util.Date;
+
+/**
+ * SHA256withECDSA signatures with keys of the host `AndroidKeyStore` provider. The restrictions
+ * of the key are enforced when the signature is initialized, like on Android.
+ */
+public final class AndroidKeyStoreSignatureSpi extends SignatureSpi {
+    private Signature delegate;
+    private String alias;
+
+    @Override
+    protected void engineInitVerify(PublicKey publicKey) throws InvalidKeyException {
+        throw new InvalidKeyException("Verification is not supported by the host AndroidKeyStore");
+    }
+
+    @Override
+    protected void engineInitSign(PrivateKey privateKey) throws InvalidKeyException {
+        if (!(privateKey instanceof AndroidKeyStoreECPrivateKey)) {
+            throw new InvalidKeyException("Only AndroidKeyStore keys are supported");
+        }
+
+        AndroidKeyStoreECPrivateKey key = (AndroidKeyStoreECPrivateKey) privateKey;
+        HostKeyStorage.Entry entry = HostKeyStorage.get(key.getAlias());
+
+        if (entry == null) {
+            throw new KeyPermanentlyInvalidatedException("Key has been deleted");
+        }
+        if (entry.spec.isUserAuthenticationRequired() && !HostKeyStorage.isUserAuthenticated()) {
+            throw new UserNotAuthenticatedException("User not authenticated");
+        }
+
+        Date now = new Date();
+        Date start = entry.spec.getKeyValidityStart();
+        Date end = entry.spec.getKeyValidityForOriginationEnd();
+        if (start != null && now.before(start)) {
+            throw new KeyNotYetValidException("Key not yet valid");
+        }
+        if (end != null && now.after(end)) {
+            throw new KeyExpiredException("Key expired");
+        }
+
+        alias = key.getAlias();
+
+        try {
+            delegate = Signature.getInstance("SHA256withECDSA", "SunEC");
+        } catch (GeneralSecurityException e) {
+            throw new InvalidKeyException("Unable to create signature", e);
+        }
+        delegate.initSign(key.getKey());
+    }
+
+    @Override
+    protected void engineUpdate(byte b) throws SignatureException {
+        delegate.update(b);
+    }
+
+    @Override
+    protected void engineUpdate(byte[] b, int off, int len) throws SignatureException {
+        delegate.update(b, off, len);
+    }
+
+    @Override
+    protected byte[] engineSign() throws SignatureException {
+        byte[] signature = delegate.sign();
+        HostKeyStorage.recordUsage(alias);
+        return signature;
+    }
+
+    @Override
+    protected boolean engineVerify(byte[] sigBytes) throws SignatureException {
+        throw new SignatureException("Verification is not supported by the host AndroidKeyStore");
+    }
+
+    @Override
+    @Deprecated
+    protected void engineSetParameter(String param, Object value) {
+        throw new UnsupportedOperationException();
+    }
+
+    @Override
+    @Deprecated
+    protected Object engineGetParameter(String param) {
+        throw new UnsupportedOperationException();
+    }
+}
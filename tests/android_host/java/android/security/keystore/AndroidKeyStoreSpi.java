@@ -0,0 +1,162 @@
+package android.security.keystore;
+
+import java.io.ByteArrayInputStream;
+import java.io.InputStream;
+import java.io.OutputStream;
+import java.security.Key;
+import java.security.KeyStore;
+import java.security.KeyStoreException;
+import java.security.KeyStoreSpi;
+import java.security.cert.Certificate;
+import java.security.cert.CertificateException;
+import java.security.cert.CertificateFactory;
+import java.util.Collections;
+import java.util.Date;
+import java.util.Enumeration;
+
+/**
+ * Key store of the host `AndroidKeyStore` provider. Like on Android, it can not be loaded from or
+ * stored to a stream and entries can only be created via the key pair generator.
+ */
+public final class AndroidKeyStoreSpi extends KeyStoreSpi {
+    @Override
+    public Key engineGetKey(String alias, char[] password) {
+        HostKeyStorage.Entry entry = HostKeyStorage.get(alias);
+        if (entry == null) {
+            return null;
+        }
+
+        return new AndroidKeyStoreECPrivateKey(alias, entry.keyPair.getPrivate());
+    }
+
+    @Override
+    public Certificate[] engineGetCertificateChain(String alias) {
+        HostKeyStorage.Entry entry = HostKeyStorage.get(alias);
+        if (entry == null) {
+            return null;
+        }
+
+        // Without an attestation, only the self-signed certificate of the key is available
+        byte[][] attestationChain = entry.attestationChain;
+        if (attestationChain == null) {
+            return new Certificate[] {engineGetCertificate(alias)};
+        }
+
+        try {
+            CertificateFactory factory = CertificateFactory.getInstance("X.509");
+            Certificate[] chain = new Certificate[attestationChain.length];
+            for (int i = 0; i < attestationChain.length; i++) {
+                chain[i] =
+                        factory.generateCertificate(new ByteArrayInputStream(attestationChain[i]));
+            }
+
+            return chain;
+        } catch (CertificateException e) {
+            throw new IllegalStateException("Invalid attestation chain", e);
+        }
+    }
+
+    @Override
+    public Certificate engineGetCertificate(String alias) {
+        HostKeyStorage.Entry entry = HostKeyStorage.get(alias);
+        if (entry == null) {
+            return null;
+        }
+
+        return new AndroidKeyStoreCertificate(entry.keyPair.getPublic());
+    }
+
+    @Override
+    public Date engineGetCreationDate(String alias) {
+        HostKeyStorage.Entry entry = HostKeyStorage.get(alias);
+        if (entry == null) {
+            return null;
+        }
+
+        return new Date(entry.creationDate.getTime());
+    }
+
+    @Override
+    public KeyStore.Entry engineGetEntry(String alias, KeyStore.ProtectionParameter protParam) {
+        HostKeyStorage.Entry entry = HostKeyStorage.get(alias);
+        if (entry == null) {
+            return null;
+        }
+
+        return new KeyStore.PrivateKeyEntry(
+                new AndroidKeyStoreECPrivateKey(alias, entry.keyPair.getPrivate()),
+                new Certificate[] {new AndroidKeyStoreCertificate(entry.keyPair.getPublic())});
+    }
+
+    @Override
+    public void engineSetKeyEntry(String alias, Key key, char[] password, Certificate[] chain)
+            throws KeyStoreException {
+        throw new KeyStoreException("Entries can only be created via the KeyPairGenerator");
+    }
+
+    @Override
+    public void engineSetKeyEntry(String alias, byte[] key, Certificate[] chain)
+            throws KeyStoreException {
+        throw new KeyStoreException("Entries can only be created via the KeyPairGenerator");
+    }
+
+    @Override
+    public void engineSetCertificateEntry(String alias, Certificate cert)
+            throws KeyStoreException {
+        throw new KeyStoreException("Entries can only be created via the KeyPairGenerator");
+    }
+
+    @Override
+    public void engineDeleteEntry(String alias) {
+        HostKeyStorage.remove(alias);
+    }
+
+    @Override
+    public Enumeration<String> engineAliases() {
+        return Collections.enumeration(HostKeyStorage.aliases());
+    }
+
+    @Override
+    public boolean engineContainsAlias(String alias) {
+        return HostKeyStorage.get(alias) != null;
+    }
+
+    @Override
+    public int engineSize() {
+        return HostKeyStorage.aliases().size();
+    }
+
+    @Override
+    public boolean engineIsKeyEntry(String alias) {
+        return engineContainsAlias(alias);
+    }
+
+    @Override
+    public boolean engineIsCertificateEntry(String alias) {
+        return false;
+    }
+
+    @Override
+    public String engineGetCertificateAlias(Certificate cert) {
+        return null;
+    }
+
+    @Override
+    public void engineStore(OutputStream stream, char[] password) {
+        throw new UnsupportedOperationException("The AndroidKeyStore can not be stored");
+    }
+
+    @Override
+    public void engineLoad(InputStream stream, char[] password) {
+        if (stream != null) {
+            throw new IllegalArgumentException("The AndroidKeyStore can not be loaded from a stream");
+        }
+    }
+
+    @Override
+    public void engineLoad(KeyStore.LoadStoreParameter param) {
+        if (param != null) {
+            throw new IllegalArgumentException("LoadStoreParameter is not supported");
+        }
+    }
+}
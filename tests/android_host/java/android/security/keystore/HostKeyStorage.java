@@ -0,0 +1,93 @@
+package android.security.keystore;
+
+import java.security.KeyPair;
+import java.util.ArrayList;
+import java.util.Date;
+import java.util.List;
+import java.util.concurrent.ConcurrentHashMap;
+import java.util.concurrent.atomic.AtomicInteger;
+
+/**
+ * In-memory storage of the host `AndroidKeyStore` provider. Only exists on the host.
+ */
+public final class HostKeyStorage {
+    public static final class Entry {
+        public final KeyPair keyPair;
+        public final KeyGenParameterSpec spec;
+        public final Date creationDate;
+        final AtomicInteger remainingUsageCount;
+        volatile byte[][] attestationChain;
+
+        Entry(KeyPair keyPair, KeyGenParameterSpec spec) {
+            this.keyPair = keyPair;
+            this.spec = spec;
+            this.creationDate = new Date();
+            this.remainingUsageCount = new AtomicInteger(spec.getMaxUsageCount());
+        }
+    }
+
+    private static final ConcurrentHashMap<String, Entry> ENTRIES = new ConcurrentHashMap<>();
+
+    private static volatile boolean strongBoxAvailable = true;
+
+    private static volatile boolean userAuthenticated;
+
+    private HostKeyStorage() {}
+
+    public static void setStrongBoxAvailable(boolean available) {
+        strongBoxAvailable = available;
+    }
+
+    public static boolean isStrongBoxAvailable() {
+        return strongBoxAvailable;
+    }
+
+    /** Simulates whether the user has recently authenticated, e.g. via a biometric prompt. */
+    public static void setUserAuthenticated(boolean authenticated) {
+        userAuthenticated = authenticated;
+    }
+
+    public static boolean isUserAuthenticated() {
+        return userAuthenticated;
+    }
+
+    static void put(String alias, KeyPair keyPair, KeyGenParameterSpec spec) {
+        ENTRIES.put(alias, new Entry(keyPair, spec));
+    }
+
+    public static Entry get(String alias) {
+        return ENTRIES.get(alias);
+    }
+
+    /**
+     * Sets the attestation chain of a key that has been generated with an attestation challenge.
+     * The host can not attest keys, so the chain is provided by the test.
+     */
+    public static void setAttestationChain(String alias, byte[][] chain) {
+        Entry entry = ENTRIES.get(alias);
+        if (entry == null || entry.spec.getAttestationChallenge() == null) {
+            throw new IllegalStateException("Key has not been generated with a challenge");
+        }
+
+        entry.attestationChain = chain.clone();
+    }
+
+    static void remove(String alias) {
+        ENTRIES.remove(alias);
+    }
+
+    /** Counts a use of a key with a usage limit, and deletes the key after its last use. */
+    static void recordUsage(String alias) {
+        Entry entry = ENTRIES.get(alias);
+
+        if (entry != null
+                && entry.spec.getMaxUsageCount() != KeyProperties.UNRESTRICTED_USAGE_COUNT
+                && entry.remainingUsageCount.decrementAndGet() <= 0) {
+            ENTRIES.remove(alias, entry);
+        }
+    }
+
+    static List<String> aliases() {
+        return new ArrayList<>(ENTRIES.keySet());
+    }
+}
@@ -0,0 +1,12 @@
+package android.security.keystore;
+
+import java.security.InvalidKeyException;
+
+/**
+ * Host stub of `android.security.keystore.KeyExpiredException`.
+ */
+public class KeyExpiredException extends InvalidKeyException {
+    public KeyExpiredException(String message) {
+        super(message);
+    }
+}
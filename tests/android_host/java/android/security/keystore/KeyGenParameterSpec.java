@@ -0,0 +1,175 @@
+package android.security.keystore;
+
+import java.security.spec.AlgorithmParameterSpec;
+import java.util.Date;
+
+/**
+ * Host stub of `android.security.keystore.KeyGenParameterSpec`.
+ */
+public final class KeyGenParameterSpec implements AlgorithmParameterSpec {
+    private final String keystoreAlias;
+    private final int purposes;
+    private final String[] digests;
+    private final int keySize;
+    private final boolean userAuthenticationRequired;
+    private final boolean invalidatedByBiometricEnrollment;
+    private final int userAuthenticationValidityDurationSeconds;
+    private final int userAuthenticationType;
+    private final boolean strongBoxBacked;
+    private final Date keyValidityStart;
+    private final Date keyValidityEnd;
+    private final int maxUsageCount;
+    private final byte[] attestationChallenge;
+
+    private KeyGenParameterSpec(Builder builder) {
+        this.keystoreAlias = builder.keystoreAlias;
+        this.purposes = builder.purposes;
+        this.digests = builder.digests.clone();
+        this.keySize = builder.keySize;
+        this.userAuthenticationRequired = builder.userAuthenticationRequired;
+        this.invalidatedByBiometricEnrollment = builder.invalidatedByBiometricEnrollment;
+        this.userAuthenticationValidityDurationSeconds =
+                builder.userAuthenticationValidityDurationSeconds;
+        this.userAuthenticationType = builder.userAuthenticationType;
+        this.strongBoxBacked = builder.strongBoxBacked;
+        this.keyValidityStart = builder.keyValidityStart;
+        this.keyValidityEnd = builder.keyValidityEnd;
+        this.maxUsageCount = builder.maxUsageCount;
+        this.attestationChallenge = builder.attestationChallenge;
+    }
+
+    public String getKeystoreAlias() {
+        return keystoreAlias;
+    }
+
+    public int getPurposes() {
+        return purposes;
+    }
+
+    public String[] getDigests() {
+        return digests.clone();
+    }
+
+    public int getKeySize() {
+        return keySize;
+    }
+
+    public boolean isUserAuthenticationRequired() {
+        return userAuthenticationRequired;
+    }
+
+    public boolean isInvalidatedByBiometricEnrollment() {
+        return invalidatedByBiometricEnrollment;
+    }
+
+    public int getUserAuthenticationValidityDurationSeconds() {
+        return userAuthenticationValidityDurationSeconds;
+    }
+
+    public int getUserAuthenticationType() {
+        return userAuthenticationType;
+    }
+
+    public boolean isStrongBoxBacked() {
+        return strongBoxBacked;
+    }
+
+    public Date getKeyValidityStart() {
+        return keyValidityStart;
+    }
+
+    public Date getKeyValidityForOriginationEnd() {
+        return keyValidityEnd;
+    }
+
+    public int getMaxUsageCount() {
+        return maxUsageCount;
+    }
+
+    public byte[] getAttestationChallenge() {
+        return attestationChallenge == null ? null : attestationChallenge.clone();
+    }
+
+    public static final class Builder {
+        private final String keystoreAlias;
+        private final int purposes;
+        private String[] digests = new String[0];
+        private int keySize = -1;
+        private boolean userAuthenticationRequired;
+        private boolean invalidatedByBiometricEnrollment = true;
+        private int userAuthenticationValidityDurationSeconds;
+        private int userAuthenticationType = KeyProperties.AUTH_BIOMETRIC_STRONG;
+        private boolean strongBoxBacked;
+        private Date keyValidityStart;
+        private Date keyValidityEnd;
+        private int maxUsageCount = KeyProperties.UNRESTRICTED_USAGE_COUNT;
+        private byte[] attestationChallenge;
+
+        public Builder(String keystoreAlias, int purposes) {
+            if (keystoreAlias == null || keystoreAlias.isEmpty()) {
+                throw new IllegalArgumentException("keystoreAlias must not be empty");
+            }
+            this.keystoreAlias = keystoreAlias;
+            this.purposes = purposes;
+        }
+
+        public Builder setDigests(String... digests) {
+            this.digests = digests.clone();
+            return this;
+        }
+
+        public Builder setKeySize(int keySize) {
+            this.keySize = keySize;
+            return this;
+        }
+
+        public Builder setUserAuthenticationRequired(boolean required) {
+            this.userAuthenticationRequired = required;
+            return this;
+        }
+
+        public Builder setInvalidatedByBiometricEnrollment(boolean invalidate) {
+            this.invalidatedByBiometricEnrollment = invalidate;
+            return this;
+        }
+
+        public Builder setUserAuthenticationParameters(int timeout, int type) {
+            this.userAuthenticationValidityDurationSeconds = timeout;
+            this.userAuthenticationType = type;
+            return this;
+        }
+
+        public Builder setIsStrongBoxBacked(boolean isStrongBoxBacked) {
+            this.strongBoxBacked = isStrongBoxBacked;
+            return this;
+        }
+
+        public Builder setKeyValidityStart(Date startDate) {
+            this.keyValidityStart = startDate;
+            return this;
+        }
+
+        public Builder setKeyValidityEnd(Date endDate) {
+            this.keyValidityEnd = endDate;
+            return this;
+        }
+
+        public Builder setMaxUsageCount(int maxUsageCount) {
+            if (maxUsageCount == 0 || maxUsageCount < KeyProperties.UNRESTRICTED_USAGE_COUNT) {
+                throw new IllegalArgumentException("maxUsageCount is not valid");
+            }
+            this.maxUsageCount = maxUsageCount;
+            return this;
+        }
+
+        public Builder setAttestationChallenge(byte[] attestationChallenge) {
+            this.attestationChallenge =
+                    attestationChallenge == null ? null : attestationChallenge.clone();
+            return this;
+        }
+
+        public KeyGenParameterSpec build() {
+            return new KeyGenParameterSpec(this);
+        }
+    }
+}
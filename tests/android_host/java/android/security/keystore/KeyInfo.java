@@ -0,0 +1,19 @@
+package android.security.keystore;
+
+import java.security.spec.KeySpec;
+
+/**
+ * Information about a key of the host `AndroidKeyStore` provider. Only the parts that are used
+ * by the library are implemented.
+ */
+public final class KeyInfo implements KeySpec {
+    private final int remainingUsageCount;
+
+    KeyInfo(int remainingUsageCount) {
+        this.remainingUsageCount = remainingUsageCount;
+    }
+
+    public int getRemainingUsageCount() {
+        return remainingUsageCount;
+    }
+}
@@ -0,0 +1,12 @@
+package android.security.keystore;
+
+import java.security.InvalidKeyException;
+
+/**
+ * Host stub of `android.security.keystore.KeyNotYetValidException`.
+ */
+public class KeyNotYetValidException extends InvalidKeyException {
+    public KeyNotYetValidException(String message) {
+        super(message);
+    }
+}
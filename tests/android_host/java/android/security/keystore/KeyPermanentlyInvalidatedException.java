@@ -0,0 +1,12 @@
+package android.security.keystore;
+
+import java.security.InvalidKeyException;
+
+/**
+ * Host stub of `android.security.keystore.KeyPermanentlyInvalidatedException`.
+ */
+public class KeyPermanentlyInvalidatedException extends InvalidKeyException {
+    public KeyPermanentlyInvalidatedException(String message) {
+        super(message);
+    }
+}
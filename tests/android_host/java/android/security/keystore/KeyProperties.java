@@ -0,0 +1,20 @@
+package android.security.keystore;
+
+/**
+ * Host stub of `android.security.keystore.KeyProperties`, with the values of the Android SDK.
+ */
+public final class KeyProperties {
+    public static final int PURPOSE_SIGN = 4;
+    public static final int PURPOSE_VERIFY = 8;
+
+    public static final String DIGEST_SHA256 = "SHA-256";
+
+    public static final int AUTH_DEVICE_CREDENTIAL = 1;
+    public static final int AUTH_BIOMETRIC_STRONG = 2;
+
+    public static final String KEY_ALGORITHM_EC = "EC";
+
+    public static final int UNRESTRICTED_USAGE_COUNT = -1;
+
+    private KeyProperties() {}
+}
@@ -0,0 +1,12 @@
+package android.security.keystore;
+
+import java.security.ProviderException;
+
+/**
+ * Host stub of `android.security.keystore.StrongBoxUnavailableException`.
+ */
+public class StrongBoxUnavailableException extends ProviderException {
+    public StrongBoxUnavailableException(String message) {
+        super(message);
+    }
+}
@@ -0,0 +1,12 @@
+package android.security.keystore;
+
+import java.security.InvalidKeyException;
+
+/**
+ * Host stub of `android.security.keystore.UserNotAuthenticatedException`.
+ */
+public class UserNotAuthenticatedException extends InvalidKeyException {
+    public UserNotAuthenticatedException(String message) {
+        super(message);
+    }
+}
@@ -0,0 +1,390 @@
+use jni::{
+    objects::{GlobalRef, JByteArray, JObject, JString, JValue},
+    InitArgsBuilder, JNIEnv, JNIVersion, JavaVM,
+};
+use once_cell::sync::Lazy;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Keystore version of a device with StrongBox support
+pub const STRONGBOX_KEYSTORE_VERSION: i32 = 300;
+
+static JVM: Lazy<JavaVM> = Lazy::new(start_jvm);
+
+fn java_sources(dir: &Path, sources: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+
+        if path.is_dir() {
+            java_sources(&path, sources);
+        } else if path
+            .extension()
+            .map_or(false, |extension| extension == "java")
+        {
+            sources.push(path);
+        }
+    }
+}
+
+fn compile_stubs() -> PathBuf {
+    let sources_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/android_host/java");
+    let classes_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("android_host_classes");
+
+    let mut sources = vec![];
+    java_sources(&sources_dir, &mut sources);
+
+    let status = Command::new("javac")
+        .arg("-d")
+        .arg(&classes_dir)
+        .args(&sources)
+        .status()
+        .expect("`javac` is required to compile the Android stubs");
+    assert!(status.success(), "Unable to compile the Android stubs");
+
+    classes_dir
+}
+
+fn start_jvm() -> JavaVM {
+    let classes_dir = compile_stubs();
+
+    let args = InitArgsBuilder::new()
+        .version(JNIVersion::V8)
+        .option(format!("-Djava.class.path={}", classes_dir.display()))
+        .build()
+        .unwrap();
+    let jvm = JavaVM::new(args).unwrap();
+
+    {
+        let mut env = jvm.attach_current_thread_as_daemon().unwrap();
+
+        env.call_static_method(
+            "android/security/keystore/AndroidKeyStoreProvider",
+            "install",
+            "()V",
+            &[],
+        )
+        .unwrap();
+
+        // Application that is found via `ActivityThread` when no context has been provided
+        let application = new_context(
+            &mut env,
+            "android/app/Application",
+            STRONGBOX_KEYSTORE_VERSION,
+        );
+        env.call_static_method(
+            "android/app/ActivityThread",
+            "setCurrentApplication",
+            "(Landroid/app/Application;)V",
+            &[(&application).into()],
+        )
+        .unwrap();
+    }
+
+    secure_env::init_with_java_vm(unsafe { JavaVM::from_raw(jvm.get_java_vm_pointer()) }.unwrap())
+        .unwrap();
+
+    jvm
+}
+
+fn new_context<'local>(
+    env: &mut JNIEnv<'local>,
+    class: &str,
+    hardware_keystore_version: i32,
+) -> JObject<'local> {
+    let package_manager = env
+        .new_object(
+            "android/content/pm/PackageManager",
+            "(I)V",
+            &[JValue::Int(hardware_keystore_version)],
+        )
+        .unwrap();
+
+    env.new_object(
+        class,
+        "(Landroid/content/pm/PackageManager;)V",
+        &[(&package_manager).into()],
+    )
+    .unwrap()
+}
+
+/// Handle to the JVM. The JVM is started on first use.
+pub fn java_vm() -> JavaVM {
+    unsafe { JavaVM::from_raw(JVM.get_java_vm_pointer()) }.unwrap()
+}
+
+/// Run `f` with the JNI environment of the current thread
+pub fn with_env<T>(f: impl FnOnce(&mut JNIEnv) -> T) -> T {
+    let mut env = JVM.attach_current_thread_as_daemon().unwrap();
+
+    f(&mut env)
+}
+
+/// New `android.content.Context` of a device with the provided keystore version
+pub fn context(hardware_keystore_version: i32) -> GlobalRef {
+    with_env(|env| {
+        let context = new_context(env, "android/content/Context", hardware_keystore_version);
+
+        env.new_global_ref(context).unwrap()
+    })
+}
+
+/// Application that is returned by `ActivityThread.currentActivityThread().getApplication()`
+pub fn current_application() -> GlobalRef {
+    with_env(|env| {
+        let activity_thread = env
+            .call_static_method(
+                "android/app/ActivityThread",
+                "currentActivityThread",
+                "()Landroid/app/ActivityThread;",
+                &[],
+            )
+            .and_then(|v| v.l())
+            .unwrap();
+        let application = env
+            .call_method(
+                activity_thread,
+                "getApplication",
+                "()Landroid/app/Application;",
+                &[],
+            )
+            .and_then(|v| v.l())
+            .unwrap();
+
+        env.new_global_ref(application).unwrap()
+    })
+}
+
+/// How often `getPackageManager` has been called on the context
+pub fn package_manager_requests(context: &GlobalRef) -> i32 {
+    with_env(|env| {
+        env.call_method(context, "getPackageManagerRequests", "()I", &[])
+            .and_then(|v| v.i())
+            .unwrap()
+    })
+}
+
+/// `KeyGenParameterSpec` that has been used to generate the key stored under `alias`
+pub fn stored_spec(alias: &str) -> Option<GlobalRef> {
+    with_env(|env| {
+        let alias = env.new_string(alias).unwrap();
+        let entry = env
+            .call_static_method(
+                "android/security/keystore/HostKeyStorage",
+                "get",
+                "(Ljava/lang/String;)Landroid/security/keystore/HostKeyStorage$Entry;",
+                &[(&alias).into()],
+            )
+            .and_then(|v| v.l())
+            .unwrap();
+
+        if entry.is_null() {
+            return None;
+        }
+
+        let spec = env
+            .get_field(
+                entry,
+                "spec",
+                "Landroid/security/keystore/KeyGenParameterSpec;",
+            )
+            .and_then(|v| v.l())
+            .unwrap();
+
+        Some(env.new_global_ref(spec).unwrap())
+    })
+}
+
+/// Generate a key under `alias` via the `KeyPairGenerator` itself, as another library of the
+/// application, or an older version of this library, would
+pub fn generate_key(alias: &str) {
+    with_env(|env| {
+        let alias = env.new_string(alias).unwrap();
+        let builder = env
+            .new_object(
+                "android/security/keystore/KeyGenParameterSpec$Builder",
+                "(Ljava/lang/String;I)V",
+                &[(&alias).into(), JValue::Int(4)],
+            )
+            .unwrap();
+        let digest = env.new_string("SHA-256").unwrap();
+        let digests = env
+            .new_object_array(1, "java/lang/String", &digest)
+            .unwrap();
+        env.call_method(
+            &builder,
+            "setDigests",
+            "([Ljava/lang/String;)Landroid/security/keystore/KeyGenParameterSpec$Builder;",
+            &[(&digests).into()],
+        )
+        .unwrap();
+        env.call_method(
+            &builder,
+            "setKeySize",
+            "(I)Landroid/security/keystore/KeyGenParameterSpec$Builder;",
+            &[JValue::Int(256)],
+        )
+        .unwrap();
+        let spec = env
+            .call_method(
+                builder,
+                "build",
+                "()Landroid/security/keystore/KeyGenParameterSpec;",
+                &[],
+            )
+            .and_then(|v| v.l())
+            .unwrap();
+
+        let algorithm = env.new_string("EC").unwrap();
+        let provider = env.new_string("AndroidKeyStore").unwrap();
+        let generator = env
+            .call_static_method(
+                "java/security/KeyPairGenerator",
+                "getInstance",
+                "(Ljava/lang/String;Ljava/lang/String;)Ljava/security/KeyPairGenerator;",
+                &[(&algorithm).into(), (&provider).into()],
+            )
+            .and_then(|v| v.l())
+            .unwrap();
+        env.call_method(
+            &generator,
+            "initialize",
+            "(Ljava/security/spec/AlgorithmParameterSpec;)V",
+            &[(&spec).into()],
+        )
+        .unwrap();
+        env.call_method(
+            &generator,
+            "generateKeyPair",
+            "()Ljava/security/KeyPair;",
+            &[],
+        )
+        .unwrap();
+    })
+}
+
+/// Call a getter of a `KeyGenParameterSpec` that returns a boolean
+pub fn spec_flag(spec: &GlobalRef, getter: &str) -> bool {
+    with_env(|env| {
+        env.call_method(spec, getter, "()Z", &[])
+            .and_then(|v| v.z())
+            .unwrap()
+    })
+}
+
+/// Call a getter of a `KeyGenParameterSpec` that returns an int
+pub fn spec_int(spec: &GlobalRef, getter: &str) -> i32 {
+    with_env(|env| {
+        env.call_method(spec, getter, "()I", &[])
+            .and_then(|v| v.i())
+            .unwrap()
+    })
+}
+
+/// Call a getter of a `KeyGenParameterSpec` that returns a `java.util.Date`, in milliseconds since
+/// the Unix epoch
+pub fn spec_date(spec: &GlobalRef, getter: &str) -> Option<i64> {
+    with_env(|env| {
+        let date = env
+            .call_method(spec, getter, "()Ljava/util/Date;", &[])
+            .and_then(|v| v.l())
+            .unwrap();
+
+        if date.is_null() {
+            return None;
+        }
+
+        Some(
+            env.call_method(date, "getTime", "()J", &[])
+                .and_then(|v| v.j())
+                .unwrap(),
+        )
+    })
+}
+
+/// Call a getter of a `KeyGenParameterSpec` that returns a byte array
+pub fn spec_bytes(spec: &GlobalRef, getter: &str) -> Option<Vec<u8>> {
+    with_env(|env| {
+        let bytes = env
+            .call_method(spec, getter, "()[B", &[])
+            .and_then(|v| v.l())
+            .unwrap();
+
+        if bytes.is_null() {
+            return None;
+        }
+
+        Some(env.convert_byte_array(JByteArray::from(bytes)).unwrap())
+    })
+}
+
+/// Set the DER encoded attestation chain of the key stored under `alias`, which must have been
+/// generated with an attestation challenge
+pub fn set_attestation_chain(alias: &str, chain: &[Vec<u8>]) {
+    with_env(|env| {
+        let alias = env.new_string(alias).unwrap();
+        let array = env
+            .new_object_array(chain.len() as i32, "[B", JObject::null())
+            .unwrap();
+        for (index, certificate) in chain.iter().enumerate() {
+            let certificate = env.byte_array_from_slice(certificate).unwrap();
+            env.set_object_array_element(&array, index as i32, certificate)
+                .unwrap();
+        }
+
+        env.call_static_method(
+            "android/security/keystore/HostKeyStorage",
+            "setAttestationChain",
+            "(Ljava/lang/String;[[B)V",
+            &[(&alias).into(), (&array).into()],
+        )
+        .unwrap();
+    })
+}
+
+/// Simulate whether the user has authenticated, which is required to sign with biometric keys
+pub fn set_user_authenticated(authenticated: bool) {
+    with_env(|env| {
+        env.call_static_method(
+            "android/security/keystore/HostKeyStorage",
+            "setUserAuthenticated",
+            "(Z)V",
+            &[authenticated.into()],
+        )
+        .unwrap();
+    })
+}
+
+/// Aliases of every key that is stored in the keystore
+pub fn stored_aliases() -> Vec<String> {
+    with_env(|env| {
+        let aliases = env
+            .call_static_method(
+                "android/security/keystore/HostKeyStorage",
+                "aliases",
+                "()Ljava/util/List;",
+                &[],
+            )
+            .and_then(|v| v.l())
+            .unwrap();
+
+        let size = env
+            .call_method(&aliases, "size", "()I", &[])
+            .and_then(|v| v.i())
+            .unwrap();
+
+        (0..size)
+            .map(|i| {
+                let alias: JString = env
+                    .call_method(&aliases, "get", "(I)Ljava/lang/Object;", &[i.into()])
+                    .and_then(|v| v.l())
+                    .unwrap()
+                    .into();
+
+                env.get_string(&alias).unwrap().into()
+            })
+            .collect()
+    })
+}
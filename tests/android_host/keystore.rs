@@ -0,0 +1,687 @@
+use crate::jvm;
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use secure_env::{
+    error::SecureEnvError, jwk_thumbprint, FileMetadataStore, KeyBackend, KeyGenerationOptions,
+    KeyId, KeyOps, PlatformHandles, SecureEnvironment, SecureEnvironmentConfig,
+    SecureEnvironmentOps,
+};
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Held by the tests that use keys without a namespace, as a wipe without a namespace deletes all
+/// of them
+static WITHOUT_NAMESPACE: Mutex<()> = Mutex::new(());
+
+fn environment(namespace: Option<&str>) -> SecureEnvironment {
+    SecureEnvironment::new(SecureEnvironmentConfig {
+        namespace: namespace.map(ToOwned::to_owned),
+        ..Default::default()
+    })
+}
+
+fn environment_with_keystore_version(version: i32) -> SecureEnvironment {
+    let context = jvm::context(version);
+
+    SecureEnvironment::new(SecureEnvironmentConfig {
+        namespace: Some("keystore".to_owned()),
+        platform: PlatformHandles::with_context(jvm::java_vm(), context.as_obj()).unwrap(),
+        ..Default::default()
+    })
+}
+
+fn verify(public_key: &[u8], msg: &[u8], signature: &[u8]) -> bool {
+    let verifying_key = VerifyingKey::from_sec1_bytes(public_key).unwrap();
+    let signature = Signature::from_slice(signature).unwrap();
+
+    verifying_key.verify(msg, &signature).is_ok()
+}
+
+#[test]
+fn load_keypair_returns_the_created_key() {
+    let environment = environment(Some("keystore"));
+
+    let key = environment.create_keypair("load").unwrap();
+    let loaded = environment.load_keypair("load").unwrap();
+
+    let public_key = key.get_public_key().unwrap();
+    assert_eq!(loaded.get_public_key().unwrap(), public_key);
+
+    let msg = b"Hello World!";
+    assert!(verify(&public_key, msg, &loaded.sign(msg).unwrap()));
+}
+
+#[test]
+fn load_keypair_that_does_not_exist() {
+    let result = environment(Some("keystore")).load_keypair("does-not-exist");
+
+    assert!(matches!(
+        result,
+        Err(SecureEnvError::UnableToGetKeyPairById(message)) if message.contains("keystore:does-not-exist")
+    ));
+}
+
+#[test]
+fn static_api_uses_the_initialized_java_vm() {
+    let _guard = WITHOUT_NAMESPACE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    let key = SecureEnvironment::generate_keypair("static-api", false).unwrap();
+    let loaded = SecureEnvironment::get_keypair_by_id("static-api").unwrap();
+
+    assert_eq!(
+        key.get_public_key().unwrap(),
+        loaded.get_public_key().unwrap()
+    );
+}
+
+#[test]
+fn keys_are_stored_under_the_prefixed_namespaced_alias() {
+    environment(Some("keystore"))
+        .create_keypair("alias")
+        .unwrap();
+
+    assert!(jvm::stored_spec("id.animo.secure-env:keystore:alias").is_some());
+    assert!(jvm::stored_spec("keystore:alias").is_none());
+    assert!(jvm::stored_spec("alias").is_none());
+}
+
+#[test]
+fn keys_without_the_alias_prefix_are_still_used() {
+    let environment = environment(Some("keystore-legacy"));
+    jvm::generate_key("keystore-legacy:legacy");
+
+    let key = environment.load_keypair("legacy").unwrap();
+    let msg = b"Hello World!";
+    assert!(verify(
+        &key.get_public_key().unwrap(),
+        msg,
+        &key.sign(msg).unwrap()
+    ));
+
+    assert_eq!(
+        environment.list_keys().unwrap(),
+        [KeyId::new("legacy").unwrap()]
+    );
+    assert!(matches!(
+        environment.create_keypair("legacy"),
+        Err(SecureEnvError::UnableToGenerateKey(_))
+    ));
+
+    environment.delete_keypair("legacy").unwrap();
+    assert!(jvm::stored_spec("keystore-legacy:legacy").is_none());
+}
+
+#[test]
+fn keys_are_usable_via_dyn_key_backend() {
+    let backend: Box<dyn KeyBackend> = Box::new(environment(Some("keystore-dyn")));
+
+    let id = KeyId::new("dyn").unwrap();
+
+    let key = backend.create_keypair(&id).unwrap();
+    let loaded = backend.load_keypair(&id).unwrap();
+
+    let msg = b"Hello World!";
+    assert!(verify(
+        &key.get_public_key().unwrap(),
+        msg,
+        &loaded.sign(msg).unwrap()
+    ));
+}
+
+#[test]
+fn generation_parameters() {
+    environment(Some("keystore"))
+        .create_keypair("parameters")
+        .unwrap();
+
+    let spec = jvm::stored_spec("id.animo.secure-env:keystore:parameters").unwrap();
+
+    assert_eq!(jvm::spec_int(&spec, "getKeySize"), 256);
+    assert_eq!(jvm::spec_int(&spec, "getPurposes"), 4);
+    assert!(!jvm::spec_flag(&spec, "isUserAuthenticationRequired"));
+}
+
+#[test]
+fn strongbox_is_used_when_supported() {
+    environment_with_keystore_version(jvm::STRONGBOX_KEYSTORE_VERSION)
+        .create_keypair("strongbox")
+        .unwrap();
+
+    let spec = jvm::stored_spec("id.animo.secure-env:keystore:strongbox").unwrap();
+    assert!(jvm::spec_flag(&spec, "isStrongBoxBacked"));
+}
+
+#[test]
+fn trusted_execution_environment_is_used_without_strongbox() {
+    environment_with_keystore_version(100)
+        .create_keypair("tee")
+        .unwrap();
+
+    let spec = jvm::stored_spec("id.animo.secure-env:keystore:tee").unwrap();
+    assert!(!jvm::spec_flag(&spec, "isStrongBoxBacked"));
+}
+
+#[test]
+fn biometric_keys_require_authentication() {
+    let key = environment(Some("keystore"))
+        .create_keypair_with_options(
+            "biometrics",
+            &KeyGenerationOptions {
+                backed_by_biometrics: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    let spec = jvm::stored_spec("id.animo.secure-env:keystore:biometrics").unwrap();
+    assert!(jvm::spec_flag(&spec, "isUserAuthenticationRequired"));
+    assert!(jvm::spec_flag(&spec, "isInvalidatedByBiometricEnrollment"));
+    assert_eq!(jvm::spec_int(&spec, "getUserAuthenticationType"), 2);
+    assert_eq!(
+        jvm::spec_int(&spec, "getUserAuthenticationValidityDurationSeconds"),
+        0
+    );
+
+    assert!(matches!(
+        key.sign(b"Hello World!"),
+        Err(SecureEnvError::UnableToCreateSignature(message)) if message.contains("UserNotAuthenticatedException")
+    ));
+
+    jvm::set_user_authenticated(true);
+    let result = key.sign(b"Hello World!");
+    jvm::set_user_authenticated(false);
+
+    assert_eq!(result.unwrap().len(), 64);
+}
+
+#[test]
+fn ephemeral_keys_are_deleted_on_drop() {
+    let environment = environment(Some("keystore-ephemeral"));
+    let ephemeral_aliases = || {
+        jvm::stored_aliases()
+            .into_iter()
+            .filter(|alias| alias.starts_with("id.animo.secure-env:keystore-ephemeral:ephemeral:"))
+            .count()
+    };
+
+    let key = environment
+        .generate_ephemeral_keypair(&KeyGenerationOptions::default())
+        .unwrap();
+    let clone = key.clone();
+
+    let msg = b"Hello World!";
+    assert!(verify(
+        &key.get_public_key().unwrap(),
+        msg,
+        &key.sign(msg).unwrap()
+    ));
+    assert_eq!(ephemeral_aliases(), 1);
+
+    // The alias is only deleted when the last clone is dropped
+    drop(key);
+    assert_eq!(ephemeral_aliases(), 1);
+    assert_eq!(clone.sign(msg).unwrap().len(), 64);
+
+    drop(clone);
+    assert_eq!(ephemeral_aliases(), 0);
+}
+
+#[test]
+fn invalid_ids_are_rejected_before_the_keystore_is_accessed() {
+    for id in ["", "namespace:id", "id with spaces"] {
+        assert!(matches!(
+            environment(Some("keystore")).create_keypair(id),
+            Err(SecureEnvError::InvalidKeyId(_))
+        ));
+    }
+
+    assert!(matches!(
+        environment(Some("invalid:namespace")).create_keypair("id"),
+        Err(SecureEnvError::InvalidKeyId(_))
+    ));
+
+    assert!(jvm::stored_spec("id.animo.secure-env:keystore:namespace:id").is_none());
+    assert!(jvm::stored_spec("invalid:namespace:id").is_none());
+}
+
+#[test]
+fn metadata_is_kept_next_to_the_keystore() {
+    let path = std::env::temp_dir().join(format!(
+        "secure-env-android-host-metadata-{}.json",
+        std::process::id()
+    ));
+    let environment = SecureEnvironment::new(SecureEnvironmentConfig {
+        namespace: Some("metadata".to_owned()),
+        metadata_store: Some(Arc::new(FileMetadataStore::new(&path))),
+        ..Default::default()
+    });
+
+    let key = environment.create_keypair("tagged").unwrap();
+    let mut metadata = key.metadata().unwrap().unwrap();
+    assert!(metadata.created_at.is_some());
+
+    metadata
+        .tags
+        .insert("purpose".to_owned(), "dpop".to_owned());
+    key.set_metadata(&metadata).unwrap();
+
+    assert_eq!(
+        environment
+            .load_keypair("tagged")
+            .unwrap()
+            .metadata()
+            .unwrap(),
+        Some(metadata)
+    );
+    assert_eq!(
+        environment.find_keys(&[("purpose", "dpop")]).unwrap(),
+        [KeyId::new("tagged").unwrap()]
+    );
+
+    environment.delete_keypair("tagged").unwrap();
+    assert!(environment
+        .find_keys(&[("purpose", "dpop")])
+        .unwrap()
+        .is_empty());
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn keypairs_are_found_via_the_local_index() {
+    let path = std::env::temp_dir().join(format!(
+        "secure-env-android-host-index-{}.json",
+        std::process::id()
+    ));
+    let indexed = SecureEnvironment::new(SecureEnvironmentConfig {
+        namespace: Some("index".to_owned()),
+        metadata_store: Some(Arc::new(FileMetadataStore::new(&path))),
+        ..Default::default()
+    });
+
+    let key = indexed.create_keypair("indexed").unwrap();
+    let public_key = key.get_public_key().unwrap();
+
+    let found = indexed
+        .find_keypair_by_public_key(&public_key)
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.get_public_key().unwrap(), public_key);
+
+    let found = indexed
+        .find_keypair_by_thumbprint(&jwk_thumbprint(&public_key).unwrap())
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.get_public_key().unwrap(), public_key);
+
+    // Without a store there is no index
+    assert!(matches!(
+        environment(Some("index")).find_keypair_by_public_key(&public_key),
+        Err(SecureEnvError::UnableToAccessMetadata(_))
+    ));
+
+    indexed.delete_keypair("indexed").unwrap();
+    assert!(indexed
+        .find_keypair_by_public_key(&public_key)
+        .unwrap()
+        .is_none());
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn wipe_deletes_stored_and_leaked_ephemeral_keys_of_the_namespace() {
+    let wiped = environment(Some("keystore-wipe"));
+    let other = environment(Some("keystore-wipe-other"));
+
+    wiped.create_keypair("first").unwrap();
+    wiped.create_keypair("second").unwrap();
+    other.create_keypair("first").unwrap();
+
+    // An ephemeral key whose alias is left behind, as if the process had been terminated
+    std::mem::forget(
+        wiped
+            .generate_ephemeral_keypair(&KeyGenerationOptions::default())
+            .unwrap(),
+    );
+
+    assert_eq!(
+        wiped.list_keys().unwrap(),
+        [KeyId::new("first").unwrap(), KeyId::new("second").unwrap()]
+    );
+
+    let report = wiped.wipe().unwrap();
+    assert!(report.is_complete());
+    assert_eq!(report.deleted.len(), 2);
+
+    assert!(wiped.list_keys().unwrap().is_empty());
+    assert!(!jvm::stored_aliases()
+        .iter()
+        .any(|alias| alias.starts_with("id.animo.secure-env:keystore-wipe:")));
+
+    // Other namespaces are untouched
+    assert_eq!(other.list_keys().unwrap(), [KeyId::new("first").unwrap()]);
+    other.delete_keypair("first").unwrap();
+}
+
+#[test]
+fn wipe_without_a_namespace_keeps_the_keys_of_other_libraries() {
+    let _guard = WITHOUT_NAMESPACE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    let wiped = environment(None);
+    let namespaced = environment(Some("keystore-wipe-namespaced"));
+
+    wiped.create_keypair("wipe-without-namespace").unwrap();
+    namespaced.create_keypair("kept").unwrap();
+    // Alias of another library that is a valid id
+    jvm::generate_key("other-library");
+
+    assert!(!wiped
+        .list_keys()
+        .unwrap()
+        .contains(&KeyId::new("other-library").unwrap()));
+
+    let report = wiped.wipe().unwrap();
+    assert!(report.is_complete());
+    assert!(report
+        .deleted
+        .contains(&KeyId::new("wipe-without-namespace").unwrap()));
+
+    assert!(wiped.list_keys().unwrap().is_empty());
+    assert!(jvm::stored_spec("other-library").is_some());
+    assert_eq!(
+        namespaced.list_keys().unwrap(),
+        [KeyId::new("kept").unwrap()]
+    );
+    namespaced.delete_keypair("kept").unwrap();
+}
+
+#[test]
+fn usage_limits_are_enforced_by_the_keystore() {
+    let environment = environment(Some("keystore-limits"));
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    // No metadata store is required, as the keystore counts the usage itself
+    let key = environment
+        .create_keypair_with_options(
+            "one-time",
+            &KeyGenerationOptions {
+                valid_from: Some(now - 60),
+                valid_until: Some(now + 3600),
+                max_usage_count: Some(1),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    let spec = jvm::stored_spec("id.animo.secure-env:keystore-limits:one-time").unwrap();
+    assert_eq!(jvm::spec_int(&spec, "getMaxUsageCount"), 1);
+    assert_eq!(
+        jvm::spec_date(&spec, "getKeyValidityStart"),
+        Some((now as i64 - 60) * 1000)
+    );
+    assert_eq!(
+        jvm::spec_date(&spec, "getKeyValidityForOriginationEnd"),
+        Some((now as i64 + 3600) * 1000)
+    );
+
+    assert_eq!(key.sign(b"first").unwrap().len(), 64);
+
+    // The keystore deletes the key after its last use
+    assert!(matches!(
+        key.sign(b"second"),
+        Err(SecureEnvError::UsageLimitExceeded(_))
+    ));
+    assert!(jvm::stored_spec("id.animo.secure-env:keystore-limits:one-time").is_none());
+}
+
+#[test]
+fn usage_limits_of_loaded_keys_are_read_from_the_keystore() {
+    let environment = environment(Some("keystore-loaded-limits"));
+
+    environment
+        .create_keypair_with_options(
+            "one-time",
+            &KeyGenerationOptions {
+                max_usage_count: Some(1),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    // Without a metadata store the limit is only known to the keystore
+    let key = environment.load_keypair("one-time").unwrap();
+    assert_eq!(key.sign(b"first").unwrap().len(), 64);
+    assert!(matches!(
+        key.sign(b"second"),
+        Err(SecureEnvError::UsageLimitExceeded(_))
+    ));
+}
+
+#[test]
+fn expired_keys_are_refused_by_the_keystore() {
+    let environment = environment(Some("keystore-limits"));
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let expired = environment
+        .generate_ephemeral_keypair(&KeyGenerationOptions {
+            valid_until: Some(now - 60),
+            ..Default::default()
+        })
+        .unwrap();
+    assert!(matches!(
+        expired.sign(b"Hello World!"),
+        Err(SecureEnvError::KeyExpired(_))
+    ));
+
+    let not_yet_valid = environment
+        .create_keypair_with_options(
+            "not-yet-valid",
+            &KeyGenerationOptions {
+                valid_from: Some(now + 3600),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert!(matches!(
+        not_yet_valid.sign(b"Hello World!"),
+        Err(SecureEnvError::KeyExpired(_))
+    ));
+    environment.delete_keypair("not-yet-valid").unwrap();
+}
+
+#[test]
+fn keys_implement_the_rust_crypto_traits() {
+    use p256::{
+        ecdsa::signature::{Keypair, Signer},
+        pkcs8::EncodePublicKey,
+    };
+
+    let environment = environment(Some("keystore"));
+    let key = environment.create_keypair("rust-crypto").unwrap();
+    assert!(key.to_public_key_der().is_ok());
+
+    let signature: Signature = Signer::sign(&key, b"Hello World!");
+    assert!(key
+        .verifying_key()
+        .verify(b"Hello World!", &signature)
+        .is_ok());
+
+    // The verifying key is retrieved when the key is loaded as well
+    let loaded = environment.load_keypair("rust-crypto").unwrap();
+    assert_eq!(loaded.verifying_key(), key.verifying_key());
+    assert_eq!(
+        loaded.verifying_key().to_encoded_point(true).as_bytes(),
+        loaded.get_public_key().unwrap()
+    );
+
+    environment.delete_keypair("rust-crypto").unwrap();
+}
+
+#[test]
+#[cfg(feature = "x509")]
+fn certificate_request_is_signed_by_the_keystore_key() {
+    use secure_env::{CertificateRequest, CertificateRequestParams};
+    use x509_parser::{certification_request::X509CertificationRequest, prelude::FromDer};
+
+    let environment = environment(Some("keystore"));
+    let key = environment.create_keypair("csr").unwrap();
+
+    let request = CertificateRequest::new(
+        &key,
+        &CertificateRequestParams {
+            subject: "CN=Device".to_owned(),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let der = request.to_der().unwrap();
+    let (_, parsed) = X509CertificationRequest::from_der(&der).unwrap();
+    let info = &parsed.certification_request_info;
+    let signature = Signature::from_der(&parsed.signature_value.data).unwrap();
+    let verifying_key = VerifyingKey::from_sec1_bytes(&key.get_public_key().unwrap()).unwrap();
+    assert!(verifying_key.verify(info.raw, &signature).is_ok());
+
+    environment.delete_keypair("csr").unwrap();
+}
+
+#[test]
+#[cfg(feature = "x509")]
+fn keystore_key_issues_certificates() {
+    use secure_env::{BasicConstraints, Certificate, CertificateParams, KeyUsage};
+    use x509_parser::{certificate::X509Certificate, prelude::FromDer};
+
+    let environment = environment(Some("keystore"));
+    let ca_key = environment.create_keypair("certificate-ca").unwrap();
+    let leaf_key = environment
+        .generate_ephemeral_keypair(&Default::default())
+        .unwrap();
+
+    let ca = Certificate::self_signed(
+        &ca_key,
+        &CertificateParams {
+            subject: "CN=Device CA".to_owned(),
+            basic_constraints: Some(BasicConstraints {
+                ca: true,
+                path_len: Some(0),
+            }),
+            key_usage: vec![KeyUsage::KeyCertSign],
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let leaf = Certificate::issue(
+        &ca_key,
+        &ca,
+        &leaf_key.get_public_key().unwrap(),
+        &CertificateParams {
+            subject: "CN=Session".to_owned(),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let verifying_key = VerifyingKey::from_sec1_bytes(&ca_key.get_public_key().unwrap()).unwrap();
+    for certificate in [ca, leaf] {
+        let der = certificate.to_der().unwrap();
+        let (_, parsed) = X509Certificate::from_der(&der).unwrap();
+        let signature = Signature::from_der(&parsed.signature_value.data).unwrap();
+        assert!(verifying_key
+            .verify(parsed.tbs_certificate.as_ref(), &signature)
+            .is_ok());
+    }
+
+    environment.delete_keypair("certificate-ca").unwrap();
+}
+
+#[test]
+#[cfg(feature = "x509")]
+fn attestation_chain_is_read_from_the_keystore() {
+    use secure_env::{
+        Certificate, CertificateParams, Openid4vciProof, Openid4vciProofKey, Openid4vciProofParams,
+        Openid4vciProofValidation, SecureEnvironmentConfig, SoftwareBackend,
+    };
+
+    let environment = environment(Some("keystore-attestation"));
+    let key = environment
+        .create_keypair_with_options(
+            "attested",
+            &KeyGenerationOptions {
+                attestation_challenge: Some(b"challenge".to_vec()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let alias = "id.animo.secure-env:keystore-attestation:attested";
+
+    let spec = jvm::stored_spec(alias).unwrap();
+    assert_eq!(
+        jvm::spec_bytes(&spec, "getAttestationChallenge").as_deref(),
+        Some(&b"challenge"[..])
+    );
+
+    // Without an attestation the keystore only has the self-signed certificate of the key
+    assert_eq!(key.attestation_chain().unwrap(), None);
+
+    // The host can not attest keys, so the chain is issued by a local CA instead
+    let ca_key = SoftwareBackend::new(SecureEnvironmentConfig::default())
+        .create_keypair("attestation-ca")
+        .unwrap();
+    let ca = Certificate::self_signed(
+        &ca_key,
+        &CertificateParams {
+            subject: "CN=Attestation CA".to_owned(),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let leaf = Certificate::issue(
+        &ca_key,
+        &ca,
+        &key.get_public_key().unwrap(),
+        &CertificateParams {
+            subject: "CN=Android Keystore Key".to_owned(),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let chain = vec![leaf.to_der().unwrap(), ca.to_der().unwrap()];
+    jvm::set_attestation_chain(alias, &chain);
+
+    assert_eq!(key.attestation_chain().unwrap(), Some(chain.clone()));
+    let loaded = environment.load_keypair("attested").unwrap();
+    assert_eq!(loaded.attestation_chain().unwrap(), Some(chain.clone()));
+
+    let token = Openid4vciProof::create(
+        &loaded,
+        &Openid4vciProofParams {
+            aud: "https://issuer.example.com".to_owned(),
+            key: Openid4vciProofKey::AttestationChain,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let proof = Openid4vciProof::verify(
+        &token,
+        &Openid4vciProofValidation {
+            aud: "https://issuer.example.com".to_owned(),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(proof.key, Openid4vciProofKey::X5c(chain));
+
+    environment.delete_keypair("attested").unwrap();
+}
@@ -0,0 +1,12 @@
+//! Runs the Android backend against a desktop JVM
+//!
+//! The JVM loads host stubs of the Android classes that are used by the backend, see `java/`. Run
+//! with `cargo test --features android_host_testing,rustls,x509`.
+
+mod conformance;
+mod context;
+mod jvm;
+mod keystore;
+mod stress;
+#[cfg(all(feature = "rustls", feature = "x509"))]
+mod tls;
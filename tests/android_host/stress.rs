@@ -0,0 +1,95 @@
+use crate::jvm;
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use secure_env::{Key, KeyOps, SecureEnvironment, SecureEnvironmentConfig};
+use std::{sync::Arc, thread};
+
+const THREADS: usize = 8;
+const KEYS_PER_THREAD: usize = 16;
+
+fn assert_valid_signature(key: &Key, msg: &[u8]) {
+    let signature = Signature::from_slice(&key.sign(msg).unwrap()).unwrap();
+    let verifying_key = VerifyingKey::from_sec1_bytes(&key.get_public_key().unwrap()).unwrap();
+
+    assert!(verifying_key.verify(msg, &signature).is_ok());
+}
+
+#[test]
+fn keys_are_usable_from_any_thread_after_creation() {
+    let environment = SecureEnvironment::new(SecureEnvironmentConfig {
+        namespace: Some("stress".to_owned()),
+        platform: secure_env::PlatformHandles::with_java_vm(jvm::java_vm()).unwrap(),
+        ..Default::default()
+    });
+
+    // Every key is created on a thread that has finished before the key is used
+    let keys: Vec<Key> = (0..THREADS)
+        .map(|thread_index| {
+            let environment = environment.clone();
+
+            thread::spawn(move || {
+                (0..KEYS_PER_THREAD)
+                    .map(|key_index| {
+                        environment
+                            .create_keypair(format!("{thread_index}-{key_index}"))
+                            .unwrap()
+                    })
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .flat_map(|handle| handle.join().unwrap())
+        .collect();
+
+    assert_eq!(keys.len(), THREADS * KEYS_PER_THREAD);
+
+    // Every thread uses every key concurrently
+    let keys = Arc::new(keys);
+    let handles: Vec<_> = (0..THREADS)
+        .map(|thread_index| {
+            let keys = keys.clone();
+
+            thread::spawn(move || {
+                for key in keys.iter() {
+                    assert_valid_signature(key, format!("message {thread_index}").as_bytes());
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    // Release the keys on threads that did not create them
+    let keys = Arc::try_unwrap(keys).unwrap();
+    let handles: Vec<_> = keys
+        .chunks(KEYS_PER_THREAD)
+        .map(|chunk| {
+            let chunk = chunk.to_vec();
+
+            thread::spawn(move || drop(chunk))
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
+
+#[test]
+fn cloned_keys_outlive_the_original() {
+    let environment = SecureEnvironment::new(SecureEnvironmentConfig {
+        namespace: Some("stress".to_owned()),
+        platform: secure_env::PlatformHandles::with_java_vm(jvm::java_vm()).unwrap(),
+        ..Default::default()
+    });
+
+    let key = environment.create_keypair("cloned").unwrap();
+    let clone = key.clone();
+    drop(key);
+
+    thread::spawn(move || assert_valid_signature(&clone, b"Hello World!"))
+        .join()
+        .unwrap();
+}
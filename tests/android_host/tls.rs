@@ -0,0 +1,123 @@
+//! Mutual TLS over loopback with a keystore key as the client key
+
+use rustls::{
+    crypto::{ring, CryptoProvider},
+    pki_types::{CertificateDer, ServerName},
+    server::WebPkiClientVerifier,
+    ClientConfig, ClientConnection, RootCertStore, ServerConfig, ServerConnection, StreamOwned,
+};
+use secure_env::{
+    BasicConstraints, Certificate, CertificateParams, RustlsCertResolver, RustlsSigningKey,
+    SecureEnvironment, SecureEnvironmentConfig, SecureKey, SoftwareBackend, SoftwareKey,
+    SubjectAltName,
+};
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::Arc,
+    thread,
+};
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(ring::default_provider())
+}
+
+/// Certificate resolver for `key`, with a certificate for `localhost` that is issued by the CA
+fn resolver(
+    ca_key: &SoftwareKey,
+    ca: &Certificate,
+    key: impl SecureKey + 'static,
+) -> Arc<RustlsCertResolver> {
+    let certificate = Certificate::issue(
+        ca_key,
+        ca,
+        &key.get_public_key().unwrap(),
+        &CertificateParams {
+            subject: "CN=localhost".to_owned(),
+            subject_alt_names: vec![SubjectAltName::Dns("localhost".to_owned())],
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    Arc::new(RustlsCertResolver::new(
+        RustlsSigningKey::new(key)
+            .unwrap()
+            .into_certified_key(vec![certificate.to_der().unwrap().into()]),
+    ))
+}
+
+#[test]
+fn keystore_key_authenticates_a_tls_client() {
+    let software = SoftwareBackend::new(SecureEnvironmentConfig::default());
+    let ca_key = software.create_keypair("tls-ca").unwrap();
+    let ca = Certificate::self_signed(
+        &ca_key,
+        &CertificateParams {
+            subject: "CN=Test CA".to_owned(),
+            basic_constraints: Some(BasicConstraints {
+                ca: true,
+                path_len: None,
+            }),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let mut roots = RootCertStore::empty();
+    roots
+        .add(CertificateDer::from(ca.to_der().unwrap()))
+        .unwrap();
+    let roots = Arc::new(roots);
+
+    let environment = SecureEnvironment::new(SecureEnvironmentConfig {
+        namespace: Some("tls".to_owned()),
+        ..Default::default()
+    });
+    let client_key = environment.create_keypair("client").unwrap();
+
+    let server_config = ServerConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_client_cert_verifier(
+            WebPkiClientVerifier::builder_with_provider(roots.clone(), provider())
+                .build()
+                .unwrap(),
+        )
+        .with_cert_resolver(resolver(
+            &ca_key,
+            &ca,
+            software.create_keypair("server").unwrap(),
+        ));
+    let client_config = ClientConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_client_cert_resolver(resolver(&ca_key, &ca, client_key));
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (socket, _) = listener.accept().unwrap();
+        let connection = ServerConnection::new(Arc::new(server_config)).unwrap();
+        let mut stream = StreamOwned::new(connection, socket);
+
+        let mut request = [0u8; 4];
+        stream.read_exact(&mut request).unwrap();
+        stream.write_all(&request).unwrap();
+        stream.flush().unwrap();
+    });
+
+    let connection = ClientConnection::new(
+        Arc::new(client_config),
+        ServerName::try_from("localhost").unwrap(),
+    )
+    .unwrap();
+    let mut stream = StreamOwned::new(connection, TcpStream::connect(address).unwrap());
+    stream.write_all(b"ping").unwrap();
+    let mut response = [0u8; 4];
+    stream.read_exact(&mut response).unwrap();
+    assert_eq!(&response, b"ping");
+    server.join().unwrap();
+
+    environment.delete_keypair("client").unwrap();
+}
@@ -0,0 +1,294 @@
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use secure_env::{
+    error::SecureEnvError, BasicConstraints, Certificate, CertificateParams, KeyOps, KeyUsage,
+    SecureEnvironmentConfig, SoftwareBackend, SoftwareKey, SubjectAltName,
+};
+use x509_parser::{
+    certificate::X509Certificate,
+    extensions::{GeneralName, ParsedExtension},
+    prelude::FromDer,
+    x509::X509Version,
+};
+
+fn backend() -> SoftwareBackend {
+    SoftwareBackend::new(SecureEnvironmentConfig::default())
+}
+
+fn ca(key: &SoftwareKey) -> Certificate {
+    Certificate::self_signed(
+        key,
+        &CertificateParams {
+            subject: "CN=Device CA".to_owned(),
+            basic_constraints: Some(BasicConstraints {
+                ca: true,
+                path_len: Some(0),
+            }),
+            key_usage: vec![KeyUsage::KeyCertSign, KeyUsage::CrlSign],
+            ..Default::default()
+        },
+    )
+    .unwrap()
+}
+
+/// Verify the signature of the certificate with the SEC1 encoded `public_key` of the issuer
+fn verify(certificate: &X509Certificate, public_key: &[u8]) {
+    let verifying_key = VerifyingKey::from_sec1_bytes(public_key).unwrap();
+    let signature = Signature::from_der(&certificate.signature_value.data).unwrap();
+
+    assert!(verifying_key
+        .verify(certificate.tbs_certificate.as_ref(), &signature)
+        .is_ok());
+}
+
+fn subject_key_identifier<'a>(certificate: &'a X509Certificate) -> &'a [u8] {
+    certificate
+        .extensions()
+        .iter()
+        .find_map(|extension| match extension.parsed_extension() {
+            ParsedExtension::SubjectKeyIdentifier(key_identifier) => Some(key_identifier.0),
+            _ => None,
+        })
+        .unwrap()
+}
+
+fn authority_key_identifier<'a>(certificate: &'a X509Certificate) -> &'a [u8] {
+    certificate
+        .extensions()
+        .iter()
+        .find_map(|extension| match extension.parsed_extension() {
+            ParsedExtension::AuthorityKeyIdentifier(identifier) => identifier
+                .key_identifier
+                .as_ref()
+                .map(|key_identifier| key_identifier.0),
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[test]
+fn self_signed_certificate_is_parsed_and_verified_by_x509_parser() {
+    let key = backend().create_keypair("ca").unwrap();
+    let der = ca(&key).to_der().unwrap();
+
+    let (rest, certificate) = X509Certificate::from_der(&der).unwrap();
+    assert!(rest.is_empty());
+    verify(&certificate, &key.get_public_key().unwrap());
+
+    assert_eq!(certificate.version(), X509Version::V3);
+    assert_eq!(certificate.subject().to_string(), "CN=Device CA");
+    assert_eq!(certificate.issuer(), certificate.subject());
+    assert_eq!(
+        certificate.signature_algorithm.algorithm.to_id_string(),
+        "1.2.840.10045.4.3.2"
+    );
+
+    let constraints = certificate.basic_constraints().unwrap().unwrap();
+    assert!(constraints.critical);
+    assert!(constraints.value.ca);
+    assert_eq!(constraints.value.path_len_constraint, Some(0));
+
+    let usage = certificate.key_usage().unwrap().unwrap();
+    assert!(usage.critical);
+    assert!(usage.value.key_cert_sign());
+    assert!(usage.value.crl_sign());
+    assert!(!usage.value.digital_signature());
+
+    assert_eq!(subject_key_identifier(&certificate).len(), 20);
+    assert_eq!(
+        authority_key_identifier(&certificate),
+        subject_key_identifier(&certificate)
+    );
+
+    // Without an end of the validity period, the certificate does not expire
+    assert_eq!(certificate.validity().not_after.to_datetime().year(), 9999);
+}
+
+#[test]
+fn issued_certificate_is_signed_by_the_issuer() {
+    let backend = backend();
+    let ca_key = backend.create_keypair("ca").unwrap();
+    let ca = ca(&ca_key);
+    let leaf_key = backend
+        .generate_ephemeral_keypair(&Default::default())
+        .unwrap();
+
+    let leaf = Certificate::issue(
+        &ca_key,
+        &ca,
+        &leaf_key.get_public_key().unwrap(),
+        &CertificateParams {
+            serial_number: Some(vec![0x01, 0x02, 0x03]),
+            subject: "CN=Session".to_owned(),
+            subject_alt_names: vec![SubjectAltName::Dns("device.example.com".to_owned())],
+            valid_from: Some(1_700_000_000),
+            valid_until: Some(1_700_000_300),
+            key_usage: vec![KeyUsage::DigitalSignature],
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let ca_der = ca.to_der().unwrap();
+    let (_, ca) = X509Certificate::from_der(&ca_der).unwrap();
+    let der = leaf.to_der().unwrap();
+    let (_, certificate) = X509Certificate::from_der(&der).unwrap();
+    verify(&certificate, &ca_key.get_public_key().unwrap());
+
+    assert_eq!(certificate.issuer(), ca.subject());
+    assert_eq!(certificate.subject().to_string(), "CN=Session");
+    assert_eq!(certificate.raw_serial(), [0x01, 0x02, 0x03]);
+    assert_eq!(certificate.validity().not_before.timestamp(), 1_700_000_000);
+    assert_eq!(certificate.validity().not_after.timestamp(), 1_700_000_300);
+    assert!(certificate.basic_constraints().unwrap().is_none());
+    assert!(certificate
+        .key_usage()
+        .unwrap()
+        .unwrap()
+        .value
+        .digital_signature());
+    assert_eq!(
+        certificate
+            .subject_alternative_name()
+            .unwrap()
+            .unwrap()
+            .value
+            .general_names,
+        vec![GeneralName::DNSName("device.example.com")]
+    );
+
+    assert_eq!(
+        authority_key_identifier(&certificate),
+        subject_key_identifier(&ca)
+    );
+    assert_ne!(
+        subject_key_identifier(&certificate),
+        subject_key_identifier(&ca)
+    );
+    assert_eq!(
+        VerifyingKey::from_sec1_bytes(&certificate.public_key().subject_public_key.data)
+            .unwrap()
+            .to_encoded_point(true)
+            .as_bytes(),
+        leaf_key.get_public_key().unwrap()
+    );
+}
+
+#[test]
+fn empty_subject_makes_the_subject_alternative_name_critical() {
+    let backend = backend();
+    let ca_key = backend.create_keypair("ca").unwrap();
+    let ca = ca(&ca_key);
+    let leaf_key = backend
+        .generate_ephemeral_keypair(&Default::default())
+        .unwrap();
+
+    let leaf = Certificate::issue(
+        &ca_key,
+        &ca,
+        &leaf_key.get_public_key().unwrap(),
+        &CertificateParams {
+            subject_alt_names: vec![SubjectAltName::Uri("https://device.example.com".to_owned())],
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let der = leaf.to_der().unwrap();
+    let (_, certificate) = X509Certificate::from_der(&der).unwrap();
+    verify(&certificate, &ca_key.get_public_key().unwrap());
+
+    assert!(certificate.subject().iter().next().is_none());
+
+    let san = certificate.subject_alternative_name().unwrap().unwrap();
+    assert!(san.critical);
+    assert_eq!(
+        san.value.general_names,
+        vec![GeneralName::URI("https://device.example.com")]
+    );
+}
+
+#[test]
+fn serial_numbers_are_random_and_positive() {
+    let key = backend().create_keypair("ca").unwrap();
+    let first = ca(&key).to_der().unwrap();
+    let second = ca(&key).to_der().unwrap();
+
+    let (_, first) = X509Certificate::from_der(&first).unwrap();
+    let (_, second) = X509Certificate::from_der(&second).unwrap();
+    assert_ne!(first.raw_serial(), second.raw_serial());
+    assert_eq!(first.raw_serial().len(), 16);
+    assert!(first.raw_serial()[0] < 0x80);
+}
+
+#[test]
+fn validity_after_2049_is_a_generalized_time() {
+    let key = backend().create_keypair("ca").unwrap();
+    let certificate = Certificate::self_signed(
+        &key,
+        &CertificateParams {
+            subject: "CN=Device".to_owned(),
+            valid_from: Some(1_700_000_000),
+            // 2060-01-01T00:00:00Z
+            valid_until: Some(2_840_140_800),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let der = certificate.to_der().unwrap();
+    let (_, certificate) = X509Certificate::from_der(&der).unwrap();
+    assert_eq!(certificate.validity().not_before.timestamp(), 1_700_000_000);
+    assert_eq!(certificate.validity().not_after.timestamp(), 2_840_140_800);
+}
+
+#[test]
+fn certificate_round_trips_through_der_and_pem() {
+    let key = backend().create_keypair("ca").unwrap();
+    let certificate = ca(&key);
+
+    let pem = certificate.to_pem().unwrap();
+    assert!(pem.starts_with("-----BEGIN CERTIFICATE-----\n"));
+    assert_eq!(Certificate::from_pem(&pem).unwrap(), certificate);
+    assert_eq!(
+        Certificate::from_der(&certificate.to_der().unwrap()).unwrap(),
+        certificate
+    );
+
+    assert!(matches!(
+        Certificate::from_der(b"not a certificate"),
+        Err(SecureEnvError::InvalidCertificate(_))
+    ));
+}
+
+#[test]
+fn invalid_params_are_rejected() {
+    let backend = backend();
+    let key = backend.create_keypair("ca").unwrap();
+    let other_key = backend.create_keypair("other").unwrap();
+    let ca = ca(&key);
+    let public_key = other_key.get_public_key().unwrap();
+
+    let result = Certificate::self_signed(
+        &key,
+        &CertificateParams {
+            subject: "CN=Device".to_owned(),
+            valid_from: Some(1_700_000_300),
+            valid_until: Some(1_700_000_000),
+            ..Default::default()
+        },
+    );
+    assert!(matches!(
+        result,
+        Err(SecureEnvError::UnableToCreateCertificate(_))
+    ));
+
+    // The certificate of the issuer must certify the key that signs
+    let result = Certificate::issue(&other_key, &ca, &public_key, &Default::default());
+    assert!(matches!(
+        result,
+        Err(SecureEnvError::UnableToCreateCertificate(_))
+    ));
+
+    let result = Certificate::issue(&key, &ca, b"not a public key", &Default::default());
+    assert!(matches!(result, Err(SecureEnvError::InvalidPublicKey(_))));
+}
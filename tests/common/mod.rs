@@ -0,0 +1,39 @@
+//! Helpers that are shared by the integration tests
+//!
+//! Every test crate compiles this module on its own and only uses some of the helpers.
+#![allow(dead_code)]
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use secure_env::{KeyOps, SecureEnvironmentConfig, SoftwareBackend, SoftwareKey};
+use serde_json::Value;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub fn create_key(id: &str) -> SoftwareKey {
+    SoftwareBackend::new(SecureEnvironmentConfig::default())
+        .create_keypair(id)
+        .unwrap()
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// JSON of a base64url encoded part of a JWT
+pub fn decode(part: &str) -> Value {
+    serde_json::from_slice(&URL_SAFE_NO_PAD.decode(part).unwrap()).unwrap()
+}
+
+/// Sign an arbitrary header and claims, to create JWTs that the crate does not
+pub fn sign(key: &SoftwareKey, header: Value, claims: Value) -> String {
+    let signing_input = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(header.to_string()),
+        URL_SAFE_NO_PAD.encode(claims.to_string())
+    );
+    let signature = key.sign(signing_input.as_bytes()).unwrap();
+
+    format!("{signing_input}.{}", URL_SAFE_NO_PAD.encode(signature))
+}
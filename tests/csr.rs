@@ -0,0 +1,206 @@
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use secure_env::{
+    error::SecureEnvError, CertificateRequest, CertificateRequestParams, KeyOps,
+    SecureEnvironmentConfig, SoftwareBackend, SoftwareKey, SubjectAltName, X509Extension,
+};
+use x509_parser::{
+    certification_request::X509CertificationRequest,
+    cri_attributes::ParsedCriAttribute,
+    extensions::{GeneralName, ParsedExtension},
+    prelude::FromDer,
+};
+
+/// DER encoded extended key usage extension value with `id-kp-clientAuth`
+const CLIENT_AUTH: [u8; 12] = [
+    0x30, 0x0a, 0x06, 0x08, 0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x02,
+];
+
+fn key() -> SoftwareKey {
+    SoftwareBackend::new(SecureEnvironmentConfig::default())
+        .create_keypair("csr")
+        .unwrap()
+}
+
+/// Verify the signature of the request with the public key in the request
+fn verify(request: &X509CertificationRequest) {
+    let info = &request.certification_request_info;
+    let verifying_key =
+        VerifyingKey::from_sec1_bytes(&info.subject_pki.subject_public_key.data).unwrap();
+    let signature = Signature::from_der(&request.signature_value.data).unwrap();
+
+    assert!(verifying_key.verify(info.raw, &signature).is_ok());
+}
+
+#[test]
+fn request_is_parsed_and_verified_by_x509_parser() {
+    let key = key();
+    let request = CertificateRequest::new(
+        &key,
+        &CertificateRequestParams {
+            subject: "CN=Device,O=Animo".to_owned(),
+            subject_alt_names: vec![
+                SubjectAltName::Dns("device.example.com".to_owned()),
+                SubjectAltName::Uri("urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6".to_owned()),
+                SubjectAltName::Email("device@example.com".to_owned()),
+                SubjectAltName::Ip("192.168.1.1".parse().unwrap()),
+            ],
+            extensions: vec![X509Extension {
+                oid: "2.5.29.37".to_owned(),
+                critical: false,
+                value: CLIENT_AUTH.to_vec(),
+            }],
+        },
+    )
+    .unwrap();
+
+    let der = request.to_der().unwrap();
+    let (rest, parsed) = X509CertificationRequest::from_der(&der).unwrap();
+    assert!(rest.is_empty());
+    verify(&parsed);
+
+    let info = &parsed.certification_request_info;
+    assert_eq!(info.subject.to_string(), "O=Animo, CN=Device");
+    assert_eq!(
+        VerifyingKey::from_sec1_bytes(&info.subject_pki.subject_public_key.data)
+            .unwrap()
+            .to_encoded_point(true)
+            .as_bytes(),
+        key.get_public_key().unwrap()
+    );
+    assert_eq!(
+        parsed.signature_algorithm.algorithm.to_id_string(),
+        "1.2.840.10045.4.3.2"
+    );
+
+    let extensions = parsed.requested_extensions().unwrap().collect::<Vec<_>>();
+    assert_eq!(extensions.len(), 2);
+
+    let ParsedExtension::SubjectAlternativeName(san) = extensions[0] else {
+        panic!(
+            "Expected the subject alternative name, got {:?}",
+            extensions[0]
+        );
+    };
+    assert_eq!(
+        san.general_names,
+        vec![
+            GeneralName::DNSName("device.example.com"),
+            GeneralName::URI("urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6"),
+            GeneralName::RFC822Name("device@example.com"),
+            GeneralName::IPAddress(&[192, 168, 1, 1]),
+        ]
+    );
+
+    let ParsedExtension::ExtendedKeyUsage(usage) = extensions[1] else {
+        panic!("Expected the extended key usage, got {:?}", extensions[1]);
+    };
+    assert!(usage.client_auth);
+}
+
+#[test]
+fn empty_subject_makes_the_subject_alternative_name_critical() {
+    let request = CertificateRequest::new(
+        &key(),
+        &CertificateRequestParams {
+            subject_alt_names: vec![SubjectAltName::Dns("device.example.com".to_owned())],
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let der = request.to_der().unwrap();
+    let (_, parsed) = X509CertificationRequest::from_der(&der).unwrap();
+    verify(&parsed);
+
+    assert!(parsed
+        .certification_request_info
+        .subject
+        .iter()
+        .next()
+        .is_none());
+
+    let extensions = parsed.requested_extensions().unwrap().collect::<Vec<_>>();
+    let [ParsedExtension::SubjectAlternativeName(san)] = extensions.as_slice() else {
+        panic!("Expected the subject alternative name, got {extensions:?}");
+    };
+    assert_eq!(
+        san.general_names,
+        vec![GeneralName::DNSName("device.example.com")]
+    );
+
+    // `requested_extensions` only returns the parsed values, so the criticality is checked via the
+    // extension itself
+    let attribute = &parsed.certification_request_info.attributes()[0];
+    let ParsedCriAttribute::ExtensionRequest(request) = attribute.parsed_attribute() else {
+        panic!("Expected the extension request, got {attribute:?}");
+    };
+    assert!(request.extensions[0].critical);
+}
+
+#[test]
+fn request_without_extensions_has_no_extension_request() {
+    let request = CertificateRequest::new(
+        &key(),
+        &CertificateRequestParams {
+            subject: "CN=Device".to_owned(),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let der = request.to_der().unwrap();
+    let (_, parsed) = X509CertificationRequest::from_der(&der).unwrap();
+    verify(&parsed);
+    assert!(parsed.requested_extensions().is_none());
+}
+
+#[test]
+fn pem_contains_the_der_encoding() {
+    let request = CertificateRequest::new(
+        &key(),
+        &CertificateRequestParams {
+            subject: "CN=Device".to_owned(),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let pem = request.to_pem().unwrap();
+    let (_, parsed) = x509_parser::pem::parse_x509_pem(pem.as_bytes()).unwrap();
+    assert_eq!(parsed.label, "CERTIFICATE REQUEST");
+    assert_eq!(parsed.contents, request.to_der().unwrap());
+}
+
+#[test]
+fn invalid_params_are_rejected() {
+    let key = key();
+
+    let result = CertificateRequest::new(
+        &key,
+        &CertificateRequestParams {
+            subject: "not a distinguished name".to_owned(),
+            ..Default::default()
+        },
+    );
+    assert!(matches!(
+        result,
+        Err(SecureEnvError::UnableToCreateCertificateRequest(_))
+    ));
+
+    let result = CertificateRequest::new(
+        &key,
+        &CertificateRequestParams {
+            subject: "CN=Device".to_owned(),
+            extensions: vec![X509Extension {
+                oid: "not an oid".to_owned(),
+                critical: false,
+                value: CLIENT_AUTH.to_vec(),
+            }],
+            ..Default::default()
+        },
+    );
+    assert!(matches!(
+        result,
+        Err(SecureEnvError::UnableToCreateCertificateRequest(_))
+    ));
+}
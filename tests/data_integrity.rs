@@ -0,0 +1,227 @@
+use p256::{
+    ecdsa::{signature::Verifier, Signature, VerifyingKey},
+    SecretKey,
+};
+use secure_env::{
+    canonicalize_json, did_key, error::SecureEnvError, DataIntegrityProof,
+    DataIntegrityProofOptions, KeyOps,
+};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+mod common;
+
+use common::create_key;
+
+fn credential() -> Value {
+    json!({
+        "@context": [
+            "https://www.w3.org/ns/credentials/v2",
+            "https://www.w3.org/ns/credentials/examples/v2"
+        ],
+        "id": "urn:uuid:58172aac-d8ba-11ed-83dd-0b3aef56cc33",
+        "type": ["VerifiableCredential", "AlumniCredential"],
+        "name": "Alumni Credential",
+        "description": "A minimum viable example of an Alumni Credential.",
+        "issuer": "https://vc.example/issuers/5678",
+        "validFrom": "2023-01-01T00:00:00Z",
+        "credentialSubject": {
+            "id": "did:example:abcdefgh",
+            "alumniOf": "The School of Examples"
+        }
+    })
+}
+
+#[test]
+#[allow(clippy::excessive_precision)]
+fn jcs_matches_rfc_8785() {
+    // RFC 8785 section 3.2.2, built from literals as the default float parsing of `serde_json`
+    // is not exact
+    let input = json!({
+        "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+        "string": "\u{20ac}$\u{f}\nA'B\"\\\\\"/",
+        "literals": [null, true, false]
+    });
+
+    assert_eq!(
+        canonicalize_json(&input).unwrap(),
+        r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+    );
+}
+
+#[test]
+fn jcs_sorts_by_utf16_code_units_and_formats_numbers_as_ecmascript() {
+    // RFC 8785 section 3.2.3: U+1F600 (surrogate pair) sorts before U+FB33 in UTF-16
+    let input = json!({ "\u{20ac}": 1, "\r": 2, "\u{fb33}": 3, "1": 4, "\u{1f600}": 5, "\u{80}": 6, "\u{f6}": 7 });
+    assert_eq!(
+        canonicalize_json(&input).unwrap(),
+        "{\"\\r\":2,\"1\":4,\"\u{80}\":6,\"\u{f6}\":7,\"\u{20ac}\":1,\"\u{1f600}\":5,\"\u{fb33}\":3}"
+    );
+
+    for (number, expected) in [
+        (json!(0), "0"),
+        (json!(-0.0), "0"),
+        (json!(1), "1"),
+        (json!(-1.5), "-1.5"),
+        (json!(1e21), "1e+21"),
+        (json!(1e20), "100000000000000000000"),
+        (json!(123456789012345680000.0), "123456789012345680000"),
+        (json!(0.000001), "0.000001"),
+        (json!(0.0000001), "1e-7"),
+        (json!(1.5e-7), "1.5e-7"),
+        (json!(9007199254740992u64), "9007199254740992"),
+        (json!(5e-324), "5e-324"),
+        (json!(1.7976931348623157e308), "1.7976931348623157e+308"),
+    ] {
+        assert_eq!(canonicalize_json(&number).unwrap(), expected);
+    }
+}
+
+#[test]
+fn did_key_matches_the_cryptosuite_test_vector() {
+    // Key pair of the test vectors of the ECDSA cryptosuites specification, with the secret key
+    // `z42twTcNeSYcnqg1FLuSFs2bsGH3ZqbRHFmvS9XMsYhjxvHN` (multicodec `p256-priv`)
+    let secret = bs58::decode("42twTcNeSYcnqg1FLuSFs2bsGH3ZqbRHFmvS9XMsYhjxvHN")
+        .into_vec()
+        .unwrap();
+    assert_eq!(secret[..2], [0x86, 0x26]);
+    let public_key = SecretKey::from_slice(&secret[2..])
+        .unwrap()
+        .public_key()
+        .to_sec1_bytes();
+
+    assert_eq!(
+        did_key(&public_key).unwrap(),
+        "did:key:zDnaepBuvsQ8cpsWrVKw8fbpGpvPeNSjVPTWoq6cRqaYzBKVP"
+    );
+}
+
+#[test]
+fn proof_is_added_to_the_document() {
+    let key = create_key("data-integrity-proof");
+
+    let secured = DataIntegrityProof::sign(
+        &key,
+        &credential(),
+        &DataIntegrityProofOptions {
+            challenge: Some("1f44d55f-f161-4938-a659-f8026467f126".to_owned()),
+            domain: Some("https://verifier.example".to_owned()),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let mut unsecured = secured.clone();
+    let proof = unsecured.as_object_mut().unwrap().remove("proof").unwrap();
+    assert_eq!(unsecured, credential());
+
+    let did = did_key(&key.get_public_key().unwrap()).unwrap();
+    assert_eq!(proof["type"], "DataIntegrityProof");
+    assert_eq!(proof["cryptosuite"], "ecdsa-jcs-2019");
+    assert_eq!(proof["proofPurpose"], "assertionMethod");
+    assert_eq!(
+        proof["verificationMethod"],
+        format!("{did}#{}", did.strip_prefix("did:key:").unwrap())
+    );
+    assert_eq!(proof["challenge"], "1f44d55f-f161-4938-a659-f8026467f126");
+    assert_eq!(proof["domain"], "https://verifier.example");
+    let created = proof["created"].as_str().unwrap();
+    assert!(created.len() == 20 && created.ends_with('Z') && created.as_bytes()[10] == b'T');
+
+    let verified = DataIntegrityProof::verify(&secured).unwrap();
+    assert_eq!(verified, serde_json::from_value(proof).unwrap());
+}
+
+#[test]
+fn proof_value_signs_the_hashes_of_the_proof_configuration_and_document() {
+    let key = create_key("data-integrity-hash-data");
+    let secured = DataIntegrityProof::sign(&key, &credential(), &Default::default()).unwrap();
+
+    let mut proof_config = secured["proof"].clone();
+    let proof_value = proof_config
+        .as_object_mut()
+        .unwrap()
+        .remove("proofValue")
+        .unwrap();
+    proof_config["@context"] = credential()["@context"].clone();
+
+    let mut hash_data =
+        Sha256::digest(canonicalize_json(&proof_config).unwrap().as_bytes()).to_vec();
+    hash_data.extend(Sha256::digest(
+        canonicalize_json(&credential()).unwrap().as_bytes(),
+    ));
+
+    let proof_value = proof_value.as_str().unwrap().strip_prefix('z').unwrap();
+    let signature = Signature::from_slice(&bs58::decode(proof_value).into_vec().unwrap()).unwrap();
+    let verifying_key = VerifyingKey::from_sec1_bytes(&key.get_public_key().unwrap()).unwrap();
+    assert!(verifying_key.verify(&hash_data, &signature).is_ok());
+}
+
+#[test]
+fn proof_with_its_own_context_is_verified() {
+    let key = create_key("data-integrity-proof-context");
+    let mut secured = DataIntegrityProof::sign(&key, &credential(), &Default::default()).unwrap();
+
+    // Proof created over a document whose contexts are a prefix of the secured document
+    secured["proof"]["@context"] = credential()["@context"].clone();
+    DataIntegrityProof::verify(&secured).unwrap();
+
+    secured["proof"]["@context"] = json!(["https://www.w3.org/2018/credentials/v1"]);
+    assert!(matches!(
+        DataIntegrityProof::verify(&secured),
+        Err(SecureEnvError::InvalidSignature(_))
+    ));
+}
+
+#[test]
+fn tampered_documents_and_proofs_are_rejected() {
+    let key = create_key("data-integrity-tampered");
+    let other = create_key("data-integrity-other");
+    let secured = DataIntegrityProof::sign(&key, &credential(), &Default::default()).unwrap();
+
+    let mut tampered_subject = secured.clone();
+    tampered_subject["credentialSubject"]["alumniOf"] = json!("Another School");
+    let mut tampered_purpose = secured.clone();
+    tampered_purpose["proof"]["proofPurpose"] = json!("authentication");
+    let mut other_key = secured.clone();
+    let did = did_key(&other.get_public_key().unwrap()).unwrap();
+    other_key["proof"]["verificationMethod"] =
+        json!(format!("{did}#{}", did.strip_prefix("did:key:").unwrap()));
+    let mut other_cryptosuite = secured.clone();
+    other_cryptosuite["proof"]["cryptosuite"] = json!("ecdsa-rdfc-2019");
+    let mut not_multibase = secured.clone();
+    not_multibase["proof"]["proofValue"] = json!("uAAAA");
+
+    for invalid in [
+        tampered_subject,
+        tampered_purpose,
+        other_key,
+        other_cryptosuite,
+        not_multibase,
+    ] {
+        assert!(matches!(
+            DataIntegrityProof::verify(&invalid),
+            Err(SecureEnvError::InvalidSignature(_))
+        ));
+    }
+
+    let mut other_method = secured;
+    other_method["proof"]["verificationMethod"] = json!("did:web:issuer.example#key-1");
+    assert!(matches!(
+        DataIntegrityProof::verify(&other_method),
+        Err(SecureEnvError::InvalidPublicKey(_))
+    ));
+}
+
+#[test]
+fn documents_must_be_unsecured_objects() {
+    let key = create_key("data-integrity-documents");
+    let secured = DataIntegrityProof::sign(&key, &credential(), &Default::default()).unwrap();
+
+    for invalid in [json!(["not", "an", "object"]), secured] {
+        assert!(matches!(
+            DataIntegrityProof::sign(&key, &invalid, &Default::default()),
+            Err(SecureEnvError::UnableToCreateSignature(_))
+        ));
+    }
+}
@@ -0,0 +1,220 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use secure_env::{
+    access_token_hash, error::SecureEnvError, DpopProof, DpopProofParams, DpopValidation, Jwk,
+    KeyOps, SoftwareKey,
+};
+use serde_json::json;
+
+mod common;
+
+use common::{create_key, decode, now, sign};
+
+const ACCESS_TOKEN: &str = "Kz~8mXK1EalYznwH-LC-1fBAo.4Ljp~zsPE_NeO.gxU";
+
+fn params() -> DpopProofParams {
+    DpopProofParams {
+        htm: "GET".to_owned(),
+        htu: "https://resource.example.org/protectedresource?query=value#fragment".to_owned(),
+        nonce: Some("eyJ7S_zG.eyJH0-Z.HX4w-7v".to_owned()),
+        access_token: Some(ACCESS_TOKEN.to_owned()),
+    }
+}
+
+fn validation(key: &SoftwareKey) -> DpopValidation {
+    DpopValidation {
+        htm: "GET".to_owned(),
+        htu: "https://resource.example.org/protectedresource".to_owned(),
+        nonce: Some("eyJ7S_zG.eyJH0-Z.HX4w-7v".to_owned()),
+        access_token: Some(ACCESS_TOKEN.to_owned()),
+        jkt: Some(
+            Jwk::from_public_key(&key.get_public_key().unwrap())
+                .unwrap()
+                .thumbprint(),
+        ),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn access_token_hash_matches_rfc_9449() {
+    // RFC 9449 section 7.1
+    assert_eq!(
+        access_token_hash(ACCESS_TOKEN),
+        "fUHyO2r2Z3DZ53EsNrWBb0xWXoaNy59IiKCAqksmQEo"
+    );
+}
+
+#[test]
+fn proof_contains_the_key_and_claims() {
+    let key = create_key("dpop");
+    let token = DpopProof::create(&key, &params()).unwrap();
+
+    let parts = token.split('.').collect::<Vec<_>>();
+    assert_eq!(parts.len(), 3);
+
+    let header = decode(parts[0]);
+    assert_eq!(header["typ"], "dpop+jwt");
+    assert_eq!(header["alg"], "ES256");
+    let jwk: Jwk = serde_json::from_value(header["jwk"].clone()).unwrap();
+    assert_eq!(jwk.to_public_key().unwrap(), key.get_public_key().unwrap());
+
+    let claims = decode(parts[1]);
+    assert_eq!(claims["htm"], "GET");
+    assert_eq!(
+        claims["htu"],
+        "https://resource.example.org/protectedresource"
+    );
+    assert_eq!(claims["nonce"], "eyJ7S_zG.eyJH0-Z.HX4w-7v");
+    assert_eq!(claims["ath"], access_token_hash(ACCESS_TOKEN));
+    assert!(claims["jti"].as_str().unwrap().len() >= 16);
+    assert!(claims["iat"].as_u64().unwrap().abs_diff(now()) < 60);
+
+    // Every proof has a unique identifier
+    let other = DpopProof::create(&key, &params()).unwrap();
+    assert_ne!(
+        decode(other.split('.').nth(1).unwrap())["jti"],
+        claims["jti"]
+    );
+}
+
+#[test]
+fn proof_without_optional_claims_omits_them() {
+    let key = create_key("dpop");
+    let token = DpopProof::create(
+        &key,
+        &DpopProofParams {
+            htm: "POST".to_owned(),
+            htu: "https://server.example.com/token".to_owned(),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let claims = decode(token.split('.').nth(1).unwrap());
+    assert!(claims.get("nonce").is_none());
+    assert!(claims.get("ath").is_none());
+}
+
+#[test]
+fn valid_proof_is_accepted() {
+    let key = create_key("dpop");
+    let token = DpopProof::create(&key, &params()).unwrap();
+
+    let proof = DpopProof::validate(&token, &validation(&key)).unwrap();
+    assert_eq!(
+        proof.jwk.to_public_key().unwrap(),
+        key.get_public_key().unwrap()
+    );
+    assert_eq!(proof.claims.ath, Some(access_token_hash(ACCESS_TOKEN)));
+}
+
+#[test]
+fn proof_for_another_request_is_rejected() {
+    let key = create_key("dpop");
+    let token = DpopProof::create(&key, &params()).unwrap();
+    let other_key = create_key("other");
+
+    let invalid = [
+        DpopValidation {
+            htm: "POST".to_owned(),
+            ..validation(&key)
+        },
+        DpopValidation {
+            htu: "https://resource.example.org/other".to_owned(),
+            ..validation(&key)
+        },
+        DpopValidation {
+            nonce: Some("other-nonce".to_owned()),
+            ..validation(&key)
+        },
+        DpopValidation {
+            access_token: Some("other-token".to_owned()),
+            ..validation(&key)
+        },
+        validation(&other_key),
+    ];
+
+    for validation in invalid {
+        assert!(matches!(
+            DpopProof::validate(&token, &validation),
+            Err(SecureEnvError::InvalidToken(_))
+        ));
+    }
+}
+
+#[test]
+fn tampered_proof_is_rejected() {
+    let key = create_key("dpop");
+    let token = DpopProof::create(&key, &params()).unwrap();
+
+    let parts = token.split('.').collect::<Vec<_>>();
+    let mut claims = decode(parts[1]);
+    claims["htm"] = json!("POST");
+    let tampered = format!(
+        "{}.{}.{}",
+        parts[0],
+        URL_SAFE_NO_PAD.encode(claims.to_string()),
+        parts[2]
+    );
+
+    assert!(matches!(
+        DpopProof::validate(
+            &tampered,
+            &DpopValidation {
+                htm: "POST".to_owned(),
+                ..validation(&key)
+            }
+        ),
+        Err(SecureEnvError::InvalidSignature(_))
+    ));
+}
+
+#[test]
+fn malformed_proofs_are_rejected() {
+    let key = create_key("dpop");
+    let jwk = serde_json::to_value(Jwk::from_public_key(&key.get_public_key().unwrap()).unwrap())
+        .unwrap();
+    let claims = |iat: u64| {
+        json!({
+            "jti": "e1j3V_bKic8-LAEB",
+            "htm": "GET",
+            "htu": "https://resource.example.org/protectedresource",
+            "iat": iat,
+            "nonce": "eyJ7S_zG.eyJH0-Z.HX4w-7v",
+            "ath": access_token_hash(ACCESS_TOKEN),
+        })
+    };
+    let header = json!({ "typ": "dpop+jwt", "alg": "ES256", "jwk": jwk });
+
+    // The handcrafted proof is accepted when it is well-formed
+    assert!(DpopProof::validate(
+        &sign(&key, header.clone(), claims(now())),
+        &validation(&key)
+    )
+    .is_ok());
+
+    let mut private_jwk = header.clone();
+    private_jwk["jwk"]["d"] = json!("private");
+    let mut other_type = header.clone();
+    other_type["typ"] = json!("jwt");
+
+    let invalid = [
+        sign(&key, private_jwk, claims(now())),
+        sign(&key, other_type, claims(now())),
+        sign(
+            &key,
+            json!({ "typ": "dpop+jwt", "alg": "ES256" }),
+            claims(now()),
+        ),
+        sign(&key, header.clone(), claims(now() - 600)),
+        sign(&key, header, claims(now() + 600)),
+        "not.a.token".to_owned(),
+    ];
+
+    for token in invalid {
+        assert!(matches!(
+            DpopProof::validate(&token, &validation(&key)),
+            Err(SecureEnvError::InvalidToken(_))
+        ));
+    }
+}
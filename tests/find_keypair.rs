@@ -0,0 +1,148 @@
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use secure_env::{
+    error::SecureEnvError, jwk_thumbprint, FileMetadataStore, Jwk, KeyBackend,
+    KeyGenerationOptions, KeyId, KeyOps, SecureEnvironmentConfig, SoftwareBackend,
+};
+use std::sync::Arc;
+
+/// Example key of RFC 7517, appendix A.1
+fn rfc_7517_jwk() -> Jwk {
+    Jwk {
+        kty: "EC".to_owned(),
+        crv: "P-256".to_owned(),
+        x: "MKBCTNIcKUSDii11ySs3526iDZ8AiTo7Tu6KPAqv7D4".to_owned(),
+        y: "4Etl6SRW2YiLUrN5vfvVHuhp7x8PxltmWWlbbM4IFyM".to_owned(),
+    }
+}
+
+fn uncompressed(public_key: &[u8]) -> Vec<u8> {
+    p256::PublicKey::from_sec1_bytes(public_key)
+        .unwrap()
+        .to_encoded_point(false)
+        .as_bytes()
+        .to_vec()
+}
+
+#[test]
+fn jwk_thumbprint_of_the_rfc_example() {
+    let jwk = rfc_7517_jwk();
+
+    assert_eq!(
+        jwk.thumbprint(),
+        "cn-I_WNMClehiVp51i_0VpOENW1upEerA8sEam5hn-s"
+    );
+
+    // Compressed and uncompressed public keys result in the same JWK
+    let public_key = jwk.to_public_key().unwrap();
+    assert_eq!(public_key.len(), 33);
+    assert_eq!(Jwk::from_public_key(&public_key).unwrap(), jwk);
+    assert_eq!(
+        Jwk::from_public_key(&uncompressed(&public_key)).unwrap(),
+        jwk
+    );
+}
+
+#[test]
+fn invalid_public_keys_are_rejected() {
+    assert!(matches!(
+        jwk_thumbprint(&[0x07; 33]),
+        Err(SecureEnvError::InvalidPublicKey(_))
+    ));
+
+    let jwk = Jwk {
+        crv: "P-384".to_owned(),
+        ..rfc_7517_jwk()
+    };
+    assert!(matches!(
+        jwk.to_public_key(),
+        Err(SecureEnvError::InvalidPublicKey(_))
+    ));
+}
+
+#[test]
+fn keypair_is_found_by_public_key() {
+    let backend = SoftwareBackend::new(SecureEnvironmentConfig::default());
+    backend.create_keypair("other").unwrap();
+    let key = backend.create_keypair("found").unwrap();
+    let public_key = key.get_public_key().unwrap();
+
+    let found = backend
+        .find_keypair_by_public_key(&public_key)
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.get_public_key().unwrap(), public_key);
+
+    let found = backend
+        .find_keypair_by_public_key(&uncompressed(&public_key))
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.get_public_key().unwrap(), public_key);
+}
+
+#[test]
+fn keypair_is_found_by_thumbprint() {
+    let backend: Box<dyn KeyBackend> =
+        Box::new(SoftwareBackend::new(SecureEnvironmentConfig::default()));
+    let key = backend
+        .create_keypair(&KeyId::new("found").unwrap())
+        .unwrap();
+    let public_key = key.get_public_key().unwrap();
+
+    let found = backend
+        .find_keypair_by_thumbprint(&jwk_thumbprint(&public_key).unwrap())
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.get_public_key().unwrap(), public_key);
+
+    assert!(backend
+        .find_keypair_by_thumbprint(&rfc_7517_jwk().thumbprint())
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn deleted_and_ephemeral_keypairs_are_not_found() {
+    let backend = SoftwareBackend::new(SecureEnvironmentConfig::default());
+
+    let key = backend.create_keypair("deleted").unwrap();
+    backend.delete_keypair("deleted").unwrap();
+    assert!(backend
+        .find_keypair_by_public_key(&key.get_public_key().unwrap())
+        .unwrap()
+        .is_none());
+
+    let ephemeral = backend
+        .generate_ephemeral_keypair(&KeyGenerationOptions::default())
+        .unwrap();
+    assert!(backend
+        .find_keypair_by_public_key(&ephemeral.get_public_key().unwrap())
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn thumbprint_is_stored_in_the_metadata() {
+    let path = std::env::temp_dir().join(format!(
+        "secure-env-find-keypair-{}.json",
+        std::process::id()
+    ));
+    let backend = SoftwareBackend::new(SecureEnvironmentConfig {
+        metadata_store: Some(Arc::new(FileMetadataStore::new(&path))),
+        ..Default::default()
+    });
+
+    let key = backend.create_keypair("indexed").unwrap();
+    let thumbprint = jwk_thumbprint(&key.get_public_key().unwrap()).unwrap();
+    assert_eq!(
+        key.metadata().unwrap().unwrap().thumbprint,
+        Some(thumbprint.clone())
+    );
+
+    // Replacing the tags keeps the index
+    key.set_metadata(&Default::default()).unwrap();
+    let metadata = key.metadata().unwrap().unwrap();
+    assert_eq!(metadata.thumbprint, Some(thumbprint));
+    assert!(metadata.created_at.is_some());
+
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,248 @@
+use secure_env::{error::SecureEnvError, HttpRequest, HttpSignatureParams, KeyOps, SoftwareKey};
+
+mod common;
+
+use common::create_key;
+
+const BODY: &[u8] = br#"{"hello": "world"}"#;
+
+/// Example request of RFC 9421 appendix B.2
+fn request() -> HttpRequest {
+    HttpRequest {
+        method: "POST".to_owned(),
+        target_uri: "https://example.com/foo?param=Value&Pet=dog".to_owned(),
+        headers: [
+            ("Host", "example.com"),
+            ("Date", "Tue, 20 Apr 2021 02:07:55 GMT"),
+            ("Content-Type", "application/json"),
+            (
+                "Content-Digest",
+                "sha-512=:WZDPaVn/7XgHaAy8pmojAkGWoRx2UFChF41A2svX+TaPm+AbwAgBWnrIiYllu7BNNyealdVLvRwEmTHWXvJwew==:",
+            ),
+            ("Content-Length", "18"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_owned(), value.to_owned()))
+        .collect(),
+    }
+}
+
+fn params(components: &[&str]) -> HttpSignatureParams {
+    HttpSignatureParams {
+        components: components.iter().map(|c| (*c).to_owned()).collect(),
+        ..Default::default()
+    }
+}
+
+/// Add the signature headers of `params` to the request
+fn sign(request: &mut HttpRequest, key: &SoftwareKey, params: &HttpSignatureParams) {
+    let headers = request.sign(key, params).unwrap();
+    request
+        .headers
+        .push(("Signature-Input".to_owned(), headers.signature_input));
+    request
+        .headers
+        .push(("Signature".to_owned(), headers.signature));
+}
+
+#[test]
+fn signature_base_matches_rfc_9421() {
+    // RFC 9421 appendix B.2.6
+    let params = HttpSignatureParams {
+        created: Some(1618884473),
+        key_id: Some("test-key-ed25519".to_owned()),
+        ..params(&[
+            "date",
+            "@method",
+            "@path",
+            "@authority",
+            "content-type",
+            "content-length",
+        ])
+    };
+
+    assert_eq!(
+        request().signature_base(&params).unwrap(),
+        [
+            r#""date": Tue, 20 Apr 2021 02:07:55 GMT"#,
+            r#""@method": POST"#,
+            r#""@path": /foo"#,
+            r#""@authority": example.com"#,
+            r#""content-type": application/json"#,
+            r#""content-length": 18"#,
+            r#""@signature-params": ("date" "@method" "@path" "@authority" "content-type" "content-length");created=1618884473;keyid="test-key-ed25519""#,
+        ]
+        .join("\n")
+    );
+}
+
+#[test]
+fn derived_components_are_taken_from_the_target_uri() {
+    let request = HttpRequest {
+        method: "GET".to_owned(),
+        target_uri: "HTTPS://WWW.Example.com:443/path/to?a=b&c#fragment".to_owned(),
+        headers: vec![],
+    };
+    let params = HttpSignatureParams {
+        created: Some(1),
+        ..params(&[
+            "@scheme",
+            "@authority",
+            "@path",
+            "@query",
+            "@request-target",
+        ])
+    };
+
+    assert_eq!(
+        request.signature_base(&params).unwrap(),
+        [
+            r#""@scheme": https"#,
+            r#""@authority": www.example.com"#,
+            r#""@path": /path/to"#,
+            r#""@query": ?a=b&c"#,
+            r#""@request-target": /path/to?a=b&c"#,
+            r#""@signature-params": ("@scheme" "@authority" "@path" "@query" "@request-target");created=1"#,
+        ]
+        .join("\n")
+    );
+}
+
+#[test]
+fn content_digest_matches_rfc_9530() {
+    let mut request = request();
+    request.set_content_digest(BODY);
+
+    assert_eq!(
+        request.header("content-digest").unwrap(),
+        "sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:"
+    );
+    assert!(request.verify_content_digest(BODY).is_ok());
+    assert!(matches!(
+        request.verify_content_digest(br#"{"hello": "there"}"#),
+        Err(SecureEnvError::InvalidSignature(_))
+    ));
+}
+
+#[test]
+fn signed_request_is_verified() {
+    let key = create_key("http-signature");
+    let mut request = request();
+    request.set_content_digest(BODY);
+
+    let params = HttpSignatureParams {
+        key_id: Some("http-signature".to_owned()),
+        nonce: Some("a \"quoted\" nonce".to_owned()),
+        tag: Some("app".to_owned()),
+        ..params(&["@method", "@target-uri", "content-type", "content-digest"])
+    };
+    sign(&mut request, &key, &params);
+
+    assert!(request
+        .header("signature-input")
+        .unwrap()
+        .starts_with(r#"sig1=("@method" "@target-uri" "content-type" "content-digest");created="#));
+
+    let verified = request
+        .verify_signature(&key.get_public_key().unwrap(), "sig1")
+        .unwrap();
+    assert_eq!(verified.components, params.components);
+    assert_eq!(verified.key_id, params.key_id);
+    assert_eq!(verified.nonce, params.nonce);
+    assert_eq!(verified.tag, params.tag);
+    assert!(verified.created.is_some());
+    assert!(request.verify_content_digest(BODY).is_ok());
+}
+
+#[test]
+fn tampered_request_is_rejected() {
+    let key = create_key("http-signature");
+    let mut request = request();
+    request.set_content_digest(BODY);
+    sign(
+        &mut request,
+        &key,
+        &params(&["@method", "@target-uri", "content-digest"]),
+    );
+    let public_key = key.get_public_key().unwrap();
+
+    let mut tampered = request.clone();
+    tampered.method = "PUT".to_owned();
+    assert!(matches!(
+        tampered.verify_signature(&public_key, "sig1"),
+        Err(SecureEnvError::InvalidSignature(_))
+    ));
+
+    let mut tampered = request.clone();
+    tampered.set_content_digest(br#"{"hello": "there"}"#);
+    assert!(matches!(
+        tampered.verify_signature(&public_key, "sig1"),
+        Err(SecureEnvError::InvalidSignature(_))
+    ));
+
+    let other_key = create_key("other").get_public_key().unwrap();
+    assert!(matches!(
+        request.verify_signature(&other_key, "sig1"),
+        Err(SecureEnvError::InvalidSignature(_))
+    ));
+
+    assert!(matches!(
+        request.verify_signature(&public_key, "sig2"),
+        Err(SecureEnvError::InvalidSignature(_))
+    ));
+}
+
+#[test]
+fn multiple_signatures_are_verified_by_label() {
+    let first = create_key("first");
+    let second = create_key("second");
+    let mut request = request();
+
+    sign(&mut request, &first, &params(&["@method"]));
+    sign(
+        &mut request,
+        &second,
+        &HttpSignatureParams {
+            label: "proxy".to_owned(),
+            ..params(&["@authority", "date"])
+        },
+    );
+
+    let first = first.get_public_key().unwrap();
+    let second = second.get_public_key().unwrap();
+    assert!(request.verify_signature(&first, "sig1").is_ok());
+    assert!(request.verify_signature(&second, "proxy").is_ok());
+    assert!(request.verify_signature(&second, "sig1").is_err());
+}
+
+#[test]
+fn expired_signature_is_rejected() {
+    let key = create_key("http-signature");
+    let mut request = request();
+    sign(
+        &mut request,
+        &key,
+        &HttpSignatureParams {
+            created: Some(1618884473),
+            expires: Some(1618884773),
+            ..Default::default()
+        },
+    );
+
+    assert!(matches!(
+        request.verify_signature(&key.get_public_key().unwrap(), "sig1"),
+        Err(SecureEnvError::InvalidSignature(_))
+    ));
+}
+
+#[test]
+fn missing_components_are_rejected() {
+    let key = create_key("http-signature");
+
+    for components in [&["x-missing"][..], &["@status"], &["@method", "@method"]] {
+        assert!(matches!(
+            request().sign(&key, &params(components)),
+            Err(SecureEnvError::UnableToCreateSignature(_))
+        ));
+    }
+}
@@ -0,0 +1,81 @@
+use secure_env::{error::SecureEnvError, KeyId, SecureEnvironmentConfig, SoftwareBackend};
+
+fn backend(namespace: Option<&str>) -> SoftwareBackend {
+    SoftwareBackend::new(SecureEnvironmentConfig {
+        namespace: namespace.map(ToOwned::to_owned),
+        ..Default::default()
+    })
+}
+
+#[test]
+fn valid_ids() {
+    for id in ["a", "my-key_1.0", "550e8400-e29b-41d4-a716-446655440000"] {
+        assert_eq!(KeyId::new(id).unwrap().as_str(), id);
+    }
+
+    assert!(KeyId::new("a".repeat(KeyId::MAX_LEN)).is_ok());
+}
+
+#[test]
+fn invalid_ids() {
+    let too_long = "a".repeat(KeyId::MAX_LEN + 1);
+
+    for id in [
+        "",
+        too_long.as_str(),
+        "namespace:id",
+        "with space",
+        "ключ",
+        "a/b",
+    ] {
+        assert!(
+            matches!(KeyId::new(id), Err(SecureEnvError::InvalidKeyId(_))),
+            "'{id}' should be invalid"
+        );
+    }
+}
+
+#[test]
+fn non_ascii_ids_are_rejected_for_their_characters() {
+    // 80 characters, but 160 bytes
+    let id = "ключ".repeat(20);
+    assert!(id.len() > KeyId::MAX_LEN);
+
+    let Err(SecureEnvError::InvalidKeyId(message)) = KeyId::new(id) else {
+        panic!("A non-ASCII id should be invalid");
+    };
+    assert!(message.contains("contains 'к'"), "{message}");
+}
+
+#[test]
+fn invalid_ids_are_rejected_by_the_backend() {
+    let backend = backend(None);
+
+    assert!(matches!(
+        backend.create_keypair(""),
+        Err(SecureEnvError::InvalidKeyId(_))
+    ));
+    assert!(matches!(
+        backend.load_keypair("a:b"),
+        Err(SecureEnvError::InvalidKeyId(_))
+    ));
+    assert!(matches!(
+        backend.delete_keypair("a b"),
+        Err(SecureEnvError::InvalidKeyId(_))
+    ));
+}
+
+#[test]
+fn invalid_namespaces_are_rejected() {
+    let too_long = "a".repeat(KeyId::MAX_NAMESPACE_LEN + 1);
+
+    for namespace in ["", too_long.as_str(), "a:b"] {
+        assert!(
+            matches!(
+                backend(Some(namespace)).create_keypair("id"),
+                Err(SecureEnvError::InvalidKeyId(_))
+            ),
+            "'{namespace}' should be invalid"
+        );
+    }
+}
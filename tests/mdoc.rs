@@ -0,0 +1,238 @@
+use coset::cbor::{self, value::Value};
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use secure_env::{error::SecureEnvError, DeviceAuthentication, DeviceKeyInfo, KeyOps};
+
+mod common;
+
+use common::create_key;
+
+/// `DeviceKeyInfo` of the mobile security object of the example mDL in ISO/IEC 18013-5 annex D
+const ANNEX_D_DEVICE_KEY_INFO: &str = "a1696465766963654b6579a40102200121582096313d6c63e24e3372742bfdb1a33ba2c897dcd68ab8c753e4fbd48dca6b7f9a2258201fb3269edd418857de1b39a4e4a44b92fa484caa722c228288f01d0c03a2c3d6";
+
+/// `DeviceNameSpacesBytes` of the example mDL in ISO/IEC 18013-5 annex D, without device signed
+/// data elements
+const ANNEX_D_DEVICE_NAME_SPACES_BYTES: &str = "d81841a0";
+
+/// `SessionTranscriptBytes` of ISO/IEC 18013-5 annex D.5.1: the device engagement, the ephemeral
+/// reader key and the NFC handover of the example presentation
+const ANNEX_D_SESSION_TRANSCRIPT_BYTES: &str = "d81859024183d8185858a20063312e30018201d818584ba4010220012158205a88d182bce5f42efa59943f33359d2e8a968ff289d93e5fa444b624343167fe225820b16e8cf858ddc7690407ba61d4c338237a8cfcf3de6aa672fc60a557aa32fc67d818584ba40102200121582060e3392385041f51403051f2415531cb56dd3f999c71687013aac6768bc8187e225820e58deb8fdbe907f7dd5368245551a34796f7d2215c440c339bb0f7b67beccdfa8258c391020f487315d10209616301013001046d646f631a200c016170706c69636174696f6e2f766e642e626c7565746f6f74682e6c652e6f6f6230081b28128b37282801021c015c1e580469736f2e6f72673a31383031333a646576696365656e676167656d656e746d646f63a20063312e30018201d818584ba4010220012158205a88d182bce5f42efa59943f33359d2e8a968ff289d93e5fa444b624343167fe225820b16e8cf858ddc7690407ba61d4c338237a8cfcf3de6aa672fc60a557aa32fc6758cd91022548721591020263720102110204616301013000110206616301036e6663005102046163010157001a201e016170706c69636174696f6e2f766e642e626c7565746f6f74682e6c652e6f6f6230081b28078080bf2801021c021107c832fff6d26fa0beb34dfcd555d4823a1c11010369736f2e6f72673a31383031333a6e66636e6663015a172b016170706c69636174696f6e2f766e642e7766612e6e616e57030101032302001324fec9a70b97ac9684a4e326176ef5b981c5e8533e5f00298cfccbc35e700a6b020414";
+
+/// `DeviceAuthenticationBytes` of ISO/IEC 18013-5 annex D.5.1, over the session transcript above
+/// for the example mDL without device signed data elements
+const ANNEX_D_DEVICE_AUTHENTICATION_BYTES: &str = "d818590271847444657669636541757468656e7469636174696f6e83d8185858a20063312e30018201d818584ba4010220012158205a88d182bce5f42efa59943f33359d2e8a968ff289d93e5fa444b624343167fe225820b16e8cf858ddc7690407ba61d4c338237a8cfcf3de6aa672fc60a557aa32fc67d818584ba40102200121582060e3392385041f51403051f2415531cb56dd3f999c71687013aac6768bc8187e225820e58deb8fdbe907f7dd5368245551a34796f7d2215c440c339bb0f7b67beccdfa8258c391020f487315d10209616301013001046d646f631a200c016170706c69636174696f6e2f766e642e626c7565746f6f74682e6c652e6f6f6230081b28128b37282801021c015c1e580469736f2e6f72673a31383031333a646576696365656e676167656d656e746d646f63a20063312e30018201d818584ba4010220012158205a88d182bce5f42efa59943f33359d2e8a968ff289d93e5fa444b624343167fe225820b16e8cf858ddc7690407ba61d4c338237a8cfcf3de6aa672fc60a557aa32fc6758cd91022548721591020263720102110204616301013000110206616301036e6663005102046163010157001a201e016170706c69636174696f6e2f766e642e626c7565746f6f74682e6c652e6f6f6230081b28078080bf2801021c021107c832fff6d26fa0beb34dfcd555d4823a1c11010369736f2e6f72673a31383031333a6e66636e6663015a172b016170706c69636174696f6e2f766e642e7766612e6e616e57030101032302001324fec9a70b97ac9684a4e326176ef5b981c5e8533e5f00298cfccbc35e700a6b020414756f72672e69736f2e31383031332e352e312e6d444cd81841a0";
+
+const DOC_TYPE: &str = "org.iso.18013.5.1.mDL";
+
+fn hex(value: &str) -> Vec<u8> {
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&value[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn encode(value: &Value) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    cbor::ser::into_writer(value, &mut bytes).unwrap();
+    bytes
+}
+
+/// Content of a tag 24 (encoded CBOR data item) byte string
+fn untagged(bytes: &[u8]) -> Vec<u8> {
+    let Value::Tag(24, encoded) = cbor::de::from_reader(bytes).unwrap() else {
+        panic!("Bytes are not tagged as encoded CBOR");
+    };
+    let Value::Bytes(encoded) = *encoded else {
+        panic!("Tag 24 does not contain a byte string");
+    };
+
+    encoded
+}
+
+/// `SessionTranscript` of annex D
+fn session_transcript() -> Vec<u8> {
+    untagged(&hex(ANNEX_D_SESSION_TRANSCRIPT_BYTES))
+}
+
+/// `SessionTranscript` of annex D with a QR handover (`null`) instead of the NFC handover
+fn other_session_transcript() -> Vec<u8> {
+    let Value::Array(mut session_transcript) =
+        cbor::de::from_reader(session_transcript().as_slice()).unwrap()
+    else {
+        panic!("SessionTranscript is not an array");
+    };
+    session_transcript[2] = Value::Null;
+
+    encode(&Value::Array(session_transcript))
+}
+
+#[test]
+fn device_key_info_matches_annex_d() {
+    let annex_d = hex(ANNEX_D_DEVICE_KEY_INFO);
+
+    let device_key_info = DeviceKeyInfo::from_cbor(&annex_d).unwrap();
+    let mut public_key = vec![0x04];
+    public_key.extend(hex(
+        "96313d6c63e24e3372742bfdb1a33ba2c897dcd68ab8c753e4fbd48dca6b7f9a",
+    ));
+    public_key.extend(hex(
+        "1fb3269edd418857de1b39a4e4a44b92fa484caa722c228288f01d0c03a2c3d6",
+    ));
+    assert_eq!(
+        device_key_info,
+        DeviceKeyInfo::from_public_key(&public_key).unwrap()
+    );
+    assert_eq!(device_key_info.public_key.len(), 33);
+
+    assert_eq!(device_key_info.to_cbor().unwrap(), annex_d);
+    // The `COSE_Key` follows the `deviceKey` text string in the map
+    assert_eq!(device_key_info.to_cose_key().unwrap(), annex_d[11..]);
+    assert_eq!(
+        DeviceKeyInfo::from_cose_key(&annex_d[11..]).unwrap(),
+        device_key_info
+    );
+}
+
+#[test]
+fn device_key_info_exports_the_key() {
+    let key = create_key("mdoc-device-key-info");
+
+    let device_key_info = DeviceKeyInfo::from_key(&key).unwrap();
+    assert_eq!(device_key_info.public_key, key.get_public_key().unwrap());
+    assert_eq!(
+        DeviceKeyInfo::from_cbor(&device_key_info.to_cbor().unwrap()).unwrap(),
+        device_key_info
+    );
+}
+
+#[test]
+fn device_key_info_rejects_other_keys() {
+    // EC2 key on P-384
+    let mut p384 = hex(ANNEX_D_DEVICE_KEY_INFO);
+    p384[15] = 0x02;
+    // OKP key
+    let mut okp = hex(ANNEX_D_DEVICE_KEY_INFO);
+    okp[13] = 0x01;
+
+    for invalid in [p384, okp] {
+        assert!(matches!(
+            DeviceKeyInfo::from_cbor(&invalid),
+            Err(SecureEnvError::InvalidPublicKey(_))
+        ));
+    }
+
+    assert!(matches!(
+        DeviceKeyInfo::from_cbor(&hex("a0")),
+        Err(SecureEnvError::InvalidCbor(_))
+    ));
+    assert!(matches!(
+        DeviceKeyInfo::from_cbor(&hex("a169")),
+        Err(SecureEnvError::InvalidCbor(_))
+    ));
+}
+
+#[test]
+fn device_authentication_matches_annex_d() {
+    let annex_d = hex(ANNEX_D_DEVICE_AUTHENTICATION_BYTES);
+    let device_authentication = DeviceAuthentication::new(session_transcript(), DOC_TYPE);
+    assert_eq!(
+        device_authentication.device_name_spaces,
+        untagged(&hex(ANNEX_D_DEVICE_NAME_SPACES_BYTES))
+    );
+
+    assert_eq!(device_authentication.to_bytes().unwrap(), annex_d);
+    assert_eq!(device_authentication.to_cbor().unwrap(), untagged(&annex_d));
+}
+
+#[test]
+fn device_signature_is_a_detached_cose_sign1() {
+    let key = create_key("mdoc-device-signature");
+    let device_authentication = DeviceAuthentication::new(session_transcript(), DOC_TYPE);
+
+    let device_signature = device_authentication.sign(&key).unwrap();
+
+    // Untagged COSE_Sign1 (RFC 9052 section 4.2): [protected, unprotected, payload, signature]
+    let Value::Array(cose_sign1) = cbor::de::from_reader(device_signature.as_slice()).unwrap()
+    else {
+        panic!("Device signature is not an array");
+    };
+    let [protected, unprotected, payload, signature] = <[Value; 4]>::try_from(cose_sign1).unwrap();
+    // Protected header `{1: -7}`, the `ES256` algorithm (RFC 9053 section 2.1)
+    assert_eq!(protected, Value::Bytes(hex("a10126")));
+    assert_eq!(unprotected, Value::Map(vec![]));
+    // ISO/IEC 18013-5 section 9.1.3.6: the DeviceAuthenticationBytes payload is detached
+    assert_eq!(payload, Value::Null);
+    let Value::Bytes(signature) = signature else {
+        panic!("Signature is not a byte string");
+    };
+
+    // Sig_structure: ["Signature1", protected, external_aad, payload]
+    let sig_structure = encode(&Value::Array(vec![
+        Value::Text("Signature1".to_owned()),
+        Value::Bytes(hex("a10126")),
+        Value::Bytes(vec![]),
+        Value::Bytes(hex(ANNEX_D_DEVICE_AUTHENTICATION_BYTES)),
+    ]));
+    let verifying_key = VerifyingKey::from_sec1_bytes(&key.get_public_key().unwrap()).unwrap();
+    let signature = Signature::from_slice(&signature).unwrap();
+    assert!(verifying_key.verify(&sig_structure, &signature).is_ok());
+
+    device_authentication
+        .verify(&device_signature, &key.get_public_key().unwrap())
+        .unwrap();
+}
+
+#[test]
+fn device_signature_is_bound_to_the_session_and_key() {
+    let key = create_key("mdoc-binding");
+    let device_authentication = DeviceAuthentication::new(session_transcript(), DOC_TYPE);
+    let device_signature = device_authentication.sign(&key).unwrap();
+
+    let other_session = DeviceAuthentication::new(other_session_transcript(), DOC_TYPE);
+    let other_doc_type = DeviceAuthentication::new(
+        device_authentication.session_transcript.clone(),
+        "org.iso.23220.photoid.1",
+    );
+    let mut device_signed = device_authentication.clone();
+    device_signed.device_name_spaces = encode(&Value::Map(vec![(
+        Value::Text("org.iso.18013.5.1".to_owned()),
+        Value::Map(vec![(
+            Value::Text("family_name".to_owned()),
+            Value::Text("Doe".to_owned()),
+        )]),
+    )]));
+
+    for other in [other_session, other_doc_type, device_signed] {
+        assert!(matches!(
+            other.verify(&device_signature, &key.get_public_key().unwrap()),
+            Err(SecureEnvError::InvalidSignature(_))
+        ));
+    }
+
+    let other_key = create_key("mdoc-binding-other");
+    assert!(matches!(
+        device_authentication.verify(&device_signature, &other_key.get_public_key().unwrap()),
+        Err(SecureEnvError::InvalidSignature(_))
+    ));
+}
+
+#[test]
+fn malformed_structures_are_rejected() {
+    let key = create_key("mdoc-malformed");
+
+    let mut trailing_data = session_transcript();
+    trailing_data.push(0xf6);
+    let mut not_a_map = DeviceAuthentication::new(session_transcript(), DOC_TYPE);
+    not_a_map.device_name_spaces = hex("80");
+
+    for invalid in [
+        DeviceAuthentication::new(hex("f6"), DOC_TYPE),
+        DeviceAuthentication::new(trailing_data, DOC_TYPE),
+        DeviceAuthentication::new(hex("83f6"), DOC_TYPE),
+        not_a_map,
+    ] {
+        assert!(matches!(
+            invalid.sign(&key),
+            Err(SecureEnvError::InvalidCbor(_))
+        ));
+    }
+}
@@ -0,0 +1,226 @@
+use secure_env::{
+    error::SecureEnvError, FileMetadataStore, KeyBackend, KeyGenerationOptions, KeyId, KeyMetadata,
+    KeyOps, MetadataStore, SecureEnvironmentConfig, SoftwareBackend,
+};
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+/// File in the temporary directory that is removed when the test is done
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new() -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        Self(std::env::temp_dir().join(format!(
+            "secure-env-metadata-{}-{}.json",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        )))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn backend(store: &Arc<FileMetadataStore>, namespace: Option<&str>) -> SoftwareBackend {
+    SoftwareBackend::new(SecureEnvironmentConfig {
+        namespace: namespace.map(ToOwned::to_owned),
+        metadata_store: Some(store.clone()),
+        ..Default::default()
+    })
+}
+
+fn tagged(tags: &[(&str, &str)]) -> KeyMetadata {
+    KeyMetadata {
+        tags: tags
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn creation_time_is_stored() {
+    let file = TempFile::new();
+    let backend = backend(&Arc::new(FileMetadataStore::new(&file.0)), None);
+
+    let key = backend.create_keypair("created").unwrap();
+
+    let metadata = key.metadata().unwrap().unwrap();
+    assert!(metadata.created_at.is_some());
+    assert!(metadata.tags.is_empty());
+
+    // A loaded key reads the same metadata
+    assert_eq!(
+        backend.load_keypair("created").unwrap().metadata().unwrap(),
+        Some(metadata)
+    );
+}
+
+#[test]
+fn metadata_is_persisted() {
+    let file = TempFile::new();
+    let metadata = tagged(&[("purpose", "dpop")]);
+
+    backend(&Arc::new(FileMetadataStore::new(&file.0)), None)
+        .create_keypair("persisted")
+        .unwrap()
+        .set_metadata(&metadata)
+        .unwrap();
+
+    // A new store reads the metadata from the file
+    let store = FileMetadataStore::new(&file.0);
+    let stored = store
+        .load(None, &KeyId::new("persisted").unwrap())
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.tags, metadata.tags);
+    assert!(stored.created_at.is_some());
+}
+
+#[test]
+fn keys_are_found_by_tags() {
+    let file = TempFile::new();
+    let backend = backend(&Arc::new(FileMetadataStore::new(&file.0)), None);
+
+    for (id, tags) in [
+        ("first", tagged(&[("purpose", "dpop"), ("wallet", "a")])),
+        ("second", tagged(&[("purpose", "dpop"), ("wallet", "b")])),
+        ("third", tagged(&[("purpose", "holder-binding")])),
+    ] {
+        backend
+            .create_keypair(id)
+            .unwrap()
+            .set_metadata(&tags)
+            .unwrap();
+    }
+
+    let ids = |tags: &[(&str, &str)]| -> Vec<String> {
+        backend
+            .find_keys(tags)
+            .unwrap()
+            .into_iter()
+            .map(KeyId::into_string)
+            .collect()
+    };
+
+    assert_eq!(ids(&[("purpose", "dpop")]), ["first", "second"]);
+    assert_eq!(ids(&[("purpose", "dpop"), ("wallet", "b")]), ["second"]);
+    assert_eq!(ids(&[("purpose", "unknown")]), Vec::<String>::new());
+    assert_eq!(ids(&[]), ["first", "second", "third"]);
+}
+
+#[test]
+fn namespaces_share_a_store() {
+    let file = TempFile::new();
+    let store = Arc::new(FileMetadataStore::new(&file.0));
+    let first = backend(&store, Some("first"));
+    let second = backend(&store, Some("second"));
+
+    first
+        .create_keypair("id")
+        .unwrap()
+        .set_metadata(&tagged(&[("namespace", "first")]))
+        .unwrap();
+    second.create_keypair("id").unwrap();
+
+    assert_eq!(
+        first.find_keys(&[("namespace", "first")]).unwrap(),
+        [KeyId::new("id").unwrap()]
+    );
+    assert!(second
+        .find_keys(&[("namespace", "first")])
+        .unwrap()
+        .is_empty());
+
+    // Deleting the key of one namespace keeps the metadata of the other
+    second.delete_keypair("id").unwrap();
+    assert!(first
+        .load_keypair("id")
+        .unwrap()
+        .metadata()
+        .unwrap()
+        .is_some());
+}
+
+#[test]
+fn deleting_a_key_removes_its_metadata() {
+    let file = TempFile::new();
+    let store = Arc::new(FileMetadataStore::new(&file.0));
+    let backend = backend(&store, None);
+
+    let key = backend.create_keypair("deleted").unwrap();
+    key.set_metadata(&tagged(&[("purpose", "dpop")])).unwrap();
+
+    backend.delete_keypair("deleted").unwrap();
+
+    assert!(store.list(None).unwrap().is_empty());
+    assert!(backend
+        .find_keys(&[("purpose", "dpop")])
+        .unwrap()
+        .is_empty());
+
+    // A new key with the same id starts without tags
+    let key = backend.create_keypair("deleted").unwrap();
+    assert!(key.metadata().unwrap().unwrap().tags.is_empty());
+}
+
+#[test]
+fn ephemeral_keys_have_no_metadata() {
+    let file = TempFile::new();
+    let backend = backend(&Arc::new(FileMetadataStore::new(&file.0)), None);
+
+    let key = backend
+        .generate_ephemeral_keypair(&KeyGenerationOptions::default())
+        .unwrap();
+
+    assert!(matches!(
+        key.metadata(),
+        Err(SecureEnvError::UnableToAccessMetadata(_))
+    ));
+    assert!(matches!(
+        key.set_metadata(&KeyMetadata::default()),
+        Err(SecureEnvError::UnableToAccessMetadata(_))
+    ));
+}
+
+#[test]
+fn metadata_requires_a_store() {
+    let backend = SoftwareBackend::new(SecureEnvironmentConfig::default());
+
+    let key = backend.create_keypair("no-store").unwrap();
+
+    assert!(matches!(
+        key.metadata(),
+        Err(SecureEnvError::UnableToAccessMetadata(_))
+    ));
+    assert!(matches!(
+        backend.find_keys(&[]),
+        Err(SecureEnvError::UnableToAccessMetadata(_))
+    ));
+
+    // Keys can still be deleted without a store
+    backend.delete_keypair("no-store").unwrap();
+}
+
+#[test]
+fn corrupt_files_are_reported() {
+    let file = TempFile::new();
+    std::fs::write(&file.0, b"not json").unwrap();
+    let backend = backend(&Arc::new(FileMetadataStore::new(&file.0)), None);
+
+    assert!(matches!(
+        backend.find_keys(&[]),
+        Err(SecureEnvError::UnableToAccessMetadata(_))
+    ));
+}
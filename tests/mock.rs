@@ -0,0 +1,416 @@
+use secure_env::{
+    error::SecureEnvError, jwk_thumbprint, KeyBackend, KeyGenerationOptions, KeyId, KeyOps,
+    MockBackend, MockCall, MockFault, MockFaultPlan, MockOperation, RustCryptoKey,
+    SecureEnvironmentConfig,
+};
+use std::time::{Duration, Instant};
+
+fn backend() -> MockBackend {
+    MockBackend::new(SecureEnvironmentConfig::default())
+}
+
+#[test]
+fn nth_sign_fails_with_the_fault() {
+    let backend = backend().with_fault_plan(MockFaultPlan::new().fail(
+        MockOperation::Sign,
+        2,
+        MockFault::UserCancelled,
+    ));
+    let key = backend.create_keypair("cancelled").unwrap();
+
+    assert!(key.sign(b"first").is_ok());
+    assert!(matches!(
+        key.sign(b"second"),
+        Err(SecureEnvError::UnableToCreateSignature(message)) if message == MockFault::UserCancelled.to_string()
+    ));
+    assert!(key.sign(b"third").is_ok());
+}
+
+#[test]
+fn cancelled_signatures_do_not_use_up_the_key() {
+    let backend = backend().with_fault_plan(MockFaultPlan::new().fail(
+        MockOperation::Sign,
+        1,
+        MockFault::UserCancelled,
+    ));
+    let key = backend
+        .generate_ephemeral_keypair(&KeyGenerationOptions {
+            max_usage_count: Some(1),
+            ..Default::default()
+        })
+        .unwrap();
+
+    assert!(key.sign(b"cancelled").is_err());
+    assert!(key.sign(b"first").is_ok());
+    assert!(matches!(
+        key.sign(b"second"),
+        Err(SecureEnvError::UsageLimitExceeded(_))
+    ));
+}
+
+#[test]
+fn invalidated_key_fails_every_later_call() {
+    let backend = backend().with_fault_plan(MockFaultPlan::new().fail_from(
+        MockOperation::Sign,
+        2,
+        MockFault::KeyInvalidated,
+    ));
+    let key = backend.create_keypair("invalidated").unwrap();
+
+    assert!(key.sign(b"Hello World!").is_ok());
+    for _ in 0..3 {
+        assert!(matches!(
+            key.sign(b"Hello World!"),
+            Err(SecureEnvError::UnableToCreateSignature(message)) if message == MockFault::KeyInvalidated.to_string()
+        ));
+    }
+}
+
+#[test]
+fn first_matching_fault_is_used() {
+    let backend = backend().with_fault_plan(
+        MockFaultPlan::new()
+            .fail(MockOperation::Sign, 1, MockFault::UserCancelled)
+            .fail_from(MockOperation::Sign, 1, MockFault::Other("Busy".to_owned())),
+    );
+    let key = backend.create_keypair("order").unwrap();
+
+    assert!(matches!(
+        key.sign(b"Hello World!"),
+        Err(SecureEnvError::UnableToCreateSignature(message)) if message == MockFault::UserCancelled.to_string()
+    ));
+    assert!(matches!(
+        key.sign(b"Hello World!"),
+        Err(SecureEnvError::UnableToCreateSignature(message)) if message == "Busy"
+    ));
+}
+
+#[test]
+fn strongbox_unavailable_during_generation() {
+    let backend = backend().with_fault_plan(MockFaultPlan::new().fail(
+        MockOperation::CreateKeypair,
+        1,
+        MockFault::StrongBoxUnavailable,
+    ));
+
+    assert!(matches!(
+        backend.create_keypair("strongbox"),
+        Err(SecureEnvError::HardwareBackedKeysAreNotSupported(_))
+    ));
+
+    // The failed call did not store a key
+    assert!(backend.load_keypair("strongbox").is_err());
+    assert!(backend.create_keypair("strongbox").is_ok());
+}
+
+#[test]
+fn faults_use_the_error_of_the_operation() {
+    let fault = || MockFault::Other("Failure".to_owned());
+    let backend = backend().with_fault_plan(
+        MockFaultPlan::new()
+            .fail(MockOperation::LoadKeypair, 1, fault())
+            .fail(MockOperation::DeleteKeypair, 1, fault())
+            .fail(MockOperation::GetPublicKey, 1, fault()),
+    );
+    let key = backend.create_keypair("errors").unwrap();
+
+    assert!(matches!(
+        backend.load_keypair("errors"),
+        Err(SecureEnvError::UnableToGetKeyPairById(_))
+    ));
+    assert!(matches!(
+        backend.delete_keypair("errors"),
+        Err(SecureEnvError::UnableToDeleteKey(_))
+    ));
+    assert!(matches!(
+        key.get_public_key(),
+        Err(SecureEnvError::UnableToGetPublicKey(_))
+    ));
+}
+
+#[test]
+fn latency_is_added() {
+    let latency = Duration::from_millis(50);
+    let backend =
+        backend().with_fault_plan(MockFaultPlan::new().latency(MockOperation::Sign, latency));
+    let key = backend.create_keypair("slow").unwrap();
+
+    let start = Instant::now();
+    key.sign(b"Hello World!").unwrap();
+
+    assert!(start.elapsed() >= latency);
+}
+
+#[test]
+fn replacing_the_fault_plan_resets_the_call_count() {
+    let plan = MockFaultPlan::new().fail(MockOperation::Sign, 1, MockFault::UserCancelled);
+    let backend = backend().with_fault_plan(plan.clone());
+    let key = backend.create_keypair("reset").unwrap();
+
+    assert!(key.sign(b"Hello World!").is_err());
+    assert!(key.sign(b"Hello World!").is_ok());
+
+    backend.set_fault_plan(plan);
+    assert!(key.sign(b"Hello World!").is_err());
+}
+
+#[test]
+fn seeded_keys_are_deterministic() {
+    let public_key = |backend: &MockBackend, id: &str| {
+        backend
+            .create_keypair(id)
+            .unwrap()
+            .get_public_key()
+            .unwrap()
+    };
+
+    let first = backend().with_seed(42);
+    let second = backend().with_seed(42);
+
+    // Independent of the order of creation
+    let a = public_key(&first, "a");
+    let b = public_key(&first, "b");
+    assert_eq!(public_key(&second, "b"), b);
+    assert_eq!(public_key(&second, "a"), a);
+    assert_ne!(a, b);
+
+    assert_ne!(public_key(&backend().with_seed(43), "a"), a);
+
+    // A recreated id gets a new, but still deterministic, key
+    first.delete_keypair("a").unwrap();
+    second.delete_keypair("a").unwrap();
+    let recreated = public_key(&first, "a");
+    assert_ne!(recreated, a);
+    assert_eq!(public_key(&second, "a"), recreated);
+}
+
+#[test]
+fn seeded_keys_depend_on_the_namespace() {
+    let namespaced = MockBackend::new(SecureEnvironmentConfig {
+        namespace: Some("namespace".to_owned()),
+        ..Default::default()
+    })
+    .with_seed(42);
+
+    assert_ne!(
+        namespaced
+            .create_keypair("id")
+            .unwrap()
+            .get_public_key()
+            .unwrap(),
+        backend()
+            .with_seed(42)
+            .create_keypair("id")
+            .unwrap()
+            .get_public_key()
+            .unwrap()
+    );
+}
+
+#[test]
+fn calls_are_logged() {
+    let backend = backend().with_fault_plan(MockFaultPlan::new().fail(
+        MockOperation::Sign,
+        1,
+        MockFault::UserCancelled,
+    ));
+
+    // A clone shares the log, e.g. when it is handed to the code under test
+    let key = backend.clone().create_keypair("logged").unwrap();
+    let _ = key.sign(b"Hello World!");
+    key.get_public_key().unwrap();
+    let _ = backend.load_keypair("missing");
+
+    assert_eq!(
+        backend.calls(),
+        vec![
+            MockCall {
+                operation: MockOperation::CreateKeypair,
+                id: "logged".to_owned(),
+                result: Ok(()),
+            },
+            MockCall {
+                operation: MockOperation::Sign,
+                id: "logged".to_owned(),
+                result: Err(SecureEnvError::UnableToCreateSignature(
+                    MockFault::UserCancelled.to_string()
+                )
+                .to_string()),
+            },
+            MockCall {
+                operation: MockOperation::GetPublicKey,
+                id: "logged".to_owned(),
+                result: Ok(()),
+            },
+            MockCall {
+                operation: MockOperation::LoadKeypair,
+                id: "missing".to_owned(),
+                result: Err(SecureEnvError::UnableToGetKeyPairById(
+                    "Key reference with id: 'missing' not found.".to_owned()
+                )
+                .to_string()),
+            },
+        ]
+    );
+
+    backend.clear_calls();
+    assert!(backend.calls().is_empty());
+}
+
+#[test]
+fn deleted_keys_can_not_sign() {
+    let backend = backend();
+    let key = backend.create_keypair("deleted").unwrap();
+
+    backend.delete_keypair("deleted").unwrap();
+
+    assert!(matches!(
+        key.sign(b"Hello World!"),
+        Err(SecureEnvError::UnableToCreateSignature(_))
+    ));
+}
+
+#[test]
+fn biometric_keys_are_supported_via_dyn_key_backend() {
+    let backend: Box<dyn KeyBackend> = Box::new(backend());
+
+    let key = backend
+        .create_keypair_with_options(
+            &KeyId::new("biometrics").unwrap(),
+            &KeyGenerationOptions {
+                backed_by_biometrics: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    assert_eq!(key.sign(b"Hello World!").unwrap().len(), 64);
+}
+
+#[test]
+fn ephemeral_keys_are_not_stored() {
+    let seeded = backend().with_seed(42);
+
+    let key = seeded
+        .generate_ephemeral_keypair(&KeyGenerationOptions::default())
+        .unwrap();
+    assert_eq!(key.sign(b"Hello World!").unwrap().len(), 64);
+
+    let other = seeded
+        .generate_ephemeral_keypair(&KeyGenerationOptions::default())
+        .unwrap();
+    assert_ne!(
+        key.get_public_key().unwrap(),
+        other.get_public_key().unwrap()
+    );
+
+    // Seeded ephemeral keys are deterministic as well
+    let same_seed = backend()
+        .with_seed(42)
+        .generate_ephemeral_keypair(&KeyGenerationOptions::default())
+        .unwrap();
+    assert_eq!(
+        same_seed.get_public_key().unwrap(),
+        key.get_public_key().unwrap()
+    );
+
+    assert_eq!(
+        seeded.calls()[0],
+        MockCall {
+            operation: MockOperation::GenerateEphemeralKeypair,
+            id: String::new(),
+            result: Ok(()),
+        }
+    );
+}
+
+#[test]
+fn keypairs_are_found_by_public_key() {
+    let backend = MockBackend::new(SecureEnvironmentConfig {
+        namespace: Some("namespace".to_owned()),
+        ..Default::default()
+    });
+    let key = backend.create_keypair("found").unwrap();
+    let public_key = key.get_public_key().unwrap();
+
+    let found = backend
+        .find_keypair_by_public_key(&public_key)
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.get_public_key().unwrap(), public_key);
+
+    backend.delete_keypair("found").unwrap();
+    assert!(backend
+        .find_keypair_by_public_key(&public_key)
+        .unwrap()
+        .is_none());
+
+    let thumbprint = jwk_thumbprint(&public_key).unwrap();
+    assert!(backend.calls().contains(&MockCall {
+        operation: MockOperation::FindKeypair,
+        id: thumbprint,
+        result: Ok(()),
+    }));
+}
+
+#[test]
+fn wipe_reports_failures_and_deletes_the_other_keypairs() {
+    let backend = backend().with_fault_plan(MockFaultPlan::new().fail(
+        MockOperation::DeleteKeypair,
+        1,
+        MockFault::Other("Keystore is locked".to_owned()),
+    ));
+    for id in ["first", "second", "third"] {
+        backend.create_keypair(id).unwrap();
+    }
+
+    let report = backend.wipe().unwrap();
+    assert!(!report.is_complete());
+    assert_eq!(report.failures.len(), 1);
+    assert_eq!(report.failures[0].id, KeyId::new("first").unwrap());
+    assert!(matches!(
+        report.failures[0].error,
+        SecureEnvError::UnableToDeleteKey(_)
+    ));
+    assert_eq!(
+        report.deleted,
+        [KeyId::new("second").unwrap(), KeyId::new("third").unwrap()]
+    );
+
+    // Wiping again deletes what has been left behind
+    let report = backend.wipe().unwrap();
+    assert!(report.is_complete());
+    assert_eq!(report.deleted, [KeyId::new("first").unwrap()]);
+    assert!(backend.list_keys().unwrap().is_empty());
+}
+
+#[test]
+fn failing_to_list_keys_leaves_the_namespace_untouched() {
+    let backend = backend().with_fault_plan(MockFaultPlan::new().fail(
+        MockOperation::ListKeys,
+        1,
+        MockFault::Other("Keystore is locked".to_owned()),
+    ));
+    backend.create_keypair("kept").unwrap();
+
+    assert!(matches!(
+        backend.wipe(),
+        Err(SecureEnvError::UnableToListKeys(_))
+    ));
+    assert!(backend.load_keypair("kept").is_ok());
+}
+
+#[test]
+fn wrapping_a_key_without_a_public_key_fails() {
+    let backend = backend().with_fault_plan(MockFaultPlan::new().fail(
+        MockOperation::GetPublicKey,
+        1,
+        MockFault::KeyInvalidated,
+    ));
+    let key = backend.create_keypair("rust-crypto").unwrap();
+
+    assert!(matches!(
+        RustCryptoKey::new(key),
+        Err(SecureEnvError::UnableToGetPublicKey(_))
+    ));
+}
@@ -0,0 +1,306 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use secure_env::{
+    error::{SecureEnvError, SecureEnvResult},
+    BasicConstraints, Certificate, CertificateParams, Jwk, KeyGenerationOptions, KeyOps, KeyUsage,
+    Openid4vciProof, Openid4vciProofKey, Openid4vciProofParams, Openid4vciProofValidation,
+    SecureEnvironmentConfig, SoftwareBackend, SoftwareKey,
+};
+use serde_json::json;
+
+mod common;
+
+use common::{create_key, decode, now, sign};
+
+const ISSUER: &str = "https://credential-issuer.example.com";
+const NONCE: &str = "tZignsnFbp";
+
+fn params(key: Openid4vciProofKey) -> Openid4vciProofParams {
+    Openid4vciProofParams {
+        aud: ISSUER.to_owned(),
+        nonce: Some(NONCE.to_owned()),
+        iss: Some("s6BhdRkqt3".to_owned()),
+        key,
+    }
+}
+
+fn validation() -> Openid4vciProofValidation {
+    Openid4vciProofValidation {
+        aud: ISSUER.to_owned(),
+        nonce: Some(NONCE.to_owned()),
+        ..Default::default()
+    }
+}
+
+/// Certificate chain of a local CA that certifies `key`, leaf certificate first
+fn attestation_chain(key: &SoftwareKey) -> Vec<Vec<u8>> {
+    let ca_key = create_key("attestation-ca");
+    let ca = Certificate::self_signed(
+        &ca_key,
+        &CertificateParams {
+            subject: "CN=Attestation CA".to_owned(),
+            basic_constraints: Some(BasicConstraints {
+                ca: true,
+                path_len: Some(0),
+            }),
+            key_usage: vec![KeyUsage::KeyCertSign],
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let leaf = Certificate::issue(
+        &ca_key,
+        &ca,
+        &key.get_public_key().unwrap(),
+        &CertificateParams {
+            subject: "CN=Device key".to_owned(),
+            key_usage: vec![KeyUsage::DigitalSignature],
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    vec![leaf.to_der().unwrap(), ca.to_der().unwrap()]
+}
+
+/// Key with an attestation chain, like a key of the `AndroidKeyStore` that has been generated with
+/// an attestation challenge
+struct AttestedKey {
+    key: SoftwareKey,
+    chain: Vec<Vec<u8>>,
+}
+
+impl KeyOps for AttestedKey {
+    fn get_public_key(&self) -> SecureEnvResult<Vec<u8>> {
+        self.key.get_public_key()
+    }
+
+    fn sign(&self, msg: &[u8]) -> SecureEnvResult<Vec<u8>> {
+        self.key.sign(msg)
+    }
+
+    fn attestation_chain(&self) -> SecureEnvResult<Option<Vec<Vec<u8>>>> {
+        Ok(Some(self.chain.clone()))
+    }
+}
+
+#[test]
+fn proof_with_jwk_has_the_required_header_and_claims() {
+    let key = create_key("openid4vci-jwk");
+
+    let token = Openid4vciProof::create(&key, &params(Openid4vciProofKey::Jwk)).unwrap();
+
+    let parts = token.split('.').collect::<Vec<_>>();
+    let header = decode(parts[0]);
+    let claims = decode(parts[1]);
+    assert_eq!(header["typ"], "openid4vci-proof+jwt");
+    assert_eq!(header["alg"], "ES256");
+    assert_eq!(header["jwk"]["kty"], "EC");
+    assert_eq!(header["jwk"]["crv"], "P-256");
+    assert!(header.get("kid").is_none() && header.get("x5c").is_none());
+    assert_eq!(claims["iss"], "s6BhdRkqt3");
+    assert_eq!(claims["aud"], ISSUER);
+    assert_eq!(claims["nonce"], NONCE);
+    assert!(now().abs_diff(claims["iat"].as_u64().unwrap()) <= 1);
+
+    let proof = Openid4vciProof::verify(&token, &validation()).unwrap();
+    assert_eq!(proof.key, Openid4vciProofKey::Jwk);
+    assert_eq!(proof.public_key, key.get_public_key().unwrap());
+    assert_eq!(proof.claims.iss.as_deref(), Some("s6BhdRkqt3"));
+}
+
+#[test]
+fn proof_with_kid_is_verified_with_the_resolved_key() {
+    let key = create_key("openid4vci-kid");
+    let kid = "did:example:holder#key-1".to_owned();
+
+    let token =
+        Openid4vciProof::create(&key, &params(Openid4vciProofKey::Kid(kid.clone()))).unwrap();
+    assert_eq!(decode(token.split('.').next().unwrap())["kid"], kid);
+
+    assert!(matches!(
+        Openid4vciProof::verify(&token, &validation()),
+        Err(SecureEnvError::InvalidToken(_))
+    ));
+
+    let proof = Openid4vciProof::verify(
+        &token,
+        &Openid4vciProofValidation {
+            public_key: Some(key.get_public_key().unwrap()),
+            ..validation()
+        },
+    )
+    .unwrap();
+    assert_eq!(proof.key, Openid4vciProofKey::Kid(kid));
+
+    let other = create_key("openid4vci-kid-other");
+    assert!(matches!(
+        Openid4vciProof::verify(
+            &token,
+            &Openid4vciProofValidation {
+                public_key: Some(other.get_public_key().unwrap()),
+                ..validation()
+            },
+        ),
+        Err(SecureEnvError::InvalidSignature(_))
+    ));
+}
+
+#[test]
+fn proof_embeds_the_certificate_chain() {
+    let key = create_key("openid4vci-x5c");
+    let chain = attestation_chain(&key);
+
+    let token =
+        Openid4vciProof::create(&key, &params(Openid4vciProofKey::X5c(chain.clone()))).unwrap();
+
+    let header = decode(token.split('.').next().unwrap());
+    let x5c = header["x5c"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|certificate| STANDARD.decode(certificate.as_str().unwrap()).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(x5c, chain);
+    assert!(header.get("jwk").is_none());
+
+    let proof = Openid4vciProof::verify(&token, &validation()).unwrap();
+    assert_eq!(proof.key, Openid4vciProofKey::X5c(chain));
+    assert_eq!(proof.public_key, key.get_public_key().unwrap());
+}
+
+#[test]
+fn proof_embeds_the_attestation_chain_of_the_key() {
+    let key = create_key("openid4vci-attestation");
+    let chain = attestation_chain(&key);
+    let key = AttestedKey {
+        key,
+        chain: chain.clone(),
+    };
+
+    let token =
+        Openid4vciProof::create(&key, &params(Openid4vciProofKey::AttestationChain)).unwrap();
+
+    let proof = Openid4vciProof::verify(&token, &validation()).unwrap();
+    assert_eq!(proof.key, Openid4vciProofKey::X5c(chain));
+    assert_eq!(proof.public_key, key.get_public_key().unwrap());
+
+    // Keys of the software backend are not attested
+    assert!(matches!(
+        SoftwareBackend::new(SecureEnvironmentConfig::default()).create_keypair_with_options(
+            "openid4vci-challenge",
+            &KeyGenerationOptions {
+                attestation_challenge: Some(NONCE.as_bytes().to_vec()),
+                ..Default::default()
+            }
+        ),
+        Err(SecureEnvError::UnableToGenerateKey(_))
+    ));
+    assert!(matches!(
+        Openid4vciProof::create(
+            &create_key("openid4vci-no-attestation"),
+            &params(Openid4vciProofKey::AttestationChain)
+        ),
+        Err(SecureEnvError::UnableToCreateSignature(_))
+    ));
+}
+
+#[test]
+fn attestation_chain_must_certify_the_key() {
+    let key = create_key("openid4vci-x5c-key");
+    let other = create_key("openid4vci-x5c-other");
+
+    assert!(matches!(
+        Openid4vciProof::create(
+            &key,
+            &params(Openid4vciProofKey::X5c(attestation_chain(&other)))
+        ),
+        Err(SecureEnvError::UnableToCreateSignature(_))
+    ));
+    assert!(matches!(
+        Openid4vciProof::create(&key, &params(Openid4vciProofKey::X5c(vec![]))),
+        Err(SecureEnvError::UnableToCreateSignature(_))
+    ));
+
+    // A proof signed by another key than the leaf certificate certifies
+    let chain = attestation_chain(&other)
+        .iter()
+        .map(|certificate| STANDARD.encode(certificate))
+        .collect::<Vec<_>>();
+    let token = sign(
+        &key,
+        json!({ "typ": "openid4vci-proof+jwt", "alg": "ES256", "x5c": chain }),
+        json!({ "aud": ISSUER, "iat": now(), "nonce": NONCE }),
+    );
+    assert!(matches!(
+        Openid4vciProof::verify(&token, &validation()),
+        Err(SecureEnvError::InvalidSignature(_))
+    ));
+}
+
+#[test]
+fn proof_is_rejected_for_another_issuer_or_nonce() {
+    let key = create_key("openid4vci-claims");
+    let token = Openid4vciProof::create(&key, &params(Openid4vciProofKey::Jwk)).unwrap();
+
+    for validation in [
+        Openid4vciProofValidation {
+            aud: "https://other-issuer.example.com".to_owned(),
+            ..validation()
+        },
+        Openid4vciProofValidation {
+            nonce: Some("other-nonce".to_owned()),
+            ..validation()
+        },
+    ] {
+        assert!(matches!(
+            Openid4vciProof::verify(&token, &validation),
+            Err(SecureEnvError::InvalidToken(_))
+        ));
+    }
+}
+
+#[test]
+fn proof_must_be_recent_and_reference_a_single_key() {
+    let key = create_key("openid4vci-header");
+    let jwk = Jwk::from_public_key(&key.get_public_key().unwrap()).unwrap();
+
+    let invalid = [
+        // Too old
+        sign(
+            &key,
+            json!({ "typ": "openid4vci-proof+jwt", "alg": "ES256", "jwk": jwk }),
+            json!({ "aud": ISSUER, "iat": now() - 600, "nonce": NONCE }),
+        ),
+        // In the future
+        sign(
+            &key,
+            json!({ "typ": "openid4vci-proof+jwt", "alg": "ES256", "jwk": jwk }),
+            json!({ "aud": ISSUER, "iat": now() + 600, "nonce": NONCE }),
+        ),
+        // Another type
+        sign(
+            &key,
+            json!({ "typ": "JWT", "alg": "ES256", "jwk": jwk }),
+            json!({ "aud": ISSUER, "iat": now(), "nonce": NONCE }),
+        ),
+        // Both a jwk and a kid
+        sign(
+            &key,
+            json!({ "typ": "openid4vci-proof+jwt", "alg": "ES256", "jwk": jwk, "kid": "key-1" }),
+            json!({ "aud": ISSUER, "iat": now(), "nonce": NONCE }),
+        ),
+        // No key
+        sign(
+            &key,
+            json!({ "typ": "openid4vci-proof+jwt", "alg": "ES256" }),
+            json!({ "aud": ISSUER, "iat": now(), "nonce": NONCE }),
+        ),
+    ];
+
+    for token in invalid {
+        assert!(matches!(
+            Openid4vciProof::verify(&token, &validation()),
+            Err(SecureEnvError::InvalidToken(_))
+        ));
+    }
+}
@@ -0,0 +1,64 @@
+use p256::{
+    ecdsa::{
+        signature::{Keypair, Signer, Verifier},
+        Signature, VerifyingKey,
+    },
+    pkcs8::{DecodePublicKey, EncodePublicKey},
+};
+use secure_env::{
+    BoxedKey, KeyBackend, KeyId, KeyOps, RustCryptoKey, SecureEnvironmentConfig, SoftwareBackend,
+};
+
+/// Sign and verify like a crate that is generic over the RustCrypto traits
+fn sign_and_verify<K>(key: &K, msg: &[u8])
+where
+    K: Signer<Signature> + Keypair<VerifyingKey = VerifyingKey>,
+{
+    let signature: Signature = key.sign(msg);
+
+    assert!(key.verifying_key().verify(msg, &signature).is_ok());
+    assert!(key.verifying_key().verify(b"other", &signature).is_err());
+}
+
+#[test]
+fn software_keys_implement_the_traits() {
+    let key = SoftwareBackend::new(SecureEnvironmentConfig::default())
+        .create_keypair("rust-crypto")
+        .unwrap();
+
+    sign_and_verify(&key, b"Hello World!");
+
+    assert_eq!(
+        key.verifying_key().to_encoded_point(true).as_bytes(),
+        key.get_public_key().unwrap()
+    );
+    assert_eq!(
+        RustCryptoKey::new(key.clone()).unwrap().verifying_key(),
+        key.verifying_key()
+    );
+}
+
+#[test]
+fn boxed_keys_implement_the_traits() {
+    let backend: Box<dyn KeyBackend> =
+        Box::new(SoftwareBackend::new(SecureEnvironmentConfig::default()));
+    let key: BoxedKey = backend
+        .create_keypair(&KeyId::new("rust-crypto").unwrap())
+        .unwrap();
+
+    sign_and_verify(&RustCryptoKey::new(key).unwrap(), b"Hello World!");
+}
+
+#[test]
+fn public_key_is_encoded_as_spki() {
+    let key = SoftwareBackend::new(SecureEnvironmentConfig::default())
+        .create_keypair("rust-crypto")
+        .unwrap();
+
+    let der = key.to_public_key_der().unwrap();
+    let decoded = VerifyingKey::from_public_key_der(der.as_bytes()).unwrap();
+    assert_eq!(decoded, key.verifying_key());
+
+    let pem = key.to_public_key_pem(p256::pkcs8::LineEnding::LF).unwrap();
+    assert!(pem.starts_with("-----BEGIN PUBLIC KEY-----\n"));
+}
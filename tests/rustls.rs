@@ -0,0 +1,167 @@
+use rustls::{
+    crypto::{ring, CryptoProvider},
+    pki_types::{CertificateDer, ServerName},
+    server::WebPkiClientVerifier,
+    ClientConfig, ClientConnection, RootCertStore, ServerConfig, ServerConnection, StreamOwned,
+    SupportedProtocolVersion,
+};
+use secure_env::{
+    BasicConstraints, Certificate, CertificateParams, KeyOps, KeyUsage, RustlsCertResolver,
+    RustlsSigningKey, SecureEnvironmentConfig, SecureKey, SoftwareBackend, SubjectAltName,
+};
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::Arc,
+    thread,
+};
+
+/// Certificate authority that issues the certificates of both peers
+struct Ca {
+    key: Box<dyn SecureKey>,
+    certificate: Certificate,
+}
+
+impl Ca {
+    fn new(backend: &SoftwareBackend) -> Self {
+        let key = backend.create_keypair("ca").unwrap();
+        let certificate = Certificate::self_signed(
+            &key,
+            &CertificateParams {
+                subject: "CN=Test CA".to_owned(),
+                basic_constraints: Some(BasicConstraints {
+                    ca: true,
+                    path_len: Some(0),
+                }),
+                key_usage: vec![KeyUsage::KeyCertSign],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        Self {
+            key: Box::new(key),
+            certificate,
+        }
+    }
+
+    fn roots(&self) -> Arc<RootCertStore> {
+        let mut roots = RootCertStore::empty();
+        roots
+            .add(CertificateDer::from(self.certificate.to_der().unwrap()))
+            .unwrap();
+        Arc::new(roots)
+    }
+
+    /// Certificate resolver for `key`, with a certificate for `localhost`
+    fn resolver(&self, key: impl SecureKey + 'static, subject: &str) -> Arc<RustlsCertResolver> {
+        let certificate = Certificate::issue(
+            &self.key,
+            &self.certificate,
+            &key.get_public_key().unwrap(),
+            &CertificateParams {
+                subject: subject.to_owned(),
+                subject_alt_names: vec![SubjectAltName::Dns("localhost".to_owned())],
+                key_usage: vec![KeyUsage::DigitalSignature],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let certified_key = RustlsSigningKey::new(key)
+            .unwrap()
+            .into_certified_key(vec![certificate.to_der().unwrap().into()]);
+        Arc::new(RustlsCertResolver::new(certified_key))
+    }
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(ring::default_provider())
+}
+
+/// Connect a client to a server over a loopback TCP connection, with mutual authentication, and
+/// return the client certificate that the server received
+fn loopback(
+    version: &'static SupportedProtocolVersion,
+    ca: &Ca,
+    server: Arc<RustlsCertResolver>,
+    client: Arc<RustlsCertResolver>,
+) -> CertificateDer<'static> {
+    let verifier = WebPkiClientVerifier::builder_with_provider(ca.roots(), provider())
+        .build()
+        .unwrap();
+    let server_config = ServerConfig::builder_with_provider(provider())
+        .with_protocol_versions(&[version])
+        .unwrap()
+        .with_client_cert_verifier(verifier)
+        .with_cert_resolver(server);
+    let client_config = ClientConfig::builder_with_provider(provider())
+        .with_protocol_versions(&[version])
+        .unwrap()
+        .with_root_certificates(ca.roots())
+        .with_client_cert_resolver(client);
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let server = thread::spawn(move || {
+        let (socket, _) = listener.accept().unwrap();
+        let connection = ServerConnection::new(Arc::new(server_config)).unwrap();
+        let mut stream = StreamOwned::new(connection, socket);
+
+        let mut request = [0u8; 4];
+        stream.read_exact(&mut request).unwrap();
+        assert_eq!(&request, b"ping");
+        stream.write_all(b"pong").unwrap();
+        stream.flush().unwrap();
+
+        stream.conn.peer_certificates().unwrap()[0].clone()
+    });
+
+    let connection = ClientConnection::new(
+        Arc::new(client_config),
+        ServerName::try_from("localhost").unwrap(),
+    )
+    .unwrap();
+    let mut stream = StreamOwned::new(connection, TcpStream::connect(address).unwrap());
+
+    stream.write_all(b"ping").unwrap();
+    let mut response = [0u8; 4];
+    stream.read_exact(&mut response).unwrap();
+    assert_eq!(&response, b"pong");
+
+    server.join().unwrap()
+}
+
+#[test]
+fn mutual_tls_over_loopback() {
+    let backend = SoftwareBackend::new(SecureEnvironmentConfig::default());
+    let ca = Ca::new(&backend);
+
+    for version in [&rustls::version::TLS13, &rustls::version::TLS12] {
+        let client_key = backend
+            .generate_ephemeral_keypair(&Default::default())
+            .unwrap();
+        let client_public_key = client_key.get_public_key().unwrap();
+
+        let server = ca.resolver(
+            backend
+                .generate_ephemeral_keypair(&Default::default())
+                .unwrap(),
+            "CN=Server",
+        );
+        let client = ca.resolver(client_key, "CN=Client");
+
+        let client_certificate = loopback(version, &ca, server, client);
+        let (_, client_certificate) =
+            x509_parser::parse_x509_certificate(&client_certificate).unwrap();
+        assert_eq!(client_certificate.subject().to_string(), "CN=Client");
+        assert_eq!(
+            p256::PublicKey::from_sec1_bytes(
+                &client_certificate.public_key().subject_public_key.data
+            )
+            .unwrap(),
+            p256::PublicKey::from_sec1_bytes(&client_public_key).unwrap()
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use secure_env::{
+    append_key_binding_jwt, error::SecureEnvError, sd_hash, Jwk, KeyBindingParams, KeyOps,
+    SoftwareKey,
+};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+mod common;
+
+use common::{create_key, decode, now};
+
+const DISCLOSURES: &str =
+    "WyJlbHVWNU9nM2dTTklJOEVZbnN4QV9BIiwgImZhbWlseV9uYW1lIiwgIkRvZSJd~WyI2SWo3dE0tYTVpVlBHYm9TNXRtdlZBIiwgImVtYWlsIiwgImpvaG5kb2VAZXhhbXBsZS5jb20iXQ~";
+
+/// SD-JWT with the issuer payload `payload` followed by the disclosures, without key binding JWT
+///
+/// The issuer signature is not checked by the holder, so a placeholder is used.
+fn presentation(payload: Value) -> String {
+    format!(
+        "eyJhbGciOiJFUzI1NiIsInR5cCI6ImRjK3NkLWp3dCJ9.{}.c2lnbmF0dXJl~{DISCLOSURES}",
+        URL_SAFE_NO_PAD.encode(payload.to_string())
+    )
+}
+
+fn bound_to(key: &SoftwareKey) -> Value {
+    json!({
+        "iss": "https://issuer.example.com",
+        "vct": "https://credentials.example.com/identity_credential",
+        "_sd_alg": "sha-256",
+        "cnf": { "jwk": Jwk::from_public_key(&key.get_public_key().unwrap()).unwrap() },
+    })
+}
+
+fn params() -> KeyBindingParams {
+    KeyBindingParams {
+        aud: "https://verifier.example.org".to_owned(),
+        nonce: "1234567890".to_owned(),
+    }
+}
+
+#[test]
+fn sd_hash_is_the_base64url_sha256_of_the_presentation() {
+    let presentation = presentation(json!({ "cnf": {} }));
+
+    assert_eq!(
+        sd_hash(&presentation),
+        URL_SAFE_NO_PAD.encode(Sha256::digest(presentation.as_bytes()))
+    );
+    assert_eq!(sd_hash(""), "47DEQpj8HBSa-_TImW-5JCeuQeRkm5NMpJWZG3hSuFU");
+}
+
+#[test]
+fn key_binding_jwt_is_appended_and_signed_by_the_key() {
+    let key = create_key("sd-jwt-holder");
+    let presentation = presentation(bound_to(&key));
+
+    let presented = append_key_binding_jwt(&key, &presentation, &params()).unwrap();
+
+    let key_binding_jwt = presented.strip_prefix(&presentation).unwrap();
+    assert!(!key_binding_jwt.contains('~'));
+
+    let parts = key_binding_jwt.split('.').collect::<Vec<_>>();
+    assert_eq!(parts.len(), 3);
+
+    let header = decode(parts[0]);
+    assert_eq!(header["typ"], "kb+jwt");
+    assert_eq!(header["alg"], "ES256");
+
+    let claims = decode(parts[1]);
+    assert_eq!(claims["aud"], "https://verifier.example.org");
+    assert_eq!(claims["nonce"], "1234567890");
+    assert_eq!(claims["sd_hash"], sd_hash(&presentation));
+    assert!(now().abs_diff(claims["iat"].as_u64().unwrap()) <= 1);
+
+    let verifying_key = VerifyingKey::from_sec1_bytes(&key.get_public_key().unwrap()).unwrap();
+    let signature = Signature::from_slice(&URL_SAFE_NO_PAD.decode(parts[2]).unwrap()).unwrap();
+    assert!(verifying_key
+        .verify(format!("{}.{}", parts[0], parts[1]).as_bytes(), &signature)
+        .is_ok());
+}
+
+#[test]
+fn presentation_without_disclosures_is_bound() {
+    let key = create_key("sd-jwt-no-disclosures");
+    let issuer_jwt = presentation(bound_to(&key));
+    let presentation = format!("{}~", issuer_jwt.split('~').next().unwrap());
+
+    let presented = append_key_binding_jwt(&key, &presentation, &params()).unwrap();
+
+    let key_binding_jwt = presented.strip_prefix(&presentation).unwrap();
+    let claims = decode(key_binding_jwt.split('.').nth(1).unwrap());
+    assert_eq!(claims["sd_hash"], sd_hash(&presentation));
+}
+
+#[test]
+fn credential_must_be_bound_to_the_key() {
+    let key = create_key("sd-jwt-key");
+    let other = create_key("sd-jwt-other");
+
+    assert!(matches!(
+        append_key_binding_jwt(&key, &presentation(bound_to(&other)), &params()),
+        Err(SecureEnvError::UnableToCreateSignature(_))
+    ));
+    assert!(matches!(
+        append_key_binding_jwt(
+            &key,
+            &presentation(json!({ "iss": "https://issuer.example.com" })),
+            &params()
+        ),
+        Err(SecureEnvError::InvalidToken(_))
+    ));
+}
+
+#[test]
+fn malformed_presentations_are_rejected() {
+    let key = create_key("sd-jwt-malformed");
+    let presentation = presentation(bound_to(&key));
+    let presented = append_key_binding_jwt(&key, &presentation, &params()).unwrap();
+
+    let mut sha_512 = bound_to(&key);
+    sha_512["_sd_alg"] = json!("sha-512");
+
+    for invalid in [
+        presented,
+        presentation.trim_end_matches('~').to_owned(),
+        format!("not-a-jwt~{DISCLOSURES}"),
+        self::presentation(sha_512),
+    ] {
+        assert!(matches!(
+            append_key_binding_jwt(&key, &invalid, &params()),
+            Err(SecureEnvError::InvalidToken(_))
+        ));
+    }
+}
@@ -0,0 +1,211 @@
+use secure_env::{
+    error::SecureEnvError, FileMetadataStore, KeyGenerationOptions, KeyMetadata, KeyOps,
+    SecureEnvironmentConfig, SoftwareBackend,
+};
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+mod common;
+
+use common::now;
+
+/// Backend with a metadata store in the temporary directory, which is removed on drop
+struct Backend {
+    backend: SoftwareBackend,
+    path: PathBuf,
+}
+
+impl Backend {
+    fn new() -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let path = std::env::temp_dir().join(format!(
+            "secure-env-usage-limits-{}-{}.json",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let backend = SoftwareBackend::new(SecureEnvironmentConfig {
+            metadata_store: Some(Arc::new(FileMetadataStore::new(&path))),
+            ..Default::default()
+        });
+
+        Self { backend, path }
+    }
+}
+
+impl Drop for Backend {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[test]
+fn signing_beyond_the_usage_limit_fails() {
+    let Backend { backend, .. } = &Backend::new();
+    let options = KeyGenerationOptions {
+        max_usage_count: Some(2),
+        ..Default::default()
+    };
+
+    let key = backend
+        .create_keypair_with_options("limited", &options)
+        .unwrap();
+    key.sign(b"first").unwrap();
+
+    // The count is shared with every instance of the key
+    let loaded = backend.load_keypair("limited").unwrap();
+    loaded.sign(b"second").unwrap();
+
+    assert!(matches!(
+        key.sign(b"third"),
+        Err(SecureEnvError::UsageLimitExceeded(_))
+    ));
+    assert!(matches!(
+        loaded.sign(b"third"),
+        Err(SecureEnvError::UsageLimitExceeded(_))
+    ));
+
+    let metadata = key.metadata().unwrap().unwrap();
+    assert_eq!(metadata.max_usage_count, Some(2));
+    assert_eq!(metadata.usage_count, 2);
+}
+
+#[test]
+fn signing_outside_of_the_validity_period_fails() {
+    let Backend { backend, .. } = &Backend::new();
+
+    let expired = backend
+        .create_keypair_with_options(
+            "expired",
+            &KeyGenerationOptions {
+                valid_until: Some(now() - 60),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert!(matches!(
+        expired.sign(b"Hello World!"),
+        Err(SecureEnvError::KeyExpired(_))
+    ));
+
+    let not_yet_valid = backend
+        .create_keypair_with_options(
+            "not-yet-valid",
+            &KeyGenerationOptions {
+                valid_from: Some(now() + 3600),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert!(matches!(
+        not_yet_valid.sign(b"Hello World!"),
+        Err(SecureEnvError::KeyExpired(_))
+    ));
+
+    let valid = backend
+        .create_keypair_with_options(
+            "valid",
+            &KeyGenerationOptions {
+                valid_from: Some(now() - 60),
+                valid_until: Some(now() + 3600),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(valid.sign(b"Hello World!").unwrap().len(), 64);
+}
+
+#[test]
+fn usage_limits_can_not_be_lifted_via_the_metadata() {
+    let Backend { backend, .. } = &Backend::new();
+
+    let key = backend
+        .create_keypair_with_options(
+            "one-time",
+            &KeyGenerationOptions {
+                max_usage_count: Some(1),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    key.sign(b"first").unwrap();
+
+    key.set_metadata(&KeyMetadata::default()).unwrap();
+
+    let metadata = key.metadata().unwrap().unwrap();
+    assert_eq!(metadata.max_usage_count, Some(1));
+    assert_eq!(metadata.usage_count, 1);
+    assert!(matches!(
+        backend.load_keypair("one-time").unwrap().sign(b"second"),
+        Err(SecureEnvError::UsageLimitExceeded(_))
+    ));
+}
+
+#[test]
+fn ephemeral_keys_count_their_usage_in_memory() {
+    let backend = SoftwareBackend::new(SecureEnvironmentConfig::default());
+
+    let key = backend
+        .generate_ephemeral_keypair(&KeyGenerationOptions {
+            max_usage_count: Some(1),
+            ..Default::default()
+        })
+        .unwrap();
+    let clone = key.clone();
+
+    key.sign(b"first").unwrap();
+    assert!(matches!(
+        clone.sign(b"second"),
+        Err(SecureEnvError::UsageLimitExceeded(_))
+    ));
+}
+
+#[test]
+fn stored_keys_with_usage_limits_require_a_store() {
+    let backend = SoftwareBackend::new(SecureEnvironmentConfig::default());
+
+    let result = backend.create_keypair_with_options(
+        "limited",
+        &KeyGenerationOptions {
+            max_usage_count: Some(1),
+            ..Default::default()
+        },
+    );
+
+    assert!(matches!(
+        result,
+        Err(SecureEnvError::UnableToGenerateKey(_))
+    ));
+    assert!(backend.list_keys().unwrap().is_empty());
+}
+
+#[test]
+fn usage_limits_that_can_never_be_met_are_rejected() {
+    let Backend { backend, .. } = &Backend::new();
+
+    for options in [
+        KeyGenerationOptions {
+            valid_from: Some(now()),
+            valid_until: Some(now() - 60),
+            ..Default::default()
+        },
+        KeyGenerationOptions {
+            max_usage_count: Some(0),
+            ..Default::default()
+        },
+    ] {
+        assert!(matches!(
+            backend.create_keypair_with_options("never", &options),
+            Err(SecureEnvError::UnableToGenerateKey(_))
+        ));
+        assert!(matches!(
+            backend.generate_ephemeral_keypair(&options),
+            Err(SecureEnvError::UnableToGenerateKey(_))
+        ));
+    }
+}
@@ -0,0 +1,72 @@
+use secure_env::{
+    FileMetadataStore, KeyBackend, KeyGenerationOptions, KeyId, KeyMetadata, KeyOps, MetadataStore,
+    SecureEnvironmentConfig, SoftwareBackend,
+};
+use std::sync::Arc;
+
+fn ids(ids: &[&str]) -> Vec<KeyId> {
+    ids.iter().map(|id| KeyId::new(*id).unwrap()).collect()
+}
+
+#[test]
+fn wipe_deletes_every_keypair_of_the_namespace() {
+    let backend = SoftwareBackend::new(SecureEnvironmentConfig::default());
+    backend.create_keypair("first").unwrap();
+    backend.create_keypair("second").unwrap();
+    let ephemeral = backend
+        .generate_ephemeral_keypair(&KeyGenerationOptions::default())
+        .unwrap();
+
+    assert_eq!(backend.list_keys().unwrap(), ids(&["first", "second"]));
+
+    let report = backend.wipe().unwrap();
+    assert!(report.is_complete());
+    assert_eq!(report.deleted, ids(&["first", "second"]));
+    assert!(backend.list_keys().unwrap().is_empty());
+    assert!(backend.load_keypair("first").is_err());
+
+    // Ephemeral keypairs are not stored, so they keep working
+    assert_eq!(ephemeral.sign(b"Hello World!").unwrap().len(), 64);
+
+    // Wiping again is a no-op
+    let report = backend.wipe().unwrap();
+    assert!(report.is_complete());
+    assert!(report.deleted.is_empty());
+}
+
+#[test]
+fn wipe_removes_metadata_and_keeps_other_namespaces() {
+    let path = std::env::temp_dir().join(format!("secure-env-wipe-{}.json", std::process::id()));
+    let store = Arc::new(FileMetadataStore::new(&path));
+    let backend = |namespace: &str| {
+        SoftwareBackend::new(SecureEnvironmentConfig {
+            namespace: Some(namespace.to_owned()),
+            metadata_store: Some(store.clone()),
+            ..Default::default()
+        })
+    };
+
+    let wiped = backend("wiped");
+    let other = backend("other");
+    wiped.create_keypair("stored").unwrap();
+    other.create_keypair("stored").unwrap();
+
+    // Metadata of a keypair that no longer exists, e.g. after a reinstall of the application
+    store
+        .store(
+            Some("wiped"),
+            &KeyId::new("orphaned").unwrap(),
+            &KeyMetadata::default(),
+        )
+        .unwrap();
+
+    let report = wiped.wipe().unwrap();
+    assert!(report.is_complete());
+    assert_eq!(report.deleted, ids(&["stored"]));
+    assert!(store.list(Some("wiped")).unwrap().is_empty());
+
+    assert_eq!(other.list_keys().unwrap(), ids(&["stored"]));
+    assert_eq!(store.list(Some("other")).unwrap().len(), 1);
+
+    std::fs::remove_file(path).unwrap();
+}